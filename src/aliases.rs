@@ -0,0 +1,117 @@
+use crate::language::CANONICAL_LANGUAGE;
+
+use rocket::serde::json::Value;
+use std::collections::HashMap;
+
+/// Other names for areas, bundled with the API as JSON.
+///
+/// The JSON is an object keyed by the area's name (as used by `list_areas`), where each value is a
+/// list of aliases for that area: common misspellings, the area's name in other languages, or
+/// local nicknames. An alias which is the area's name in another language is written like
+/// `{"name": "Kaapstad area 15", "lang": "af"}`, so that it can be shown to people who prefer that
+/// language.
+const BUNDLED_ALIASES: &str = include_str!("../data/area_aliases.json");
+
+/// The aliases of every area that has aliases.
+#[derive(Debug, Default)]
+pub struct AreaAliases {
+    aliases: HashMap<String, Vec<String>>,
+    /// The area's name in other languages, keyed by the area's name and then the language (like
+    /// `af`).
+    localised: HashMap<String, HashMap<String, String>>,
+}
+
+impl AreaAliases {
+    /// The aliases which are bundled with the API.
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_ALIASES).expect("Bundled area aliases should be valid")
+    }
+
+    /// Parse area aliases from a JSON object of area names to lists of aliases. Each alias is
+    /// either a string, or an object with the alias's `name` and the `lang` it's in. Aliases are
+    /// trimmed, have their whitespace collapsed, and are deduplicated (ignoring case). Aliases
+    /// which are just the area's name are dropped.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let json: Value =
+            rocket::serde::json::from_str(json).map_err(|e| format!("Invalid aliases: {e}"))?;
+        let areas = json
+            .as_object()
+            .ok_or("Aliases must be an object keyed by area name")?;
+
+        let mut area_aliases = AreaAliases::default();
+        for (area_name, aliases) in areas {
+            let aliases = aliases
+                .as_array()
+                .ok_or(format!("The aliases for {area_name} must be a list"))?
+                .iter()
+                .map(|alias| parse_alias(area_name, alias))
+                .collect::<Result<Vec<_>, String>>()?;
+
+            let mut normalised: Vec<String> = vec![];
+            let mut localised = HashMap::new();
+            for (alias, language) in aliases {
+                let alias = alias.split_whitespace().collect::<Vec<_>>().join(" ");
+                let is_duplicate = alias.is_empty()
+                    || alias.eq_ignore_ascii_case(area_name)
+                    || normalised.iter().any(|a| a.eq_ignore_ascii_case(&alias));
+                if is_duplicate {
+                    continue;
+                }
+                if let Some(language) = language {
+                    if localised.contains_key(&language) {
+                        return Err(format!(
+                            "{area_name} has more than one alias in the language `{language}`"
+                        ));
+                    }
+                    localised.insert(language, alias.clone());
+                }
+                normalised.push(alias);
+            }
+            area_aliases.aliases.insert(area_name.clone(), normalised);
+            if !localised.is_empty() {
+                area_aliases.localised.insert(area_name.clone(), localised);
+            }
+        }
+        Ok(area_aliases)
+    }
+
+    /// The aliases of `area_name`, which is empty if it doesn't have any.
+    pub fn of(&self, area_name: &str) -> Vec<String> {
+        self.aliases.get(area_name).cloned().unwrap_or_default()
+    }
+
+    /// The name of `area_name` in the first of `languages` (most preferred first) that it has a
+    /// name in. None if the area's own name should be used, either because it doesn't have a name
+    /// in any of the languages or because English comes first.
+    pub fn localised(&self, area_name: &str, languages: &[String]) -> Option<&str> {
+        let localised = self.localised.get(area_name)?;
+        languages
+            .iter()
+            .take_while(|language| language.as_str() != CANONICAL_LANGUAGE)
+            .find_map(|language| localised.get(language))
+            .map(String::as_str)
+    }
+}
+
+/// Parse one of `area_name`'s aliases, which is either a string or an object like `{"name":
+/// "Kaapstad area 15", "lang": "af"}`. Gives the alias along with its language, if it has one.
+fn parse_alias(area_name: &str, alias: &Value) -> Result<(String, Option<String>), String> {
+    if let Some(alias) = alias.as_str() {
+        return Ok((alias.to_string(), None));
+    }
+    let invalid = || {
+        format!(
+            "Every alias for {area_name} must be a string, or an object with a `name` and a \
+            `lang`"
+        )
+    };
+    let object = alias.as_object().ok_or_else(invalid)?;
+    let name = object.get("name").and_then(Value::as_str);
+    let language = object.get("lang").and_then(Value::as_str);
+    match (name, language) {
+        (Some(name), Some(language)) if !language.trim().is_empty() => {
+            Ok((name.to_string(), Some(language.trim().to_lowercase())))
+        }
+        _ => Err(invalid()),
+    }
+}
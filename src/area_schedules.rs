@@ -0,0 +1,69 @@
+use rocket::serde::json::Value;
+use std::collections::HashMap;
+
+/// Which areas share a schedule, bundled with the API as JSON.
+///
+/// The JSON is an object keyed by the schedule's name, where each value is the list of areas
+/// which follow that schedule. Only schedules which are shared by several areas are listed: every
+/// other area follows a schedule of its own, named after the area.
+const BUNDLED_AREA_SCHEDULES: &str = include_str!("../data/area_schedules.json");
+
+/// The name of the schedule that each area follows, for the areas which share a schedule.
+#[derive(Debug, Default)]
+pub struct AreaSchedules(HashMap<String, String>);
+
+impl AreaSchedules {
+    /// The shared schedules which are bundled with the API.
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_AREA_SCHEDULES).expect("Bundled area schedules should be valid")
+    }
+
+    /// Parse shared schedules from a JSON object of schedule names to lists of area names. An area
+    /// can only follow one schedule.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let json: Value = rocket::serde::json::from_str(json)
+            .map_err(|e| format!("Invalid area schedules: {e}"))?;
+        let schedules = json
+            .as_object()
+            .ok_or("Area schedules must be an object keyed by schedule name")?;
+
+        let mut schedule_of = HashMap::new();
+        for (schedule_name, area_names) in schedules {
+            let area_names = area_names.as_array().ok_or(format!(
+                "The areas following {schedule_name} must be a list"
+            ))?;
+            for area_name in area_names {
+                let area_name = area_name.as_str().ok_or(format!(
+                    "Every area following {schedule_name} must be a string"
+                ))?;
+                if let Some(other) =
+                    schedule_of.insert(area_name.to_string(), schedule_name.clone())
+                {
+                    return Err(format!(
+                        "{area_name} can't follow both {other} and {schedule_name}"
+                    ));
+                }
+            }
+        }
+        Ok(AreaSchedules(schedule_of))
+    }
+
+    /// The name of the schedule that `area_name` follows, which is the area's own name unless it
+    /// shares a schedule with other areas.
+    pub fn schedule_of<'a>(&'a self, area_name: &'a str) -> &'a str {
+        self.0.get(area_name).map_or(area_name, String::as_str)
+    }
+
+    /// The other areas which follow the same schedule as `area_name`, in alphabetical order.
+    pub fn siblings_of(&self, area_name: &str) -> Vec<String> {
+        let schedule_name = self.schedule_of(area_name);
+        let mut siblings = self
+            .0
+            .iter()
+            .filter(|(other, schedule)| *other != area_name && *schedule == schedule_name)
+            .map(|(other, _schedule)| other.clone())
+            .collect::<Vec<_>>();
+        siblings.sort();
+        siblings
+    }
+}
@@ -0,0 +1,991 @@
+use crate::structs::{CacheStatus, Health, PowerOutage, RecurringSchedule, ServiceStatus};
+use crate::versions::sast_now;
+
+use chrono::{DateTime, FixedOffset};
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use rocket::serde::DeserializeOwned;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Header,
+    Orbit, Request, Response, Rocket,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Display};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex, Notify, RwLock};
+use tracing::{self, Instrument};
+
+/// Where eskom-calendar publishes the list of every known upcoming power outage, unless
+/// configured otherwise via the `MACHINE_FRIENDLY_URL` environment variable.
+pub const DEFAULT_MACHINE_FRIENDLY_URL: &str =
+    "https://github.com/beyarkay/eskom-calendar/releases/download/latest/machine_friendly.csv";
+
+/// The directory where eskom-calendar keeps every area's schedule, unless configured otherwise via
+/// the `SCHEDULES_URL` environment variable.
+pub const DEFAULT_SCHEDULES_URL: &str =
+    "https://raw.githubusercontent.com/beyarkay/eskom-calendar/main/generated";
+
+/// Where eskom-calendar's data is fetched from. This is eskom-calendar's GitHub repo by default,
+/// but it can be pointed at a fork or a local server instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataSources {
+    /// The URL of machine_friendly.csv.
+    pub machine_friendly_url: String,
+    /// The URL of the directory holding each area's schedule, without a trailing slash.
+    pub schedules_url: String,
+}
+
+impl Default for DataSources {
+    fn default() -> Self {
+        DataSources {
+            machine_friendly_url: DEFAULT_MACHINE_FRIENDLY_URL.to_string(),
+            schedules_url: DEFAULT_SCHEDULES_URL.to_string(),
+        }
+    }
+}
+
+impl DataSources {
+    /// Where the schedule for `area_name` is kept. Every area that eskom-calendar knows about has
+    /// a schedule, even if it doesn't have any outages right now.
+    pub fn schedule_url(&self, area_name: &str) -> String {
+        format!(
+            "{}/{area_name}.csv",
+            self.schedules_url.trim_end_matches('/')
+        )
+    }
+
+    /// Where the schedule file called `file` is kept, like an older version of a schedule.
+    pub fn schedule_file_url(&self, file: &str) -> String {
+        format!("{}/{file}", self.schedules_url.trim_end_matches('/'))
+    }
+
+    /// Where the metadata for `area_name`'s schedule is kept, like where the schedule came from
+    /// and when it's valid. Not every schedule has metadata.
+    pub fn schedule_metadata_url(&self, area_name: &str) -> String {
+        format!(
+            "{}/{area_name}.metadata.json",
+            self.schedules_url.trim_end_matches('/')
+        )
+    }
+}
+
+/// How long a fetched copy of machine_friendly.csv is used before it gets fetched again, unless
+/// configured otherwise via the `CACHE_TTL_SECONDS` environment variable.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How many versions of machine_friendly.csv are kept around, so that `/outages/<area>/diff` can
+/// say what's changed since an earlier version.
+pub const MAX_SNAPSHOTS: usize = 24;
+
+/// How many times fetching a file from GitHub is tried before giving up, unless configured
+/// otherwise via the `FETCH_ATTEMPTS` environment variable.
+pub const DEFAULT_FETCH_ATTEMPTS: u32 = 3;
+
+/// How long to wait before trying to fetch a file again. This doubles after every failed attempt.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// How long stale outages are served for after GitHub couldn't be reached, before trying to
+/// refresh them again. Without this, every request would wait for GitHub to fail all over again.
+pub const STALE_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How often machine_friendly.csv is checked for changes while requests are long-polling, whatever
+/// the TTL is. This is much shorter than the longest long-poll, so that long-polls hear about
+/// changes soon after they happen instead of timing out.
+pub const LONGPOLL_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How to retry fetching files from GitHub, since GitHub occasionally has a blip and it's a shame
+/// to fail a request because of that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times to try fetching a file, including the first attempt. Zero is treated as one.
+    pub attempts: u32,
+    /// How long to wait after the first failed attempt. Every later wait is twice as long as the
+    /// one before it.
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: DEFAULT_FETCH_ATTEMPTS,
+            delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+}
+
+/// How long fetching a file from GitHub can take before giving up on it, unless configured
+/// otherwise via the `FETCH_TIMEOUT_SECONDS` environment variable. Without a timeout, a stalled
+/// connection to GitHub would leave the request hanging forever.
+pub const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a file couldn't be fetched from GitHub.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpstreamError {
+    /// GitHub didn't answer within the fetch timeout.
+    TimedOut(String),
+    /// Anything else, like GitHub being unreachable or answering with an error.
+    Failed(String),
+    /// machine_friendly.csv hasn't been fetched yet, because the API has only just started and
+    /// is still warming up the cache. GitHub hasn't actually failed.
+    WarmingUp(String),
+}
+
+impl UpstreamError {
+    /// A human readable description of what went wrong.
+    pub fn message(&self) -> &str {
+        match self {
+            UpstreamError::TimedOut(message)
+            | UpstreamError::Failed(message)
+            | UpstreamError::WarmingUp(message) => message,
+        }
+    }
+
+    /// The same kind of error, but described by `message` instead.
+    pub fn with_message(&self, message: impl Into<String>) -> Self {
+        match self {
+            UpstreamError::TimedOut(_) => UpstreamError::TimedOut(message.into()),
+            UpstreamError::Failed(_) => UpstreamError::Failed(message.into()),
+            UpstreamError::WarmingUp(_) => UpstreamError::WarmingUp(message.into()),
+        }
+    }
+}
+
+impl Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl From<String> for UpstreamError {
+    fn from(message: String) -> Self {
+        UpstreamError::Failed(message)
+    }
+}
+
+impl From<&str> for UpstreamError {
+    fn from(message: &str) -> Self {
+        UpstreamError::Failed(message.to_string())
+    }
+}
+
+/// The result of fetching a file which might not have changed since it was last fetched.
+pub enum Fetched {
+    /// The file was downloaded, along with the ETag it was served with (if any).
+    Modified { text: String, etag: Option<String> },
+    /// The file hasn't changed since the ETag that was sent along with the request.
+    NotModified,
+    /// There's no file at that URL.
+    NotFound,
+}
+
+/// Something that can get the text of a file at some URL.
+///
+/// This is a trait so that the tests can inject fake data and count how many times the network
+/// would have been hit.
+#[rocket::async_trait]
+pub trait Fetcher: Send + Sync {
+    /// Fetch the file at `url`. If `etag` is given, it's sent as `If-None-Match` so that the
+    /// file doesn't have to be downloaded again if it hasn't changed.
+    async fn fetch(&self, url: &str, etag: Option<&str>) -> Result<Fetched, UpstreamError>;
+
+    /// Check whether there's a file at `url`, without downloading the whole thing.
+    async fn exists(&self, url: &str) -> Result<bool, UpstreamError>;
+}
+
+/// Fetches files over the network with reqwest. This is what the API uses in production.
+pub struct GitHubFetcher {
+    client: reqwest::Client,
+    timeout: Duration,
+}
+
+impl GitHubFetcher {
+    /// A fetcher which makes its requests with `client`, and gives up on any fetch that takes
+    /// longer than `timeout`.
+    pub fn new(client: reqwest::Client, timeout: Duration) -> Self {
+        GitHubFetcher { client, timeout }
+    }
+}
+
+/// Describe why a request to `url` failed, which was `doing` something like "get".
+fn request_error(err: reqwest::Error, doing: &str, url: &str) -> UpstreamError {
+    if err.is_timeout() {
+        UpstreamError::TimedOut(format!("Timed out trying to {doing} {url}"))
+    } else {
+        UpstreamError::Failed(format!("Failed to {doing} {url}"))
+    }
+}
+
+#[rocket::async_trait]
+impl Fetcher for GitHubFetcher {
+    async fn fetch(&self, url: &str, etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        let fetch_span = tracing::info_span!("Making GET request to GitHub");
+        let convert_span = tracing::info_span!("Converting GitHub reponse to text");
+        let mut request = self.client.get(url).timeout(self.timeout);
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request
+            .send()
+            .instrument(fetch_span)
+            .await
+            .map_err(|err| request_error(err, "get", url))?;
+
+        match response.status() {
+            StatusCode::NOT_MODIFIED => return Ok(Fetched::NotModified),
+            StatusCode::NOT_FOUND => return Ok(Fetched::NotFound),
+            status if !status.is_success() => {
+                return Err(format!("Got {status} when getting {url}").into())
+            }
+            _ => {}
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.to_string());
+        let text = response
+            .text()
+            .instrument(convert_span)
+            .await
+            .map_err(|err| request_error(err, "get the text of", url))?;
+        Ok(Fetched::Modified { text, etag })
+    }
+
+    async fn exists(&self, url: &str) -> Result<bool, UpstreamError> {
+        let response = self
+            .client
+            .head(url)
+            .timeout(self.timeout)
+            .send()
+            .instrument(tracing::info_span!("Making HEAD request to GitHub"))
+            .await
+            .map_err(|err| request_error(err, "check", url))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(format!("Got {status} when checking {url}").into()),
+        }
+    }
+}
+
+/// The parsed contents of machine_friendly.csv, along with when it was fetched.
+pub struct CachedOutages {
+    pub outages: Arc<Vec<PowerOutage>>,
+    /// machine_friendly.csv exactly as GitHub sent it.
+    pub csv: Arc<str>,
+    /// Descriptions of the rows of machine_friendly.csv which couldn't be parsed.
+    pub warnings: Vec<String>,
+    /// When machine_friendly.csv was last fetched (or confirmed to be unchanged), used for
+    /// checking the TTL.
+    pub fetched_at: Instant,
+    /// The same as `fetched_at`, but as a datetime that can be shown to users.
+    pub last_fetched: DateTime<FixedOffset>,
+    /// When machine_friendly.csv was first fetched with its current ETag and contents. Unlike
+    /// `last_fetched`, this doesn't move when GitHub says the file is unchanged.
+    pub last_changed: DateTime<FixedOffset>,
+    /// The ETag GitHub served machine_friendly.csv with, if any.
+    pub etag: Option<String>,
+    /// True if these outages should have been refreshed, but couldn't be because GitHub couldn't
+    /// be reached.
+    pub stale: bool,
+    /// While the outages are stale, when to next try refreshing them. Until then they're served
+    /// as they are.
+    pub retry_at: Option<Instant>,
+}
+
+/// One version of machine_friendly.csv's outages, kept so that later versions can be compared with
+/// it.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// When this version was fetched.
+    pub taken_at: DateTime<FixedOffset>,
+    pub outages: Arc<Vec<PowerOutage>>,
+}
+
+/// How fresh the cached outages are, so that HTTP caches can be told how long to keep responses
+/// built from them.
+pub struct Freshness {
+    /// How much longer the cached outages will be used before they're refreshed.
+    pub remaining: Duration,
+    /// When machine_friendly.csv last changed, which is when responses built from it were last
+    /// modified.
+    pub last_changed: DateTime<FixedOffset>,
+    /// The ETag GitHub served machine_friendly.csv with, if any.
+    pub etag: Option<String>,
+}
+
+/// How much the cache has been used since the API started, for the `/metrics` endpoint.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheCounters {
+    /// Requests for outages which were answered with the cached copy of machine_friendly.csv.
+    pub hits: u64,
+    /// Requests for outages which found the cached copy missing or older than the TTL.
+    pub misses: u64,
+    /// Requests made to GitHub, including checks for whether files exist.
+    pub upstream_fetches: u64,
+}
+
+/// A shared cache of machine_friendly.csv, so that every request doesn't have to go to GitHub and
+/// re-parse the whole file. Cloning it gives another handle to the same cache, so that background
+/// tasks can hold onto it.
+#[derive(Clone)]
+pub struct OutageCache(Arc<CacheState>);
+
+/// Everything kept by an `OutageCache`.
+pub struct CacheState {
+    fetcher: Box<dyn Fetcher>,
+    ttl: Duration,
+    retry: RetryPolicy,
+    sources: DataSources,
+    /// How often the outages are refreshed while anyone is long-polling for them to change.
+    longpoll_refresh: Duration,
+    cached: RwLock<Option<CachedOutages>>,
+    /// Held by whichever request is refreshing the cache, so that concurrent requests wait for
+    /// that one refresh instead of each fetching machine_friendly.csv themselves.
+    refreshing: Mutex<()>,
+    /// Areas which don't have any outages, but which have been confirmed to exist because they
+    /// have a schedule.
+    known_areas: RwLock<HashSet<String>>,
+    /// Each area's parsed schedule, along with when it was fetched. These have the same TTL as
+    /// the outages.
+    schedules: RwLock<HashMap<String, (Instant, RecurringSchedule)>>,
+    /// The last `MAX_SNAPSHOTS` versions of the outages, oldest first. A new snapshot is only
+    /// taken when machine_friendly.csv changes.
+    snapshots: RwLock<VecDeque<Snapshot>>,
+    /// The latest outages, sent every time they change so that background tasks can keep up with
+    /// them. None until machine_friendly.csv has been fetched for the first time.
+    changes: watch::Sender<Option<Arc<Vec<PowerOutage>>>>,
+    /// Set while machine_friendly.csv is first being fetched after the API starts. Requests
+    /// don't wait for it, since it might take a while.
+    warming_up: AtomicBool,
+    /// How many requests are long-polling for the outages to change right now.
+    long_pollers: AtomicUsize,
+    /// Woken whenever a request starts long-polling, so that the cache can be kept fresh for it.
+    long_poll_started: Notify,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    upstream_fetches: AtomicU64,
+}
+
+impl Deref for OutageCache {
+    type Target = CacheState;
+
+    fn deref(&self) -> &CacheState {
+        &self.0
+    }
+}
+
+impl OutageCache {
+    pub fn new(fetcher: impl Fetcher + 'static, ttl: Duration) -> Self {
+        OutageCache(Arc::new(CacheState {
+            fetcher: Box::new(fetcher),
+            ttl,
+            retry: RetryPolicy::default(),
+            sources: DataSources::default(),
+            longpoll_refresh: LONGPOLL_REFRESH_INTERVAL,
+            cached: RwLock::new(None),
+            refreshing: Mutex::new(()),
+            known_areas: RwLock::new(HashSet::new()),
+            schedules: RwLock::new(HashMap::new()),
+            snapshots: RwLock::new(VecDeque::new()),
+            changes: watch::channel(None).0,
+            warming_up: AtomicBool::new(false),
+            long_pollers: AtomicUsize::new(0),
+            long_poll_started: Notify::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            upstream_fetches: AtomicU64::new(0),
+        }))
+    }
+
+    /// The cache's state, which can only be changed while the cache is being built.
+    fn state_mut(&mut self) -> &mut CacheState {
+        Arc::get_mut(&mut self.0).expect("The cache can't be configured once it's shared")
+    }
+
+    /// Use `retry` instead of the default retry policy when fetching files.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.state_mut().retry = retry;
+        self
+    }
+
+    /// Fetch eskom-calendar's data from `sources` instead of GitHub.
+    pub fn with_sources(mut self, sources: DataSources) -> Self {
+        self.state_mut().sources = sources;
+        self
+    }
+
+    /// Refresh the outages every `interval` while anyone is long-polling, instead of every
+    /// `LONGPOLL_REFRESH_INTERVAL`.
+    #[cfg(test)]
+    pub fn with_longpoll_refresh(mut self, interval: Duration) -> Self {
+        self.state_mut().longpoll_refresh = interval;
+        self
+    }
+
+    /// Where eskom-calendar's data is fetched from.
+    pub fn sources(&self) -> &DataSources {
+        &self.sources
+    }
+
+    /// Fetch the file at `url`, trying again with exponential backoff if fetching fails.
+    async fn fetch_with_retry(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+    ) -> Result<Fetched, UpstreamError> {
+        let attempts = self.retry.attempts.max(1);
+        let mut delay = self.retry.delay;
+        let mut attempt = 1;
+        loop {
+            self.upstream_fetches.fetch_add(1, Ordering::Relaxed);
+            match self.fetcher.fetch(url, etag).await {
+                Ok(fetched) => return Ok(fetched),
+                Err(err) if attempt >= attempts => return Err(err),
+                Err(err) => {
+                    tracing::warn!(
+                        "Attempt {attempt}/{attempts} to get {url} failed, retrying in {delay:?}: \
+                        {err}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Get all the known power outages, only fetching machine_friendly.csv if the cached copy is
+    /// older than the TTL.
+    ///
+    /// While the cache is warming up, this fails straight away instead of waiting for the first
+    /// fetch to finish.
+    pub async fn outages(&self) -> Result<Arc<Vec<PowerOutage>>, UpstreamError> {
+        if let Some(outages) = self.fresh_outages().await {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(outages);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        if self.is_warming_up() {
+            return Err(UpstreamError::WarmingUp(
+                "The API has just started, and is still fetching machine_friendly.csv".to_string(),
+            ));
+        }
+        self.refresh_outages().await
+    }
+
+    /// Fetch machine_friendly.csv when the API starts, so that the first requests don't have to
+    /// wait for it. Until it's done, `outages` fails instead of waiting.
+    pub async fn warm_up(&self) {
+        self.warming_up.store(true, Ordering::Relaxed);
+        if let Err(err) = self.refresh_outages().await {
+            tracing::warn!("Couldn't warm up the cache: {err}");
+        }
+        self.warming_up.store(false, Ordering::Relaxed);
+    }
+
+    /// Count the caller as long-polling for the outages to change until the returned guard is
+    /// dropped. While anyone is long-polling, `LongPollRefresher` keeps refreshing the outages so
+    /// that changes are noticed without waiting for some other request to refresh them.
+    pub fn long_poll(&self) -> LongPolling {
+        self.long_pollers.fetch_add(1, Ordering::Relaxed);
+        self.long_poll_started.notify_one();
+        LongPolling(self.clone())
+    }
+
+    /// Refresh the outages every `longpoll_refresh` for as long as anyone is long-polling, even if
+    /// they're younger than the TTL, and wait for someone to start long-polling otherwise.
+    async fn refresh_while_long_polled(self) {
+        loop {
+            while self.long_pollers.load(Ordering::Relaxed) == 0 {
+                self.long_poll_started.notified().await;
+            }
+            tokio::time::sleep(self.longpoll_refresh).await;
+            if self.long_pollers.load(Ordering::Relaxed) == 0 {
+                continue;
+            }
+            tracing::debug!("Refreshing the outages for the requests which are long-polling");
+            if let Err(err) = self.revalidate_outages().await {
+                tracing::warn!("Couldn't refresh the outages for long-polling: {err}");
+            }
+        }
+    }
+
+    /// Whether machine_friendly.csv is still being fetched for the first time, in `warm_up`.
+    pub fn is_warming_up(&self) -> bool {
+        self.warming_up.load(Ordering::Relaxed)
+    }
+
+    /// Refresh the cached outages, unless another request refreshed them while this one waited.
+    async fn refresh_outages(&self) -> Result<Arc<Vec<PowerOutage>>, UpstreamError> {
+        let _refreshing = self.refreshing.lock().await;
+        // Some other request might have refreshed the cache while we were waiting for the lock
+        if let Some(outages) = self.fresh_outages().await {
+            return Ok(outages);
+        }
+        self.fetch_outages().await
+    }
+
+    /// Ask GitHub whether machine_friendly.csv has changed, even if the cached outages are younger
+    /// than the TTL. Since the ETag is sent along, this only downloads it again if it has. Stale
+    /// outages are still served until it's time to try GitHub again.
+    async fn revalidate_outages(&self) -> Result<Arc<Vec<PowerOutage>>, UpstreamError> {
+        let _refreshing = self.refreshing.lock().await;
+        let backing_off = self
+            .cached
+            .read()
+            .await
+            .as_ref()
+            .filter(|cached| cached.retry_at.is_some_and(|at| Instant::now() < at))
+            .map(|cached| Arc::clone(&cached.outages));
+        if let Some(outages) = backing_off {
+            return Ok(outages);
+        }
+        self.fetch_outages().await
+    }
+
+    /// Fetch machine_friendly.csv and update the cached outages. Only call this while holding the
+    /// `refreshing` lock.
+    async fn fetch_outages(&self) -> Result<Arc<Vec<PowerOutage>>, UpstreamError> {
+        let previous_etag = self
+            .cached
+            .read()
+            .await
+            .as_ref()
+            .and_then(|cached| cached.etag.clone());
+
+        let machine_friendly_span = tracing::info_span!("Getting machine friendly");
+        let fetched = self
+            .fetch_with_retry(&self.sources.machine_friendly_url, previous_etag.as_deref())
+            .instrument(machine_friendly_span)
+            .await
+            .map_err(|err| {
+                err.with_message("Failed to get machine_friendly.csv that defines the outages")
+            });
+
+        let mut cached = self.cached.write().await;
+        let refreshed = match (fetched, cached.as_mut()) {
+            (Err(err), _) => Err(err),
+            (Ok(Fetched::NotModified), Some(cached)) => {
+                tracing::info!("machine_friendly.csv hasn't changed, reusing the parsed outages");
+                cached.fetched_at = Instant::now();
+                cached.last_fetched = sast_now();
+                cached.stale = false;
+                cached.retry_at = None;
+                Ok(Arc::clone(&cached.outages))
+            }
+            (Ok(Fetched::NotModified), None) => Err(
+                "GitHub says machine_friendly.csv is unchanged, but it was never fetched".into(),
+            ),
+            (Ok(Fetched::NotFound), _) => Err("GitHub couldn't find machine_friendly.csv".into()),
+            (Ok(Fetched::Modified { text, etag }), previous) => {
+                let (outages, warnings) = parse_machine_friendly(&text);
+                let outages = Arc::new(outages);
+                let last_fetched = sast_now();
+                let last_changed = previous
+                    .filter(|previous| previous.etag == etag && *previous.csv == *text)
+                    .map_or(last_fetched, |previous| previous.last_changed);
+                *cached = Some(CachedOutages {
+                    outages: Arc::clone(&outages),
+                    csv: Arc::from(text),
+                    warnings,
+                    fetched_at: Instant::now(),
+                    last_fetched,
+                    last_changed,
+                    etag,
+                    stale: false,
+                    retry_at: None,
+                });
+                // machine_friendly.csv gets a new ETag every time it's rebuilt, even if none of the
+                // outages changed, and a snapshot of the same outages would push out an older one
+                // which actually differs
+                let mut snapshots = self.snapshots.write().await;
+                let is_unchanged = snapshots
+                    .back()
+                    .is_some_and(|snapshot| snapshot.outages == outages);
+                if !is_unchanged {
+                    if snapshots.len() == MAX_SNAPSHOTS {
+                        snapshots.pop_front();
+                    }
+                    snapshots.push_back(Snapshot {
+                        taken_at: last_fetched,
+                        outages: Arc::clone(&outages),
+                    });
+                }
+                self.changes.send_replace(Some(Arc::clone(&outages)));
+                Ok(outages)
+            }
+        };
+
+        // Old data is more useful than no data at all, so keep serving the last outages that were
+        // fetched until GitHub is back
+        match (refreshed, cached.as_mut()) {
+            (Ok(outages), _) => Ok(outages),
+            (Err(err), Some(cached)) => {
+                tracing::warn!(
+                    "{err}, serving the outages fetched at {} instead until trying again in \
+                    {STALE_RETRY_BACKOFF:?}",
+                    cached.last_fetched
+                );
+                cached.stale = true;
+                cached.retry_at = Some(Instant::now() + STALE_RETRY_BACKOFF);
+                Ok(Arc::clone(&cached.outages))
+            }
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    /// Get machine_friendly.csv exactly as GitHub sent it, along with its ETag. This is refreshed
+    /// in the same way as [`OutageCache::outages`].
+    pub async fn machine_friendly(&self) -> Result<(Arc<str>, Option<String>), UpstreamError> {
+        self.outages().await?;
+        self.cached
+            .read()
+            .await
+            .as_ref()
+            .map(|cached| (Arc::clone(&cached.csv), cached.etag.clone()))
+            .ok_or_else(|| "machine_friendly.csv has never been fetched".into())
+    }
+
+    /// Watch the outages, which will be sent again every time machine_friendly.csv changes.
+    pub fn changes(&self) -> watch::Receiver<Option<Arc<Vec<PowerOutage>>>> {
+        self.changes.subscribe()
+    }
+
+    /// How old the cached outages are, but only if they're stale because GitHub couldn't be
+    /// reached when they should have been refreshed.
+    pub async fn stale_age(&self) -> Option<Duration> {
+        self.cached
+            .read()
+            .await
+            .as_ref()
+            .filter(|cached| cached.stale)
+            .map(|cached| cached.fetched_at.elapsed())
+    }
+
+    /// How fresh the cached outages are. None if machine_friendly.csv hasn't been fetched yet.
+    pub async fn freshness(&self) -> Option<Freshness> {
+        self.cached.read().await.as_ref().map(|cached| Freshness {
+            remaining: self.ttl.saturating_sub(cached.fetched_at.elapsed()),
+            last_changed: cached.last_changed,
+            etag: cached.etag.clone(),
+        })
+    }
+
+    /// The oldest snapshot of the outages which was taken at or after `since`. If there isn't one
+    /// then the outages haven't changed since `since`, so the latest snapshot is returned. None if
+    /// machine_friendly.csv hasn't been fetched yet.
+    pub async fn snapshot_since(&self, since: DateTime<FixedOffset>) -> Option<Snapshot> {
+        let snapshots = self.snapshots.read().await;
+        snapshots
+            .iter()
+            .find(|snapshot| snapshot.taken_at >= since)
+            .or_else(|| snapshots.back())
+            .cloned()
+    }
+
+    /// Get the text of some other file from GitHub, without caching it. Returns None if there's no
+    /// file at `url`.
+    pub async fn fetch_file(&self, url: &str) -> Result<Option<String>, UpstreamError> {
+        match self.fetch_with_retry(url, None).await? {
+            Fetched::Modified { text, .. } => Ok(Some(text)),
+            Fetched::NotFound => Ok(None),
+            Fetched::NotModified => {
+                Err(format!("GitHub says {url} is unchanged without an ETag").into())
+            }
+        }
+    }
+
+    /// The schedule for `area_name`, but only if it was cached less than the TTL ago. Schedules
+    /// older than that are dropped from the cache.
+    pub async fn cached_schedule(&self, area_name: &str) -> Option<RecurringSchedule> {
+        {
+            let schedules = self.schedules.read().await;
+            match schedules.get(area_name) {
+                None => return None,
+                Some((fetched_at, schedule)) if fetched_at.elapsed() < self.ttl => {
+                    return Some(schedule.clone())
+                }
+                Some(_stale) => {}
+            }
+        }
+        self.schedules.write().await.remove(area_name);
+        None
+    }
+
+    /// Remember the schedule for `area_name`, which has just been fetched.
+    pub async fn cache_schedule(&self, area_name: &str, schedule: RecurringSchedule) {
+        self.schedules
+            .write()
+            .await
+            .insert(area_name.to_string(), (Instant::now(), schedule));
+    }
+
+    /// Check whether `area_name` is one of the areas we already know about, either because it has
+    /// outages or because GitHub has told us it has a schedule. Unlike `is_known_area`, this never
+    /// asks GitHub about areas we haven't seen before.
+    pub async fn is_listed_area(&self, area_name: &str) -> Result<bool, UpstreamError> {
+        let has_outages = self
+            .outages()
+            .await?
+            .iter()
+            .any(|outage| outage.area_name == area_name);
+        Ok(has_outages || self.known_areas.read().await.contains(area_name))
+    }
+
+    /// Check whether eskom-calendar knows about `area_name`, even if the area doesn't have any
+    /// outages right now (which happens whenever there's no loadshedding).
+    pub async fn is_known_area(&self, area_name: &str) -> Result<bool, UpstreamError> {
+        if self.is_listed_area(area_name).await? {
+            return Ok(true);
+        }
+
+        // Area names are only ever made of lowercase letters, numbers, and dashes, so there's no
+        // point asking GitHub about anything else
+        let is_valid_name = area_name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !is_valid_name {
+            return Ok(false);
+        }
+
+        tracing::info!("Checking if {area_name} has a schedule");
+        self.upstream_fetches.fetch_add(1, Ordering::Relaxed);
+        let exists = self
+            .fetcher
+            .exists(&self.sources.schedule_url(area_name))
+            .await?;
+        if exists {
+            self.known_areas.write().await.insert(area_name.to_string());
+        }
+        Ok(exists)
+    }
+
+    /// Check whether GitHub can be reached (without downloading machine_friendly.csv) and describe
+    /// the cached data.
+    pub async fn health(&self) -> Health {
+        self.upstream_fetches.fetch_add(1, Ordering::Relaxed);
+        let upstream_reachable = self
+            .fetcher
+            .exists(&self.sources.machine_friendly_url)
+            .instrument(tracing::info_span!("Checking machine friendly exists"))
+            .await
+            .unwrap_or(false);
+        let cached = self.cached.read().await;
+        let area_count = cached.as_ref().map_or(0, |cached| {
+            cached
+                .outages
+                .iter()
+                .map(|outage| &outage.area_name)
+                .collect::<HashSet<_>>()
+                .len()
+        });
+        let status = match (upstream_reachable, cached.is_some()) {
+            (true, _) => ServiceStatus::Ok,
+            (false, true) => ServiceStatus::Degraded,
+            (false, false) => ServiceStatus::Down,
+        };
+        Health {
+            status,
+            upstream_reachable,
+            cache_age_seconds: cached
+                .as_ref()
+                .map(|cached| cached.fetched_at.elapsed().as_secs()),
+            area_count,
+        }
+    }
+
+    /// Describe how stale the cached data is, without fetching anything.
+    pub async fn status(&self) -> CacheStatus {
+        let cached = self.cached.read().await;
+        CacheStatus {
+            etag: cached.as_ref().and_then(|cached| cached.etag.clone()),
+            stale: cached.as_ref().is_some_and(|cached| cached.stale),
+            cache_age_seconds: cached
+                .as_ref()
+                .map(|cached| cached.fetched_at.elapsed().as_secs()),
+            last_fetched: cached.as_ref().map(|cached| cached.last_fetched),
+            ttl_seconds: self.ttl.as_secs(),
+            warnings: cached
+                .as_ref()
+                .map(|cached| cached.warnings.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// How much the cache has been used since it was created.
+    pub fn counters(&self) -> CacheCounters {
+        CacheCounters {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            upstream_fetches: self.upstream_fetches.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The cached outages, but only if they're younger than the TTL, or they're stale and it's not
+    /// yet time to try refreshing them again.
+    async fn fresh_outages(&self) -> Option<Arc<Vec<PowerOutage>>> {
+        self.cached
+            .read()
+            .await
+            .as_ref()
+            .filter(|cached| {
+                cached.fetched_at.elapsed() < self.ttl
+                    || cached.retry_at.is_some_and(|at| Instant::now() < at)
+            })
+            .map(|cached| Arc::clone(&cached.outages))
+    }
+}
+
+/// Fetches machine_friendly.csv in the background as soon as the API starts. Requests which need
+/// the outages get a `503 Service Unavailable` until it's done, rather than waiting on GitHub.
+pub struct CacheWarmer;
+
+#[rocket::async_trait]
+impl Fairing for CacheWarmer {
+    fn info(&self) -> Info {
+        Info {
+            name: "Cache Warmer",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(cache) = rocket.state::<OutageCache>() else {
+            tracing::error!("Can't warm up the cache without the cache");
+            return;
+        };
+        // The server doesn't start until liftoff is over, so mark the cache as warming up now
+        // rather than leaving it to the spawned task
+        cache.warming_up.store(true, Ordering::Relaxed);
+        let cache = cache.clone();
+        tokio::spawn(async move { cache.warm_up().await });
+    }
+}
+
+/// Counts a request as long-polling for as long as it's held. See [`OutageCache::long_poll`].
+pub struct LongPolling(OutageCache);
+
+impl Drop for LongPolling {
+    fn drop(&mut self) {
+        self.0.long_pollers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Refreshes the outages in the background every `LONGPOLL_REFRESH_INTERVAL` while any request is
+/// long-polling for them to change, starting when the API does. Without this, long-polls would only notice a change if
+/// some other request happened to refresh the cache while they waited.
+pub struct LongPollRefresher;
+
+#[rocket::async_trait]
+impl Fairing for LongPollRefresher {
+    fn info(&self) -> Info {
+        Info {
+            name: "Long-Poll Refresher",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(cache) = rocket.state::<OutageCache>() else {
+            tracing::error!("Can't refresh the outages for long-polling without the cache");
+            return;
+        };
+        tokio::spawn(cache.clone().refresh_while_long_polled());
+    }
+}
+
+/// Tells clients when the data they got is stale because GitHub couldn't be reached, with the
+/// headers `X-Data-Stale: true` and `X-Cache-Age-Seconds` (how many seconds old the data is).
+pub struct StaleDataHeaders;
+
+#[rocket::async_trait]
+impl Fairing for StaleDataHeaders {
+    fn info(&self) -> Info {
+        Info {
+            name: "Stale Data Headers",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(cache) = request.rocket().state::<OutageCache>() else {
+            return;
+        };
+        if let Some(age) = cache.stale_age().await {
+            response.set_header(Header::new("X-Data-Stale", "true"));
+            response.set_header(Header::new(
+                "X-Cache-Age-Seconds",
+                age.as_secs().to_string(),
+            ));
+        }
+    }
+}
+
+/// The most rows which are described when parsing a CSV file, so that a completely broken file
+/// doesn't give thousands of warnings.
+const MAX_PARSE_WARNINGS: usize = 10;
+
+/// Parse every row of a CSV file as a `Raw` and convert it into a `T`. Rows which can't be parsed
+/// or converted are skipped, and described in the returned warnings.
+pub fn parse_csv_rows<Raw, T>(mut reader: csv::Reader<&[u8]>) -> (Vec<T>, Vec<String>)
+where
+    Raw: DeserializeOwned + TryInto<T>,
+    Raw::Error: Display,
+{
+    let mut parsed = vec![];
+    let mut skipped = vec![];
+    for (i, result) in reader.deserialize::<Raw>().enumerate() {
+        // The header is the first line, so the first row is on the second line
+        let line = i + 2;
+        match result
+            .map_err(|err| err.to_string())
+            .and_then(|raw| raw.try_into().map_err(|err: Raw::Error| err.to_string()))
+        {
+            Ok(row) => parsed.push(row),
+            Err(err) => skipped.push(format!("Skipped line {line}: {err}")),
+        }
+    }
+
+    if !skipped.is_empty() {
+        tracing::warn!("Skipped {} rows which couldn't be parsed", skipped.len());
+    }
+    let mut warnings = skipped
+        .iter()
+        .take(MAX_PARSE_WARNINGS)
+        .cloned()
+        .collect::<Vec<_>>();
+    if skipped.len() > MAX_PARSE_WARNINGS {
+        warnings.push(format!(
+            "...and {} more lines were skipped",
+            skipped.len() - MAX_PARSE_WARNINGS
+        ));
+    }
+    (parsed, warnings)
+}
+
+/// Parse the text of machine_friendly.csv into a list of power outages, along with descriptions
+/// of any rows which had to be skipped.
+///
+/// The same outage is sometimes listed more than once, when several tweets announce it. Only the
+/// first of them is kept, along with its source.
+fn parse_machine_friendly(text_data: &str) -> (Vec<PowerOutage>, Vec<String>) {
+    tracing::info!("Parsing machine_friendly.csv");
+    let (mut outages, warnings) =
+        parse_csv_rows::<PowerOutage, PowerOutage>(csv::Reader::from_reader(text_data.as_bytes()));
+    let mut seen = HashSet::new();
+    outages.retain(|outage| {
+        seen.insert((
+            outage.area_name.clone(),
+            outage.stage,
+            outage.start,
+            outage.finsh,
+        ))
+    });
+    (outages, warnings)
+}
@@ -0,0 +1,57 @@
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use std::convert::Infallible;
+use std::net::IpAddr;
+
+/// How many proxies sit in front of the API, unless configured otherwise via the
+/// `TRUSTED_PROXIES` environment variable. Shuttle puts one proxy in front of the API.
+pub const DEFAULT_TRUSTED_PROXIES: usize = 1;
+
+/// How far the `X-Forwarded-For` header can be trusted, set with the `TRUSTED_PROXIES`
+/// environment variable.
+///
+/// Each proxy adds the address it got the request from to the end of `X-Forwarded-For`, so with
+/// `trusted_proxies` proxies in front of the API, the client's address is that many entries from
+/// the end. Anything before that was sent by the client, and could be made up. With no trusted
+/// proxies, `X-Forwarded-For` is ignored.
+#[derive(Debug, Default)]
+pub struct ProxyPolicy {
+    pub trusted_proxies: usize,
+}
+
+/// The IP address of whoever made `request`, taking the trusted proxies into account. None if the
+/// address can't be worked out.
+pub fn client_ip(request: &Request<'_>) -> Option<IpAddr> {
+    let trusted_proxies = request
+        .rocket()
+        .state::<ProxyPolicy>()
+        .map_or(0, |policy| policy.trusted_proxies);
+    if trusted_proxies == 0 {
+        return request.client_ip();
+    }
+
+    // The header can be split over several lines, which are the same as one comma separated line
+    let forwarded_for = request
+        .headers()
+        .get("X-Forwarded-For")
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    forwarded_for
+        .len()
+        .checked_sub(trusted_proxies)
+        .and_then(|i| forwarded_for[i].parse().ok())
+        .or_else(|| request.client_ip())
+}
+
+/// The IP address of whoever made the request, as a request guard. See [`client_ip`].
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ClientIp(client_ip(req)))
+    }
+}
@@ -0,0 +1,133 @@
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Header,
+    Request, Response,
+};
+use std::io::{Cursor, Write};
+
+/// Responses smaller than this many bytes aren't compressed, since the gzip header and the extra
+/// work would cost more than the few bytes saved.
+pub const MIN_COMPRESSED_BYTES: usize = 1024;
+
+/// The ways a response body can be compressed, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    fn compress(self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Pick the best encoding the client accepts, given its `Accept-Encoding` header. Encodings with
+/// `q=0` are ones the client explicitly doesn't want.
+fn preferred_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let accepted: Vec<&str> = accept_encoding
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let name = parts.next()?;
+            let refused = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            (!refused).then_some(name)
+        })
+        .collect();
+    [Encoding::Gzip, Encoding::Deflate]
+        .into_iter()
+        .find(|encoding| {
+            accepted
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(encoding.name()))
+        })
+}
+
+/// Compresses responses with gzip (or deflate) when the client says it can handle that with the
+/// `Accept-Encoding` header. The lists of outages and areas are very repetitive, so they compress
+/// really well.
+pub struct Compressor;
+
+#[rocket::async_trait]
+impl Fairing for Compressor {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response Compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if response.body().is_none() || response.headers().contains("Content-Encoding") {
+            return;
+        }
+        // Caches need to know that the response depends on `Accept-Encoding`, even when this
+        // particular response wasn't compressed
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+
+        let Some(encoding) = request
+            .headers()
+            .get("Accept-Encoding")
+            .find_map(preferred_encoding)
+        else {
+            return;
+        };
+        if response
+            .body()
+            .preset_size()
+            .is_some_and(|size| size < MIN_COMPRESSED_BYTES)
+        {
+            return;
+        }
+
+        let bytes = match response.body_mut().to_bytes().await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                tracing::error!("Failed to read the response body for compression: {err:?}");
+                return;
+            }
+        };
+        let compressed = if bytes.len() < MIN_COMPRESSED_BYTES {
+            None
+        } else {
+            encoding
+                .compress(&bytes)
+                .map_err(|err| {
+                    tracing::error!("Failed to {} the response: {err:?}", encoding.name())
+                })
+                .ok()
+        };
+        match compressed {
+            Some(compressed) => {
+                response.set_header(Header::new("Content-Encoding", encoding.name()));
+                response.set_sized_body(compressed.len(), Cursor::new(compressed));
+            }
+            None => response.set_sized_body(bytes.len(), Cursor::new(bytes)),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Header,
+    Request, Response,
+};
+
+/// Which websites can call the API from a browser, set with the `CORS_ALLOWED_ORIGINS`
+/// environment variable as a comma separated list like
+/// `https://eskomcalendar.co.za,https://example.com`.
+///
+/// With no origins, any website can call the API, but browsers won't send cookies or other
+/// credentials with the requests. With some origins, only those websites can call the API, and
+/// browsers will send credentials with their requests.
+#[derive(Debug, Default)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>,
+}
+
+impl CorsPolicy {
+    /// Parse a comma separated list of origins, ignoring any blank entries.
+    pub fn from_list(origins: &str) -> Self {
+        CorsPolicy {
+            allowed_origins: origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// Whether a website at `origin` can call the API.
+    fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    }
+}
+
+/// Adds the CORS headers to every response, so that websites can call the API from a browser.
+/// Which websites can is decided by the managed `CorsPolicy`.
+/// https://stackoverflow.com/a/72702246/14555505
+pub struct Cors;
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "Cross-Origin-Resource-Sharing Fairing",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let allowed_origins = request
+            .rocket()
+            .state::<CorsPolicy>()
+            .filter(|policy| !policy.allowed_origins.is_empty());
+        match allowed_origins {
+            // Browsers refuse credentials from a wildcard origin, so they're only allowed when
+            // the origin is named
+            None => {
+                response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+            }
+            Some(policy) => {
+                // The response depends on the origin, so caches mustn't share it between origins
+                response.adjoin_header(Header::new("Vary", "Origin"));
+                match request.headers().get_one("Origin") {
+                    Some(origin) if policy.allows(origin) => {
+                        response.set_header(Header::new(
+                            "Access-Control-Allow-Origin",
+                            origin.to_string(),
+                        ));
+                        response
+                            .set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+                    }
+                    _ => return,
+                }
+            }
+        }
+        response.set_header(Header::new(
+            "Access-Control-Allow-Methods",
+            "POST, PATCH, PUT, DELETE, HEAD, OPTIONS, GET",
+        ));
+        response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
+    }
+}
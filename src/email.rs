@@ -0,0 +1,84 @@
+use crate::error::ApiError;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+
+/// Whether requests to the data endpoints have to include a contact email, set with the
+/// `REQUIRE_EMAIL` environment variable.
+#[derive(Debug, Default)]
+pub struct EmailPolicy {
+    pub required: bool,
+}
+
+/// Logs the contact email given with `?email=`, so that heavy users of the API can be identified
+/// and contacted. If the `EmailPolicy` says emails are required, requests without a valid email
+/// get `400 Bad Request` with an explanation of why.
+#[derive(Debug)]
+pub struct ContactEmail;
+
+/// Why a request was rejected by the `ContactEmail` guard, kept around so that the `bad_request`
+/// catcher can explain it to the user.
+struct RejectedEmail(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ContactEmail {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let required = req
+            .rocket()
+            .state::<EmailPolicy>()
+            .is_some_and(|policy| policy.required);
+        let email = req
+            .query_value::<String>("email")
+            .and_then(|email| email.ok());
+
+        let rejection = match email {
+            Some(email) if is_valid_email(&email) => {
+                tracing::info!("Request to {} from {email}", req.uri().path());
+                return Outcome::Success(ContactEmail);
+            }
+            Some(email) if required => format!(
+                "`email={email}` isn't a valid email address. Please give an email like \
+                `?email=you@example.com` so that we can get in touch if your app is using the API \
+                a lot"
+            ),
+            None if required => "Please give a contact email like `?email=you@example.com` so \
+                that we can get in touch if your app is using the API a lot"
+                .to_string(),
+            Some(email) => {
+                tracing::info!("Ignoring invalid email `{email}`");
+                return Outcome::Success(ContactEmail);
+            }
+            None => return Outcome::Success(ContactEmail),
+        };
+        req.local_cache(|| RejectedEmail(Some(rejection.clone())));
+        Outcome::Error((Status::BadRequest, rejection))
+    }
+}
+
+/// Check that `email` looks like `local@domain.tld`. This only checks the shape of the email, it
+/// doesn't check that anyone actually receives mail there.
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !email.chars().any(char::is_whitespace)
+        && !domain.contains('@')
+        && domain.contains('.')
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+/// Explain why a request was bad. Rocket's default 400 page doesn't say what was wrong, which
+/// isn't very helpful when the request was rejected for not having a contact email.
+#[catch(400)]
+pub fn bad_request(req: &Request) -> ApiError {
+    ApiError::BadRequest(
+        req.local_cache(|| RejectedEmail(None))
+            .0
+            .clone()
+            .unwrap_or_else(|| "The request was malformed".to_string()),
+    )
+}
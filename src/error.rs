@@ -0,0 +1,155 @@
+use crate::cache::UpstreamError;
+use crate::request_id::request_id;
+use crate::structs::Errors;
+
+use rocket::http::{Header, Status};
+use rocket::response::{self, Responder};
+use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::Request;
+use utoipa::ToSchema;
+
+/// Everything that can go wrong while handling a request. Each kind of error is sent with its own
+/// HTTP status code, and a JSON body like `{"error": "not_found", "message": "..."}`.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The area (or whatever else was asked for) doesn't exist. Sent as `404 Not Found`.
+    NotFound(String),
+    /// The area asked for doesn't exist, but some areas with similar names do. Sent as `404 Not
+    /// Found`, with the similar names in the body's `suggestions`.
+    UnknownArea {
+        message: String,
+        suggestions: Vec<String>,
+    },
+    /// One of the request's parameters was invalid. Sent as `400 Bad Request`.
+    BadRequest(String),
+    /// The request would go over a limit on how much one client (or everyone together) can
+    /// have, like how many webhooks are subscribed. Sent as `429 Too Many Requests`.
+    TooMany(String),
+    /// GitHub, where eskom-calendar's data lives, couldn't give us the data. Sent as `502 Bad
+    /// Gateway`.
+    Upstream(String),
+    /// GitHub took too long to give us the data. Sent as `504 Gateway Timeout`.
+    UpstreamTimeout(String),
+    /// The API has just started and doesn't have the data yet. Sent as `503 Service Unavailable`,
+    /// with a `Retry-After` header saying how many seconds to wait before trying again.
+    Unavailable(String),
+    /// Anything else. Sent as `500 Internal Server Error`.
+    Other(Errors),
+}
+
+/// The body of every error response.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiErrorBody {
+    /// What kind of error this is. One of `not_found`, `bad_request`, `too_many`, `upstream`,
+    /// `upstream_timeout`, `unavailable`, or `internal`.
+    #[schema(example = "not_found")]
+    pub error: String,
+    /// A human readable description of what went wrong.
+    #[schema(example = "No areas found that match `western-cape-stellenbosh`")]
+    pub message: String,
+    /// Areas with names similar to the one asked for, best match first. Only sent when an area
+    /// couldn't be found.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["western-cape-stellenbosch"]))]
+    pub suggestions: Vec<String>,
+}
+
+impl ApiError {
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::NotFound(_) | ApiError::UnknownArea { .. } => Status::NotFound,
+            ApiError::BadRequest(_) => Status::BadRequest,
+            ApiError::TooMany(_) => Status::TooManyRequests,
+            ApiError::Upstream(_) => Status::BadGateway,
+            ApiError::UpstreamTimeout(_) => Status::GatewayTimeout,
+            ApiError::Unavailable(_) => Status::ServiceUnavailable,
+            ApiError::Other(_) => Status::InternalServerError,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) | ApiError::UnknownArea { .. } => "not_found",
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::TooMany(_) => "too_many",
+            ApiError::Upstream(_) => "upstream",
+            ApiError::UpstreamTimeout(_) => "upstream_timeout",
+            ApiError::Unavailable(_) => "unavailable",
+            ApiError::Other(_) => "internal",
+        }
+    }
+
+    fn into_parts(self) -> (String, Vec<String>) {
+        match self {
+            ApiError::UnknownArea {
+                message,
+                suggestions,
+            } => (message, suggestions),
+            ApiError::NotFound(message)
+            | ApiError::BadRequest(message)
+            | ApiError::TooMany(message)
+            | ApiError::Upstream(message)
+            | ApiError::UpstreamTimeout(message)
+            | ApiError::Unavailable(message)
+            | ApiError::Other(Errors::Unspecified(message)) => (message, vec![]),
+        }
+    }
+}
+
+impl From<UpstreamError> for ApiError {
+    fn from(err: UpstreamError) -> Self {
+        match err {
+            UpstreamError::TimedOut(message) => ApiError::UpstreamTimeout(message),
+            UpstreamError::Failed(message) => ApiError::Upstream(message),
+            UpstreamError::WarmingUp(message) => ApiError::Unavailable(message),
+        }
+    }
+}
+
+/// Any request which none of the endpoints matched, so that mistyped API paths get a JSON body
+/// like every other error instead of Rocket's HTML `404 Not Found` page.
+#[catch(404)]
+pub fn not_found(req: &Request) -> ApiError {
+    ApiError::NotFound(format!(
+        "There's no endpoint at `{} {}`. See {} for every endpoint",
+        req.method(),
+        req.uri().path(),
+        crate::DOCS_PATH
+    ))
+}
+
+/// Anything which failed without an `ApiError` of its own, like a response which couldn't be
+/// built. The details are already logged, so the body doesn't give them away.
+#[catch(500)]
+pub fn internal_error() -> ApiError {
+    ApiError::Other(Errors::Unspecified(
+        "Something went wrong while handling the request".to_string(),
+    ))
+}
+
+/// How many seconds clients are asked to wait before trying again, while the API is starting up.
+pub const RETRY_AFTER_SECONDS: u64 = 5;
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status();
+        let kind = self.kind().to_string();
+        let (message, suggestions) = self.into_parts();
+        let body = ApiErrorBody {
+            error: kind,
+            message,
+            suggestions,
+        };
+        tracing::info!(
+            "[{}] Responding with {status}: {}",
+            request_id(req),
+            body.message
+        );
+        let mut response = (status, Json(body)).respond_to(req)?;
+        if status == Status::ServiceUnavailable {
+            response.set_header(Header::new("Retry-After", RETRY_AFTER_SECONDS.to_string()));
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::structs::{ContiguousRegion, Coords};
+
+use rocket::serde::json::Value;
+use rocket::Route;
+
+/// The geographical boundaries of areas, bundled with the API as GeoJSON.
+///
+/// Each feature should be a `Polygon` or `MultiPolygon` with an `area_name` property matching one
+/// of the areas from `list_areas`. Only the outer ring of each polygon is used. While this has no
+/// features, `/area_at` isn't mounted or documented, since it'd say that every point is in no area.
+const BUNDLED_BOUNDARIES: &str = include_str!("../data/area_boundaries.geojson");
+
+/// The name of the handler for `/area_at` in each version of the API.
+const AREA_AT_HANDLER: &str = "area_at";
+
+/// The boundary of one area, which might be made up of several separate regions.
+#[derive(Debug)]
+pub struct AreaBoundary {
+    pub area_name: String,
+    pub regions: Vec<ContiguousRegion>,
+}
+
+/// The boundaries of every area that has known boundaries.
+#[derive(Debug, Default)]
+pub struct AreaBoundaries(pub Vec<AreaBoundary>);
+
+impl AreaBoundaries {
+    /// The boundaries which are bundled with the API.
+    pub fn bundled() -> Self {
+        Self::from_geojson(BUNDLED_BOUNDARIES).expect("Bundled area boundaries should be valid")
+    }
+
+    /// Parse area boundaries from a GeoJSON FeatureCollection.
+    pub fn from_geojson(geojson: &str) -> Result<Self, String> {
+        let geojson: Value =
+            rocket::serde::json::from_str(geojson).map_err(|e| format!("Invalid GeoJSON: {e}"))?;
+        let features = geojson["features"]
+            .as_array()
+            .ok_or("GeoJSON must be a FeatureCollection")?;
+
+        features
+            .iter()
+            .map(|feature| {
+                let area_name = feature["properties"]["area_name"]
+                    .as_str()
+                    .ok_or("Every feature must have an `area_name` property")?
+                    .to_string();
+                let geometry = &feature["geometry"];
+                let polygons = match geometry["type"].as_str() {
+                    Some("Polygon") => vec![&geometry["coordinates"]],
+                    Some("MultiPolygon") => geometry["coordinates"]
+                        .as_array()
+                        .ok_or(format!("Invalid MultiPolygon for {area_name}"))?
+                        .iter()
+                        .collect(),
+                    other => return Err(format!("Unsupported geometry {other:?} for {area_name}")),
+                };
+                let regions = polygons
+                    .into_iter()
+                    .map(parse_outer_ring)
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(format!("Invalid polygon coordinates for {area_name}"))?;
+                Ok(AreaBoundary { area_name, regions })
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map(AreaBoundaries)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `routes` without `/area_at` if there aren't any boundaries to look for points in.
+    pub fn usable_routes(&self, routes: Vec<Route>) -> Vec<Route> {
+        routes
+            .into_iter()
+            .filter(|route| !self.is_empty() || route.name.as_deref() != Some(AREA_AT_HANDLER))
+            .collect()
+    }
+
+    /// The names of all areas whose boundary contains `point`, in alphabetical order.
+    pub fn areas_containing(&self, point: &Coords) -> Vec<String> {
+        let mut area_names = self
+            .0
+            .iter()
+            .filter(|boundary| boundary.regions.iter().any(|r| r.contains(point)))
+            .map(|boundary| boundary.area_name.clone())
+            .collect::<Vec<_>>();
+        area_names.sort();
+        area_names.dedup();
+        area_names
+    }
+}
+
+/// Parse a latitude and longitude given by a user, checking that they're actually on Earth.
+pub fn parse_coords(lat: &str, lng: &str) -> Result<Coords, String> {
+    let lat = lat
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|lat| (-90.0..=90.0).contains(lat))
+        .ok_or_else(|| format!("`lat={lat}` must be a latitude between -90 and 90"))?;
+    let lng = lng
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|lng| (-180.0..=180.0).contains(lng))
+        .ok_or_else(|| format!("`lng={lng}` must be a longitude between -180 and 180"))?;
+    Ok(Coords { lat, lng })
+}
+
+/// Parse the outer ring of a GeoJSON polygon. Note that GeoJSON positions are `[lng, lat]`.
+fn parse_outer_ring(polygon: &Value) -> Option<ContiguousRegion> {
+    let boundary = polygon
+        .get(0)?
+        .as_array()?
+        .iter()
+        .map(|position| {
+            Some(Coords {
+                lng: position.get(0)?.as_f64()?,
+                lat: position.get(1)?.as_f64()?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(ContiguousRegion { boundary })
+}
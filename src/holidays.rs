@@ -0,0 +1,61 @@
+use crate::structs::DayType;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use rocket::serde::json::Value;
+use std::collections::BTreeMap;
+
+/// South Africa's public holidays, bundled with the API as JSON.
+///
+/// The JSON is an object keyed by date (like `2024-04-27`), where each value is the name of the
+/// holiday. When a holiday falls on a Sunday the Monday after it is also a public holiday, so
+/// those Mondays are listed too.
+const BUNDLED_HOLIDAYS: &str = include_str!("../data/public_holidays.json");
+
+/// Every known public holiday, by date.
+#[derive(Debug, Default)]
+pub struct PublicHolidays(BTreeMap<NaiveDate, String>);
+
+impl PublicHolidays {
+    /// The public holidays which are bundled with the API.
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_HOLIDAYS).expect("Bundled public holidays should be valid")
+    }
+
+    /// Parse public holidays from a JSON object of dates to holiday names.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let json: Value = rocket::serde::json::from_str(json)
+            .map_err(|e| format!("Invalid public holidays: {e}"))?;
+        let holidays = json
+            .as_object()
+            .ok_or("Public holidays must be an object keyed by date")?;
+
+        holidays
+            .iter()
+            .map(|(date, name)| {
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_err| format!("`{date}` must be a date like 2024-04-27"))?;
+                let name = name.as_str().ok_or(format!(
+                    "The name of the holiday on {date} must be a string"
+                ))?;
+                Ok((date, name.to_string()))
+            })
+            .collect::<Result<BTreeMap<_, _>, String>>()
+            .map(PublicHolidays)
+    }
+
+    /// The name of the public holiday on `date`, if there is one.
+    pub fn on(&self, date: NaiveDate) -> Option<&str> {
+        self.0.get(&date).map(String::as_str)
+    }
+
+    /// What kind of day `date` is. Public holidays take precedence over weekends.
+    pub fn day_type(&self, date: NaiveDate) -> DayType {
+        if self.0.contains_key(&date) {
+            DayType::PublicHoliday
+        } else if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            DayType::Weekend
+        } else {
+            DayType::Weekday
+        }
+    }
+}
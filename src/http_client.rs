@@ -0,0 +1,106 @@
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::redirect;
+use std::cell::Cell;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long connecting to anything can take. Each request sets how long the whole request can
+/// take on top of this.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+thread_local! {
+    /// How many times `HttpClients` have been built on this thread, which should be once for each
+    /// rocket that's built.
+    static CLIENTS_BUILT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The HTTP clients which every request to GitHub and to webhooks goes through. These are built
+/// once along with the rocket and kept in its managed state, and everything which makes requests
+/// is given a handle to them, so that connections (and their TLS handshakes) get reused instead
+/// of being made from scratch for every request.
+#[derive(Clone)]
+pub struct HttpClients {
+    /// Used for fetching eskom-calendar's data from GitHub.
+    pub github: reqwest::Client,
+    /// Used for sending webhooks. Since anyone can choose where webhooks are sent, this client
+    /// doesn't follow redirects, and won't connect to hosts which resolve to addresses inside our
+    /// own network, like `localhost` or the cloud provider's metadata service.
+    pub webhooks: reqwest::Client,
+}
+
+impl HttpClients {
+    pub fn new() -> Self {
+        CLIENTS_BUILT.with(|built| built.set(built.get() + 1));
+        HttpClients {
+            github: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .expect("The reqwest client's TLS backend couldn't be initialised"),
+            webhooks: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .redirect(redirect::Policy::none())
+                .dns_resolver(Arc::new(PublicAddressesOnly))
+                .build()
+                .expect("The reqwest client's TLS backend couldn't be initialised"),
+        }
+    }
+}
+
+/// Whether `ip` is somewhere on the public internet, rather than this machine, a private network,
+/// or an address which can't be connected to.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // 100.64.0.0/10, which carriers use for their own private networks
+                || (ip.octets()[0] == 100 && ip.octets()[1] & 0b1100_0000 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Resolves hostnames like the system does, but drops every address which isn't public. This is
+/// checked again when connecting, and not just when a webhook is subscribed, so that a hostname
+/// can't be pointed somewhere private after it's been accepted.
+struct PublicAddressesOnly;
+
+impl Resolve for PublicAddressesOnly {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs = tokio::task::spawn_blocking(move || (host.as_str(), 0).to_socket_addrs())
+                .await??
+                .filter(|addr| is_public(addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err(
+                    format!("{} doesn't resolve to a public address", name.as_str()).into(),
+                );
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// How many times `HttpClients` have been built on this thread so far.
+#[cfg(test)]
+pub fn clients_built() -> usize {
+    CLIENTS_BUILT.with(Cell::get)
+}
@@ -0,0 +1,197 @@
+use crate::structs::{PowerOutage, Recurrence, RecurringOutage};
+use crate::versions::sast;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use uuid::Uuid;
+
+/// The IANA name of South Africa's timezone, which recurring events are given in so that calendar
+/// apps work out which day they're on in South African time.
+const SAST_TZID: &str = "Africa/Johannesburg";
+
+/// Describes South African Standard Time, which is +02:00 all year round, for calendar apps which
+/// don't know about `Africa/Johannesburg`.
+pub fn sast_vtimezone() -> String {
+    [
+        "BEGIN:VTIMEZONE".to_string(),
+        format!("TZID:{SAST_TZID}"),
+        "BEGIN:STANDARD".to_string(),
+        "DTSTART:19700101T000000".to_string(),
+        "TZOFFSETFROM:+0200".to_string(),
+        "TZOFFSETTO:+0200".to_string(),
+        "TZNAME:SAST".to_string(),
+        "END:STANDARD".to_string(),
+        "END:VTIMEZONE".to_string(),
+    ]
+    .join("\r\n")
+}
+
+/// An iCalendar file, served with the `text/calendar` content type so that calendar apps know what
+/// to do with it.
+#[derive(Responder)]
+#[response(content_type = "text/calendar")]
+pub struct Ics(pub String);
+
+/// Build a whole calendar called `name` out of the given components. These are usually events
+/// created by [`vevent`] or [`recurring_vevent`], and calendars with recurring events also need
+/// [`sast_vtimezone`].
+pub fn vcalendar(name: &str, components: &[String]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//eskom-calendar//eskom-calendar-api//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    lines.extend(components.iter().cloned());
+    lines.push("END:VCALENDAR".to_string());
+    lines.join("\r\n") + "\r\n"
+}
+
+/// Describe one power outage as a VEVENT with the given summary. `dtstamp` should be the time the
+/// calendar was generated.
+pub fn vevent(outage: &PowerOutage, summary: &str, dtstamp: DateTime<FixedOffset>) -> String {
+    [
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@eskomcalendar.co.za", uid(outage)),
+        format!("DTSTAMP:{}", utc_datetime(dtstamp)),
+        format!("DTSTART:{}", utc_datetime(outage.start)),
+        format!("DTEND:{}", utc_datetime(outage.finsh)),
+        format!("SUMMARY:{}", escape_text(summary)),
+        format!(
+            "DESCRIPTION:{}",
+            escape_text(&format!("Source: {}", outage.source))
+        ),
+        "TRANSP:OPAQUE".to_string(),
+        "END:VEVENT".to_string(),
+    ]
+    .iter()
+    .map(|line| fold(line))
+    .collect::<Vec<_>>()
+    .join("\r\n")
+}
+
+/// Describe one of a schedule's recurring outages as a VEVENT which repeats with an `RRULE`, with
+/// the given summary. The event first happens on `first`, which the outage must occur on, and
+/// stops repeating after `until` (in SAST) if that's given. `dtstamp` should be the time the
+/// calendar was generated.
+pub fn recurring_vevent(
+    area_name: &str,
+    outage: &RecurringOutage,
+    first: NaiveDate,
+    until: Option<NaiveDateTime>,
+    summary: &str,
+    dtstamp: DateTime<FixedOffset>,
+) -> String {
+    // Outages which finish at or before they start go over midnight
+    let finsh_date = if outage.finsh_time <= outage.start_time {
+        first + Duration::days(1)
+    } else {
+        first
+    };
+    let mut rrule = rrule(&outage.recurrence, outage.day1_of_recurrence);
+    if let Some(until) = until {
+        if let Some(until) = sast().from_local_datetime(&until).single() {
+            rrule.push_str(&format!(";UNTIL={}", utc_datetime(until)));
+        }
+    }
+    [
+        "BEGIN:VEVENT".to_string(),
+        format!(
+            "UID:{}@eskomcalendar.co.za",
+            recurring_uid(area_name, outage)
+        ),
+        format!("DTSTAMP:{}", utc_datetime(dtstamp)),
+        format!(
+            "DTSTART;TZID={SAST_TZID}:{}",
+            local_datetime(first.and_time(outage.start_time))
+        ),
+        format!(
+            "DTEND;TZID={SAST_TZID}:{}",
+            local_datetime(finsh_date.and_time(outage.finsh_time))
+        ),
+        format!("RRULE:{rrule}"),
+        format!("SUMMARY:{}", escape_text(summary)),
+        "TRANSP:OPAQUE".to_string(),
+        "END:VEVENT".to_string(),
+    ]
+    .iter()
+    .map(|line| fold(line))
+    .collect::<Vec<_>>()
+    .join("\r\n")
+}
+
+/// The `RRULE` value for an outage which repeats with `recurrence` on day `day1` of it, like
+/// `FREQ=MONTHLY;BYMONTHDAY=4`. Periodic outages repeat every `period_days` days from the event's
+/// first date.
+fn rrule(recurrence: &Recurrence, day1: u8) -> String {
+    const WEEKDAYS: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+    match recurrence {
+        Recurrence::Weekly => {
+            let weekday = WEEKDAYS[usize::from(day1.clamp(1, 7) - 1)];
+            format!("FREQ=WEEKLY;BYDAY={weekday}")
+        }
+        Recurrence::Monthly => format!("FREQ=MONTHLY;BYMONTHDAY={day1}"),
+        Recurrence::Periodic { period_days, .. } => {
+            format!("FREQ=DAILY;INTERVAL={period_days}")
+        }
+    }
+}
+
+/// Like [`uid`], but for a recurring outage in `area_name`'s schedule.
+fn recurring_uid(area_name: &str, outage: &RecurringOutage) -> Uuid {
+    let key = format!(
+        "{area_name}/{:?}/{}/{}/{}",
+        outage.recurrence, outage.day1_of_recurrence, outage.start_time, outage.stage
+    );
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+}
+
+/// A UID which is the same every time the same outage is converted, so that calendar apps don't
+/// show duplicates when they refresh the calendar.
+fn uid(outage: &PowerOutage) -> Uuid {
+    let key = format!(
+        "{}/{}/{}",
+        outage.area_name,
+        outage.start.to_rfc3339(),
+        outage.stage
+    );
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+}
+
+/// Format a datetime in UTC, like `20230601T160000Z`.
+pub fn utc_datetime<Tz: TimeZone>(datetime: DateTime<Tz>) -> String {
+    datetime
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Format a datetime without a timezone, like `20230601T180000`. This is used along with a `TZID`.
+fn local_datetime(datetime: NaiveDateTime) -> String {
+    datetime.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Escape the characters which have special meanings in iCalendar text values.
+pub fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// iCalendar lines should be at most 75 bytes long, and longer lines get "folded" onto following
+/// lines which start with a space.
+pub fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_len = 0;
+    for c in line.chars() {
+        if line_len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_len = 1;
+        }
+        folded.push(c);
+        line_len += c.len_utf8();
+    }
+    folded
+}
@@ -0,0 +1,58 @@
+use rocket::request::{FromRequest, Outcome, Request};
+
+/// The language that area names are in, unless the client prefers one of their localised names.
+pub const CANONICAL_LANGUAGE: &str = "en";
+
+/// The languages a client would like responses in, from their `Accept-Language` header, most
+/// preferred first. Only the primary language is kept (so `af-ZA` is `af`), and languages the
+/// client has said they don't want (with `q=0`) are left out.
+///
+/// Requests without the header get an empty list, which means area names are given as they are.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AcceptLanguage(pub Vec<String>);
+
+impl AcceptLanguage {
+    /// Parse an `Accept-Language` header like `af-ZA, af;q=0.9, en;q=0.8`. Anything that can't be
+    /// understood is ignored.
+    pub fn parse(header: &str) -> Self {
+        let mut weighted = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let language = parts.next()?.split('-').next()?.to_lowercase();
+                let weight = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                let is_language =
+                    !language.is_empty() && language.chars().all(|c| c.is_ascii_alphabetic());
+                (is_language && weight > 0.0).then_some((language, weight))
+            })
+            .collect::<Vec<_>>();
+        // Sorting is stable, so languages with the same weight stay in the order they were given
+        weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut languages: Vec<String> = vec![];
+        for (language, _weight) in weighted {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        AcceptLanguage(languages)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptLanguage {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // The header can be split over several lines, which are the same as one comma separated
+        // line
+        let header = req
+            .headers()
+            .get("Accept-Language")
+            .collect::<Vec<_>>()
+            .join(",");
+        Outcome::Success(AcceptLanguage::parse(&header))
+    }
+}
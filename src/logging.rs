@@ -0,0 +1,49 @@
+use tracing::Subscriber;
+use tracing_subscriber::{fmt::MakeWriter, EnvFilter};
+
+/// Which logs are written, unless configured otherwise via the `RUST_LOG` environment variable.
+const DEFAULT_LOG_FILTER: &str = "info";
+
+/// How logs are written, set with the `LOG_FORMAT` environment variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human readable lines of text. This is the default.
+    #[default]
+    Text,
+    /// One JSON object per line, so that the logs can be searched and aggregated. Each request's
+    /// ID, endpoint, area, and duration are separate fields.
+    Json,
+}
+
+impl LogFormat {
+    /// The format called `name` (`text` or `json`, ignoring case). Anything else is text.
+    pub fn from_name(name: &str) -> Self {
+        if name.trim().eq_ignore_ascii_case("json") {
+            LogFormat::Json
+        } else {
+            LogFormat::Text
+        }
+    }
+}
+
+/// A subscriber which writes logs in `format` to `writer`, filtered by `RUST_LOG`.
+pub fn subscriber<W>(format: LogFormat, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_err| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(builder.json().flatten_event(true).finish()),
+    }
+}
+
+/// Write all of the API's logs to stdout in `format`. Shuttle collects anything written to
+/// stdout. This does nothing if logging has already been set up.
+pub fn init(format: LogFormat) {
+    let _ = tracing::subscriber::set_global_default(subscriber(format, std::io::stdout));
+}
@@ -5,17 +5,61 @@ extern crate rocket;
 #[cfg(test)]
 mod tests;
 
-use rocket::{
-    fairing::{Fairing, Info, Kind},
-    http::Header,
-    Build, Request, Response, Rocket,
-};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::Redirect;
+use rocket::{Build, Request, Rocket};
+use std::convert::Infallible;
+use std::time::Duration;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 mod versions;
 
+mod aliases;
+use aliases::AreaAliases;
+mod area_schedules;
+use area_schedules::AreaSchedules;
+mod cache;
+mod client_ip;
+use cache::{
+    CacheWarmer, DataSources, GitHubFetcher, LongPollRefresher, OutageCache, RetryPolicy,
+    StaleDataHeaders,
+};
+use client_ip::ProxyPolicy;
+mod compression;
+mod cors;
+use compression::Compressor;
+use cors::{Cors, CorsPolicy};
+mod email;
+use email::EmailPolicy;
+mod error;
+mod geo;
+use geo::AreaBoundaries;
+mod holidays;
+use holidays::PublicHolidays;
+mod http_client;
+use http_client::HttpClients;
+mod ics;
+mod language;
+mod logging;
+use logging::LogFormat;
+mod metrics;
+use metrics::{Metrics, RequestTimer};
+mod path_guard;
+mod rate_limit;
+use rate_limit::RateLimiter;
+mod request_id;
+use request_id::RequestIds;
+mod response_cap;
+use response_cap::OutageCap;
+mod stages;
+
 use versions::*;
 mod structs;
+mod typescript;
+use typescript::TypeScriptTypes;
+mod webhooks;
+use webhooks::{StageChangeWatcher, Webhooks};
 
 /// Catches all OPTION requests in order to get the CORS related Fairing triggered.
 /// https://stackoverflow.com/a/72702246/14555505
@@ -23,40 +67,134 @@ mod structs;
 #[allow(dead_code)]
 fn all_options() {}
 
-/// An empty struct to trigger CORS
-/// https://stackoverflow.com/a/72702246/14555505
-pub struct Cors;
+/// Where the Swagger UI is served.
+const SWAGGER_UI_PATH: &str = "/docs/<_..>";
+
+/// The Swagger UI's home page. The trailing slash matters, since the page loads its scripts and
+/// stylesheets relative to itself.
+const DOCS_PATH: &str = "/docs/";
+
+/// The API's home page is its documentation.
+#[get("/")]
+fn index() -> Redirect {
+    Redirect::to(DOCS_PATH)
+}
+
+/// `/docs` without the trailing slash would load the Swagger UI's files from the wrong place.
+#[get("/docs")]
+fn docs(_slashless: NoTrailingSlash) -> Redirect {
+    Redirect::to(DOCS_PATH)
+}
+
+/// Forwards requests whose path ends with a slash. Rocket matches `/docs/` to the `/docs` route,
+/// which would otherwise redirect the Swagger UI's home page to itself.
+struct NoTrailingSlash;
 
-/// Implement Fairing so that CORS issues don't come up
-/// https://stackoverflow.com/a/72702246/14555505
 #[rocket::async_trait]
-impl Fairing for Cors {
-    fn info(&self) -> Info {
-        Info {
-            name: "Cross-Origin-Resource-Sharing Fairing",
-            kind: Kind::Response,
-        }
-    }
+impl<'r> FromRequest<'r> for NoTrailingSlash {
+    type Error = Infallible;
 
-    async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
-        response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
-        response.set_header(Header::new(
-            "Access-Control-Allow-Methods",
-            "POST, PATCH, PUT, DELETE, HEAD, OPTIONS, GET",
-        ));
-        response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
-        response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if req.uri().path().ends_with('/') {
+            Outcome::Forward(Status::NotFound)
+        } else {
+            Outcome::Success(NoTrailingSlash)
+        }
     }
 }
 
+/// Where the OpenAPI spec is served, as JSON.
+const OPENAPI_JSON_PATH: &str = "/api-docs/openapi.json";
+
 #[shuttle_runtime::main]
 async fn rocket() -> shuttle_rocket::ShuttleRocket {
+    let log_format = std::env::var("LOG_FORMAT")
+        .map(|format| LogFormat::from_name(&format))
+        .unwrap_or_default();
+    logging::init(log_format);
     Ok(build_rocket().into())
 }
 
 /// This is separated from the shuttle_runtime::main function so that it can be tested without the
 /// shuttle infrastructure.
 fn build_rocket() -> Rocket<Build> {
+    let ttl = std::env::var("CACHE_TTL_SECONDS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(cache::DEFAULT_TTL);
+    let requests_per_minute = std::env::var("RATE_LIMIT_PER_MINUTE")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(rate_limit::DEFAULT_REQUESTS_PER_MINUTE);
+    let fetch_attempts = std::env::var("FETCH_ATTEMPTS")
+        .ok()
+        .and_then(|attempts| attempts.parse().ok())
+        .unwrap_or(cache::DEFAULT_FETCH_ATTEMPTS);
+    let fetch_timeout = std::env::var("FETCH_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(cache::DEFAULT_FETCH_TIMEOUT);
+    let max_outages = std::env::var("MAX_OUTAGES_PER_RESPONSE")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .filter(|max| *max > 0)
+        .unwrap_or(response_cap::DEFAULT_MAX_OUTAGES);
+    // Any website can call the API unless some are listed
+    let cors_policy = std::env::var("CORS_ALLOWED_ORIGINS")
+        .map(|origins| CorsPolicy::from_list(&origins))
+        .unwrap_or_default();
+    let trusted_proxies = std::env::var("TRUSTED_PROXIES")
+        .ok()
+        .and_then(|proxies| proxies.parse().ok())
+        .unwrap_or(client_ip::DEFAULT_TRUSTED_PROXIES);
+    // Any value other than `true` or `1` means emails are optional
+    let require_email =
+        std::env::var("REQUIRE_EMAIL").is_ok_and(|flag| flag == "true" || flag == "1");
+    // Point these at a fork of eskom-calendar (or a local server) to use its data instead
+    let sources = DataSources {
+        machine_friendly_url: std::env::var("MACHINE_FRIENDLY_URL")
+            .unwrap_or_else(|_| cache::DEFAULT_MACHINE_FRIENDLY_URL.to_string()),
+        schedules_url: std::env::var("SCHEDULES_URL")
+            .unwrap_or_else(|_| cache::DEFAULT_SCHEDULES_URL.to_string()),
+    };
+    let clients = HttpClients::new();
+    let fetcher = GitHubFetcher::new(clients.github.clone(), fetch_timeout);
+    let cache = OutageCache::new(fetcher, ttl)
+        .with_retry(RetryPolicy {
+            attempts: fetch_attempts,
+            delay: cache::DEFAULT_RETRY_DELAY,
+        })
+        .with_sources(sources);
+    build_rocket_with_clients(cache, clients)
+        .attach(RateLimiter::per_minute(requests_per_minute))
+        .attach(CacheWarmer)
+        .attach(LongPollRefresher)
+        .attach(StageChangeWatcher)
+        .manage(EmailPolicy {
+            required: require_email,
+        })
+        .manage(ProxyPolicy { trusted_proxies })
+        .manage(OutageCap { max_outages })
+        .manage(cors_policy)
+}
+
+/// Build the rocket with a specific cache, so that the tests can control where the data comes
+/// from. This doesn't attach the rate limiter, warm up or refresh the cache in the background,
+/// require emails, trust any proxies, or limit which websites can call the API, so that the tests
+/// don't have to worry about them.
+#[cfg(test)]
+fn build_rocket_with(cache: OutageCache) -> Rocket<Build> {
+    // Building the clients loads the system's root certificates, which is slow enough to double
+    // how long the tests take, so every test's rocket shares the same clients
+    static CLIENTS: std::sync::OnceLock<HttpClients> = std::sync::OnceLock::new();
+    build_rocket_with_clients(cache, CLIENTS.get_or_init(HttpClients::new).clone())
+}
+
+/// Build the rocket with a specific cache, and the HTTP clients which the cache's fetcher was
+/// given. Everything else which makes requests shares the same clients.
+fn build_rocket_with_clients(cache: OutageCache, clients: HttpClients) -> Rocket<Build> {
     #[derive(OpenApi)]
     #[openapi(
         servers(
@@ -130,40 +268,152 @@ fn build_rocket() -> Rocket<Build> {
         ),
         paths(
             latest::list_all_areas,
+            latest::area,
+            latest::area_at,
+            latest::area_by_id,
+            latest::area_siblings,
+            latest::areas_in_municipality,
+            latest::areas_in_province,
+            latest::areas_search,
+            latest::areas_tree,
             latest::outages,
+            latest::outages_all,
+            latest::outages_daily,
+            latest::outages_diff,
+            latest::outages_for_areas,
+            latest::outages_gaps,
+            latest::outages_head,
+            latest::outages_ics,
+            latest::machine_friendly_csv,
+            latest::outages_longpoll,
+            latest::outages_today,
+            latest::outages_tomorrow,
+            latest::outlook,
+            latest::province_outages_ics,
+            latest::provinces,
+            latest::ratelimit,
+            latest::current,
+            latest::current_for_areas,
+            latest::national_stage,
+            latest::national_stats,
+            latest::next,
             latest::fuzzy_search,
             latest::schedules,
+            latest::schedules_compare,
+            latest::schedules_expand,
+            latest::schedules_ics,
+            latest::schedules_raw_csv,
+            latest::schedules_validate,
+            latest::search,
+            latest::stage_reference,
+            latest::stats,
             latest::list_areas,
+            latest::health,
             latest::health_check,
+            latest::cache_status,
+            latest::metrics,
+            latest::municipalities,
+            latest::subscribe,
+            latest::unsubscribe,
+            latest::typescript_client,
+            latest::version,
         ),
         components(schemas(
+            error::ApiErrorBody,
+            stages::StageInfo,
+            structs::AllOutages,
             structs::Area,
             structs::AreaId,
+            structs::AreaStats,
+            structs::AreasCurrentStatus,
+            structs::AreasOutages,
+            structs::AreasPage,
+            structs::AreasRequest,
+            structs::CacheStatus,
+            structs::ChangedOutage,
             structs::ContiguousRegion,
             structs::Coords,
+            structs::CurrentStatus,
+            structs::DailySummary,
+            structs::DayHours,
+            structs::DayType,
             structs::DistrictMunic,
+            structs::ExpandedOutage,
+            structs::Health,
             structs::HealthCheck,
             structs::HealthStatus,
             structs::LocalMunic,
             structs::MetroMunic,
             structs::Municipality,
+            structs::MunicipalityInfo,
+            structs::MunicipalityKind,
+            structs::NationalStage,
+            structs::NationalStats,
+            structs::NextOutage,
+            structs::OutageDiff,
+            structs::OutagesPage,
+            structs::OutlookSegment,
+            structs::PowerGap,
             structs::PowerOutage,
             structs::Province,
+            structs::ProvinceInfo,
+            structs::RateLimitStatus,
             structs::Recurrence,
             structs::RecurringOutage,
             structs::RecurringSchedule,
+            structs::ScheduleComparison,
+            structs::ScheduleGap,
             structs::ScheduleId,
+            structs::ScheduleOverlap,
+            structs::ScheduleReport,
+            structs::SearchHighlight,
+            structs::SearchKind,
+            structs::SearchMatch,
+            structs::SearchMatchResult,
             structs::SearchResult<structs::Area>,
+            structs::ServiceStatus,
+            structs::StageChange,
+            structs::StageChangeNotification,
+            structs::StageScenario,
+            structs::SubscribeRequest,
+            structs::Subscription,
+            structs::Version,
         ))
     )]
     struct ApiDoc;
 
+    let boundaries = AreaBoundaries::bundled();
+    let latest_routes = boundaries.usable_routes(latest::routes());
+    let v0_0_1_routes = boundaries.usable_routes(v0_0_1::routes());
+    let mut openapi = ApiDoc::openapi();
+    if boundaries.is_empty() {
+        openapi.paths.paths.remove("/area_at");
+    }
     rocket::build()
+        .manage(cache)
+        .manage(TypeScriptTypes::from_openapi(&openapi))
+        .manage(AreaAliases::bundled())
+        .manage(AreaSchedules::bundled())
+        .manage(boundaries)
+        .manage(PublicHolidays::bundled())
+        .manage(Metrics::default())
+        .manage(Webhooks::new(&clients))
+        .manage(clients)
+        .attach(RequestIds)
+        .attach(RequestTimer)
         .attach(Cors)
-        .mount("/", latest::routes())
-        .mount("/v0.0.1", v0_0_1::routes())
+        .attach(StaleDataHeaders)
+        .attach(Compressor)
+        .register(
+            "/",
+            catchers![email::bad_request, error::not_found, error::internal_error],
+        )
+        .mount("/", path_guard::routes())
+        .mount("/", routes![index, docs])
+        .mount("/", latest_routes)
+        .mount("/v0.0.1", v0_0_1_routes)
         .mount(
             "/",
-            SwaggerUi::new("/<_..>").url("/api-docs/openapi.json", ApiDoc::openapi()),
+            SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_JSON_PATH, openapi),
         )
 }
@@ -0,0 +1,197 @@
+use crate::cache::CacheCounters;
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    Request, Response,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The upper bounds (in seconds) of the buckets in the request latency histogram.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The endpoint label given to requests which didn't match any route.
+const UNMATCHED_ENDPOINT: &str = "unmatched";
+
+/// Prometheus metrics in the text exposition format, served as plain text.
+#[derive(Responder)]
+#[response(content_type = "plain")]
+pub struct PrometheusText(pub String);
+
+/// How long requests to one endpoint took.
+#[derive(Default)]
+struct Histogram {
+    /// How many requests fell into each of `LATENCY_BUCKETS`. These aren't cumulative, they get
+    /// added up when the histogram is rendered.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum_seconds: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|&le| seconds <= le) {
+            self.buckets[i] += 1;
+        }
+        self.sum_seconds += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counts of requests and how long they took, per endpoint. This is managed by rocket so that the
+/// `/metrics` endpoint can read it, and gets filled in by the `RequestTimer` fairing.
+///
+/// Endpoints are labelled by their route (like `/outages/<area_name>`) rather than by the URI
+/// that was requested, so that there's one time series per endpoint instead of one per area.
+#[derive(Default)]
+pub struct Metrics {
+    /// Keyed by (method, endpoint, status code).
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    /// Keyed by (method, endpoint).
+    latencies: Mutex<BTreeMap<(String, String), Histogram>>,
+}
+
+impl Metrics {
+    fn record(&self, method: &str, endpoint: &str, status: u16, seconds: f64) {
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), endpoint.to_string(), status))
+            .or_default() += 1;
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), endpoint.to_string()))
+            .or_default()
+            .observe(seconds);
+    }
+
+    /// Render every metric in the Prometheus text format, along with the cache's counters.
+    pub fn render(&self, cache: CacheCounters) -> String {
+        let mut text = String::new();
+
+        text.push_str("# HELP http_requests_total Requests handled, by endpoint and status.\n");
+        text.push_str("# TYPE http_requests_total counter\n");
+        for ((method, endpoint, status), count) in self.requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                text,
+                "http_requests_total{{method=\"{method}\",endpoint=\"{}\",status=\"{status}\"}} \
+                {count}",
+                escape_label(endpoint)
+            );
+        }
+
+        text.push_str(
+            "# HELP http_request_duration_seconds How long requests took, by endpoint.\n",
+        );
+        text.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for ((method, endpoint), histogram) in self.latencies.lock().unwrap().iter() {
+            let labels = format!(
+                "method=\"{method}\",endpoint=\"{}\"",
+                escape_label(endpoint)
+            );
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    text,
+                    "http_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                text,
+                "http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(
+                text,
+                "http_request_duration_seconds_sum{{{labels}}} {}",
+                histogram.sum_seconds
+            );
+            let _ = writeln!(
+                text,
+                "http_request_duration_seconds_count{{{labels}}} {}",
+                histogram.count
+            );
+        }
+
+        for (name, help, value) in [
+            (
+                "upstream_fetches_total",
+                "Requests made to GitHub, where eskom-calendar's data lives.",
+                cache.upstream_fetches,
+            ),
+            (
+                "cache_hits_total",
+                "Times the cached copy of machine_friendly.csv was fresh enough to use.",
+                cache.hits,
+            ),
+            (
+                "cache_misses_total",
+                "Times the cached copy of machine_friendly.csv was missing or too old.",
+                cache.misses,
+            ),
+        ] {
+            let _ = writeln!(text, "# HELP {name} {help}");
+            let _ = writeln!(text, "# TYPE {name} counter");
+            let _ = writeln!(text, "{name} {value}");
+        }
+        text
+    }
+}
+
+/// Escape a label value as described by the Prometheus text format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// When a request started being handled, kept in the request's local cache.
+struct RequestStart(Instant);
+
+/// Times every request and records it in the managed `Metrics`. Requests to `/metrics` itself
+/// aren't recorded, so that scraping the metrics doesn't change them.
+pub struct RequestTimer;
+
+#[rocket::async_trait]
+impl Fairing for RequestTimer {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request Metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut rocket::Data<'_>) {
+        request.local_cache(|| RequestStart(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(metrics) = request.rocket().state::<Metrics>() else {
+            return;
+        };
+        if request
+            .route()
+            .is_some_and(|route| route.name.as_deref() == Some("metrics"))
+        {
+            return;
+        }
+        let started = request.local_cache(|| RequestStart(Instant::now()));
+        let endpoint = request
+            .route()
+            .map_or(UNMATCHED_ENDPOINT, |route| route.uri.path());
+        metrics.record(
+            request.method().as_str(),
+            endpoint,
+            response.status().code,
+            started.0.elapsed().as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::error::ApiError;
+
+use rocket::http::{uri::fmt::Path, uri::Segments, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::Route;
+
+/// How many characters each part of a URL's path can have. Area names are much shorter than this,
+/// so anything longer is a mistake or someone trying to make fuzzy matching or regex compilation
+/// use up lots of CPU.
+pub const MAX_SEGMENT_LEN: usize = 256;
+
+/// The ranks Rocket gives routes go down to -12, so this makes sure paths are checked before any
+/// other route gets them.
+const RANK: isize = -20;
+
+/// Why a request's path was rejected. Paths which are fine are forwarded on to the actual routes,
+/// so this only succeeds for paths with a part that's too long or has control characters in it.
+struct InvalidPath(String);
+
+/// Check one part of a path, like an area's name, describing what's wrong with it if anything.
+fn check_segment(segment: &str) -> Result<(), String> {
+    let length = segment.chars().count();
+    if length > MAX_SEGMENT_LEN {
+        return Err(format!(
+            "Each part of the path can be at most {MAX_SEGMENT_LEN} characters long, but one is \
+            {length} characters long"
+        ));
+    }
+    if segment.chars().any(char::is_control) {
+        return Err(format!(
+            "The path can't contain control characters, but `{}` does",
+            segment.escape_default()
+        ));
+    }
+    Ok(())
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for InvalidPath {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.uri().path().segments().try_for_each(check_segment) {
+            Ok(()) => Outcome::Forward(Status::NotFound),
+            Err(reason) => Outcome::Success(InvalidPath(reason)),
+        }
+    }
+}
+
+/// The routes which check every request's path, to be mounted at `/`.
+pub fn routes() -> Vec<Route> {
+    routes![reject_invalid_get, reject_invalid_head]
+        .into_iter()
+        .map(|mut route| {
+            // Rocket's attributes only take positive ranks
+            route.rank = RANK;
+            route
+        })
+        .collect()
+}
+
+/// Reject requests whose path is too long or has control characters in it, before they get to
+/// any of the endpoints.
+#[get("/<_path..>")]
+fn reject_invalid_get(_path: Segments<'_, Path>, invalid: InvalidPath) -> ApiError {
+    ApiError::BadRequest(invalid.0)
+}
+
+/// The same as `reject_invalid_get`, but for HEAD requests, since some endpoints have their own
+/// HEAD routes.
+#[head("/<_path..>")]
+fn reject_invalid_head(_path: Segments<'_, Path>, invalid: InvalidPath) -> ApiError {
+    ApiError::BadRequest(invalid.0)
+}
@@ -0,0 +1,291 @@
+use crate::client_ip::client_ip;
+use crate::error::ApiError;
+use crate::structs::RateLimitStatus;
+use crate::versions::sast_now;
+use crate::{DOCS_PATH, OPENAPI_JSON_PATH};
+
+use chrono::Duration;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{Header, Method, Status},
+    request::{FromRequest, Outcome},
+    response::{self, Responder},
+    Build, Data, Request, Response, Rocket,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// How many requests each IP address can make per minute, unless configured otherwise via the
+/// `RATE_LIMIT_PER_MINUTE` environment variable.
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 60;
+
+/// Once this many IP addresses are being tracked, the ones with full buckets get forgotten so
+/// that the rate limiter doesn't slowly use up all the memory.
+const MAX_TRACKED_IPS: usize = 10_000;
+
+/// The endpoint which tells clients how many requests they have left. Asking doesn't use up a
+/// request, so that clients can check as often as they like.
+const RATE_LIMIT_STATUS_PATH: &str = "/ratelimit";
+
+/// The tokens that one IP address has left. Every request takes one token, and tokens trickle
+/// back in over time up to a maximum of one minute's worth of requests.
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Limits how many requests each IP address can make, so that one heavy user doesn't make the
+/// free API unusable for everyone else.
+///
+/// Every response has `X-RateLimit-Limit`, `X-RateLimit-Remaining`, and `X-RateLimit-Reset` (the
+/// Unix time when all of the requests will be available again) headers, so that clients can slow
+/// themselves down. Requests over the limit get `429 Too Many Requests` with a `Retry-After`
+/// header saying how many seconds to wait. The Swagger UI, the OpenAPI JSON, and `/ratelimit`
+/// aren't rate limited.
+///
+/// Requests are checked before they're routed, and requests over the limit are sent to an
+/// internal route which answers with the 429. That way they don't do any work (or, for requests
+/// like `POST /subscribe`, change anything) before being told to slow down.
+///
+/// The rate limiter manages a copy of itself and mounts the internal route when the rocket
+/// ignites, so that `/ratelimit` can look at the buckets.
+#[derive(Clone)]
+pub struct RateLimiter {
+    requests_per_minute: u32,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests_per_minute: u32) -> Self {
+        RateLimiter {
+            requests_per_minute,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn capacity(&self) -> f64 {
+        f64::from(self.requests_per_minute)
+    }
+
+    fn tokens_per_sec(&self) -> f64 {
+        self.capacity() / 60.0
+    }
+
+    /// Take one token from `ip`'s bucket, and describe what's left. If the bucket is empty,
+    /// returns the number of seconds until there'll be a token available.
+    fn take(&self, ip: IpAddr) -> Result<RateLimitStatus, u64> {
+        let capacity = self.capacity();
+        let tokens_per_sec = self.tokens_per_sec();
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_IPS {
+            buckets.retain(|_ip, bucket| {
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens + elapsed * tokens_per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(self.status(bucket.tokens))
+        } else if tokens_per_sec == 0.0 {
+            Err(60)
+        } else {
+            Err(((1.0 - bucket.tokens) / tokens_per_sec).ceil() as u64)
+        }
+    }
+
+    /// Describe how many requests `ip` has left, without taking a token.
+    pub fn peek(&self, ip: IpAddr) -> RateLimitStatus {
+        let buckets = self.buckets.lock().unwrap();
+        let tokens = buckets.get(&ip).map_or(self.capacity(), |bucket| {
+            self.refilled(bucket, Instant::now())
+        });
+        self.status(tokens)
+    }
+
+    /// How many tokens `bucket` has at `now`, after the ones which trickled back in since it was
+    /// last refilled.
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        (bucket.tokens + elapsed * self.tokens_per_sec()).min(self.capacity())
+    }
+
+    /// Describe a bucket with `tokens` left.
+    fn status(&self, tokens: f64) -> RateLimitStatus {
+        let tokens_per_sec = self.tokens_per_sec();
+        let secs_until_full = if tokens_per_sec == 0.0 {
+            60
+        } else {
+            ((self.capacity() - tokens) / tokens_per_sec).ceil() as i64
+        };
+        RateLimitStatus {
+            limit: self.requests_per_minute,
+            remaining: tokens.floor() as u32,
+            reset_at: sast_now() + Duration::seconds(secs_until_full),
+        }
+    }
+}
+
+/// Add the `X-RateLimit-*` headers describing `status` to `response`.
+fn set_rate_limit_headers(response: &mut Response<'_>, status: &RateLimitStatus) {
+    response.set_header(Header::new("X-RateLimit-Limit", status.limit.to_string()));
+    response.set_header(Header::new(
+        "X-RateLimit-Remaining",
+        status.remaining.to_string(),
+    ));
+    response.set_header(Header::new(
+        "X-RateLimit-Reset",
+        status.reset_at.timestamp().to_string(),
+    ));
+}
+
+/// What the rate limiter decided about a request, kept in the request's local cache between
+/// checking it and adding the headers to its response.
+#[derive(Debug, Clone, Copy)]
+enum Decision {
+    /// The request isn't rate limited at all.
+    Exempt,
+    /// The request is asking about its rate limit, which doesn't use up a request.
+    Peek(IpAddr),
+    /// The request took one of `IpAddr`'s tokens.
+    Allowed(IpAddr),
+    /// `IpAddr` is over the limit, and can try again after this many seconds.
+    Limited(IpAddr, u64),
+}
+
+/// Whether requests to `path` are never rate limited.
+fn is_exempt(path: &str) -> bool {
+    path == OPENAPI_JSON_PATH
+        || path == DOCS_PATH.trim_end_matches('/')
+        || path.starts_with(DOCS_PATH)
+}
+
+/// Whether `path` is `/ratelimit`, in any version of the API.
+fn is_status_path(path: &str) -> bool {
+    path.strip_prefix("/v0.0.1").unwrap_or(path) == RATE_LIMIT_STATUS_PATH
+}
+
+/// A `429 Too Many Requests` for a request that was over the limit. It's built from an empty
+/// response so that there's nothing left over from what the request asked for, and it mustn't be
+/// cached, since the client will be allowed to make requests again soon.
+pub struct TooManyRequests {
+    requests_per_minute: u32,
+    retry_after: u64,
+}
+
+/// Only requests which the `RateLimiter` sent to the internal route are over the limit, so anyone
+/// asking for that route themselves is forwarded on to a 404.
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TooManyRequests {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let limiter = request.rocket().state::<RateLimiter>();
+        let decision = request.local_cache(|| Decision::Exempt);
+        match (limiter, decision) {
+            (Some(limiter), Decision::Limited(_ip, retry_after)) => {
+                Outcome::Success(TooManyRequests {
+                    requests_per_minute: limiter.requests_per_minute,
+                    retry_after: *retry_after,
+                })
+            }
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for TooManyRequests {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = ApiError::TooMany(format!(
+            "Too many requests, the limit is {} requests per minute. Please try again in {} \
+            seconds",
+            self.requests_per_minute, self.retry_after
+        ))
+        .respond_to(req)?;
+        response.set_header(Header::new("Retry-After", self.retry_after.to_string()));
+        response.set_header(Header::new("Cache-Control", "no-store"));
+        Ok(response)
+    }
+}
+
+/// Answers the requests which the `RateLimiter` found to be over the limit. They're sent here
+/// instead of to the endpoint they were for, so that the endpoint never runs.
+#[get("/ratelimit/exceeded")]
+fn rate_limited(too_many_requests: TooManyRequests) -> TooManyRequests {
+    too_many_requests
+}
+
+/// The rate limit of whoever made the request. Fails if requests aren't being rate limited, or if
+/// the request has no IP address (since then it isn't rate limited either).
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RateLimitStatus {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let limiter = request.rocket().state::<RateLimiter>();
+        match limiter.zip(client_ip(request)) {
+            Some((limiter, ip)) => Outcome::Success(limiter.peek(ip)),
+            None => Outcome::Error((Status::NotFound, ())),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RateLimiter {
+    fn info(&self) -> Info {
+        Info {
+            name: "Per-IP Rate Limiter",
+            kind: Kind::Ignite | Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> rocket::fairing::Result {
+        Ok(rocket
+            .manage(self.clone())
+            .mount("/", routes![rate_limited]))
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let path = request.uri().path().to_string();
+        // Requests without an IP address can't be told apart, so they aren't limited
+        let decision = match client_ip(request) {
+            _ if is_exempt(&path) => Decision::Exempt,
+            None => Decision::Exempt,
+            Some(ip) if is_status_path(&path) => Decision::Peek(ip),
+            Some(ip) => match self.take(ip) {
+                Ok(_status) => Decision::Allowed(ip),
+                Err(retry_after) => Decision::Limited(ip, retry_after),
+            },
+        };
+        request.local_cache(|| decision);
+
+        if let Decision::Limited(ip, retry_after) = decision {
+            tracing::info!("Rate limiting {ip}, they can retry after {retry_after}s");
+            // HEAD requests are answered by GET routes, and keep their empty body
+            if request.method() != Method::Head {
+                request.set_method(Method::Get);
+            }
+            request.set_uri(uri!(rate_limited));
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        match *request.local_cache(|| Decision::Exempt) {
+            Decision::Exempt => {}
+            Decision::Peek(ip) | Decision::Allowed(ip) | Decision::Limited(ip, _) => {
+                set_rate_limit_headers(response, &self.peek(ip));
+            }
+        }
+    }
+}
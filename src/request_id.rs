@@ -0,0 +1,110 @@
+use crate::client_ip::client_ip;
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Header,
+    Request, Response,
+};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// The header which holds a request's ID, in both the request and the response.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The longest request ID a client can choose, so that IDs can't flood the logs.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// A request's ID and when it was received, kept in the request's local cache.
+struct RequestId {
+    id: String,
+    received: Instant,
+}
+
+/// The ID of `request`. This is the client's `X-Request-Id` if they sent a sensible one, and a
+/// new UUID otherwise.
+pub fn request_id<'r>(request: &'r Request<'_>) -> &'r str {
+    &cached_request_id(request).id
+}
+
+/// The ID of `request` and when it was received, which are worked out the first time they're
+/// needed.
+fn cached_request_id<'r>(request: &'r Request<'_>) -> &'r RequestId {
+    request.local_cache(|| {
+        let id = request
+            .headers()
+            .get_one(REQUEST_ID_HEADER)
+            .map(str::trim)
+            .filter(|id| is_valid_request_id(id))
+            .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+        RequestId {
+            id,
+            received: Instant::now(),
+        }
+    })
+}
+
+/// The area that `request` is about, if its route has an `area_name` or `name` in its path.
+fn area_name<'r>(request: &'r Request<'_>) -> Option<&'r str> {
+    let route = request.route()?;
+    let index = route
+        .uri
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .position(|segment| segment == "<area_name>" || segment == "<name>")?;
+    request.routed_segment(index)
+}
+
+/// Whether a client's request ID can be used as is. It's written to the logs, so it must be short
+/// and only contain visible ASCII characters.
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.chars().all(|c| c.is_ascii_graphic())
+}
+
+/// Gives every request an ID, which is logged along with the request and sent back in the
+/// `X-Request-Id` header. When someone reports a problem, they can quote the ID so that the logs
+/// for their request can be found.
+///
+/// The ID, endpoint, area, and how long the request took are logged as fields, so that they're
+/// kept separate when the logs are written as JSON.
+pub struct RequestIds;
+
+#[rocket::async_trait]
+impl Fairing for RequestIds {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request IDs",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut rocket::Data<'_>) {
+        let id = request_id(request);
+        let method = request.method();
+        let uri = request.uri();
+        match client_ip(request) {
+            Some(ip) => tracing::info!(
+                request_id = id,
+                %method,
+                %uri,
+                client_ip = %ip,
+                "[{id}] {method} {uri} from {ip}"
+            ),
+            None => tracing::info!(request_id = id, %method, %uri, "[{id}] {method} {uri}"),
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let RequestId { id, received } = cached_request_id(request);
+        let status = response.status();
+        tracing::info!(
+            request_id = id,
+            endpoint = request.route().map(|route| route.uri.path()),
+            area = area_name(request),
+            status = status.code,
+            duration_ms = received.elapsed().as_secs_f64() * 1000.0,
+            "[{id}] Responded with {status}"
+        );
+        response.set_header(Header::new(REQUEST_ID_HEADER, id.to_string()));
+    }
+}
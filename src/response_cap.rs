@@ -0,0 +1,111 @@
+use std::convert::Infallible;
+
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder};
+use rocket::Request;
+
+/// The most outages sent in any one response, unless `MAX_OUTAGES_PER_RESPONSE` says otherwise.
+pub const DEFAULT_MAX_OUTAGES: usize = 5000;
+
+/// The most outages sent in any one response, set with the `MAX_OUTAGES_PER_RESPONSE` environment
+/// variable. Responses which would have more outages than this are cut short and marked as
+/// truncated, so that one request can't make the API build an enormous response.
+///
+/// This is also a request guard, which gives the managed cap or the default if there isn't one.
+#[derive(Debug, Clone, Copy)]
+pub struct OutageCap {
+    pub max_outages: usize,
+}
+
+impl Default for OutageCap {
+    fn default() -> Self {
+        OutageCap {
+            max_outages: DEFAULT_MAX_OUTAGES,
+        }
+    }
+}
+
+impl OutageCap {
+    /// Cut `outages` down to the cap, returning whether any were left out.
+    pub fn apply<T>(&self, outages: &mut Vec<T>) -> bool {
+        if outages.len() <= self.max_outages {
+            return false;
+        }
+        tracing::info!(
+            "Only sending {} of {} outages",
+            self.max_outages,
+            outages.len()
+        );
+        outages.truncate(self.max_outages);
+        true
+    }
+
+    /// Cut the outages of several areas down to the cap between them, keeping the outages of the
+    /// earlier areas first. Returns whether any were left out.
+    pub fn apply_to_each<'a, T: 'a>(
+        &self,
+        lists: impl IntoIterator<Item = &'a mut Vec<T>>,
+    ) -> bool {
+        let mut remaining = self.max_outages;
+        let mut truncated = false;
+        for list in lists {
+            truncated |= list.len() > remaining;
+            list.truncate(remaining);
+            remaining -= list.len();
+        }
+        if truncated {
+            tracing::info!(
+                "Only sending {} outages across several areas",
+                self.max_outages
+            );
+        }
+        truncated
+    }
+
+    /// Tells users how to get the outages that were left out of a truncated response.
+    pub fn message(&self) -> String {
+        format!(
+            "Only {} outages were sent, which is the most that one response can have. Use \
+            filters like `start=`, `finsh=`, or `min_stage=` to ask for fewer outages, or page \
+            through the rest",
+            self.max_outages
+        )
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for OutageCap {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(
+            req.rocket()
+                .state::<OutageCap>()
+                .copied()
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Adds the `X-Truncated: true` header to a response if the `OutageCap` left some outages out of
+/// it. This is how formats without anywhere to put a `truncated` flag, like iCalendar, say so.
+pub fn mark_truncated(response: &mut response::Response<'_>, truncated: bool) {
+    if truncated {
+        response.set_header(Header::new("X-Truncated", "true"));
+    }
+}
+
+/// A response which the `OutageCap` might have left some outages out of.
+pub struct Capped<R> {
+    pub inner: R,
+    pub truncated: bool,
+}
+
+impl<'r, R: Responder<'r, 'static>> Responder<'r, 'static> for Capped<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = self.inner.respond_to(req)?;
+        mark_truncated(&mut response, self.truncated);
+        Ok(response)
+    }
+}
@@ -0,0 +1,85 @@
+use rocket::serde::Serialize;
+use utoipa::ToSchema;
+
+/// What being at a certain stage of loadshedding means in practice.
+///
+/// The numbers are approximate: each municipality draws up its own schedules, but most of them
+/// turn an area's power off 3 times over 4 days for each stage, for about 2 hours each time.
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct StageInfo {
+    /// The stage of loadshedding, from 0 (no loadshedding) to 8.
+    #[schema(example = 4)]
+    pub stage: u8,
+
+    /// What the stage means, in plain English.
+    #[schema(example = "Up to 4000MW of load may be shed nationally.")]
+    pub description: &'static str,
+
+    /// Roughly how many times a day an area's power is turned off at this stage.
+    #[schema(example = 3.0)]
+    pub daily_outage_slots: f32,
+
+    /// Roughly how many hours a day an area's power is off at this stage, in total.
+    #[schema(example = 6.0)]
+    pub hours_off_per_day: f32,
+}
+
+/// Every stage of loadshedding, from 0 to 8.
+pub const STAGES: [StageInfo; 9] = [
+    StageInfo {
+        stage: 0,
+        description: "No loadshedding. The power stays on.",
+        daily_outage_slots: 0.0,
+        hours_off_per_day: 0.0,
+    },
+    StageInfo {
+        stage: 1,
+        description: "Up to 1000MW of load may be shed nationally.",
+        daily_outage_slots: 0.75,
+        hours_off_per_day: 1.5,
+    },
+    StageInfo {
+        stage: 2,
+        description: "Up to 2000MW of load may be shed nationally.",
+        daily_outage_slots: 1.5,
+        hours_off_per_day: 3.0,
+    },
+    StageInfo {
+        stage: 3,
+        description: "Up to 3000MW of load may be shed nationally.",
+        daily_outage_slots: 2.25,
+        hours_off_per_day: 4.5,
+    },
+    StageInfo {
+        stage: 4,
+        description: "Up to 4000MW of load may be shed nationally.",
+        daily_outage_slots: 3.0,
+        hours_off_per_day: 6.0,
+    },
+    StageInfo {
+        stage: 5,
+        description: "Up to 5000MW of load may be shed nationally. Some outages may last 4 hours.",
+        daily_outage_slots: 3.75,
+        hours_off_per_day: 7.5,
+    },
+    StageInfo {
+        stage: 6,
+        description: "Up to 6000MW of load may be shed nationally. Some outages may last 4 hours.",
+        daily_outage_slots: 4.5,
+        hours_off_per_day: 9.0,
+    },
+    StageInfo {
+        stage: 7,
+        description: "Up to 7000MW of load may be shed nationally. Some outages may last 4 hours.",
+        daily_outage_slots: 5.25,
+        hours_off_per_day: 10.5,
+    },
+    StageInfo {
+        stage: 8,
+        description: "Up to 8000MW of load may be shed nationally. Expect to be without power \
+            for half of every day.",
+        daily_outage_slots: 6.0,
+        hours_off_per_day: 12.0,
+    },
+];
@@ -1,8 +1,18 @@
-use std::{cmp::Ordering, fmt::Debug};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+};
 
-use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike,
+};
 use rocket::serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::versions::sast;
 
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
@@ -12,10 +22,27 @@ pub enum Errors {
 }
 
 /// The unique ID of a schedule
-#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct ScheduleId(pub i64);
 
+impl ScheduleId {
+    /// The ID of the schedule called `schedule_name`. eskom-calendar names each schedule after the
+    /// area it belongs to, so this is usually an area's name.
+    pub fn from_name(schedule_name: &str) -> Self {
+        ScheduleId(stable_id("schedule", schedule_name))
+    }
+}
+
+/// An ID which is always the same for the same `name`, even across restarts of the API. The
+/// `kind` keeps the IDs of different kinds of things (like areas and schedules) distinct.
+fn stable_id(kind: &str, name: &str) -> i64 {
+    let key = format!("{kind}/{name}");
+    let (high, _low) = Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes()).as_u64_pair();
+    // Keep the ID positive, so it looks a bit friendlier in URLs
+    (high & i64::MAX as u64) as i64
+}
+
 /// Describes the health of the entire system. Right now it always returns healthy and doesn't do
 /// any deep checks, but in future this will be expanded.
 #[derive(Deserialize, Serialize, Debug, ToSchema)]
@@ -41,9 +68,119 @@ pub enum HealthStatus {
     Dead(Option<String>),
 }
 
-/// A loadshedding schedule that repeats over some period.
+/// A quick summary of whether the API can serve data, for uptime monitoring.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct Health {
+    /// `ok` if everything is working, `degraded` if GitHub can't be reached but there's still
+    /// cached data to serve, and `down` if there's no data to serve at all.
+    pub status: ServiceStatus,
+
+    /// Whether GitHub (where eskom-calendar's data lives) could be reached just now.
+    #[schema(example = true)]
+    pub upstream_reachable: bool,
+
+    /// How many seconds ago the cached data was fetched. Will be None if nothing has been fetched
+    /// yet.
+    #[schema(example = 42)]
+    pub cache_age_seconds: Option<u64>,
+
+    /// How many areas are in the cached data.
+    #[schema(example = 1234)]
+    pub area_count: usize,
+}
+
+/// How well the API is working overall.
+#[derive(Deserialize, Serialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum ServiceStatus {
+    Ok,
+    Degraded,
+    Down,
+}
+
+/// How many requests the caller can still make before being rate limited.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct RateLimitStatus {
+    /// How many requests can be made per minute.
+    #[schema(example = 60)]
+    pub limit: u32,
+
+    /// How many requests can be made right now without being rate limited.
+    #[schema(example = 57)]
+    pub remaining: u32,
+
+    /// When all of the requests will be available again, if no more are made before then.
+    #[schema(example = "2023-06-01T20:00:03+02:00")]
+    pub reset_at: DateTime<FixedOffset>,
+}
+
+/// Describes which deployment of the API is running, and how fresh its data is.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct Version {
+    /// The version of the API, as given in its Cargo.toml.
+    #[schema(example = "0.0.1")]
+    pub api_version: String,
+
+    /// The git commit the API was built from, or `unknown` if that couldn't be found out.
+    #[schema(example = "df02ca7a6c1e4b0f8f7d9d1b1f2b9b0f6b8a3c2d")]
+    pub git_sha: String,
+
+    /// When the API was built.
+    #[schema(example = "2023-06-01T18:00:00+02:00")]
+    pub built_at: DateTime<FixedOffset>,
+
+    /// The last time the API checked GitHub for new data. Will be None if the data hasn't been
+    /// fetched yet.
+    #[schema(example = "2023-06-01T20:00:00+02:00")]
+    pub machine_friendly_last_fetched: Option<DateTime<FixedOffset>>,
+
+    /// The ETag that GitHub served machine_friendly.csv with. This changes whenever the data
+    /// changes. Will be None if the data hasn't been fetched yet.
+    #[schema(example = "\"0x8DB6A2F6C5A1B2C\"")]
+    pub machine_friendly_etag: Option<String>,
+}
+
+/// Describes how fresh the API's copy of eskom-calendar's data is.
 #[derive(Deserialize, Serialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
+pub struct CacheStatus {
+    /// The ETag that GitHub served machine_friendly.csv with. This changes whenever the data
+    /// changes. Will be None if the data hasn't been fetched yet.
+    #[schema(example = "\"0x8DB6A2F6C5A1B2C\"")]
+    pub etag: Option<String>,
+
+    /// True if the data is older than it should be, because GitHub couldn't be reached when the
+    /// data was due to be refreshed. The API keeps serving the old data until GitHub is back.
+    #[schema(example = false)]
+    pub stale: bool,
+
+    /// How many seconds ago the data was fetched. Will be None if the data hasn't been fetched
+    /// yet.
+    #[schema(example = 42)]
+    pub cache_age_seconds: Option<u64>,
+
+    /// The last time the API checked GitHub for new data. Will be None if the data hasn't been
+    /// fetched yet.
+    #[schema(example = "2023-06-01T20:00:00+02:00")]
+    pub last_fetched: Option<DateTime<FixedOffset>>,
+
+    /// How many seconds the data is kept before GitHub is checked for new data.
+    #[schema(example = 300)]
+    pub ttl_seconds: u64,
+
+    /// Rows of machine_friendly.csv which couldn't be understood, and so were left out of the
+    /// outages. Only sent if there were any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["Skipped line 12: invalid digit found in string"]))]
+    pub warnings: Vec<String>,
+}
+
+/// A loadshedding schedule that repeats over some period.
+#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
 pub struct RecurringSchedule {
     pub id: ScheduleId,
 
@@ -79,6 +216,380 @@ pub struct RecurringSchedule {
     /// deprecated in favour of some other new schedule.
     #[schema(example = "2099-01-01T00:00:00+02:00")]
     pub valid_until: Option<NaiveDateTime>,
+
+    /// Rows of the schedule which couldn't be understood, and so were left out of `outages`. Only
+    /// sent if there were any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["Skipped line 4: `start_time` must be a time like 22:30, not `25:00`"]))]
+    pub warnings: Vec<String>,
+
+    /// Older versions of this schedule, which can be fetched if they're asked for.
+    #[serde(skip)]
+    pub superseded: Vec<SupersededSchedule>,
+}
+
+impl RecurringSchedule {
+    /// Whether this schedule is in use on `date`. Schedules without a `valid_from` or
+    /// `valid_until` are valid from or until forever.
+    pub fn is_valid_on(&self, date: NaiveDate) -> bool {
+        is_valid_between(self.valid_from, self.valid_until, date)
+    }
+
+    /// Write the schedule back out as a CSV file like the ones eskom-calendar keeps, with
+    /// `date_of_month`, `day_of_week`, or `day_of_cycle` columns depending on how its outages
+    /// recur. Parsing the CSV gives back the same outages.
+    ///
+    /// Fails if some outages are monthly and others are weekly (say), since one file can only
+    /// have one set of columns. A schedule without any outages is written with the monthly
+    /// columns, since that's what most schedules use.
+    pub fn to_raw_csv(&self) -> Result<String, String> {
+        let kinds = self
+            .outages
+            .iter()
+            .map(|outage| std::mem::discriminant(&outage.recurrence))
+            .collect::<HashSet<_>>();
+        if kinds.len() > 1 {
+            return Err("The schedule mixes outages that recur in different ways".to_string());
+        }
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        if self.outages.is_empty() {
+            writer
+                .write_record(["start_time", "finsh_time", "stage", "date_of_month"])
+                .map_err(|err| err.to_string())?;
+        }
+        for outage in &self.outages {
+            let start_time = outage.start_time.format("%H:%M").to_string();
+            let finsh_time = outage.finsh_time.format("%H:%M").to_string();
+            let written = match &outage.recurrence {
+                Recurrence::Monthly => writer.serialize(RawMonthlyShedding {
+                    start_time,
+                    finsh_time,
+                    stage: outage.stage,
+                    date_of_month: outage.day1_of_recurrence,
+                }),
+                Recurrence::Weekly => writer.serialize(RawWeeklyShedding {
+                    start_time,
+                    finsh_time,
+                    stage: outage.stage,
+                    day_of_week: outage.day1_of_recurrence,
+                }),
+                Recurrence::Periodic {
+                    offset,
+                    period_days,
+                } => writer.serialize(RawPeriodicShedding {
+                    start_time,
+                    finsh_time,
+                    stage: outage.stage,
+                    day_of_cycle: outage.day1_of_recurrence,
+                    period_of_cycle: *period_days,
+                    start_of_cycle: offset.format("%Y-%m-%d").to_string(),
+                }),
+            };
+            written.map_err(|err| err.to_string())?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|err| err.into_error().to_string())?;
+        String::from_utf8(bytes).map_err(|err| err.to_string())
+    }
+
+    /// Work out when the power will actually be off at `stage`, for any outages that overlap the
+    /// window from `from` to `to`.
+    ///
+    /// Stages are cumulative, so being at stage 4 means the outages for stages 1, 2, 3, and 4 all
+    /// apply. The outages are given in SAST and in order of when they start.
+    pub fn expand(
+        &self,
+        area_name: &str,
+        source: &str,
+        stage: u8,
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+    ) -> Vec<PowerOutage> {
+        let sast = sast();
+        let at = |date: NaiveDate, time: NaiveTime| {
+            sast.from_local_datetime(&date.and_time(time)).unwrap()
+        };
+
+        // Start a day early, since an outage that started yesterday might go over midnight
+        let mut date = from.with_timezone(&sast).date_naive() - Duration::days(1);
+        let last_date = to.with_timezone(&sast).date_naive();
+        let mut outages = vec![];
+        while date <= last_date {
+            for recurring in &self.outages {
+                if recurring.stage == 0 || recurring.stage > stage || !recurring.occurs_on(date) {
+                    continue;
+                }
+                let finsh_date = if recurring.finsh_time <= recurring.start_time {
+                    date + Duration::days(1)
+                } else {
+                    date
+                };
+                let outage = PowerOutage {
+                    area_name: area_name.to_string(),
+                    stage,
+                    start: at(date, recurring.start_time),
+                    finsh: at(finsh_date, recurring.finsh_time),
+                    source: source.to_string(),
+                };
+                if outage.start < to && outage.finsh > from {
+                    outages.push(outage);
+                }
+            }
+            date += Duration::days(1);
+        }
+        outages.sort_by_key(|outage| (outage.start, outage.finsh));
+        outages
+    }
+}
+
+/// What kind of day an outage is on, since some schedules are different on weekends and public
+/// holidays.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum DayType {
+    /// Monday to Friday, unless it's a public holiday.
+    Weekday,
+    /// Saturday or Sunday, unless it's a public holiday.
+    Weekend,
+    /// A South African public holiday, including Mondays which are holidays because the holiday
+    /// itself fell on a Sunday.
+    PublicHoliday,
+}
+
+/// An outage worked out from an area's schedule, along with what kind of day it starts on.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ExpandedOutage {
+    #[serde(flatten)]
+    pub outage: PowerOutage,
+
+    /// What kind of day the outage starts on (in SAST).
+    #[schema(example = "public_holiday")]
+    pub day_type: DayType,
+
+    /// The name of the public holiday the outage starts on, if it starts on one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Freedom Day")]
+    pub holiday: Option<String>,
+}
+
+impl RecurringOutage {
+    /// Whether this outage starts on `date`.
+    pub fn occurs_on(&self, date: NaiveDate) -> bool {
+        let day1 = u32::from(self.day1_of_recurrence);
+        match &self.recurrence {
+            Recurrence::Weekly => date.weekday().number_from_monday() == day1,
+            Recurrence::Monthly => date.day() == day1,
+            Recurrence::Periodic {
+                offset,
+                period_days,
+            } => {
+                *period_days > 0
+                    && (date - *offset)
+                        .num_days()
+                        .rem_euclid(i64::from(*period_days))
+                        + 1
+                        == i64::from(day1)
+            }
+        }
+    }
+
+    /// The first date on or after `date` that this outage starts on. None if it never does, which
+    /// happens when the day of the recurrence doesn't exist (like the 32nd of the month).
+    pub fn first_on_or_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        // Every recurrence repeats within a year, so there's no need to look any further
+        date.iter_days()
+            .take(366)
+            .find(|date| self.occurs_on(*date))
+    }
+}
+
+/// Two recurring outages in a schedule which are at the same stage on the same day, and whose
+/// times overlap.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ScheduleOverlap {
+    /// The stage of both outages.
+    #[schema(example = 2)]
+    pub stage: u8,
+    /// The day of the recurrence that both outages are on.
+    #[schema(example = 3)]
+    pub day: u8,
+    /// The outage which starts first.
+    pub first: RecurringOutage,
+    /// The outage which starts second, before `first` has finished.
+    pub second: RecurringOutage,
+}
+
+/// A day which doesn't have any outages at a certain stage, even though the schedule's other
+/// days do.
+#[derive(Deserialize, Serialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ScheduleGap {
+    /// The stage which doesn't have any outages on `day`.
+    #[schema(example = 1)]
+    pub stage: u8,
+    /// The day of the recurrence which is missing outages.
+    #[schema(example = 5)]
+    pub day: u8,
+    /// How the missing outages would repeat.
+    #[schema(example = "Weekly")]
+    pub recurrence: Recurrence,
+}
+
+/// Possible mistakes in an area's schedule, for checking eskom-calendar's data.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ScheduleReport {
+    /// The area whose schedule this is.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area_name: String,
+    /// True if no overlaps or gaps were found.
+    #[schema(example = true)]
+    pub is_valid: bool,
+    /// Outages which overlap another outage at the same stage on the same day.
+    pub overlaps: Vec<ScheduleOverlap>,
+    /// Days which don't have any outages at a stage that the schedule's other days have outages
+    /// at.
+    pub gaps: Vec<ScheduleGap>,
+}
+
+impl ScheduleReport {
+    /// Check `schedule` for overlapping outages and for gaps.
+    pub fn check(area_name: String, schedule: &RecurringSchedule) -> Self {
+        let overlaps = schedule_overlaps(&schedule.outages);
+        let gaps = schedule_gaps(&schedule.outages);
+        ScheduleReport {
+            area_name,
+            is_valid: overlaps.is_empty() && gaps.is_empty(),
+            overlaps,
+            gaps,
+        }
+    }
+}
+
+/// The minutes after midnight that an outage starts and finishes. Outages which go over midnight
+/// finish more than 24 hours after midnight.
+fn minutes_of_day(outage: &RecurringOutage) -> (u32, u32) {
+    let minutes = |time: NaiveTime| time.hour() * 60 + time.minute();
+    let (start, finsh) = (minutes(outage.start_time), minutes(outage.finsh_time));
+    if finsh <= start {
+        (start, finsh + 24 * 60)
+    } else {
+        (start, finsh)
+    }
+}
+
+/// Every pair of outages at the same stage on the same day whose times overlap.
+fn schedule_overlaps(outages: &[RecurringOutage]) -> Vec<ScheduleOverlap> {
+    let mut overlaps = vec![];
+    for (i, a) in outages.iter().enumerate() {
+        for b in &outages[i + 1..] {
+            let same_slot = a.stage == b.stage
+                && a.recurrence == b.recurrence
+                && a.day1_of_recurrence == b.day1_of_recurrence;
+            if !same_slot {
+                continue;
+            }
+            let ((a_start, a_finsh), (b_start, b_finsh)) = (minutes_of_day(a), minutes_of_day(b));
+            if a_start < b_finsh && b_start < a_finsh {
+                let (first, second) = if a_start <= b_start { (a, b) } else { (b, a) };
+                overlaps.push(ScheduleOverlap {
+                    stage: a.stage,
+                    day: a.day1_of_recurrence,
+                    first: first.clone(),
+                    second: second.clone(),
+                });
+            }
+        }
+    }
+    overlaps
+}
+
+/// Every day without any outages at a stage which other days have outages at. Days are counted
+/// up to the length of the recurrence: 7 for weekly schedules, 31 for monthly schedules, and the
+/// period for periodic schedules.
+fn schedule_gaps(outages: &[RecurringOutage]) -> Vec<ScheduleGap> {
+    let mut slots: Vec<(&Recurrence, u8)> = vec![];
+    for outage in outages {
+        if !slots.contains(&(&outage.recurrence, outage.stage)) {
+            slots.push((&outage.recurrence, outage.stage));
+        }
+    }
+    slots.sort_by_key(|(_recurrence, stage)| *stage);
+
+    let mut gaps = vec![];
+    for (recurrence, stage) in slots {
+        let days = match recurrence {
+            Recurrence::Weekly => 7,
+            Recurrence::Monthly => 31,
+            Recurrence::Periodic { period_days, .. } => *period_days,
+        };
+        for day in 1..=days {
+            let has_outage = outages.iter().any(|outage| {
+                &outage.recurrence == recurrence
+                    && outage.stage == stage
+                    && outage.day1_of_recurrence == day
+            });
+            if !has_outage {
+                gaps.push(ScheduleGap {
+                    stage,
+                    day,
+                    recurrence: recurrence.clone(),
+                });
+            }
+        }
+    }
+    gaps
+}
+
+/// Details about a schedule which aren't in the schedule's CSV file, read from the schedule's
+/// companion `<area_name>.metadata.json` in eskom-calendar. Everything is optional, since
+/// eskom-calendar might not know where a schedule came from or when it's valid.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(crate = "rocket::serde", default)]
+pub struct ScheduleMetadata {
+    pub source: Vec<String>,
+    pub info: Vec<String>,
+    pub last_updated: Option<NaiveDateTime>,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_until: Option<NaiveDateTime>,
+    /// Older versions of the schedule, which have since been replaced.
+    pub superseded: Vec<SupersededSchedule>,
+}
+
+/// An older version of a schedule, listed in the current version's metadata. Its CSV file is kept
+/// alongside the current one, and has the same format.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(crate = "rocket::serde", default)]
+pub struct SupersededSchedule {
+    /// The name of the older version's CSV file, like `western-cape-stellenbosch.2022.csv`.
+    pub file: String,
+    pub source: Vec<String>,
+    pub info: Vec<String>,
+    pub last_updated: Option<NaiveDateTime>,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_until: Option<NaiveDateTime>,
+}
+
+impl SupersededSchedule {
+    /// Whether this version of the schedule was in use on `date`.
+    pub fn is_valid_on(&self, date: NaiveDate) -> bool {
+        is_valid_between(self.valid_from, self.valid_until, date)
+    }
+}
+
+/// Whether `date` is between `valid_from` and `valid_until`, including both of them. Either can
+/// be missing, meaning there's no limit on that side.
+fn is_valid_between(
+    valid_from: Option<NaiveDateTime>,
+    valid_until: Option<NaiveDateTime>,
+    date: NaiveDate,
+) -> bool {
+    valid_from.is_none_or(|valid_from| valid_from.date() <= date)
+        && valid_until.is_none_or(|valid_until| date <= valid_until.date())
 }
 
 /// A recurring time during which the power *could* be out.
@@ -86,7 +597,7 @@ pub struct RecurringSchedule {
 /// Note that this is *different* to `PowerOutage`. A recurring outage does not describe a time
 /// when your power will be out, but rather describes a time when your power *could* be out,
 /// depending on what stage of loadshedding is announced.
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct RecurringOutage {
     /// The time at which this outage starts
@@ -135,17 +646,29 @@ pub enum Recurrence {
 }
 
 /// The ID of an `Area`
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct AreaId(pub i64);
 
+impl AreaId {
+    /// The ID of the area called `area_name`, which is the same every time it's calculated.
+    pub fn from_name(area_name: &str) -> Self {
+        AreaId(stable_id("area", area_name))
+    }
+}
+
 /// A geographical area which has a loadshedding schedule. Note that multiple Areas might share the
 /// same schedule.
 #[derive(Deserialize, Serialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct Area {
-    /// The standardised name of this area
+    /// The standardised name of this area, or its name in the language asked for with the
+    /// `Accept-Language` header if it has one
     pub name: String,
+    /// The standardised name of this area, if `name` is its name in another language. Use this
+    /// when asking the API about the area
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_name: Option<String>,
     /// The ID of this area
     pub id: AreaId,
     /// The ID of the schedule that this area follows
@@ -159,25 +682,86 @@ pub struct Area {
     pub municipality: Option<Municipality>,
 }
 
+impl Area {
+    /// Create an area with the given name, and whatever other information can be worked out from
+    /// that name.
+    pub fn from_name(name: String) -> Self {
+        Area {
+            province: province_from_area_name(&name),
+            municipality: municipality_from_area_name(&name),
+            id: AreaId::from_name(&name),
+            schedule: ScheduleId::from_name(&name),
+            name,
+            canonical_name: None,
+            aliases: vec![],
+        }
+    }
+}
+
 /// A region on the surface of Earth that is fully connected. So you can't have two "islands",
 /// every point in a ContiguousRegion must be reachable from every other point in the same
 /// ContiguousRegion.
 #[derive(Deserialize, Serialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct ContiguousRegion {
-    boundary: Vec<Coords>,
+    pub boundary: Vec<Coords>,
+}
+
+impl ContiguousRegion {
+    /// Check if a point is inside this region using the ray-casting algorithm, treating
+    /// latitude/longitude as flat x/y coordinates (which is fine for regions as small as
+    /// municipalities). Points exactly on the boundary count as being inside the region, so a
+    /// point on the border between two regions is in both of them.
+    pub fn contains(&self, point: &Coords) -> bool {
+        let n = self.boundary.len();
+        if n < 3 {
+            return false;
+        }
+        let edges = || (0..n).map(|i| (&self.boundary[i], &self.boundary[(i + 1) % n]));
+
+        if edges().any(|(a, b)| point.is_on_segment(a, b)) {
+            return true;
+        }
+
+        // Cast a ray from the point in the direction of increasing longitude, and count how many
+        // edges it crosses. An odd number of crossings means the point is inside.
+        edges()
+            .filter(|(a, b)| (a.lat > point.lat) != (b.lat > point.lat))
+            .filter(|(a, b)| {
+                let crossing_lng = a.lng + (point.lat - a.lat) / (b.lat - a.lat) * (b.lng - a.lng);
+                point.lng < crossing_lng
+            })
+            .count()
+            % 2
+            == 1
+    }
 }
 
 /// A point on the earth
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct Coords {
-    lat: f64,
-    lng: f64,
+    #[schema(example = -33.9321)]
+    pub lat: f64,
+    #[schema(example = 18.8602)]
+    pub lng: f64,
+}
+
+impl Coords {
+    /// Whether this point lies on the straight line segment from `a` to `b`.
+    fn is_on_segment(&self, a: &Coords, b: &Coords) -> bool {
+        const EPSILON: f64 = 1e-12;
+        let cross = (b.lng - a.lng) * (self.lat - a.lat) - (b.lat - a.lat) * (self.lng - a.lng);
+        cross.abs() < EPSILON
+            && self.lng >= a.lng.min(b.lng) - EPSILON
+            && self.lng <= a.lng.max(b.lng) + EPSILON
+            && self.lat >= a.lat.min(b.lat) - EPSILON
+            && self.lat <= a.lat.max(b.lat) + EPSILON
+    }
 }
 
 /// One of the nine provinces of South Africa
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub enum Province {
     EasternCape,
@@ -191,12 +775,168 @@ pub enum Province {
     WesternCape,
 }
 
+impl Province {
+    /// All nine provinces, in alphabetical order.
+    pub const ALL: [Province; 9] = [
+        Province::EasternCape,
+        Province::FreeState,
+        Province::Gauteng,
+        Province::KwaZuluNatal,
+        Province::Limpopo,
+        Province::Mpumalanga,
+        Province::NorthWest,
+        Province::NorthernCape,
+        Province::WesternCape,
+    ];
+
+    /// The province's name as eskom-calendar writes it at the start of area names, like
+    /// `western-cape`.
+    pub fn slug(&self) -> &'static str {
+        match self {
+            Province::EasternCape => "eastern-cape",
+            Province::FreeState => "free-state",
+            Province::Gauteng => "gauteng",
+            Province::KwaZuluNatal => "kwazulu-natal",
+            Province::Limpopo => "limpopo",
+            Province::Mpumalanga => "mpumalanga",
+            Province::NorthWest => "north-west",
+            Province::NorthernCape => "northern-cape",
+            Province::WesternCape => "western-cape",
+        }
+    }
+
+    /// The province with the given slug (like `western-cape`), if there is one.
+    pub fn from_slug(slug: &str) -> Option<Province> {
+        Province::ALL
+            .into_iter()
+            .find(|province| province.slug() == slug)
+    }
+
+    /// The province's name as it's usually written, like `KwaZulu-Natal`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Province::EasternCape => "Eastern Cape",
+            Province::FreeState => "Free State",
+            Province::Gauteng => "Gauteng",
+            Province::KwaZuluNatal => "KwaZulu-Natal",
+            Province::Limpopo => "Limpopo",
+            Province::Mpumalanga => "Mpumalanga",
+            Province::NorthWest => "North West",
+            Province::NorthernCape => "Northern Cape",
+            Province::WesternCape => "Western Cape",
+        }
+    }
+}
+
+/// One of South Africa's provinces, as listed by `/provinces`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ProvinceInfo {
+    pub province: Province,
+    /// The province's name as eskom-calendar writes it at the start of area names.
+    #[schema(example = "western-cape")]
+    pub slug: String,
+    /// The province's name as it's usually written.
+    #[schema(example = "Western Cape")]
+    pub name: String,
+}
+
+impl ProvinceInfo {
+    pub fn new(province: Province) -> Self {
+        ProvinceInfo {
+            province,
+            slug: province.slug().to_string(),
+            name: province.name().to_string(),
+        }
+    }
+}
+
+/// Work out the province of an area from its name, for example `western-cape-stellenbosch` is in
+/// the Western Cape. Areas which don't start with a province's name (like those with schedules
+/// directly from a municipality such as `city-of-cape-town-area-15`) give None.
+pub fn province_from_area_name(area_name: &str) -> Option<Province> {
+    Province::ALL.into_iter().find(|province| {
+        area_name
+            .strip_prefix(province.slug())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+    })
+}
+
+/// What areas are grouped under when their province or municipality can't be worked out.
+pub const UNKNOWN_GROUP: &str = "unknown";
+
+/// Group areas by province and then by municipality, like
+/// `{"western-cape": {"stellenbosch": ["western-cape-stellenbosch"]}}`. Areas whose province or
+/// municipality can't be worked out go under `unknown` instead.
+pub fn areas_tree(
+    area_names: impl IntoIterator<Item = String>,
+) -> BTreeMap<String, BTreeMap<String, Vec<String>>> {
+    let mut tree: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for area_name in area_names {
+        let province = province_from_area_name(&area_name)
+            .map_or(UNKNOWN_GROUP.to_string(), |province| {
+                province.slug().to_string()
+            });
+        let municipality = municipality_from_area_name(&area_name)
+            .map_or(UNKNOWN_GROUP.to_string(), |municipality| {
+                municipality.slug()
+            });
+        tree.entry(province)
+            .or_default()
+            .entry(municipality)
+            .or_default()
+            .push(area_name);
+    }
+    for area_names in tree.values_mut().flat_map(BTreeMap::values_mut) {
+        area_names.sort();
+    }
+    tree
+}
+
+/// Work out the municipality of an area from its name, for example `western-cape-stellenbosch` is
+/// in the Stellenbosch Local Municipality (part of the Cape Winelands District Municipality).
+///
+/// This is only a best-effort guess: it looks for the name of a metro or local municipality
+/// somewhere in the area's name, and gives None if there isn't exactly one such municipality.
+pub fn municipality_from_area_name(area_name: &str) -> Option<Municipality> {
+    let words = area_name
+        .to_lowercase()
+        .split('-')
+        .map(String::from)
+        .collect::<Vec<_>>();
+    // Every run of consecutive words squashed together, so that `city-of-cape-town-area-15`
+    // contains `cityofcapetown`
+    let runs = (0..words.len())
+        .flat_map(|start| (start + 1..=words.len()).map(move |end| (start, end)))
+        .map(|(start, end)| words[start..end].concat())
+        .collect::<HashSet<_>>();
+    let is_mentioned = |munic_name: String| runs.contains(&munic_name.to_lowercase());
+
+    let metros = MetroMunic::ALL
+        .into_iter()
+        .filter(|metro| is_mentioned(format!("{metro:?}")))
+        .map(Municipality::Metro);
+    let locals = LocalMunic::ALL
+        .into_iter()
+        .filter(|local| is_mentioned(format!("{local:?}")))
+        .map(|local| Municipality::District {
+            district: local.district(),
+            local,
+        });
+
+    let mut municipalities = metros.chain(locals);
+    match (municipalities.next(), municipalities.next()) {
+        (Some(municipality), None) => Some(municipality),
+        _ => None,
+    }
+}
+
 /// Municipalities in South Africa can either be Metropolitan Municipalities, or they can be
 /// District Municipalities (in which case they are subdivided into Local Municipalities).
 ///
 /// The metro municipalities are generally high density cities and the surrounding areas, and the
 /// district municipalities are everywhere else.
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub enum Municipality {
     Metro(MetroMunic),
@@ -206,10 +946,135 @@ pub enum Municipality {
     },
 }
 
+impl Municipality {
+    /// Every metro and local municipality, metros first.
+    pub fn all() -> impl Iterator<Item = Municipality> {
+        MetroMunic::ALL
+            .into_iter()
+            .map(Municipality::Metro)
+            .chain(
+                LocalMunic::ALL
+                    .into_iter()
+                    .map(|local| Municipality::District {
+                        district: local.district(),
+                        local,
+                    }),
+            )
+    }
+
+    /// The municipality's name written in lowercase with dashes, like `city-of-cape-town`. For
+    /// district municipalities this is the name of the local municipality.
+    pub fn slug(&self) -> String {
+        match self {
+            Municipality::Metro(metro) => munic_slug(&format!("{metro:?}")),
+            Municipality::District { local, .. } => munic_slug(&format!("{local:?}")),
+        }
+    }
+
+    /// Find the metro or local municipality written like `city-of-cape-town` or `stellenbosch`.
+    /// Dashes and capitalisation are ignored, so `CityOfCapeTown` also works.
+    pub fn from_slug(slug: &str) -> Option<Municipality> {
+        let squashed = slug.replace('-', "").to_lowercase();
+        let is_named = |munic_name: String| munic_name.to_lowercase() == squashed;
+        MetroMunic::ALL
+            .into_iter()
+            .find(|metro| is_named(format!("{metro:?}")))
+            .map(Municipality::Metro)
+            .or_else(|| {
+                LocalMunic::ALL
+                    .into_iter()
+                    .find(|local| is_named(format!("{local:?}")))
+                    .map(|local| Municipality::District {
+                        district: local.district(),
+                        local,
+                    })
+            })
+    }
+}
+
+/// Write a municipality's name (like `CityOfCapeTown`) in lowercase with dashes, like
+/// `city-of-cape-town`.
+fn munic_slug(name: &str) -> String {
+    let mut slug = String::new();
+    let mut previous = None;
+    for (i, c) in name.chars().enumerate() {
+        // Names like `eThekwini` and `uMngeni` start with a lowercase prefix that isn't a
+        // separate word
+        if c.is_uppercase() && i > 1 && previous.is_some_and(char::is_lowercase) {
+            slug.push('-');
+        }
+        slug.extend(c.to_lowercase());
+        previous = Some(c);
+    }
+    slug
+}
+
+/// What kind of municipality a `MunicipalityInfo` is.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum MunicipalityKind {
+    /// A Metropolitan Municipality, which isn't part of a district.
+    Metro,
+    /// A District Municipality, which is split into Local Municipalities.
+    District,
+    /// A Local Municipality, which is part of a district.
+    Local,
+}
+
+/// One of South Africa's municipalities, as listed by `/municipalities`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct MunicipalityInfo {
+    /// The municipality's name, as it's written in `MetroMunic`, `DistrictMunic`, or
+    /// `LocalMunic`.
+    #[schema(example = "Stellenbosch")]
+    pub name: String,
+    /// The municipality's name in lowercase with dashes, as used by
+    /// `/areas/municipality/{municipality}`.
+    #[schema(example = "stellenbosch")]
+    pub slug: String,
+    #[schema(example = "local")]
+    pub kind: MunicipalityKind,
+    /// The District Municipality that a Local Municipality is part of. Will be None for metro and
+    /// district municipalities.
+    #[schema(example = "CapeWinelands")]
+    pub district: Option<DistrictMunic>,
+}
+
+impl MunicipalityInfo {
+    fn new(name: String, kind: MunicipalityKind, district: Option<DistrictMunic>) -> Self {
+        MunicipalityInfo {
+            slug: munic_slug(&name),
+            name,
+            kind,
+            district,
+        }
+    }
+
+    /// Every municipality: metros, then districts, then locals, each in the same order as
+    /// they're declared.
+    pub fn all() -> Vec<MunicipalityInfo> {
+        let metros = MetroMunic::ALL.into_iter().map(|metro| {
+            MunicipalityInfo::new(format!("{metro:?}"), MunicipalityKind::Metro, None)
+        });
+        let districts = DistrictMunic::ALL.into_iter().map(|district| {
+            MunicipalityInfo::new(format!("{district:?}"), MunicipalityKind::District, None)
+        });
+        let locals = LocalMunic::ALL.into_iter().map(|local| {
+            MunicipalityInfo::new(
+                format!("{local:?}"),
+                MunicipalityKind::Local,
+                Some(local.district()),
+            )
+        });
+        metros.chain(districts).chain(locals).collect()
+    }
+}
+
 /// All the Metropolitan Municipalities in South Africa
 ///
 /// https://en.wikipedia.org/wiki/List_of_municipalities_in_South_Africa#Metropolitan_municipalities
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 #[allow(non_camel_case_types)]
 pub enum MetroMunic {
@@ -223,10 +1088,24 @@ pub enum MetroMunic {
     eThekwini,
 }
 
+impl MetroMunic {
+    /// All the Metropolitan Municipalities, in the same order as they're declared.
+    pub const ALL: [MetroMunic; 8] = [
+        MetroMunic::BuffaloCity,
+        MetroMunic::CityOfCapeTown,
+        MetroMunic::CityOfEkurhuleni,
+        MetroMunic::CityOfJohannesburg,
+        MetroMunic::CityOfTshwane,
+        MetroMunic::Mangaung,
+        MetroMunic::NelsonMandelaBay,
+        MetroMunic::eThekwini,
+    ];
+}
+
 /// All the district municipalities in South Africa
 ///
 /// https://en.wikipedia.org/wiki/List_of_municipalities_in_South_Africa#Local_municipalities
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 #[allow(non_camel_case_types)]
 pub enum DistrictMunic {
@@ -276,10 +1155,60 @@ pub enum DistrictMunic {
     uThukela,
 }
 
+impl DistrictMunic {
+    /// All the District Municipalities, in the same order as they're declared.
+    pub const ALL: [DistrictMunic; 44] = [
+        DistrictMunic::AlfredNzo,
+        DistrictMunic::Amajuba,
+        DistrictMunic::Amathole,
+        DistrictMunic::Bojanala,
+        DistrictMunic::CapeWinelands,
+        DistrictMunic::Capricorn,
+        DistrictMunic::CentralKaroo,
+        DistrictMunic::ChrisHani,
+        DistrictMunic::DrKennethKaunda,
+        DistrictMunic::DrRuthSegomotsiMompati,
+        DistrictMunic::Ehlanzeni,
+        DistrictMunic::FezileDabi,
+        DistrictMunic::FrancesBaard,
+        DistrictMunic::GardenRoute,
+        DistrictMunic::GertSibande,
+        DistrictMunic::HarryGwala,
+        DistrictMunic::JoeGqabi,
+        DistrictMunic::JohnTaoloGaetsewe,
+        DistrictMunic::KingCetshwayo,
+        DistrictMunic::Lejweleputswa,
+        DistrictMunic::Mopani,
+        DistrictMunic::Namakwa,
+        DistrictMunic::NgakaModiriMolema,
+        DistrictMunic::Nkangala,
+        DistrictMunic::ORTambo,
+        DistrictMunic::Overberg,
+        DistrictMunic::PixleykaSeme,
+        DistrictMunic::SarahBaartman,
+        DistrictMunic::Sedibeng,
+        DistrictMunic::Sekhukhune,
+        DistrictMunic::ThaboMofutsanyana,
+        DistrictMunic::Ugu,
+        DistrictMunic::Vhembe,
+        DistrictMunic::Waterberg,
+        DistrictMunic::WestCoast,
+        DistrictMunic::WestRand,
+        DistrictMunic::Xhariep,
+        DistrictMunic::ZFMgcawu,
+        DistrictMunic::Zululand,
+        DistrictMunic::iLembe,
+        DistrictMunic::uMgungundlovu,
+        DistrictMunic::uMkhanyakude,
+        DistrictMunic::uMzinyathi,
+        DistrictMunic::uThukela,
+    ];
+}
+
 /// All Local Municipalities of South Africa.
 ///
 /// https://en.wikipedia.org/wiki/List_of_municipalities_in_South_Africa#Local_municipalities
-#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(crate = "rocket::serde")]
 #[allow(non_camel_case_types)]
 pub enum LocalMunic {
@@ -490,6 +1419,427 @@ pub enum LocalMunic {
     uPhongolo,
 }
 
+impl LocalMunic {
+    /// Every Local Municipality, in the same order as they're declared.
+    pub const ALL: [LocalMunic; 205] = [
+        LocalMunic::Abaqulusi,
+        LocalMunic::AlbertLuthuli,
+        LocalMunic::AlfredDuma,
+        LocalMunic::Amahlathi,
+        LocalMunic::BaPhalaborwa,
+        LocalMunic::BeaufortWest,
+        LocalMunic::BelaBela,
+        LocalMunic::Bergrivier,
+        LocalMunic::BigFiveHlabisa,
+        LocalMunic::Bitou,
+        LocalMunic::Blouberg,
+        LocalMunic::BlueCraneRoute,
+        LocalMunic::BreedeValley,
+        LocalMunic::Bushbuckridge,
+        LocalMunic::CapeAgulhas,
+        LocalMunic::Cederberg,
+        LocalMunic::CityOfMatlosana,
+        LocalMunic::CollinsChabane,
+        LocalMunic::Dannhauser,
+        LocalMunic::DawidKruiper,
+        LocalMunic::Dihlabeng,
+        LocalMunic::Dikgatlong,
+        LocalMunic::Dipaleseng,
+        LocalMunic::Ditsobotla,
+        LocalMunic::DrBeyersNaude,
+        LocalMunic::DrJSMoroka,
+        LocalMunic::DrNkosazanaDlaminiZuma,
+        LocalMunic::Drakenstein,
+        LocalMunic::EliasMotsoaledi,
+        LocalMunic::Elundini,
+        LocalMunic::Emakhazeni,
+        LocalMunic::EmalahleniEasternCape,
+        LocalMunic::EmalahleniMpumalanga,
+        LocalMunic::Emfuleni,
+        LocalMunic::Emthanjeni,
+        LocalMunic::Endumeni,
+        LocalMunic::Engcobo,
+        LocalMunic::EnochMgijima,
+        LocalMunic::EphraimMogale,
+        LocalMunic::FetakgomoTubatse,
+        LocalMunic::GaSegonyana,
+        LocalMunic::Gamagara,
+        LocalMunic::George,
+        LocalMunic::GovanMbeki,
+        LocalMunic::GreatKei,
+        LocalMunic::GreaterGiyani,
+        LocalMunic::GreaterKokstad,
+        LocalMunic::GreaterLetaba,
+        LocalMunic::GreaterTaung,
+        LocalMunic::GreaterTzaneen,
+        LocalMunic::Hantam,
+        LocalMunic::Hessequa,
+        LocalMunic::Impendle,
+        LocalMunic::IngquzaHill,
+        LocalMunic::InkosiLangalibalele,
+        LocalMunic::IntsikaYethu,
+        LocalMunic::InxubaYethemba,
+        LocalMunic::JBMarks,
+        LocalMunic::JoeMorolong,
+        LocalMunic::Jozini,
+        LocalMunic::KagisanoMolopo,
+        LocalMunic::KaiGarib,
+        LocalMunic::Kamiesberg,
+        LocalMunic::Kannaland,
+        LocalMunic::Kareeberg,
+        LocalMunic::KarooHoogland,
+        LocalMunic::Kgatelopele,
+        LocalMunic::Kgetlengrivier,
+        LocalMunic::KhaiMa,
+        LocalMunic::Kheis,
+        LocalMunic::KingSabataDalindyebo,
+        LocalMunic::Knysna,
+        LocalMunic::Kopanong,
+        LocalMunic::KouKamma,
+        LocalMunic::Kouga,
+        LocalMunic::KwaDukuza,
+        LocalMunic::Laingsburg,
+        LocalMunic::Langeberg,
+        LocalMunic::Lekwa,
+        LocalMunic::LekwaTeemane,
+        LocalMunic::LepelleNkumpi,
+        LocalMunic::Lephalale,
+        LocalMunic::Lesedi,
+        LocalMunic::Letsemeng,
+        LocalMunic::Madibeng,
+        LocalMunic::Mafube,
+        LocalMunic::Magareng,
+        LocalMunic::Mahikeng,
+        LocalMunic::Makana,
+        LocalMunic::Makhado,
+        LocalMunic::Makhuduthamaga,
+        LocalMunic::MalutiAPhofung,
+        LocalMunic::Mamusa,
+        LocalMunic::Mandeni,
+        LocalMunic::Mantsopa,
+        LocalMunic::Maphumulo,
+        LocalMunic::MaquassiHills,
+        LocalMunic::Maruleng,
+        LocalMunic::Masilonyana,
+        LocalMunic::Matatiele,
+        LocalMunic::Matjhabeng,
+        LocalMunic::Matzikama,
+        LocalMunic::Mbhashe,
+        LocalMunic::Mbombela,
+        LocalMunic::MerafongCity,
+        LocalMunic::Metsimaholo,
+        LocalMunic::Mhlontlo,
+        LocalMunic::Midvaal,
+        LocalMunic::Mkhambathini,
+        LocalMunic::Mkhondo,
+        LocalMunic::Mnquma,
+        LocalMunic::ModimolleMookgophong,
+        LocalMunic::Mogalakwena,
+        LocalMunic::MogaleCity,
+        LocalMunic::Mohokare,
+        LocalMunic::Molemole,
+        LocalMunic::Moqhaka,
+        LocalMunic::Moretele,
+        LocalMunic::MosesKotane,
+        LocalMunic::MosselBay,
+        LocalMunic::Mpofana,
+        LocalMunic::Msinga,
+        LocalMunic::Msukaligwa,
+        LocalMunic::Msunduzi,
+        LocalMunic::Mthonjaneni,
+        LocalMunic::Mtubatuba,
+        LocalMunic::Musina,
+        LocalMunic::Nala,
+        LocalMunic::Naledi,
+        LocalMunic::NamaKhoi,
+        LocalMunic::Ndlambe,
+        LocalMunic::Ndwedwe,
+        LocalMunic::Newcastle,
+        LocalMunic::Ngqushwa,
+        LocalMunic::Ngwathe,
+        LocalMunic::Nkandla,
+        LocalMunic::Nketoana,
+        LocalMunic::Nkomazi,
+        LocalMunic::Nongoma,
+        LocalMunic::Nqutu,
+        LocalMunic::Ntabankulu,
+        LocalMunic::Nyandeni,
+        LocalMunic::Okhahlamba,
+        LocalMunic::Oudtshoorn,
+        LocalMunic::Overstrand,
+        LocalMunic::Phokwane,
+        LocalMunic::Phumelela,
+        LocalMunic::PixleykaSeme,
+        LocalMunic::Polokwane,
+        LocalMunic::PortStJohns,
+        LocalMunic::PrinceAlbert,
+        LocalMunic::RamotshereMoiloa,
+        LocalMunic::RandWestCity,
+        LocalMunic::Ratlou,
+        LocalMunic::RayNkonyeni,
+        LocalMunic::RaymondMhlaba,
+        LocalMunic::Renosterberg,
+        LocalMunic::Richmond,
+        LocalMunic::Richtersveld,
+        LocalMunic::Rustenburg,
+        LocalMunic::Sakhisizwe,
+        LocalMunic::SaldanhaBay,
+        LocalMunic::Senqu,
+        LocalMunic::Setsoto,
+        LocalMunic::Siyancuma,
+        LocalMunic::Siyathemba,
+        LocalMunic::SolPlaatje,
+        LocalMunic::Stellenbosch,
+        LocalMunic::SteveTshwete,
+        LocalMunic::SundaysRiverValley,
+        LocalMunic::Swartland,
+        LocalMunic::Swellendam,
+        LocalMunic::ThabaChweu,
+        LocalMunic::Thabazimbi,
+        LocalMunic::Theewaterskloof,
+        LocalMunic::Thembelihle,
+        LocalMunic::ThembisileHani,
+        LocalMunic::Thulamela,
+        LocalMunic::Tokologo,
+        LocalMunic::Tsantsabane,
+        LocalMunic::Tswaing,
+        LocalMunic::Tswelopele,
+        LocalMunic::Ubuhlebezwe,
+        LocalMunic::Ubuntu,
+        LocalMunic::Ulundi,
+        LocalMunic::Umdoni,
+        LocalMunic::Umsobomvu,
+        LocalMunic::Umvoti,
+        LocalMunic::Umzimkhulu,
+        LocalMunic::Umzimvubu,
+        LocalMunic::Umzumbe,
+        LocalMunic::VictorKhanye,
+        LocalMunic::WalterSisulu,
+        LocalMunic::WinnieMadikizelaMandela,
+        LocalMunic::Witzenberg,
+        LocalMunic::eDumbe,
+        LocalMunic::eMadlangeni,
+        LocalMunic::uMfolozi,
+        LocalMunic::uMhlabuyalingana,
+        LocalMunic::uMhlathuze,
+        LocalMunic::uMlalazi,
+        LocalMunic::uMngeni,
+        LocalMunic::uMshwathi,
+        LocalMunic::uMuziwabantu,
+        LocalMunic::uPhongolo,
+    ];
+
+    /// The District Municipality which this Local Municipality is a part of.
+    pub fn district(&self) -> DistrictMunic {
+        match self {
+            LocalMunic::Matatiele
+            | LocalMunic::Ntabankulu
+            | LocalMunic::Umzimvubu
+            | LocalMunic::WinnieMadikizelaMandela => DistrictMunic::AlfredNzo,
+            LocalMunic::Dannhauser | LocalMunic::Newcastle | LocalMunic::eMadlangeni => {
+                DistrictMunic::Amajuba
+            }
+            LocalMunic::Amahlathi
+            | LocalMunic::GreatKei
+            | LocalMunic::Mbhashe
+            | LocalMunic::Mnquma
+            | LocalMunic::Ngqushwa
+            | LocalMunic::RaymondMhlaba => DistrictMunic::Amathole,
+            LocalMunic::Kgetlengrivier
+            | LocalMunic::Madibeng
+            | LocalMunic::Moretele
+            | LocalMunic::MosesKotane
+            | LocalMunic::Rustenburg => DistrictMunic::Bojanala,
+            LocalMunic::BreedeValley
+            | LocalMunic::Drakenstein
+            | LocalMunic::Langeberg
+            | LocalMunic::Stellenbosch
+            | LocalMunic::Witzenberg => DistrictMunic::CapeWinelands,
+            LocalMunic::Blouberg
+            | LocalMunic::LepelleNkumpi
+            | LocalMunic::Molemole
+            | LocalMunic::Polokwane => DistrictMunic::Capricorn,
+            LocalMunic::BeaufortWest | LocalMunic::Laingsburg | LocalMunic::PrinceAlbert => {
+                DistrictMunic::CentralKaroo
+            }
+            LocalMunic::EmalahleniEasternCape
+            | LocalMunic::Engcobo
+            | LocalMunic::EnochMgijima
+            | LocalMunic::IntsikaYethu
+            | LocalMunic::InxubaYethemba
+            | LocalMunic::Sakhisizwe => DistrictMunic::ChrisHani,
+            LocalMunic::CityOfMatlosana | LocalMunic::JBMarks | LocalMunic::MaquassiHills => {
+                DistrictMunic::DrKennethKaunda
+            }
+            LocalMunic::GreaterTaung
+            | LocalMunic::KagisanoMolopo
+            | LocalMunic::LekwaTeemane
+            | LocalMunic::Mamusa
+            | LocalMunic::Naledi => DistrictMunic::DrRuthSegomotsiMompati,
+            LocalMunic::Bushbuckridge
+            | LocalMunic::Mbombela
+            | LocalMunic::Nkomazi
+            | LocalMunic::ThabaChweu => DistrictMunic::Ehlanzeni,
+            LocalMunic::Mafube
+            | LocalMunic::Metsimaholo
+            | LocalMunic::Moqhaka
+            | LocalMunic::Ngwathe => DistrictMunic::FezileDabi,
+            LocalMunic::Dikgatlong
+            | LocalMunic::Magareng
+            | LocalMunic::Phokwane
+            | LocalMunic::SolPlaatje => DistrictMunic::FrancesBaard,
+            LocalMunic::Bitou
+            | LocalMunic::George
+            | LocalMunic::Hessequa
+            | LocalMunic::Kannaland
+            | LocalMunic::Knysna
+            | LocalMunic::MosselBay
+            | LocalMunic::Oudtshoorn => DistrictMunic::GardenRoute,
+            LocalMunic::AlbertLuthuli
+            | LocalMunic::Dipaleseng
+            | LocalMunic::GovanMbeki
+            | LocalMunic::Lekwa
+            | LocalMunic::Mkhondo
+            | LocalMunic::Msukaligwa
+            | LocalMunic::PixleykaSeme => DistrictMunic::GertSibande,
+            LocalMunic::DrNkosazanaDlaminiZuma
+            | LocalMunic::GreaterKokstad
+            | LocalMunic::Ubuhlebezwe
+            | LocalMunic::Umzimkhulu => DistrictMunic::HarryGwala,
+            LocalMunic::Elundini | LocalMunic::Senqu | LocalMunic::WalterSisulu => {
+                DistrictMunic::JoeGqabi
+            }
+            LocalMunic::GaSegonyana | LocalMunic::Gamagara | LocalMunic::JoeMorolong => {
+                DistrictMunic::JohnTaoloGaetsewe
+            }
+            LocalMunic::Mthonjaneni
+            | LocalMunic::Nkandla
+            | LocalMunic::uMfolozi
+            | LocalMunic::uMhlathuze
+            | LocalMunic::uMlalazi => DistrictMunic::KingCetshwayo,
+            LocalMunic::Masilonyana
+            | LocalMunic::Matjhabeng
+            | LocalMunic::Nala
+            | LocalMunic::Tokologo
+            | LocalMunic::Tswelopele => DistrictMunic::Lejweleputswa,
+            LocalMunic::BaPhalaborwa
+            | LocalMunic::GreaterGiyani
+            | LocalMunic::GreaterLetaba
+            | LocalMunic::GreaterTzaneen
+            | LocalMunic::Maruleng => DistrictMunic::Mopani,
+            LocalMunic::Hantam
+            | LocalMunic::Kamiesberg
+            | LocalMunic::KarooHoogland
+            | LocalMunic::KhaiMa
+            | LocalMunic::NamaKhoi
+            | LocalMunic::Richtersveld => DistrictMunic::Namakwa,
+            LocalMunic::Ditsobotla
+            | LocalMunic::Mahikeng
+            | LocalMunic::RamotshereMoiloa
+            | LocalMunic::Ratlou
+            | LocalMunic::Tswaing => DistrictMunic::NgakaModiriMolema,
+            LocalMunic::DrJSMoroka
+            | LocalMunic::Emakhazeni
+            | LocalMunic::EmalahleniMpumalanga
+            | LocalMunic::SteveTshwete
+            | LocalMunic::ThembisileHani
+            | LocalMunic::VictorKhanye => DistrictMunic::Nkangala,
+            LocalMunic::IngquzaHill
+            | LocalMunic::KingSabataDalindyebo
+            | LocalMunic::Mhlontlo
+            | LocalMunic::Nyandeni
+            | LocalMunic::PortStJohns => DistrictMunic::ORTambo,
+            LocalMunic::CapeAgulhas
+            | LocalMunic::Overstrand
+            | LocalMunic::Swellendam
+            | LocalMunic::Theewaterskloof => DistrictMunic::Overberg,
+            LocalMunic::Emthanjeni
+            | LocalMunic::Kareeberg
+            | LocalMunic::Renosterberg
+            | LocalMunic::Siyancuma
+            | LocalMunic::Siyathemba
+            | LocalMunic::Thembelihle
+            | LocalMunic::Ubuntu
+            | LocalMunic::Umsobomvu => DistrictMunic::PixleykaSeme,
+            LocalMunic::BlueCraneRoute
+            | LocalMunic::DrBeyersNaude
+            | LocalMunic::KouKamma
+            | LocalMunic::Kouga
+            | LocalMunic::Makana
+            | LocalMunic::Ndlambe
+            | LocalMunic::SundaysRiverValley => DistrictMunic::SarahBaartman,
+            LocalMunic::Emfuleni | LocalMunic::Lesedi | LocalMunic::Midvaal => {
+                DistrictMunic::Sedibeng
+            }
+            LocalMunic::EliasMotsoaledi
+            | LocalMunic::EphraimMogale
+            | LocalMunic::FetakgomoTubatse
+            | LocalMunic::Makhuduthamaga => DistrictMunic::Sekhukhune,
+            LocalMunic::Dihlabeng
+            | LocalMunic::MalutiAPhofung
+            | LocalMunic::Mantsopa
+            | LocalMunic::Nketoana
+            | LocalMunic::Phumelela
+            | LocalMunic::Setsoto => DistrictMunic::ThaboMofutsanyana,
+            LocalMunic::RayNkonyeni
+            | LocalMunic::Umdoni
+            | LocalMunic::Umzumbe
+            | LocalMunic::uMuziwabantu => DistrictMunic::Ugu,
+            LocalMunic::CollinsChabane
+            | LocalMunic::Makhado
+            | LocalMunic::Musina
+            | LocalMunic::Thulamela => DistrictMunic::Vhembe,
+            LocalMunic::BelaBela
+            | LocalMunic::Lephalale
+            | LocalMunic::ModimolleMookgophong
+            | LocalMunic::Mogalakwena
+            | LocalMunic::Thabazimbi => DistrictMunic::Waterberg,
+            LocalMunic::Bergrivier
+            | LocalMunic::Cederberg
+            | LocalMunic::Matzikama
+            | LocalMunic::SaldanhaBay
+            | LocalMunic::Swartland => DistrictMunic::WestCoast,
+            LocalMunic::MerafongCity | LocalMunic::MogaleCity | LocalMunic::RandWestCity => {
+                DistrictMunic::WestRand
+            }
+            LocalMunic::Kopanong | LocalMunic::Letsemeng | LocalMunic::Mohokare => {
+                DistrictMunic::Xhariep
+            }
+            LocalMunic::DawidKruiper
+            | LocalMunic::KaiGarib
+            | LocalMunic::Kgatelopele
+            | LocalMunic::Kheis
+            | LocalMunic::Tsantsabane => DistrictMunic::ZFMgcawu,
+            LocalMunic::Abaqulusi
+            | LocalMunic::Nongoma
+            | LocalMunic::Ulundi
+            | LocalMunic::eDumbe
+            | LocalMunic::uPhongolo => DistrictMunic::Zululand,
+            LocalMunic::KwaDukuza
+            | LocalMunic::Mandeni
+            | LocalMunic::Maphumulo
+            | LocalMunic::Ndwedwe => DistrictMunic::iLembe,
+            LocalMunic::Impendle
+            | LocalMunic::Mkhambathini
+            | LocalMunic::Mpofana
+            | LocalMunic::Msunduzi
+            | LocalMunic::Richmond
+            | LocalMunic::uMngeni
+            | LocalMunic::uMshwathi => DistrictMunic::uMgungundlovu,
+            LocalMunic::BigFiveHlabisa
+            | LocalMunic::Jozini
+            | LocalMunic::Mtubatuba
+            | LocalMunic::uMhlabuyalingana => DistrictMunic::uMkhanyakude,
+            LocalMunic::Endumeni | LocalMunic::Msinga | LocalMunic::Nqutu | LocalMunic::Umvoti => {
+                DistrictMunic::uMzinyathi
+            }
+            LocalMunic::AlfredDuma | LocalMunic::InkosiLangalibalele | LocalMunic::Okhahlamba => {
+                DistrictMunic::uThukela
+            }
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct RawPeriodicShedding {
@@ -499,26 +1849,44 @@ pub struct RawPeriodicShedding {
     pub finsh_time: String,
     /// The stage of loadshedding.
     pub stage: u8,
-    /// The day of the 20 day cycle, with the first day being 1, the second day being 2, etc
+    /// The day of the cycle, with the first day being 1, the second day being 2, etc. Older
+    /// schedules called this `day_of_20_day_cycle`, since Nelson Mandela Bay's cycle is 20 days.
+    #[serde(alias = "day_of_20_day_cycle")]
     pub day_of_cycle: u8,
+    /// How many days the cycle lasts before it repeats.
     pub period_of_cycle: u8,
+    /// The date of the first day of the cycle, like `2023-02-18`.
     pub start_of_cycle: String,
 }
 
-impl From<RawPeriodicShedding> for RecurringOutage {
-    fn from(raw: RawPeriodicShedding) -> Self {
-        assert!(
-            raw.day_of_cycle <= raw.period_of_cycle,
-            "Day of the cycle {} must be <= period of the cycle {}",
-            raw.day_of_cycle,
-            raw.period_of_cycle
-        );
+/// Parse a time of day from a schedule, like `22:30`.
+fn parse_schedule_time(name: &str, value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_err| format!("`{name}` must be a time like 22:30, not `{value}`"))
+}
 
-        let offset = NaiveDate::parse_from_str(&raw.start_of_cycle, "%Y-%m-%d").unwrap();
+impl TryFrom<RawPeriodicShedding> for RecurringOutage {
+    type Error = String;
 
-        RecurringOutage {
-            start_time: NaiveTime::parse_from_str(&raw.start_time, "%H:%M").unwrap(),
-            finsh_time: NaiveTime::parse_from_str(&raw.finsh_time, "%H:%M").unwrap(),
+    fn try_from(raw: RawPeriodicShedding) -> Result<Self, Self::Error> {
+        if raw.day_of_cycle == 0 || raw.day_of_cycle > raw.period_of_cycle {
+            return Err(format!(
+                "Day of the cycle {} must be between 1 and the period of the cycle {}",
+                raw.day_of_cycle, raw.period_of_cycle
+            ));
+        }
+
+        let offset =
+            NaiveDate::parse_from_str(&raw.start_of_cycle, "%Y-%m-%d").map_err(|_err| {
+                format!(
+                    "`start_of_cycle` must be a date like 2023-02-18, not `{}`",
+                    raw.start_of_cycle
+                )
+            })?;
+
+        Ok(RecurringOutage {
+            start_time: parse_schedule_time("start_time", &raw.start_time)?,
+            finsh_time: parse_schedule_time("finsh_time", &raw.finsh_time)?,
             stage: raw.stage,
             recurrence: Recurrence::Periodic {
                 // As declared by
@@ -530,7 +1898,7 @@ impl From<RawPeriodicShedding> for RecurringOutage {
                 period_days: raw.period_of_cycle,
             },
             day1_of_recurrence: raw.day_of_cycle,
-        }
+        })
     }
 }
 
@@ -547,19 +1915,23 @@ pub struct RawWeeklyShedding {
     pub day_of_week: u8,
 }
 
-impl From<RawWeeklyShedding> for RecurringOutage {
-    fn from(raw: RawWeeklyShedding) -> Self {
-        assert!(
-            0 < raw.day_of_week && raw.day_of_week < 8,
-            "Day of the week must be one of 1, 2, 3, 4, 5, 6, 7"
-        );
-        RecurringOutage {
-            start_time: NaiveTime::parse_from_str(&raw.start_time, "%H:%M").unwrap(),
-            finsh_time: NaiveTime::parse_from_str(&raw.finsh_time, "%H:%M").unwrap(),
+impl TryFrom<RawWeeklyShedding> for RecurringOutage {
+    type Error = String;
+
+    fn try_from(raw: RawWeeklyShedding) -> Result<Self, Self::Error> {
+        if !(1..=7).contains(&raw.day_of_week) {
+            return Err(format!(
+                "Day of the week must be one of 1, 2, 3, 4, 5, 6, 7, not {}",
+                raw.day_of_week
+            ));
+        }
+        Ok(RecurringOutage {
+            start_time: parse_schedule_time("start_time", &raw.start_time)?,
+            finsh_time: parse_schedule_time("finsh_time", &raw.finsh_time)?,
             stage: raw.stage,
             recurrence: Recurrence::Weekly,
             day1_of_recurrence: raw.day_of_week,
-        }
+        })
     }
 }
 
@@ -576,20 +1948,24 @@ pub struct RawMonthlyShedding {
     pub date_of_month: u8,
 }
 
-impl From<RawMonthlyShedding> for RecurringOutage {
-    fn from(raw: RawMonthlyShedding) -> Self {
-        assert!(
-            0 < raw.date_of_month && raw.date_of_month <= 31,
-            "Date of month must be in the range (0, 31]"
-        );
+impl TryFrom<RawMonthlyShedding> for RecurringOutage {
+    type Error = String;
+
+    fn try_from(raw: RawMonthlyShedding) -> Result<Self, Self::Error> {
+        if !(1..=31).contains(&raw.date_of_month) {
+            return Err(format!(
+                "Date of month must be in the range [1, 31], not {}",
+                raw.date_of_month
+            ));
+        }
 
-        RecurringOutage {
-            start_time: NaiveTime::parse_from_str(&raw.start_time, "%H:%M").unwrap(),
-            finsh_time: NaiveTime::parse_from_str(&raw.finsh_time, "%H:%M").unwrap(),
+        Ok(RecurringOutage {
+            start_time: parse_schedule_time("start_time", &raw.start_time)?,
+            finsh_time: parse_schedule_time("finsh_time", &raw.finsh_time)?,
             stage: raw.stage,
             recurrence: Recurrence::Monthly,
             day1_of_recurrence: raw.date_of_month,
-        }
+        })
     }
 }
 
@@ -599,7 +1975,7 @@ impl From<RawMonthlyShedding> for RecurringOutage {
 /// will actually be turned off, but a `RecurringOutage` describes the Monthly/Weekly schedules
 /// that say "In this area, at this stage, on this date, your power will be off from this time to
 /// that time".
-#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, ToSchema)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct PowerOutage {
     /// The area experiencing the power outage.
@@ -624,19 +2000,1227 @@ pub struct PowerOutage {
     pub source: String,
 }
 
-/// A generic search result that gets returned after you searched for something.
-///
-/// It simply wraps the object you were looking for with a score for how well that object matched
-/// your search query. Higher numbers are better.
-#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+impl PowerOutage {
+    /// The names of every field, in the same order as the columns of eskom-calendar's
+    /// `machine_friendly.csv`.
+    pub const FIELDS: [&'static str; 5] = ["area_name", "stage", "start", "finsh", "source"];
+
+    /// Whether the power is off due to this outage at the given time. An outage includes its
+    /// `start` but not its `finsh`, so back-to-back outages never both contain the same moment.
+    pub fn is_active_at(&self, time: DateTime<FixedOffset>) -> bool {
+        self.start <= time && time < self.finsh
+    }
+
+    /// Combine back-to-back outages in the same area at the same stage into one continuous
+    /// outage. Outages are back-to-back if one finishes within `MERGE_TOLERANCE_SECONDS` of when
+    /// the next one starts. The merged outage lists each of the different sources, separated by
+    /// ` ; `.
+    ///
+    /// The result is sorted by area name and then start time.
+    pub fn merge_contiguous(mut outages: Vec<PowerOutage>) -> Vec<PowerOutage> {
+        outages.sort_by(|a, b| (&a.area_name, a.start).cmp(&(&b.area_name, b.start)));
+        let mut merged: Vec<PowerOutage> = vec![];
+        for outage in outages {
+            match merged.last_mut() {
+                Some(previous)
+                    if previous.area_name == outage.area_name
+                        && previous.stage == outage.stage
+                        && (outage.start - previous.finsh).num_seconds().abs()
+                            <= MERGE_TOLERANCE_SECONDS =>
+                {
+                    previous.finsh = previous.finsh.max(outage.finsh);
+                    if !previous.source.split(" ; ").any(|s| s == outage.source) {
+                        previous.source = format!("{} ; {}", previous.source, outage.source);
+                    }
+                }
+                _ => merged.push(outage),
+            }
+        }
+        merged
+    }
+}
+
+/// How many seconds apart the end of one outage and the start of the next can be for them to count
+/// as back-to-back, for `PowerOutage::merge_contiguous`.
+pub const MERGE_TOLERANCE_SECONDS: i64 = 60;
+
+/// A request for information about several areas at once.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
-pub struct SearchResult<T> {
-    /// How close a match the thing is to the search query. Higher is better.
-    #[schema(example = 100)]
-    pub score: i64,
-    /// The thing that's been found
-    #[schema(value_type=Area)]
+pub struct AreasRequest {
+    /// The names of the areas.
+    #[schema(example = json!(["western-cape-stellenbosch", "city-of-cape-town-area-15"]))]
+    pub areas: Vec<String>,
+}
+
+/// Whether or not several areas are being loadshed right now.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct AreasCurrentStatus {
+    /// The current status of each requested area, keyed by the area's name. Areas which couldn't
+    /// be found aren't included.
+    #[schema(value_type = Object, example = json!({
+        "western-cape-stellenbosch": {
+            "area_name": "western-cape-stellenbosch",
+            "is_shedding": true,
+            "current_outage": {
+                "area_name": "western-cape-stellenbosch",
+                "stage": 6,
+                "start": "2023-06-01T18:00:00+02:00",
+                "finsh": "2023-06-01T20:30:00+02:00",
+                "source": "https://twitter.com/Eskom_SA/status/1664250326818365440"
+            },
+            "next_outage": null
+        }
+    }))]
+    pub statuses: HashMap<String, CurrentStatus>,
+
+    /// The names of any requested areas which eskom-calendar doesn't know about, in alphabetical
+    /// order.
+    #[schema(example = json!(["western-cape-stellenbosh"]))]
+    pub not_found: Vec<String>,
+}
+
+/// A request to be told whenever an area's loadshedding stage changes.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct SubscribeRequest {
+    /// The name of the area to watch.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area: String,
+    /// Where to `POST` a `StageChangeNotification` whenever the area's stage changes. Must be an
+    /// `http` or `https` URL.
+    #[schema(example = "https://example.com/eskom-calendar-webhook")]
+    pub callback_url: String,
+    /// A contact email, so that we can get in touch if something goes wrong with the webhook.
+    #[schema(example = "you@example.com")]
+    pub email: String,
+}
+
+/// A registered webhook, which is told whenever an area's loadshedding stage changes.
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct Subscription {
+    /// The ID of this subscription, which is included in every notification.
+    #[schema(example = "7c4b4d3e9a0f4f0c8b0a6f0e5d2c1b3a")]
+    pub id: String,
+    /// The name of the area being watched.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area_name: String,
+    /// Where notifications are sent.
+    #[schema(example = "https://example.com/eskom-calendar-webhook")]
+    pub callback_url: String,
+    /// The secret used to sign notifications. Each notification has an `X-Signature-256` header
+    /// of `sha256=` followed by the hex HMAC-SHA256 of the body, keyed with this secret. This is
+    /// only ever sent once, so keep it somewhere safe.
+    #[schema(example = "5f1e0c9d8b7a6f5e4d3c2b1a0f9e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b3a2f1e")]
+    pub secret: String,
+}
+
+/// What's sent to a webhook when the stage of the area it's watching changes.
+#[derive(Deserialize, Serialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct StageChangeNotification {
+    /// The ID of the subscription this notification is for.
+    #[schema(example = "7c4b4d3e9a0f4f0c8b0a6f0e5d2c1b3a")]
+    pub subscription_id: String,
+    /// The name of the area whose stage changed.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area_name: String,
+    /// The area's stage before the change, where 0 means the area wasn't being loadshed.
+    #[schema(example = 0)]
+    pub previous_stage: u8,
+    /// The area's stage now, where 0 means the area isn't being loadshed.
+    #[schema(example = 4)]
+    pub stage: u8,
+    /// The outage that's happening now, or None if the area isn't being loadshed anymore.
+    pub outage: Option<PowerOutage>,
+}
+
+/// One page of a list of area names.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct AreasPage {
+    /// How many areas there are in total, across every page.
+    #[schema(example = 1234)]
+    pub total: usize,
+    /// How many areas were skipped before this page.
+    #[schema(example = 0)]
+    pub offset: usize,
+    /// The most areas that a page can have. Will be None if every area is on one page.
+    #[schema(example = 100)]
+    pub limit: Option<usize>,
+    /// The names of the areas on this page, in alphabetical order.
+    #[schema(example = json!(["city-of-cape-town-area-1", "city-of-cape-town-area-15"]))]
+    pub areas: Vec<String>,
+    /// The names of the areas on this page in the language asked for with the `Accept-Language`
+    /// header, keyed by the areas' actual names. Only areas with a name in that language are
+    /// included, and this is left out if there aren't any.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(example = json!({"city-of-cape-town-area-15": "Kaapstad area 15"}))]
+    pub localised_names: BTreeMap<String, String>,
+}
+
+impl AreasPage {
+    /// Take one page of `areas`, which should already be sorted. Offsets past the end of the list
+    /// give an empty page.
+    pub fn paginate(areas: Vec<String>, offset: usize, limit: Option<usize>) -> Self {
+        AreasPage {
+            total: areas.len(),
+            offset,
+            limit,
+            areas: areas
+                .into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect(),
+            localised_names: BTreeMap::new(),
+        }
+    }
+}
+
+/// One page of every area's power outages, for syncing the whole dataset at once.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct AllOutages {
+    /// How many areas have outages, across every page.
+    #[schema(example = 1234)]
+    pub total: usize,
+    /// How many areas were skipped before this page.
+    #[schema(example = 0)]
+    pub offset: usize,
+    /// The most areas that a page can have.
+    #[schema(example = 500)]
+    pub limit: usize,
+    /// The outages for each area on this page, keyed by the area's name. Areas are paged in
+    /// alphabetical order.
+    #[schema(value_type = Object, example = json!({
+        "western-cape-stellenbosch": [{
+            "area_name": "western-cape-stellenbosch",
+            "stage": 6,
+            "start": "2023-06-01T18:00:00+02:00",
+            "finsh": "2023-06-01T20:30:00+02:00",
+            "source": "https://twitter.com/Eskom_SA/status/1664250326818365440"
+        }]
+    }))]
+    pub outages: BTreeMap<String, Vec<PowerOutage>>,
+    /// Whether these areas had more outages between them than one response can have, so that the
+    /// page has fewer areas than `limit`. The next page starts at `offset` plus the number of
+    /// areas on this page.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub truncated: bool,
+    /// How to get the outages that were left out, if `truncated` is true.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// The power outages for several areas at once.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct AreasOutages {
+    /// The outages for each requested area, keyed by the area's name. Areas which couldn't be
+    /// found have an empty list.
+    #[schema(value_type = Object, example = json!({
+        "western-cape-stellenbosch": [{
+            "area_name": "western-cape-stellenbosch",
+            "stage": 6,
+            "start": "2023-06-01T18:00:00+02:00",
+            "finsh": "2023-06-01T20:30:00+02:00",
+            "source": "https://twitter.com/Eskom_SA/status/1664250326818365440"
+        }],
+        "western-cape-stellenbosh": []
+    }))]
+    pub outages: HashMap<String, Vec<PowerOutage>>,
+
+    /// The requested areas which couldn't be found.
+    #[schema(example = json!(["western-cape-stellenbosh"]))]
+    pub not_found: Vec<String>,
+
+    /// Whether the areas had more outages between them than one response can have. If so, the
+    /// areas were filled in alphabetical order until the limit was reached, and the rest have
+    /// fewer outages (or none) than they should.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub truncated: bool,
+    /// How to get the outages that were left out, if `truncated` is true.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// An outage which starts at the same time in two versions of eskom-calendar's data, but whose
+/// stage or finish time changed.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ChangedOutage {
+    /// The outage as it was in the older version.
+    pub before: PowerOutage,
+    /// The outage as it is now.
+    pub after: PowerOutage,
+}
+
+/// What changed in an area's outages between an older version of eskom-calendar's data and the
+/// current one.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct OutageDiff {
+    /// The area whose outages were compared.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area_name: String,
+
+    /// When the older version of the data was fetched. This is the first version fetched at or
+    /// after the time that was asked for, so it can be a bit later than that time.
+    #[schema(example = "2023-06-01T12:05:00+02:00")]
+    pub compared_with: DateTime<FixedOffset>,
+
+    /// Outages which are new since the older version.
+    pub added: Vec<PowerOutage>,
+
+    /// Outages which were in the older version, but have since been cancelled.
+    pub removed: Vec<PowerOutage>,
+
+    /// Outages which still start at the same time, but whose stage or finish time changed.
+    pub changed: Vec<ChangedOutage>,
+}
+
+impl OutageDiff {
+    /// Compare the outages `before` with the outages `after`. Outages are the same if they have
+    /// the same stage, start, and finish (their sources don't matter), and are changed if they
+    /// only share a start.
+    pub fn between(
+        area_name: String,
+        compared_with: DateTime<FixedOffset>,
+        before: &[PowerOutage],
+        after: &[PowerOutage],
+    ) -> Self {
+        let key = |outage: &PowerOutage| (outage.stage, outage.start, outage.finsh);
+        let before_keys = before.iter().map(key).collect::<HashSet<_>>();
+        let after_keys = after.iter().map(key).collect::<HashSet<_>>();
+
+        let mut removed = before
+            .iter()
+            .filter(|outage| !after_keys.contains(&key(outage)))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut added = vec![];
+        let mut changed = vec![];
+        for outage in after
+            .iter()
+            .filter(|outage| !before_keys.contains(&key(outage)))
+        {
+            match removed.iter().position(|old| old.start == outage.start) {
+                Some(i) => changed.push(ChangedOutage {
+                    before: removed.remove(i),
+                    after: outage.clone(),
+                }),
+                None => added.push(outage.clone()),
+            }
+        }
+        added.sort_by_key(|outage| outage.start);
+        removed.sort_by_key(|outage| outage.start);
+        changed.sort_by_key(|change| change.after.start);
+
+        OutageDiff {
+            area_name,
+            compared_with,
+            added,
+            removed,
+            changed,
+        }
+    }
+}
+
+/// How many hours of loadshedding there are on one day.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct DayHours {
+    /// The day, in South African time.
+    #[schema(example = "2023-06-01")]
+    pub date: NaiveDate,
+    /// How many hours of loadshedding are on this day.
+    #[schema(example = 4.5)]
+    pub hours: f64,
+}
+
+/// What an area's schedule means at one stage of loadshedding, over some window of time. One of
+/// the stages in a `ScheduleComparison`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct StageScenario {
+    /// The stage of loadshedding.
+    #[schema(example = 4)]
+    pub stage: u8,
+
+    /// How many hours the power would be off during the window. Outages which are only partly
+    /// in the window only count for the part that is.
+    #[schema(example = 12.5)]
+    pub total_hours: f64,
+
+    /// The outages there would be at this stage, in order of when they start.
+    pub outages: Vec<PowerOutage>,
+}
+
+impl StageScenario {
+    /// Describe the `outages` there would be at `stage`, between `from` and `to`.
+    pub fn new(
+        stage: u8,
+        outages: Vec<PowerOutage>,
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+    ) -> Self {
+        let total_hours = outages
+            .iter()
+            .map(|outage| {
+                let overlap = outage.finsh.min(to) - outage.start.max(from);
+                overlap.num_seconds().max(0) as f64 / 3600.0
+            })
+            .sum();
+        StageScenario {
+            stage,
+            total_hours,
+            outages,
+        }
+    }
+}
+
+/// How an area's schedule plays out at several stages of loadshedding over the same window, so
+/// that the stages can be compared.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ScheduleComparison {
+    /// The area whose schedule this is.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area_name: String,
+
+    /// When the window starts.
+    #[schema(example = "2023-06-05T00:00:00+02:00")]
+    pub from: DateTime<FixedOffset>,
+
+    /// When the window finishes.
+    #[schema(example = "2023-06-12T00:00:00+02:00")]
+    pub to: DateTime<FixedOffset>,
+
+    /// Each of the stages asked for, lowest stage first.
+    pub stages: Vec<StageScenario>,
+}
+
+/// Figures describing how badly an area is affected by loadshedding, worked out from all of the
+/// area's known outages.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct AreaStats {
+    /// The area these figures are for.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area_name: String,
+
+    /// How many outages there are.
+    #[schema(example = 12)]
+    pub outage_count: usize,
+
+    /// How many hours of loadshedding there are, across every outage.
+    #[schema(example = 27.5)]
+    pub total_hours: f64,
+
+    /// How long the average outage is, in hours. This is 0 if there aren't any outages.
+    #[schema(example = 2.29)]
+    pub average_hours: f64,
+
+    /// How many hours of loadshedding there are at each stage, keyed by the stage.
+    #[schema(value_type = Object, example = json!({"4": 10.0, "6": 17.5}))]
+    pub hours_by_stage: BTreeMap<u8, f64>,
+
+    /// The day with the most hours of loadshedding. Outages that go over midnight count towards
+    /// both days. Will be None if there aren't any outages.
+    pub busiest_day: Option<DayHours>,
+}
+
+/// Split `outage` at each midnight (in SAST), so that every day it covers gets its share. Each
+/// part is the day, along with when the outage starts and finishes on that day.
+fn split_at_midnights(
+    outage: &PowerOutage,
+) -> Vec<(NaiveDate, DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let sast = sast();
+    let mut start = outage.start.with_timezone(&sast);
+    let finsh = outage.finsh.with_timezone(&sast);
+    let mut parts = vec![];
+    while start < finsh {
+        let next_midnight = sast
+            .from_local_datetime(
+                &(start.date_naive() + Duration::days(1))
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            )
+            .unwrap();
+        let end = finsh.min(next_midnight);
+        parts.push((start.date_naive(), start, end));
+        start = end;
+    }
+    parts
+}
+
+/// A summary of one day of loadshedding in an area, which is one row of
+/// `/outages/{area_name}/daily`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct DailySummary {
+    /// The day, in South African time.
+    #[schema(example = "2023-06-01")]
+    pub date: NaiveDate,
+    /// How many minutes the power is off on this day. Outages that go over midnight are split
+    /// between the days they cover.
+    #[schema(example = 270)]
+    pub total_minutes_off: i64,
+    /// The highest stage of any outage on this day, or 0 if there aren't any.
+    #[schema(example = 4)]
+    pub max_stage: u8,
+    /// How many outages there are on this day. Outages that go over midnight count towards both
+    /// days.
+    #[schema(example = 2)]
+    pub num_outages: usize,
+}
+
+impl DailySummary {
+    /// Summarise `outages` for every day from `from` to `to` (including both). Days without any
+    /// outages are included, with everything set to 0.
+    pub fn from_outages(outages: &[PowerOutage], from: NaiveDate, to: NaiveDate) -> Vec<Self> {
+        let mut days = from
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| {
+                let summary = DailySummary {
+                    date,
+                    total_minutes_off: 0,
+                    max_stage: 0,
+                    num_outages: 0,
+                };
+                (date, summary)
+            })
+            .collect::<BTreeMap<_, _>>();
+        for outage in outages {
+            for (date, start, end) in split_at_midnights(outage) {
+                if let Some(day) = days.get_mut(&date) {
+                    day.total_minutes_off += (end - start).num_minutes();
+                    day.max_stage = day.max_stage.max(outage.stage);
+                    day.num_outages += 1;
+                }
+            }
+        }
+        days.into_values().collect()
+    }
+}
+
+impl AreaStats {
+    /// Work out the figures for `area_name`, given that area's outages.
+    pub fn from_outages(area_name: String, outages: &[PowerOutage]) -> Self {
+        let hours = |start: DateTime<FixedOffset>, finsh: DateTime<FixedOffset>| {
+            (finsh - start).num_seconds().max(0) as f64 / 3600.0
+        };
+
+        let mut hours_by_stage = BTreeMap::new();
+        let mut hours_by_day: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+        for outage in outages {
+            *hours_by_stage.entry(outage.stage).or_default() += hours(outage.start, outage.finsh);
+            for (date, start, end) in split_at_midnights(outage) {
+                *hours_by_day.entry(date).or_default() += hours(start, end);
+            }
+        }
+
+        let total_hours = hours_by_stage.values().sum();
+        let busiest_day = hours_by_day
+            .into_iter()
+            // The earliest day wins any ties, since `max_by` would pick the latest
+            .rev()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(date, hours)| DayHours { date, hours });
+        AreaStats {
+            area_name,
+            outage_count: outages.len(),
+            total_hours,
+            average_hours: if outages.is_empty() {
+                0.0
+            } else {
+                total_hours / outages.len() as f64
+            },
+            hours_by_stage,
+            busiest_day,
+        }
+    }
+}
+
+/// Figures describing loadshedding across the whole country.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct NationalStats {
+    /// How many areas are being loadshed right now.
+    #[schema(example = 873)]
+    pub areas_shedding: usize,
+
+    /// How many areas are at each stage right now, keyed by the stage. Areas which have upcoming
+    /// outages but aren't being loadshed right now are counted under stage 0. If an area has
+    /// overlapping outages, it's counted under the highest stage.
+    #[schema(value_type = Object, example = json!({"0": 1201, "4": 673, "6": 200}))]
+    pub areas_by_stage: BTreeMap<u8, usize>,
+
+    /// How many hours of loadshedding are scheduled today (in South African time), added up
+    /// across every area. So two areas with two hours of loadshedding each give four area-hours.
+    #[schema(example = 5123.5)]
+    pub area_hours_today: f64,
+}
+
+impl NationalStats {
+    /// Work out the figures at the time `now`, in one pass over all the known outages.
+    pub fn at(outages: &[PowerOutage], now: DateTime<FixedOffset>) -> Self {
+        let sast = sast();
+        let today = now.with_timezone(&sast).date_naive();
+        let midnight = |date: NaiveDate| {
+            sast.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+                .unwrap()
+        };
+        let (today_start, today_finsh) = (midnight(today), midnight(today + Duration::days(1)));
+
+        let mut stages: HashMap<&str, u8> = HashMap::new();
+        let mut area_seconds_today = 0;
+        for outage in outages {
+            let stage = stages.entry(&outage.area_name).or_default();
+            if outage.is_active_at(now) {
+                *stage = (*stage).max(outage.stage);
+            }
+            let start = outage.start.max(today_start);
+            let finsh = outage.finsh.min(today_finsh);
+            area_seconds_today += (finsh - start).num_seconds().max(0);
+        }
+
+        let mut areas_by_stage = BTreeMap::new();
+        for stage in stages.into_values() {
+            *areas_by_stage.entry(stage).or_default() += 1;
+        }
+        NationalStats {
+            areas_shedding: areas_by_stage
+                .iter()
+                .filter(|(stage, _)| **stage > 0)
+                .map(|(_, count)| count)
+                .sum(),
+            areas_by_stage,
+            area_hours_today: area_seconds_today as f64 / 3600.0,
+        }
+    }
+}
+
+/// Whether or not an area is being loadshed right now.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct CurrentStatus {
+    /// The area this status is for.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub area_name: String,
+
+    /// True if the power is off in this area right now due to loadshedding.
+    #[schema(example = true)]
+    pub is_shedding: bool,
+
+    /// The outage which is happening right now, if any. If multiple outages overlap, this is
+    /// the one with the highest stage.
+    pub current_outage: Option<PowerOutage>,
+
+    /// The next outage to start after right now, if any are known.
+    pub next_outage: Option<PowerOutage>,
+}
+
+impl CurrentStatus {
+    /// Work out the status of `area_name` at the time `now`, given that area's outages.
+    pub fn at(area_name: String, outages: &[PowerOutage], now: DateTime<FixedOffset>) -> Self {
+        let current_outage = outages
+            .iter()
+            .filter(|outage| outage.is_active_at(now))
+            .max_by_key(|outage| outage.stage)
+            .cloned();
+        let next_outage = outages
+            .iter()
+            .filter(|outage| outage.start > now)
+            .min_by_key(|outage| outage.start)
+            .cloned();
+        CurrentStatus {
+            area_name,
+            is_shedding: current_outage.is_some(),
+            current_outage,
+            next_outage,
+        }
+    }
+}
+
+/// Optional query parameters for narrowing down a list of power outages.
+#[derive(FromForm, IntoParams, Default, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct OutageFilter {
+    /// Only include outages that are still happening at or after this ISO-8601 datetime. Outages
+    /// which straddle this datetime are included. If no timezone is given, SAST is assumed.
+    #[param(example = "2023-06-01T00:00:00+02:00")]
+    pub start: Option<String>,
+
+    /// Only include outages that start before this ISO-8601 datetime (note the spelling).
+    /// Outages which straddle this datetime are included. If no timezone is given, SAST is
+    /// assumed.
+    #[param(example = "2023-06-02T00:00:00+02:00")]
+    pub finsh: Option<String>,
+
+    /// Only include outages at this stage or higher. Must be between 0 and 8.
+    #[param(value_type = Option<u8>, example = 4)]
+    pub min_stage: Option<String>,
+
+    /// Only include outages at this stage or lower. Must be between 0 and 8.
+    #[param(value_type = Option<u8>, example = 8)]
+    pub max_stage: Option<String>,
+
+    /// Only include outages which last at least this many minutes. Back-to-back outages are
+    /// merged before this is checked if `merge=true`.
+    #[param(value_type = Option<u32>, example = 120)]
+    pub min_minutes: Option<String>,
+
+    /// Only include outages whose source contains this text, ignoring case. Handy for only
+    /// getting the outages announced by one account, like `Eskom_SA` or `CityofCT`.
+    #[param(example = "Eskom_SA")]
+    pub source_contains: Option<String>,
+}
+
+impl OutageFilter {
+    /// Remove any outages which don't match this filter, or describe why the filter is invalid.
+    pub fn apply(&self, outages: Vec<PowerOutage>) -> Result<Vec<PowerOutage>, String> {
+        let start = self
+            .start
+            .as_deref()
+            .map(|start| parse_datetime("start", start))
+            .transpose()?;
+        let finsh = self
+            .finsh
+            .as_deref()
+            .map(|finsh| parse_datetime("finsh", finsh))
+            .transpose()?;
+        let min_stage = self
+            .min_stage
+            .as_deref()
+            .map(|min_stage| parse_stage("min_stage", min_stage))
+            .transpose()?;
+        let max_stage = self
+            .max_stage
+            .as_deref()
+            .map(|max_stage| parse_stage("max_stage", max_stage))
+            .transpose()?;
+        let min_duration = self
+            .min_minutes
+            .as_deref()
+            .map(|min_minutes| {
+                min_minutes
+                    .trim()
+                    .parse::<u32>()
+                    .map(|minutes| Duration::minutes(minutes.into()))
+                    .map_err(|_| {
+                        format!("`min_minutes={min_minutes}` must be a whole number of minutes")
+                    })
+            })
+            .transpose()?;
+        let source_contains = self
+            .source_contains
+            .as_deref()
+            .map(|source_contains| source_contains.trim().to_lowercase());
+
+        Ok(outages
+            .into_iter()
+            .filter(|outage| start.is_none_or(|start| outage.finsh > start))
+            .filter(|outage| finsh.is_none_or(|finsh| outage.start < finsh))
+            .filter(|outage| min_stage.is_none_or(|min_stage| outage.stage >= min_stage))
+            .filter(|outage| max_stage.is_none_or(|max_stage| outage.stage <= max_stage))
+            .filter(|outage| {
+                min_duration.is_none_or(|min_duration| outage.finsh - outage.start >= min_duration)
+            })
+            .filter(|outage| {
+                source_contains.as_deref().is_none_or(|source_contains| {
+                    outage.source.to_lowercase().contains(source_contains)
+                })
+            })
+            .collect())
+    }
+}
+
+/// The order to give outages in, as asked for with `sort=`. A leading `-` means descending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutageOrder {
+    /// Earliest first. This is the default.
+    #[default]
+    Start,
+    /// Latest first.
+    StartDescending,
+    /// Lowest stage first, and earliest first within each stage.
+    Stage,
+    /// Highest stage first, and earliest first within each stage.
+    StageDescending,
+}
+
+impl OutageOrder {
+    /// Every order, in the same order as they're documented.
+    pub const ALL: [OutageOrder; 4] = [
+        OutageOrder::Start,
+        OutageOrder::StartDescending,
+        OutageOrder::Stage,
+        OutageOrder::StageDescending,
+    ];
+
+    /// How the order is written in the `sort` query parameter, like `-stage`.
+    pub fn param(&self) -> &'static str {
+        match self {
+            OutageOrder::Start => "start",
+            OutageOrder::StartDescending => "-start",
+            OutageOrder::Stage => "stage",
+            OutageOrder::StageDescending => "-stage",
+        }
+    }
+
+    /// The order written as `param` in the `sort` query parameter, if there is one.
+    pub fn from_param(param: &str) -> Option<OutageOrder> {
+        OutageOrder::ALL
+            .into_iter()
+            .find(|order| order.param() == param)
+    }
+
+    /// Sort `outages` into this order.
+    pub fn sort(&self, outages: &mut [PowerOutage]) {
+        outages.sort_by(|a, b| self.compare(OutageKey::of(a), OutageKey::of(b)));
+    }
+
+    /// Compare two outages in this order. Ties are broken by when the outages finish and then by
+    /// their stage, so that outages are always in the same order, which paging relies on.
+    fn compare(&self, a: OutageKey, b: OutageKey) -> Ordering {
+        let order = match self {
+            OutageOrder::Start => a.start.cmp(&b.start),
+            OutageOrder::StartDescending => b.start.cmp(&a.start),
+            OutageOrder::Stage => a.stage.cmp(&b.stage).then(a.start.cmp(&b.start)),
+            OutageOrder::StageDescending => b.stage.cmp(&a.stage).then(a.start.cmp(&b.start)),
+        };
+        order
+            .then(a.finsh.cmp(&b.finsh))
+            .then(a.stage.cmp(&b.stage))
+    }
+}
+
+/// The parts of an outage which it's sorted by. No two of an area's outages have the same key,
+/// since duplicate outages are dropped when machine_friendly.csv is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OutageKey {
+    stage: u8,
+    start: DateTime<FixedOffset>,
+    finsh: DateTime<FixedOffset>,
+}
+
+impl OutageKey {
+    fn of(outage: &PowerOutage) -> Self {
+        OutageKey {
+            stage: outage.stage,
+            start: outage.start,
+            finsh: outage.finsh,
+        }
+    }
+}
+
+/// Where a page of outages ended, so that the next page can carry on from there. It's sent to
+/// users as an opaque string, and only makes sense with the order it was made in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutageCursor {
+    order: OutageOrder,
+    last: OutageKey,
+}
+
+impl OutageCursor {
+    /// A cursor for the outages after `outage`, when they're in `order`.
+    pub fn after(order: OutageOrder, outage: &PowerOutage) -> Self {
+        OutageCursor {
+            order,
+            last: OutageKey::of(outage),
+        }
+    }
+
+    /// Write the cursor as the string given to users.
+    pub fn encode(&self) -> String {
+        let text = format!(
+            "{}|{}|{}|{}",
+            self.order.param(),
+            self.last.stage,
+            self.last.start.to_rfc3339(),
+            self.last.finsh.to_rfc3339()
+        );
+        hex::encode(text)
+    }
+
+    /// Read a cursor given by a user, which must be one made by `encode` for outages in `order`.
+    pub fn decode(cursor: &str, order: OutageOrder) -> Result<Self, String> {
+        let invalid = || format!("`cursor={cursor}` isn't a cursor from a previous page");
+        let text = hex::decode(cursor.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid)?;
+        let [param, stage, start, finsh] = text.split('|').collect::<Vec<_>>()[..] else {
+            return Err(invalid());
+        };
+        if param != order.param() {
+            return Err(format!(
+                "`cursor={cursor}` is for outages sorted by `{param}`, so `sort` must be \
+                `{param}` too"
+            ));
+        }
+        Ok(OutageCursor {
+            order,
+            last: OutageKey {
+                stage: stage.parse().map_err(|_err| invalid())?,
+                start: DateTime::parse_from_rfc3339(start).map_err(|_err| invalid())?,
+                finsh: DateTime::parse_from_rfc3339(finsh).map_err(|_err| invalid())?,
+            },
+        })
+    }
+
+    /// Whether `outage` comes after the cursor, and so belongs on a later page.
+    pub fn is_before(&self, outage: &PowerOutage) -> bool {
+        self.order.compare(self.last, OutageKey::of(outage)) == Ordering::Less
+    }
+}
+
+/// One page of an area's outages, from `/outages/{area_name}` with `limit` or `cursor`.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct OutagesPage {
+    /// The outages on this page.
+    pub outages: Vec<PowerOutage>,
+    /// Give this as `cursor` to get the next page. Will be None if this is the last page.
+    #[schema(example = "73746172747c323032332d30362d30315432303a30303a30302b30323a3030")]
+    pub next_cursor: Option<String>,
+    /// Whether there were more outages than one response can have, so that this page is shorter
+    /// than asked for. The rest are still on the following pages.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub truncated: bool,
+    /// How to get the outages that were left out, if `truncated` is true.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Parse a loadshedding stage given by a user as the query parameter `name`.
+pub fn parse_stage(name: &str, value: &str) -> Result<u8, String> {
+    value
+        .trim()
+        .parse::<u8>()
+        .ok()
+        .filter(|stage| *stage <= 8)
+        .ok_or_else(|| format!("`{name}={value}` must be a stage between 0 and 8 (inclusive)"))
+}
+
+/// Parse a date like `2023-06-01` given by a user as the query parameter `name`.
+pub fn parse_date(name: &str, value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_err| format!("`{name}={value}` must be a date like 2023-06-01"))
+}
+
+/// Parse an ISO-8601 datetime given by a user as the query parameter `name`. Datetimes without a
+/// timezone are assumed to be in SAST.
+pub fn parse_datetime(name: &str, value: &str) -> Result<DateTime<FixedOffset>, String> {
+    // A `+` in a URL's query gets decoded as a space, so `+02:00` will often arrive as ` 02:00`
+    let value = value.trim().replace(' ', "+");
+    DateTime::parse_from_rfc3339(&value)
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S")
+                .ok()
+                .and_then(|naive| sast().from_local_datetime(&naive).single())
+        })
+        .ok_or_else(|| {
+            format!(
+                "Couldn't parse `{name}={value}` as an ISO-8601 datetime. Try something like \
+                `2023-06-01T18:00:00+02:00` (remember to URL-encode the `+` as `%2B`)"
+            )
+        })
+}
+
+/// The next power outage that will happen in an area.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct NextOutage {
+    /// How many minutes from now until the outage starts (rounded down).
+    #[schema(example = 42)]
+    pub minutes_until: i64,
+
+    /// The outage itself.
+    pub outage: PowerOutage,
+}
+
+impl NextOutage {
+    /// Find the first outage which starts strictly after `now`. Outages which are already in
+    /// progress at `now` are skipped.
+    pub fn after(outages: &[PowerOutage], now: DateTime<FixedOffset>) -> Option<Self> {
+        outages
+            .iter()
+            .filter(|outage| outage.start > now)
+            .min_by_key(|outage| outage.start)
+            .map(|outage| NextOutage {
+                minutes_until: (outage.start - now).num_minutes(),
+                outage: outage.clone(),
+            })
+    }
+}
+
+/// The loadshedding stage of the whole country.
+///
+/// Different areas can technically be at different stages at the same time, so this is the
+/// highest stage of any area.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct NationalStage {
+    /// The highest stage of any outage happening right now, or 0 if nobody is being loadshed.
+    #[schema(example = 4)]
+    pub stage: u8,
+
+    /// When the country went to `stage`. Will be None if the country has been at `stage` since
+    /// before the earliest known outage.
+    #[schema(example = "2023-06-01T16:00:00+02:00")]
+    pub effective_from: Option<DateTime<FixedOffset>>,
+
+    /// The next time the national stage changes, if any changes are known.
+    pub next_change: Option<StageChange>,
+}
+
+/// A change of the national loadshedding stage.
+#[derive(Deserialize, Serialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct StageChange {
+    /// When the stage changes.
+    #[schema(example = "2023-06-01T22:00:00+02:00")]
+    pub at: DateTime<FixedOffset>,
+
+    /// The stage after the change.
+    #[schema(example = 2)]
+    pub stage: u8,
+}
+
+impl NationalStage {
+    /// Work out the national stage at the time `now`, given every area's outages.
+    pub fn at(outages: &[PowerOutage], now: DateTime<FixedOffset>) -> Self {
+        let stage = max_stage_at(outages, now);
+
+        // The national stage can only change when an outage starts or finishes
+        let mut changes = outages
+            .iter()
+            .flat_map(|outage| [outage.start, outage.finsh])
+            .collect::<Vec<_>>();
+        changes.sort();
+        changes.dedup();
+        let (past, future) = changes.split_at(changes.partition_point(|change| *change <= now));
+
+        let effective_from = past
+            .iter()
+            .rev()
+            .take_while(|change| max_stage_at(outages, **change) == stage)
+            .last()
+            .copied();
+        let next_change = future.iter().find_map(|change| {
+            let next_stage = max_stage_at(outages, *change);
+            (next_stage != stage).then_some(StageChange {
+                at: *change,
+                stage: next_stage,
+            })
+        });
+
+        NationalStage {
+            stage,
+            effective_from,
+            next_change,
+        }
+    }
+}
+
+/// A stretch of time when an area's power is on, between two of its outages.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct PowerGap {
+    /// When the power comes back on, or the start of the window if it's already on then.
+    #[schema(example = "2023-06-01T20:30:00+02:00")]
+    pub start: DateTime<FixedOffset>,
+
+    /// When the power goes off again, or the end of the window if it doesn't.
+    ///
+    /// Note that this is spelt `finsh`, without the second `i`, so that it lines up with `start`.
+    #[schema(example = "2023-06-02T10:00:00+02:00")]
+    pub finsh: DateTime<FixedOffset>,
+
+    /// How many minutes the power is on for.
+    #[schema(example = 810)]
+    pub minutes: i64,
+}
+
+impl PowerGap {
+    /// The times between `from` and `to` when none of `outages` are happening. Outages which
+    /// overlap or are back-to-back don't have a gap between them.
+    pub fn between(
+        outages: &[PowerOutage],
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+    ) -> Vec<Self> {
+        OutlookSegment::timeline(outages, from, to)
+            .into_iter()
+            .filter(|segment| segment.stage == 0)
+            .map(|segment| PowerGap {
+                start: segment.from,
+                finsh: segment.to,
+                minutes: (segment.to - segment.from).num_minutes(),
+            })
+            .collect()
+    }
+}
+
+/// A stretch of time during which an area is at one stage of loadshedding.
+#[derive(Deserialize, Serialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct OutlookSegment {
+    #[schema(example = "2023-06-01T18:00:00+02:00")]
+    pub from: DateTime<FixedOffset>,
+
+    #[schema(example = "2023-06-01T20:30:00+02:00")]
+    pub to: DateTime<FixedOffset>,
+
+    /// The stage of loadshedding during this segment, or 0 if there's no loadshedding.
+    #[schema(example = 4)]
+    pub stage: u8,
+}
+
+impl OutlookSegment {
+    /// Collapse `outages` into a timeline covering `from` until `to`. Times without any outages
+    /// are stage 0, overlapping outages use the highest stage, and neighbouring segments with the
+    /// same stage are merged.
+    pub fn timeline(
+        outages: &[PowerOutage],
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+    ) -> Vec<Self> {
+        let mut changes = outages
+            .iter()
+            .flat_map(|outage| [outage.start, outage.finsh])
+            .filter(|change| from < *change && *change < to)
+            .chain([from, to])
+            .collect::<Vec<_>>();
+        changes.sort();
+        changes.dedup();
+
+        let mut segments: Vec<OutlookSegment> = vec![];
+        for window in changes.windows(2) {
+            let stage = max_stage_at(outages, window[0]);
+            match segments.last_mut() {
+                Some(previous) if previous.stage == stage => previous.to = window[1],
+                _ => segments.push(OutlookSegment {
+                    from: window[0],
+                    to: window[1],
+                    stage,
+                }),
+            }
+        }
+        segments
+    }
+}
+
+/// The highest stage of all the outages happening at `time`, or 0 if there aren't any.
+fn max_stage_at(outages: &[PowerOutage], time: DateTime<FixedOffset>) -> u8 {
+    outages
+        .iter()
+        .filter(|outage| outage.is_active_at(time))
+        .map(|outage| outage.stage)
+        .max()
+        .unwrap_or(0)
+}
+
+/// A generic search result that gets returned after you searched for something.
+///
+/// It simply wraps the object you were looking for with a score for how well that object matched
+/// your search query. Higher numbers are better.
+#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct SearchResult<T> {
+    /// How close a match the thing is to the search query, out of 100. The best match of a search
+    /// scores 100, and the others are scored relative to it.
+    #[schema(example = 100)]
+    pub score: i64,
+    /// The fuzzy matcher's own score, which grows with the length of the search query. Higher is
+    /// better.
+    #[schema(example = 176)]
+    pub raw_score: i64,
+    /// The thing that's been found
+    #[schema(value_type=Area)]
     pub result: T,
+    /// Which characters matched the search query. Only sent if asked for with `highlight=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<SearchHighlight>,
+}
+
+/// Which characters of a search result matched the search query, so that they can be shown in
+/// bold.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct SearchHighlight {
+    /// The name that matched the query best. This is the area's name, or one of its aliases.
+    #[schema(example = "western-cape-stellenbosch")]
+    pub name: String,
+    /// The runs of characters in `name` which matched, as `[start, end)` character indices.
+    #[schema(example = json!([[0, 4], [15, 19]]))]
+    pub ranges: Vec<[usize; 2]>,
+}
+
+impl SearchHighlight {
+    /// Group the indices of the characters in `name` which matched (in increasing order) into
+    /// runs of consecutive characters.
+    pub fn new(name: String, indices: &[usize]) -> Self {
+        let mut ranges: Vec<[usize; 2]> = vec![];
+        for &index in indices {
+            match ranges.last_mut() {
+                Some(range) if range[1] == index => range[1] = index + 1,
+                _ => ranges.push([index, index + 1]),
+            }
+        }
+        SearchHighlight { name, ranges }
+    }
+}
+
+/// What kind of thing a `SearchMatch` is.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum SearchKind {
+    Province,
+    Municipality,
+    Area,
+}
+
+/// The thing a `SearchMatch` found, which is described by the match's `kind`.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde", untagged)]
+pub enum SearchMatchResult {
+    Area(Area),
+    Province(Province),
+    Municipality(Municipality),
+}
+
+/// One result of searching areas, provinces, and municipalities all at once.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct SearchMatch {
+    /// Whether this is an area, a province, or a municipality.
+    #[schema(example = "province")]
+    pub kind: SearchKind,
+    /// How close a match this is to the search query, out of 100. The best match of a search
+    /// scores 100, and the others are scored relative to it.
+    #[schema(example = 100)]
+    pub score: i64,
+    /// The fuzzy matcher's own score, which grows with the length of the search query. Higher is
+    /// better.
+    #[schema(example = 176)]
+    pub raw_score: i64,
+    /// The name to use with the other endpoints: an area name for `/outages/{area_name}`, a
+    /// province for `/areas/province/{province}`, or a municipality for
+    /// `/areas/municipality/{municipality}`.
+    #[schema(example = "gauteng")]
+    pub name: String,
+    /// The area, province, or municipality that matched.
+    pub result: SearchMatchResult,
 }
 
 impl<T> Ord for SearchResult<T> {
@@ -647,7 +3231,7 @@ impl<T> Ord for SearchResult<T> {
 
 impl<T> PartialOrd for SearchResult<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.score.cmp(&other.score))
+        Some(self.cmp(other))
     }
 }
 
@@ -1,16 +1,5324 @@
-use crate::{build_rocket, rocket};
-use rocket::http::Status;
+use crate::aliases::AreaAliases;
+use crate::area_schedules::AreaSchedules;
+use crate::cache::{
+    CacheWarmer, DataSources, Fetched, Fetcher, GitHubFetcher, LongPollRefresher, OutageCache,
+    RetryPolicy, UpstreamError, DEFAULT_MACHINE_FRIENDLY_URL, DEFAULT_TTL,
+};
+use crate::client_ip::ProxyPolicy;
+use crate::compression::MIN_COMPRESSED_BYTES;
+use crate::cors::CorsPolicy;
+use crate::email::{is_valid_email, EmailPolicy};
+use crate::error::{ApiError, ApiErrorBody, RETRY_AFTER_SECONDS};
+use crate::geo::{self, AreaBoundaries};
+use crate::holidays::PublicHolidays;
+use crate::http_client::{self, HttpClients};
+use crate::language::AcceptLanguage;
+use crate::logging::{self, LogFormat};
+use crate::path_guard::MAX_SEGMENT_LEN;
+use crate::rate_limit::RateLimiter;
+use crate::request_id::REQUEST_ID_HEADER;
+use crate::response_cap::OutageCap;
+use crate::structs::{
+    municipality_from_area_name, province_from_area_name, AllOutages, Area, AreaId, AreaStats,
+    AreasCurrentStatus, AreasOutages, AreasPage, CacheStatus, ContiguousRegion, Coords,
+    CurrentStatus, DailySummary, DayHours, DayType, DistrictMunic, ExpandedOutage, Health,
+    LocalMunic, MetroMunic, Municipality, MunicipalityInfo, MunicipalityKind, NationalStage,
+    NationalStats, NextOutage, OutageDiff, OutagesPage, OutlookSegment, PowerGap, PowerOutage,
+    Province, ProvinceInfo, RateLimitStatus, RawMonthlyShedding, RawPeriodicShedding,
+    RawWeeklyShedding, Recurrence, RecurringOutage, RecurringSchedule, ScheduleComparison,
+    ScheduleGap, ScheduleId, ScheduleReport, SearchKind, SearchMatch, SearchMatchResult,
+    SearchResult, ServiceStatus, StageChange, StageChangeNotification, Subscription, Version,
+};
+use crate::versions::{sast, sast_now};
+use crate::webhooks::{sign, Webhooks, MAX_SUBSCRIPTIONS_PER_SUBSCRIBER, SIGNATURE_HEADER};
+use crate::{build_rocket, build_rocket_with, rocket};
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset, NaiveDate, NaiveTime};
+use flate2::read::GzDecoder;
+use rocket::http::{Accept, ContentType, Header, Status};
 use rocket::local::blocking::Client;
+use rocket::response::Responder;
+use rocket::State;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const MACHINE_FRIENDLY: &str = "\
+area_name,start,finsh,stage,source
+western-cape-stellenbosch,2023-06-01T18:00:00+02:00,2023-06-01T20:30:00+02:00,6,https://twitter.com/Eskom_SA/status/1664250326818365440
+western-cape-stellenbosch,2023-06-02T10:00:00+02:00,2023-06-02T12:30:00+02:00,4,https://twitter.com/Eskom_SA/status/1664250326818365440
+city-of-cape-town-area-15,2023-06-01T20:00:00+02:00,2023-06-01T22:00:00+02:00,4,https://twitter.com/CityofCT/status/1664263353022742528
+";
+
+/// Areas which have a schedule, but which don't have any outages in any of the fixtures.
+const QUIET_AREAS: [&str; 1] = ["western-cape-quiet-town"];
+
+/// Make a machine_friendly.csv row for a power outage in `area_name` starting `start_mins` minutes
+/// from now and finishing `finsh_mins` minutes from now.
+fn row_from_now(area_name: &str, start_mins: i64, finsh_mins: i64, stage: u8) -> String {
+    let now = sast_now();
+    format!(
+        "{area_name},{},{},{stage},https://twitter.com/Eskom_SA\n",
+        (now + ChronoDuration::minutes(start_mins)).to_rfc3339(),
+        (now + ChronoDuration::minutes(finsh_mins)).to_rfc3339(),
+    )
+}
+
+/// Make a machine_friendly.csv with the given rows.
+fn machine_friendly(rows: &[String]) -> String {
+    format!("area_name,start,finsh,stage,source\n{}", rows.concat())
+}
+
+/// Build a client whose machine_friendly.csv is `body`.
+fn client_with(body: &str) -> Client {
+    let (cache, _fetches) = fake_cache(body, Duration::ZERO);
+    Client::tracked(build_rocket_with(cache)).expect("valid rocket instance")
+}
+
+fn datetime(s: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(s).unwrap()
+}
+
+fn outage(start: &str, finsh: &str, stage: u8) -> PowerOutage {
+    PowerOutage {
+        area_name: "western-cape-stellenbosch".to_string(),
+        stage,
+        start: datetime(start),
+        finsh: datetime(finsh),
+        source: "https://twitter.com/Eskom_SA".to_string(),
+    }
+}
+
+/// A fetcher that always returns the same text and counts how often it was asked to fetch. If it
+/// has an ETag, it behaves like GitHub and says the text is unchanged when given that ETag.
+struct FakeFetcher {
+    body: String,
+    etag: Option<String>,
+    fetches: Arc<AtomicUsize>,
+    delay: Duration,
+}
+
+/// A weekly schedule, served as the schedule of `western-cape-stellenbosch` and
+/// `city-of-cape-town-area-15`.
+const SCHEDULE_FIXTURE: &str = "\
+start_time,finsh_time,stage,day_of_week
+22:00,00:30,1,1
+08:00,10:30,2,3
+";
+
+/// The metadata for `western-cape-stellenbosch`'s schedule. `city-of-cape-town-area-15`'s schedule
+/// doesn't have any metadata.
+const SCHEDULE_METADATA_FIXTURE: &str = r#"{
+    "source": ["https://www.eskom.co.za/distribution/wp-content/uploads/2022/09/WesternCape_LS.xlsx"],
+    "info": ["https://www.eskom.co.za/distribution/customer-service/outages/"],
+    "last_updated": "2023-05-01T12:00:00",
+    "valid_from": "2023-05-15T00:00:00",
+    "valid_until": "2024-05-15T00:00:00"
+}"#;
+
+#[rocket::async_trait]
+impl Fetcher for FakeFetcher {
+    async fn fetch(&self, url: &str, etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        let sources = DataSources::default();
+        let schedules = ["western-cape-stellenbosch", "city-of-cape-town-area-15"];
+        if schedules
+            .iter()
+            .any(|area| url == sources.schedule_url(area))
+        {
+            return Ok(Fetched::Modified {
+                text: SCHEDULE_FIXTURE.to_string(),
+                etag: None,
+            });
+        } else if url == sources.schedule_metadata_url("western-cape-stellenbosch") {
+            return Ok(Fetched::Modified {
+                text: SCHEDULE_METADATA_FIXTURE.to_string(),
+                etag: None,
+            });
+        } else if url != DEFAULT_MACHINE_FRIENDLY_URL {
+            return Ok(Fetched::NotFound);
+        }
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        if etag.is_some() && etag == self.etag.as_deref() {
+            return Ok(Fetched::NotModified);
+        }
+        Ok(Fetched::Modified {
+            text: self.body.clone(),
+            etag: self.etag.clone(),
+        })
+    }
+
+    async fn exists(&self, url: &str) -> Result<bool, UpstreamError> {
+        let sources = DataSources::default();
+        Ok(url == DEFAULT_MACHINE_FRIENDLY_URL
+            || QUIET_AREAS
+                .iter()
+                .any(|area_name| url == sources.schedule_url(area_name)))
+    }
+}
+
+/// Create a cache that serves `body` as machine_friendly.csv, along with a counter of how many
+/// times it was fetched.
+fn fake_cache(body: &str, delay: Duration) -> (OutageCache, Arc<AtomicUsize>) {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetcher = FakeFetcher {
+        body: body.to_string(),
+        etag: None,
+        fetches: Arc::clone(&fetches),
+        delay,
+    };
+    (OutageCache::new(fetcher, DEFAULT_TTL), fetches)
+}
 
 #[test]
 fn non_empty_all_areas() {
     let client = Client::tracked(build_rocket()).expect("valid rocket instance");
-    let response = client.get(uri!(crate::latest::list_all_areas)).dispatch();
+    // The cache is warmed up in the background when the API starts, and until then requests get
+    // `503 Service Unavailable`
+    let cache = client.rocket().state::<OutageCache>().unwrap();
+    while cache.is_warming_up() {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let response = client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .dispatch();
     assert_eq!(response.status(), Status::Ok);
-    let list_of_areas = response.into_json::<Vec<String>>().unwrap();
+    let list_of_areas = response.into_json::<AreasPage>().unwrap().areas;
     assert!(
         !list_of_areas.is_empty(),
         "All areas length was {} which is not >0",
         list_of_areas.len()
     );
 }
+
+#[test]
+fn cache_only_fetches_once() {
+    let (cache, fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let response = client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response
+            .into_json::<Vec<serde_json::Value>>()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn cache_refetches_after_ttl() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetcher = FakeFetcher {
+        body: MACHINE_FRIENDLY.to_string(),
+        etag: None,
+        fetches: Arc::clone(&fetches),
+        delay: Duration::ZERO,
+    };
+    let cache = OutageCache::new(fetcher, Duration::ZERO);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .dispatch();
+    client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .dispatch();
+
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+}
+
+#[rocket::async_test]
+async fn concurrent_requests_share_one_refresh() {
+    let (cache, fetches) = fake_cache(MACHINE_FRIENDLY, Duration::from_millis(50));
+    let cache = Arc::new(cache);
+
+    let handles = (0..10)
+        .map(|_| {
+            let cache = Arc::clone(&cache);
+            tokio::spawn(async move { cache.outages().await.map(|outages| outages.len()) })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap(), Ok(3));
+    }
+
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[rocket::async_test]
+async fn not_modified_reuses_parsed_outages() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetcher = FakeFetcher {
+        body: MACHINE_FRIENDLY.to_string(),
+        etag: Some("\"abc123\"".to_string()),
+        fetches: Arc::clone(&fetches),
+        delay: Duration::ZERO,
+    };
+    // A TTL of zero means every call goes to the fetcher
+    let cache = OutageCache::new(fetcher, Duration::ZERO);
+
+    let first = cache.outages().await.unwrap();
+    let second = cache.outages().await.unwrap();
+
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    assert!(
+        Arc::ptr_eq(&first, &second),
+        "machine_friendly.csv was re-parsed after a 304"
+    );
+    assert_eq!(cache.status().await.etag.as_deref(), Some("\"abc123\""));
+}
+
+#[test]
+fn cache_status_before_and_after_fetch() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let response = client.get(uri!(crate::latest::cache_status)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let status = response.into_json::<serde_json::Value>().unwrap();
+    assert!(status["last_fetched"].is_null());
+
+    client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .dispatch();
+
+    let response = client.get(uri!(crate::latest::cache_status)).dispatch();
+    let status = response.into_json::<serde_json::Value>().unwrap();
+    assert!(status["last_fetched"].is_string());
+    assert_eq!(status["ttl_seconds"], DEFAULT_TTL.as_secs());
+}
+
+#[test]
+fn current_status_at_boundaries() {
+    let outages = vec![
+        outage("2023-06-01T18:00:00+02:00", "2023-06-01T20:00:00+02:00", 4),
+        outage("2023-06-01T20:00:00+02:00", "2023-06-01T22:00:00+02:00", 2),
+    ];
+    let area = "western-cape-stellenbosch".to_string();
+
+    let before = CurrentStatus::at(
+        area.clone(),
+        &outages,
+        datetime("2023-06-01T17:59:59+02:00"),
+    );
+    assert!(!before.is_shedding);
+    assert_eq!(before.next_outage, Some(outages[0].clone()));
+
+    // Exactly at the start of an outage counts as being in it
+    let at_start = CurrentStatus::at(
+        area.clone(),
+        &outages,
+        datetime("2023-06-01T18:00:00+02:00"),
+    );
+    assert_eq!(at_start.current_outage, Some(outages[0].clone()));
+    assert_eq!(at_start.next_outage, Some(outages[1].clone()));
+
+    // Exactly at the end of one outage and the start of the next is only in the next one
+    let handover = CurrentStatus::at(
+        area.clone(),
+        &outages,
+        datetime("2023-06-01T20:00:00+02:00"),
+    );
+    assert_eq!(handover.current_outage, Some(outages[1].clone()));
+    assert_eq!(handover.next_outage, None);
+
+    let after = CurrentStatus::at(area, &outages, datetime("2023-06-01T22:00:00+02:00"));
+    assert!(!after.is_shedding);
+    assert_eq!(after.current_outage, None);
+}
+
+#[test]
+fn current_status_prefers_highest_overlapping_stage() {
+    let outages = vec![
+        outage("2023-06-01T18:00:00+02:00", "2023-06-01T20:00:00+02:00", 2),
+        outage("2023-06-01T19:00:00+02:00", "2023-06-01T21:00:00+02:00", 6),
+        outage("2023-06-01T18:30:00+02:00", "2023-06-01T19:30:00+02:00", 4),
+    ];
+    let status = CurrentStatus::at(
+        "western-cape-stellenbosch".to_string(),
+        &outages,
+        datetime("2023-06-01T19:15:00+02:00"),
+    );
+    assert!(status.is_shedding);
+    assert_eq!(status.current_outage.unwrap().stage, 6);
+}
+
+#[test]
+fn current_endpoint() {
+    let client = client_with(&machine_friendly(&[
+        row_from_now("western-cape-stellenbosch", -30, 90, 4),
+        row_from_now("western-cape-stellenbosch", 600, 720, 2),
+        row_from_now("city-of-cape-town-area-15", 60, 120, 4),
+    ]));
+
+    let response = client
+        .get(uri!(crate::latest::current("western-cape-stellenbosch")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let status = response.into_json::<CurrentStatus>().unwrap();
+    assert!(status.is_shedding);
+    assert_eq!(status.current_outage.unwrap().stage, 4);
+    assert_eq!(status.next_outage.unwrap().stage, 2);
+
+    let response = client
+        .get(uri!(crate::latest::current("city-of-cape-town-area-15")))
+        .dispatch();
+    let status = response.into_json::<CurrentStatus>().unwrap();
+    assert!(!status.is_shedding);
+    assert!(status.next_outage.is_some());
+}
+
+#[test]
+fn next_skips_outage_in_progress() {
+    let client = client_with(&machine_friendly(&[
+        row_from_now("western-cape-stellenbosch", 600, 720, 2),
+        row_from_now("western-cape-stellenbosch", -30, 90, 4),
+        row_from_now("western-cape-stellenbosch", 1440, 1560, 6),
+    ]));
+
+    let response = client
+        .get(uri!(crate::latest::next("western-cape-stellenbosch")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let next = response.into_json::<NextOutage>().unwrap();
+    assert_eq!(next.outage.stage, 2);
+    assert!(
+        (599..=600).contains(&next.minutes_until),
+        "Expected ~600 minutes until the next outage, got {}",
+        next.minutes_until
+    );
+}
+
+#[test]
+fn next_with_no_future_outages() {
+    let client = client_with(&machine_friendly(&[
+        row_from_now("western-cape-stellenbosch", -300, -180, 2),
+        row_from_now("western-cape-stellenbosch", -30, 90, 4),
+    ]));
+
+    let response = client
+        .get(uri!(crate::latest::next("western-cape-stellenbosch")))
+        .dispatch();
+    assert_eq!(response.status(), Status::NoContent);
+    assert!(response.into_string().unwrap_or_default().is_empty());
+}
+
+/// Outages for one area over two days, used for testing filters.
+const FILTER_FIXTURE: &str = "\
+area_name,start,finsh,stage,source
+western-cape-stellenbosch,2023-06-01T08:00:00+02:00,2023-06-01T10:30:00+02:00,2,https://twitter.com/Eskom_SA/status/1
+western-cape-stellenbosch,2023-06-01T18:00:00+02:00,2023-06-01T20:30:00+02:00,4,https://twitter.com/Eskom_SA/status/1
+western-cape-stellenbosch,2023-06-02T02:00:00+02:00,2023-06-02T04:30:00+02:00,6,https://twitter.com/Eskom_SA/status/2
+western-cape-stellenbosch,2023-06-02T16:00:00+02:00,2023-06-02T18:30:00+02:00,8,https://twitter.com/CityofCT/status/3
+";
+
+/// Get the outages for Stellenbosch from [`FILTER_FIXTURE`] with the given query string. The
+/// fixtures' outages are long finished, so past outages are always included.
+fn filtered_outages(client: &Client, query: &str) -> (Status, Vec<PowerOutage>) {
+    let response = client
+        .get(format!(
+            "/outages/western-cape-stellenbosch?include_past=true&{query}"
+        ))
+        .dispatch();
+    let status = response.status();
+    (status, response.into_json().unwrap_or_default())
+}
+
+fn stages(outages: &[PowerOutage]) -> Vec<u8> {
+    outages.iter().map(|outage| outage.stage).collect()
+}
+
+#[test]
+fn outages_filtered_by_window() {
+    let client = client_with(FILTER_FIXTURE);
+
+    // The window straddles the outages at stage 4 and stage 6, so they're included
+    let (status, outages) = filtered_outages(
+        &client,
+        "start=2023-06-01T19:00:00%2B02:00&finsh=2023-06-02T03:00:00%2B02:00",
+    );
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), vec![4, 6]);
+
+    let (_, outages) = filtered_outages(&client, "start=2023-06-02T00:00:00%2B02:00");
+    assert_eq!(stages(&outages), vec![6, 8]);
+
+    // Datetimes without a timezone are assumed to be SAST
+    let (_, outages) = filtered_outages(&client, "finsh=2023-06-01T18:00:00");
+    assert_eq!(stages(&outages), vec![2]);
+
+    // An unescaped `+` gets decoded to a space, but should still work
+    let (_, outages) = filtered_outages(&client, "finsh=2023-06-01T18:00:01+02:00");
+    assert_eq!(stages(&outages), vec![2, 4]);
+}
+
+#[test]
+fn outages_with_invalid_window() {
+    let client = client_with(FILTER_FIXTURE);
+    let response = client
+        .get("/outages/western-cape-stellenbosch?start=yesterday")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(response.into_string().unwrap().contains("start=yesterday"));
+}
+
+#[test]
+fn outages_filtered_by_stage() {
+    let client = client_with(FILTER_FIXTURE);
+
+    let (status, outages) = filtered_outages(&client, "min_stage=4&max_stage=6");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), vec![4, 6]);
+
+    let (_, outages) = filtered_outages(&client, "min_stage=0&max_stage=8");
+    assert_eq!(stages(&outages), vec![2, 4, 6, 8]);
+
+    let (_, outages) = filtered_outages(&client, "min_stage=8");
+    assert_eq!(stages(&outages), vec![8]);
+
+    let (_, outages) = filtered_outages(&client, "max_stage=2");
+    assert_eq!(stages(&outages), vec![2]);
+
+    let (_, outages) = filtered_outages(&client, "min_stage=6&max_stage=4");
+    assert!(outages.is_empty());
+
+    // Stage filters combine with the datetime window
+    let (_, outages) = filtered_outages(&client, "min_stage=4&finsh=2023-06-02T00:00:00");
+    assert_eq!(stages(&outages), vec![4]);
+}
+
+#[test]
+fn outages_with_invalid_stage() {
+    let client = client_with(FILTER_FIXTURE);
+    for query in [
+        "min_stage=9",
+        "max_stage=-1",
+        "min_stage=four",
+        "max_stage=256",
+    ] {
+        let (status, _) = filtered_outages(&client, query);
+        assert_eq!(status, Status::BadRequest, "{query} should be rejected");
+    }
+}
+
+#[test]
+fn outages_as_ics() {
+    let client = client_with(FILTER_FIXTURE);
+    let response = client
+        .get(uri!(crate::latest::outages_ics(
+            "western-cape-stellenbosch"
+        )))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::Calendar));
+    let ics = response.into_string().unwrap();
+
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 4);
+    assert_eq!(ics.matches("END:VEVENT").count(), 4);
+    assert!(ics.contains("SUMMARY:Stage 6 loadshedding"));
+    // 2023-06-02T02:00:00+02:00 in UTC
+    assert!(ics.contains("DTSTART:20230602T000000Z"));
+    assert!(ics.lines().all(|line| line.len() <= 75));
+
+    // UIDs are unique per outage, and stay the same across requests
+    let uids = |ics: &str| {
+        ics.lines()
+            .filter(|line| line.starts_with("UID:"))
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>()
+    };
+    let first_uids = uids(&ics);
+    assert_eq!(first_uids.iter().collect::<HashSet<_>>().len(), 4);
+    let ics = client
+        .get(uri!(crate::latest::outages_ics(
+            "western-cape-stellenbosch"
+        )))
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert_eq!(uids(&ics), first_uids);
+}
+
+#[test]
+fn outages_as_json_by_default() {
+    let client = client_with(FILTER_FIXTURE);
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true")
+        .header(Accept::JSON)
+        .dispatch();
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+    assert_eq!(response.into_json::<Vec<PowerOutage>>().unwrap().len(), 4);
+
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+}
+
+#[test]
+fn outages_as_csv_when_accepted() {
+    let client = client_with(FILTER_FIXTURE);
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true&min_stage=6")
+        .header(Accept::CSV)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::CSV));
+
+    let body = response.into_string().unwrap();
+    assert!(body.starts_with("area_name,stage,start,finsh,source\n"));
+    let outages = csv::Reader::from_reader(body.as_bytes())
+        .deserialize::<PowerOutage>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(stages(&outages), vec![6, 8]);
+    assert_eq!(outages[0].start, datetime("2023-06-02T02:00:00+02:00"));
+}
+
+#[test]
+fn outages_for_several_areas() {
+    let (cache, fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let response = client
+        .get("/outages?areas=western-cape-stellenbosch,city-of-cape-town-area-15,atlantis,narnia")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let areas = response.into_json::<AreasOutages>().unwrap();
+
+    assert_eq!(areas.outages.len(), 4);
+    assert_eq!(areas.outages["western-cape-stellenbosch"].len(), 2);
+    assert_eq!(areas.outages["city-of-cape-town-area-15"].len(), 1);
+    assert!(areas.outages["atlantis"].is_empty());
+    assert!(areas.outages["narnia"].is_empty());
+    assert_eq!(areas.not_found, vec!["atlantis", "narnia"]);
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn outages_for_several_areas_with_filter() {
+    let client = client_with(MACHINE_FRIENDLY);
+
+    let response = client
+        .get("/outages?areas=western-cape-stellenbosch,%20city-of-cape-town-area-15&min_stage=5")
+        .dispatch();
+    let areas = response.into_json::<AreasOutages>().unwrap();
+    assert_eq!(stages(&areas.outages["western-cape-stellenbosch"]), vec![6]);
+    assert!(areas.outages["city-of-cape-town-area-15"].is_empty());
+    // The area exists, it just doesn't have any outages that match the filter
+    assert!(areas.not_found.is_empty());
+
+    let response = client
+        .get("/outages?areas=western-cape-stellenbosch&min_stage=10")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+/// Two squares which share the edge at lng=1.0, and a triangle off to the side.
+const BOUNDARIES_FIXTURE: &str = r#"{
+  "type": "FeatureCollection",
+  "features": [
+    {
+      "type": "Feature",
+      "properties": { "area_name": "west-square" },
+      "geometry": {
+        "type": "Polygon",
+        "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]]]
+      }
+    },
+    {
+      "type": "Feature",
+      "properties": { "area_name": "east-square-and-triangle" },
+      "geometry": {
+        "type": "MultiPolygon",
+        "coordinates": [
+          [[[1.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [1.0, 0.0]]],
+          [[[5.0, 5.0], [7.0, 5.0], [6.0, 7.0], [5.0, 5.0]]]
+        ]
+      }
+    }
+  ]
+}"#;
+
+fn coords(lat: f64, lng: f64) -> Coords {
+    Coords { lat, lng }
+}
+
+#[test]
+fn point_in_polygon() {
+    let square = ContiguousRegion {
+        boundary: vec![
+            coords(0.0, 0.0),
+            coords(0.0, 1.0),
+            coords(1.0, 1.0),
+            coords(1.0, 0.0),
+        ],
+    };
+    assert!(square.contains(&coords(0.5, 0.5)));
+    assert!(!square.contains(&coords(1.5, 0.5)));
+    assert!(!square.contains(&coords(-0.5, 0.5)));
+    // Points on edges and vertices are inside
+    assert!(square.contains(&coords(0.0, 0.5)));
+    assert!(square.contains(&coords(1.0, 1.0)));
+    // A point level with a vertex shouldn't get counted twice by the ray
+    assert!(!square.contains(&coords(1.0, -1.0)));
+
+    let concave = ContiguousRegion {
+        boundary: vec![
+            coords(0.0, 0.0),
+            coords(0.0, 3.0),
+            coords(3.0, 3.0),
+            coords(3.0, 2.0),
+            coords(1.0, 2.0),
+            coords(1.0, 1.0),
+            coords(3.0, 1.0),
+            coords(3.0, 0.0),
+        ],
+    };
+    assert!(concave.contains(&coords(0.5, 1.5)));
+    assert!(!concave.contains(&coords(2.0, 1.5)));
+    assert!(concave.contains(&coords(2.0, 2.5)));
+}
+
+#[test]
+fn areas_containing_point() {
+    let boundaries = AreaBoundaries::from_geojson(BOUNDARIES_FIXTURE).unwrap();
+    assert_eq!(
+        boundaries.areas_containing(&coords(0.5, 0.5)),
+        vec!["west-square"]
+    );
+    assert_eq!(
+        boundaries.areas_containing(&coords(6.0, 6.0)),
+        vec!["east-square-and-triangle"]
+    );
+    // On the shared edge, so in both
+    assert_eq!(
+        boundaries.areas_containing(&coords(0.5, 1.0)),
+        vec!["east-square-and-triangle", "west-square"]
+    );
+    assert!(boundaries.areas_containing(&coords(-33.9, 18.8)).is_empty());
+}
+
+#[test]
+fn bundled_boundaries_are_valid() {
+    let bundled = AreaBoundaries::bundled();
+    assert!(AreaBoundaries::from_geojson(r#"{"type": "Feature"}"#).is_err());
+
+    // `/area_at` is only served and documented once there are boundaries for it to look in
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/area_at?lat=-33.9321&lng=18.8602").dispatch();
+    let openapi = client
+        .get("/api-docs/openapi.json")
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    if bundled.is_empty() {
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(openapi["paths"].get("/area_at").is_none());
+    } else {
+        assert_eq!(response.status(), Status::Ok);
+        assert!(openapi["paths"].get("/area_at").is_some());
+    }
+}
+
+#[rocket::async_test]
+async fn area_at_endpoint() {
+    let boundaries = AreaBoundaries::from_geojson(BOUNDARIES_FIXTURE).unwrap();
+    let area_at = |lat: &str, lng: &str| {
+        crate::v0_0_1::area_at(lat.to_string(), lng.to_string(), State::from(&boundaries))
+    };
+
+    let areas = area_at("0.5", "0.5").await.unwrap().into_inner();
+    assert_eq!(
+        areas
+            .iter()
+            .map(|area| area.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["west-square"]
+    );
+    assert!(area_at("-33.9321", "18.8602")
+        .await
+        .unwrap()
+        .into_inner()
+        .is_empty());
+
+    for (lat, lng) in [("-91", "18"), ("-33", "181"), ("north", "18")] {
+        let err = area_at(lat, lng).await.unwrap_err();
+        assert_eq!(err.status(), Status::BadRequest, "{lat},{lng}");
+    }
+    assert!(geo::parse_coords(" -33.9 ", "18.8").is_ok());
+}
+
+#[test]
+fn province_from_each_area_name() {
+    let cases = [
+        ("eastern-cape-gqeberha", Province::EasternCape),
+        ("free-state-bloemfontein", Province::FreeState),
+        ("gauteng-ekurhuleni-block-1", Province::Gauteng),
+        ("kwazulu-natal-pietermaritzburg", Province::KwaZuluNatal),
+        ("limpopo-polokwane", Province::Limpopo),
+        ("mpumalanga-mbombela", Province::Mpumalanga),
+        ("north-west-zeerust", Province::NorthWest),
+        ("northern-cape-kimberley", Province::NorthernCape),
+        ("western-cape-stellenbosch", Province::WesternCape),
+    ];
+    for (area_name, province) in cases {
+        assert_eq!(
+            province_from_area_name(area_name),
+            Some(province),
+            "{area_name}"
+        );
+    }
+    assert_eq!(province_from_area_name("city-of-cape-town-area-15"), None);
+    assert_eq!(province_from_area_name("eskom-direct-1"), None);
+    assert_eq!(province_from_area_name("gautengish-town"), None);
+}
+
+#[test]
+fn fuzzy_search_includes_province() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get(uri!(crate::latest::fuzzy_search(
+            "stellenbosch",
+            _,
+            _,
+            _,
+            _
+        )))
+        .dispatch();
+    let results = response.into_json::<Vec<SearchResult<Area>>>().unwrap();
+    assert_eq!(results[0].result.name, "western-cape-stellenbosch");
+    assert_eq!(results[0].result.province, Some(Province::WesternCape));
+}
+
+#[test]
+fn municipality_from_metro_area_name() {
+    assert_eq!(
+        municipality_from_area_name("city-of-cape-town-area-15"),
+        Some(Municipality::Metro(MetroMunic::CityOfCapeTown))
+    );
+}
+
+#[test]
+fn municipality_from_local_area_name() {
+    assert_eq!(
+        municipality_from_area_name("western-cape-stellenbosch"),
+        Some(Municipality::District {
+            district: DistrictMunic::CapeWinelands,
+            local: LocalMunic::Stellenbosch,
+        })
+    );
+}
+
+#[test]
+fn municipality_from_unknown_area_name() {
+    assert_eq!(municipality_from_area_name("eskom-direct-1"), None);
+    // Both George and Knysna are mentioned, so it's ambiguous
+    assert_eq!(
+        municipality_from_area_name("western-cape-george-knysna"),
+        None
+    );
+}
+
+#[test]
+fn area_ids_are_stable() {
+    let search = || {
+        client_with(MACHINE_FRIENDLY)
+            .get(uri!(crate::latest::fuzzy_search(
+                "stellenbosch",
+                _,
+                _,
+                _,
+                _
+            )))
+            .dispatch()
+            .into_json::<Vec<SearchResult<Area>>>()
+            .unwrap()
+            .remove(0)
+            .result
+    };
+    let first = search();
+    let second = search();
+    assert_eq!(first.id, second.id);
+    assert_eq!(first.schedule, second.schedule);
+    assert_ne!(first.id, AreaId(0));
+}
+
+#[test]
+fn area_by_id() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let id = AreaId::from_name("western-cape-stellenbosch");
+    let response = client.get(uri!(crate::latest::area_by_id(id.0))).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let area = response.into_json::<Area>().unwrap();
+    assert_eq!(area.name, "western-cape-stellenbosch");
+    assert_eq!(area.id, id);
+
+    let response = client.get(uri!(crate::latest::area_by_id(1))).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn rate_limit_per_ip() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let rocket = build_rocket_with(cache).attach(RateLimiter::per_minute(3));
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+    let heavy_user: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+    let light_user: SocketAddr = "5.6.7.8:1234".parse().unwrap();
+
+    for _ in 0..3 {
+        let response = client
+            .get(uri!(crate::latest::list_all_areas(_, _)))
+            .remote(heavy_user)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+    let response = client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .remote(heavy_user)
+        .dispatch();
+    assert_eq!(response.status(), Status::TooManyRequests);
+    let retry_after = response.headers().get_one("Retry-After").unwrap();
+    assert!(retry_after.parse::<u64>().unwrap() > 0);
+
+    // Other IPs aren't affected
+    let response = client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .remote(light_user)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // The docs aren't rate limited
+    let response = client
+        .get("/api-docs/openapi.json")
+        .remote(heavy_user)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn valid_emails() {
+    assert!(is_valid_email("you@example.com"));
+    assert!(is_valid_email("first.last+api@mail.example.co.za"));
+    assert!(!is_valid_email("you"));
+    assert!(!is_valid_email("@example.com"));
+    assert!(!is_valid_email("you@example"));
+    assert!(!is_valid_email("you@example..com"));
+    assert!(!is_valid_email("you@@example.com"));
+    assert!(!is_valid_email("you @example.com"));
+}
+
+#[test]
+fn email_is_optional_by_default() {
+    let client = client_with(MACHINE_FRIENDLY);
+    for query in ["?email=you@example.com", "?email=not-an-email", ""] {
+        let response = client
+            .get(format!("/outages/western-cape-stellenbosch{query}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok, "{query}");
+    }
+}
+
+#[test]
+fn email_is_required_when_configured() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let rocket = build_rocket_with(cache).manage(EmailPolicy { required: true });
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+
+    let response = client
+        .get("/fuzzy_search/stellenbosch?email=you@example.com")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .get("/fuzzy_search/stellenbosch?email=not-an-email")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(response
+        .into_string()
+        .unwrap()
+        .contains("isn't a valid email"));
+
+    let response = client.get("/fuzzy_search/stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(response.into_string().unwrap().contains("contact email"));
+
+    // Nor can outages be got some other way without one
+    for (without_email, with_email) in [
+        (
+            "/outages?areas=western-cape-stellenbosch",
+            "/outages?areas=western-cape-stellenbosch&email=you@example.com",
+        ),
+        (
+            "/outages/western-cape-stellenbosch/calendar.ics",
+            "/outages/western-cape-stellenbosch/calendar.ics?email=you@example.com",
+        ),
+    ] {
+        let response = client.get(without_email).dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{without_email}");
+        let response = client.get(with_email).dispatch();
+        assert_eq!(response.status(), Status::Ok, "{with_email}");
+    }
+
+    // Nor can schedules
+    let response = client
+        .get("/schedules/western-cape-stellenbosch/raw.csv")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    // The area lists don't need an email
+    let response = client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+fn areas_page(client: &Client, query: &str) -> (Status, Option<AreasPage>) {
+    let response = client.get(format!("/list_areas{query}")).dispatch();
+    (response.status(), response.into_json::<AreasPage>())
+}
+
+#[test]
+fn list_areas_pages() {
+    let rows = ["a", "b", "c", "d", "e"]
+        .map(|area| row_from_now(&format!("area-{area}"), 0, 60, 2))
+        .to_vec();
+    let client = client_with(&machine_friendly(&rows));
+
+    let (_, page) = areas_page(&client, "");
+    let page = page.unwrap();
+    assert_eq!(page.total, 5);
+    assert_eq!(page.limit, None);
+    assert_eq!(page.areas.len(), 5);
+
+    let (_, page) = areas_page(&client, "?limit=2");
+    let page = page.unwrap();
+    assert_eq!((page.total, page.offset, page.limit), (5, 0, Some(2)));
+    assert_eq!(page.areas, vec!["area-a", "area-b"]);
+
+    let (_, page) = areas_page(&client, "?limit=2&offset=2");
+    assert_eq!(page.unwrap().areas, vec!["area-c", "area-d"]);
+
+    let (_, page) = areas_page(&client, "?limit=2&offset=4");
+    assert_eq!(page.unwrap().areas, vec!["area-e"]);
+
+    let (_, page) = areas_page(&client, "?offset=10");
+    let page = page.unwrap();
+    assert_eq!(page.total, 5);
+    assert!(page.areas.is_empty());
+
+    let (status, _) = areas_page(&client, "?limit=-1");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn national_stage_is_the_maximum() {
+    let mut outages = vec![
+        outage("2023-06-01T16:00:00+02:00", "2023-06-01T20:00:00+02:00", 2),
+        outage("2023-06-01T17:00:00+02:00", "2023-06-01T19:00:00+02:00", 4),
+    ];
+    outages[1].area_name = "city-of-cape-town-area-15".to_string();
+
+    let national = NationalStage::at(&outages, datetime("2023-06-01T18:00:00+02:00"));
+    assert_eq!(national.stage, 4);
+    assert_eq!(
+        national.effective_from,
+        Some(datetime("2023-06-01T17:00:00+02:00"))
+    );
+    assert_eq!(
+        national.next_change,
+        Some(StageChange {
+            at: datetime("2023-06-01T19:00:00+02:00"),
+            stage: 2,
+        })
+    );
+
+    let national = NationalStage::at(&outages, datetime("2023-06-01T21:00:00+02:00"));
+    assert_eq!(national.stage, 0);
+    assert_eq!(
+        national.effective_from,
+        Some(datetime("2023-06-01T20:00:00+02:00"))
+    );
+    assert_eq!(national.next_change, None);
+}
+
+#[test]
+fn national_stage_endpoint() {
+    let rows = [
+        row_from_now("western-cape-stellenbosch", -60, 60, 2),
+        row_from_now("city-of-cape-town-area-15", -30, 30, 4),
+    ];
+    let client = client_with(&machine_friendly(&rows));
+    let response = client.get(uri!(crate::latest::national_stage)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let national = response.into_json::<NationalStage>().unwrap();
+    assert_eq!(national.stage, 4);
+    assert_eq!(national.next_change.map(|change| change.stage), Some(2));
+}
+
+#[test]
+fn outlook_merges_adjacent_outages() {
+    let outages = vec![
+        outage("2023-06-01T16:00:00+02:00", "2023-06-01T18:00:00+02:00", 2),
+        outage("2023-06-01T18:00:00+02:00", "2023-06-01T20:00:00+02:00", 2),
+    ];
+    let segment = |from: &str, to: &str, stage: u8| OutlookSegment {
+        from: datetime(from),
+        to: datetime(to),
+        stage,
+    };
+    let outlook = OutlookSegment::timeline(
+        &outages,
+        datetime("2023-06-01T15:00:00+02:00"),
+        datetime("2023-06-01T21:00:00+02:00"),
+    );
+    assert_eq!(
+        outlook,
+        vec![
+            segment("2023-06-01T15:00:00+02:00", "2023-06-01T16:00:00+02:00", 0),
+            segment("2023-06-01T16:00:00+02:00", "2023-06-01T20:00:00+02:00", 2),
+            segment("2023-06-01T20:00:00+02:00", "2023-06-01T21:00:00+02:00", 0),
+        ]
+    );
+}
+
+#[test]
+fn outlook_uses_highest_overlapping_stage() {
+    let outages = vec![
+        outage("2023-06-01T16:00:00+02:00", "2023-06-01T20:00:00+02:00", 2),
+        outage("2023-06-01T17:00:00+02:00", "2023-06-01T18:00:00+02:00", 6),
+    ];
+    let outlook = OutlookSegment::timeline(
+        &outages,
+        datetime("2023-06-01T16:30:00+02:00"),
+        datetime("2023-06-01T19:00:00+02:00"),
+    );
+    let stages = outlook.iter().map(|s| s.stage).collect::<Vec<_>>();
+    assert_eq!(stages, vec![2, 6, 2]);
+    assert_eq!(outlook[0].from, datetime("2023-06-01T16:30:00+02:00"));
+    assert_eq!(outlook[2].to, datetime("2023-06-01T19:00:00+02:00"));
+}
+
+#[test]
+fn outlook_endpoint() {
+    let rows = [row_from_now("western-cape-stellenbosch", 60, 120, 4)];
+    let client = client_with(&machine_friendly(&rows));
+    let response = client
+        .get(uri!(crate::latest::outlook("western-cape-stellenbosch")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outlook = response.into_json::<Vec<OutlookSegment>>().unwrap();
+    let stages = outlook.iter().map(|s| s.stage).collect::<Vec<_>>();
+    assert_eq!(stages, vec![0, 4, 0]);
+}
+
+#[test]
+fn missing_area_is_not_found() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/outages/narnia").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+    let error = response.into_json::<ApiErrorBody>().unwrap();
+    assert_eq!(error.error, "not_found");
+    assert!(error.message.contains("narnia"));
+}
+
+#[test]
+fn malformed_regex_is_bad_request() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/list_areas/(unclosed").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let error = response.into_json::<ApiErrorBody>().unwrap();
+    assert_eq!(error.error, "bad_request");
+}
+
+/// A fetcher for when GitHub can't be reached.
+struct FailingFetcher;
+
+#[rocket::async_trait]
+impl Fetcher for FailingFetcher {
+    async fn fetch(&self, url: &str, _etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        Err(format!("Failed to get {url}").into())
+    }
+
+    async fn exists(&self, url: &str) -> Result<bool, UpstreamError> {
+        Err(format!("Failed to check {url}").into())
+    }
+}
+
+#[test]
+fn upstream_failure_is_bad_gateway() {
+    let cache = OutageCache::new(FailingFetcher, DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::BadGateway);
+    let error = response.into_json::<ApiErrorBody>().unwrap();
+    assert_eq!(error.error, "upstream");
+}
+
+#[test]
+fn known_area_without_outages() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/outages/western-cape-quiet-town").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.into_json::<Vec<PowerOutage>>().unwrap().is_empty());
+
+    let response = client
+        .get(uri!(crate::latest::current("western-cape-quiet-town")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(!response.into_json::<CurrentStatus>().unwrap().is_shedding);
+
+    let response = client
+        .get("/outages?areas=western-cape-quiet-town,narnia")
+        .dispatch();
+    let areas = response.into_json::<AreasOutages>().unwrap();
+    assert_eq!(areas.not_found, vec!["narnia"]);
+}
+
+#[test]
+fn unknown_area() {
+    let client = client_with(MACHINE_FRIENDLY);
+    for area_name in ["western-cape-narnia", "Western%20Cape"] {
+        let response = client.get(format!("/outages/{area_name}")).dispatch();
+        assert_eq!(response.status(), Status::NotFound, "{area_name}");
+    }
+}
+
+#[test]
+fn merge_chained_outages() {
+    let mut outages = vec![
+        outage("2023-06-01T20:30:00+02:00", "2023-06-01T22:30:00+02:00", 4),
+        outage("2023-06-01T18:00:00+02:00", "2023-06-01T20:30:00+02:00", 4),
+        outage("2023-06-01T22:30:00+02:00", "2023-06-02T00:30:00+02:00", 4),
+    ];
+    outages[2].source = "https://twitter.com/CityofCT".to_string();
+
+    let merged = PowerOutage::merge_contiguous(outages);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].start, datetime("2023-06-01T18:00:00+02:00"));
+    assert_eq!(merged[0].finsh, datetime("2023-06-02T00:30:00+02:00"));
+    assert_eq!(
+        merged[0].source,
+        "https://twitter.com/Eskom_SA ; https://twitter.com/CityofCT"
+    );
+}
+
+#[test]
+fn merge_keeps_gaps_and_stage_changes() {
+    let outages = vec![
+        outage("2023-06-01T18:00:00+02:00", "2023-06-01T20:30:00+02:00", 4),
+        outage("2023-06-01T20:30:00+02:00", "2023-06-01T22:30:00+02:00", 6),
+        outage("2023-06-02T10:00:00+02:00", "2023-06-02T12:30:00+02:00", 6),
+    ];
+    assert_eq!(PowerOutage::merge_contiguous(outages).len(), 3);
+}
+
+#[test]
+fn outages_merged_on_request() {
+    let rows = [
+        row_from_now("western-cape-stellenbosch", 60, 120, 4),
+        row_from_now("western-cape-stellenbosch", 120, 180, 4),
+    ];
+    let client = client_with(&machine_friendly(&rows));
+    let (status, outages) = filtered_outages(&client, "merge=true");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(outages.len(), 1);
+    let (_, outages) = filtered_outages(&client, "merge=false");
+    assert_eq!(outages.len(), 2);
+    let (status, _) = filtered_outages(&client, "merge=yes");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn fuzzy_search_limit_and_min_score() {
+    let rows = (0..15)
+        .map(|i| row_from_now(&format!("western-cape-town-{i:02}"), 60, 120, 2))
+        .collect::<Vec<_>>();
+    let client = client_with(&machine_friendly(&rows));
+    let search = |query: &str| {
+        client
+            .get(format!("/fuzzy_search/{query}"))
+            .dispatch()
+            .into_json::<Vec<SearchResult<Area>>>()
+            .unwrap()
+    };
+
+    let results = search("town");
+    assert_eq!(results.len(), 10);
+    // All the scores are tied, so they're alphabetical
+    assert_eq!(results[0].result.name, "western-cape-town-00");
+    assert_eq!(results[9].result.name, "western-cape-town-09");
+    assert_eq!(search("town?limit=3").len(), 3);
+    assert_eq!(search("town?limit=100").len(), 15);
+
+    let best_score = results[0].score;
+    assert_eq!(search(&format!("town?min_score={best_score}")).len(), 10);
+    assert!(search(&format!("town?min_score={}", best_score + 1)).is_empty());
+
+    let response = client.get("/fuzzy_search/town?limit=lots").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn fuzzy_search_in_one_province() {
+    let rows = [
+        row_from_now("kwazulu-natal-richmond", 60, 120, 2),
+        row_from_now("northern-cape-richmond", 60, 120, 2),
+    ];
+    let client = client_with(&machine_friendly(&rows));
+    let search = |query: &str| {
+        client
+            .get(format!("/fuzzy_search/{query}"))
+            .dispatch()
+            .into_json::<Vec<SearchResult<Area>>>()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.result.name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(search("richmond").len(), 2);
+    assert_eq!(
+        search("richmond?province=northern-cape"),
+        vec!["northern-cape-richmond"]
+    );
+
+    let response = client
+        .get("/fuzzy_search/richmond?province=karoo")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let error = response.into_json::<ApiErrorBody>().unwrap();
+    assert!(error.message.contains("western-cape"));
+}
+
+#[test]
+fn health_with_cached_data() {
+    let client = client_with(MACHINE_FRIENDLY);
+    client.get("/outages/western-cape-stellenbosch").dispatch();
+
+    let response = client.get(uri!(crate::latest::health)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let health = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(health["status"], "ok");
+    assert_eq!(health["upstream_reachable"], true);
+    assert!(health["cache_age_seconds"].is_u64());
+    assert_eq!(health["area_count"], 2);
+}
+
+#[test]
+fn health_without_upstream() {
+    let cache = OutageCache::new(FailingFetcher, DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let response = client.get(uri!(crate::latest::health)).dispatch();
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    let health = response.into_json::<Health>().unwrap();
+    assert_eq!(health.status, ServiceStatus::Down);
+    assert!(!health.upstream_reachable);
+    assert_eq!(health.cache_age_seconds, None);
+}
+
+#[test]
+fn metrics_count_requests_and_cache_use() {
+    let client = client_with(MACHINE_FRIENDLY);
+    client.get("/outages/western-cape-stellenbosch").dispatch();
+    client.get("/outages/western-cape-stellenbosch").dispatch();
+
+    let response = client.get(uri!(crate::latest::metrics)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::Plain));
+    let text = response.into_string().unwrap();
+    assert!(text.contains(
+        "http_requests_total{method=\"GET\",endpoint=\"/outages/<area_name>\",status=\"200\"} 2"
+    ));
+    assert!(text.contains(
+        "http_request_duration_seconds_count{method=\"GET\",endpoint=\"/outages/<area_name>\"} 2"
+    ));
+    assert!(text.contains("upstream_fetches_total 1\n"));
+    assert!(text.contains("cache_hits_total 1\n"));
+    assert!(text.contains("cache_misses_total 1\n"));
+    // Scraping the metrics doesn't count as a request
+    assert!(!text.contains("endpoint=\"/metrics\""));
+
+    client.get("/outages/western-cape-stellenbosch").dispatch();
+    let text = client
+        .get(uri!(crate::latest::metrics))
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(text.contains(
+        "http_requests_total{method=\"GET\",endpoint=\"/outages/<area_name>\",status=\"200\"} 3"
+    ));
+    assert!(text.contains("cache_hits_total 2\n"));
+    assert!(!text.contains("endpoint=\"/metrics\""));
+}
+
+#[test]
+fn version_describes_build_and_data() {
+    let fetcher = FakeFetcher {
+        body: MACHINE_FRIENDLY.to_string(),
+        etag: Some("\"v1\"".to_string()),
+        fetches: Arc::new(AtomicUsize::new(0)),
+        delay: Duration::ZERO,
+    };
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let before = client
+        .get(uri!(crate::latest::version))
+        .dispatch()
+        .into_json::<Version>()
+        .unwrap();
+    assert_eq!(before.api_version, env!("CARGO_PKG_VERSION"));
+    assert!(!before.git_sha.is_empty());
+    assert!(before.built_at <= sast_now());
+    assert_eq!(before.machine_friendly_last_fetched, None);
+
+    client.get("/outages/western-cape-stellenbosch").dispatch();
+    let response = client.get(uri!(crate::latest::version)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let after = response.into_json::<serde_json::Value>().unwrap();
+    for field in [
+        "api_version",
+        "git_sha",
+        "built_at",
+        "machine_friendly_last_fetched",
+        "machine_friendly_etag",
+    ] {
+        assert!(
+            after[field].as_str().is_some_and(|value| !value.is_empty()),
+            "{field} should be a non-empty string, got {}",
+            after[field]
+        );
+    }
+}
+
+#[test]
+fn large_responses_are_gzipped() {
+    let rows: Vec<String> = (0..50)
+        .map(|i| row_from_now("western-cape-stellenbosch", i * 60, i * 60 + 30, 4))
+        .collect();
+    let client = client_with(&machine_friendly(&rows));
+
+    let plain = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(plain.headers().get_one("Content-Encoding"), None);
+    assert_eq!(plain.headers().get_one("Vary"), Some("Accept-Encoding"));
+    let plain = plain.into_bytes().unwrap();
+    assert!(plain.len() >= MIN_COMPRESSED_BYTES);
+
+    let response = client
+        .get("/outages/western-cape-stellenbosch")
+        .header(Header::new("Accept-Encoding", "deflate, gzip;q=1.0, br"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+    assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+    let compressed = response.into_bytes().unwrap();
+    assert!(compressed.len() < plain.len());
+
+    let mut decompressed = vec![];
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, plain);
+}
+
+#[test]
+fn small_or_unwanted_responses_are_not_compressed() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get(uri!(crate::latest::health_check))
+        .header(Header::new("Accept-Encoding", "gzip"))
+        .dispatch();
+    assert_eq!(response.headers().get_one("Content-Encoding"), None);
+    assert!(response.into_json::<serde_json::Value>().is_some());
+
+    let rows: Vec<String> = (0..50)
+        .map(|i| row_from_now("western-cape-stellenbosch", i * 60, i * 60 + 30, 4))
+        .collect();
+    let client = client_with(&machine_friendly(&rows));
+    let response = client
+        .get("/outages/western-cape-stellenbosch")
+        .header(Header::new("Accept-Encoding", "gzip;q=0"))
+        .dispatch();
+    assert_eq!(response.headers().get_one("Content-Encoding"), None);
+    assert!(response.into_json::<Vec<PowerOutage>>().is_some());
+}
+
+#[test]
+fn outages_converted_to_other_timezones() {
+    let client = client_with(MACHINE_FRIENDLY);
+    for (tz, start, finsh) in [
+        (
+            "utc",
+            "2023-06-01T16:00:00+00:00",
+            "2023-06-01T18:30:00+00:00",
+        ),
+        (
+            "%2B05:30",
+            "2023-06-01T21:30:00+05:30",
+            "2023-06-02T00:00:00+05:30",
+        ),
+        // An unencoded `+` arrives as a space
+        (
+            "+00:00",
+            "2023-06-01T16:00:00+00:00",
+            "2023-06-01T18:30:00+00:00",
+        ),
+        (
+            "-03:00",
+            "2023-06-01T13:00:00-03:00",
+            "2023-06-01T15:30:00-03:00",
+        ),
+        // British Summer Time is +01:00
+        (
+            "Europe/London",
+            "2023-06-01T17:00:00+01:00",
+            "2023-06-01T19:30:00+01:00",
+        ),
+    ] {
+        let (status, outages) = filtered_outages(&client, &format!("tz={tz}"));
+        assert_eq!(status, Status::Ok, "tz={tz}");
+        assert_eq!(outages[0].start.to_rfc3339(), start, "tz={tz}");
+        assert_eq!(outages[0].finsh.to_rfc3339(), finsh, "tz={tz}");
+        // The moment in time doesn't change, only how it's written
+        assert_eq!(outages[0].start, datetime("2023-06-01T18:00:00+02:00"));
+    }
+
+    let (status, outages) = filtered_outages(&client, "");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(outages[0].start.to_rfc3339(), "2023-06-01T18:00:00+02:00");
+}
+
+#[test]
+fn outages_with_invalid_timezone() {
+    let client = client_with(MACHINE_FRIENDLY);
+    for tz in ["Mars/Olympus_Mons", "%2B25:00", "%2B02:75", "sast"] {
+        let response = client
+            .get(format!("/outages/western-cape-stellenbosch?tz={tz}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "tz={tz}");
+        let body = response.into_json::<ApiErrorBody>().unwrap();
+        assert_eq!(body.error, "bad_request");
+    }
+}
+
+#[test]
+fn schedules_with_metadata() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get("/schedules/western-cape-stellenbosch")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let schedule = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(schedule["outages"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        schedule["source"],
+        serde_json::json!([
+            "https://www.eskom.co.za/distribution/wp-content/uploads/2022/09/WesternCape_LS.xlsx"
+        ])
+    );
+    assert_eq!(
+        schedule["info"],
+        serde_json::json!(["https://www.eskom.co.za/distribution/customer-service/outages/"])
+    );
+    assert_eq!(schedule["last_updated"], "2023-05-01T12:00:00");
+    assert_eq!(schedule["valid_from"], "2023-05-15T00:00:00");
+    assert_eq!(schedule["valid_until"], "2024-05-15T00:00:00");
+}
+
+#[test]
+fn schedules_without_metadata() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get("/schedules/city-of-cape-town-area-15")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let schedule = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(schedule["outages"].as_array().unwrap().len(), 2);
+    assert_eq!(schedule["source"], serde_json::json!([]));
+    assert_eq!(schedule["info"], serde_json::json!([]));
+    assert!(schedule["last_updated"].is_null());
+    assert!(schedule["valid_from"].is_null());
+    assert!(schedule["valid_until"].is_null());
+
+    let response = client.get("/schedules/western-cape-atlantis").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+/// A schedule made of the given recurring outages, without any metadata.
+fn recurring_schedule(outages: Vec<RecurringOutage>) -> RecurringSchedule {
+    RecurringSchedule {
+        id: ScheduleId::from_name("western-cape-stellenbosch"),
+        outages,
+        source: vec![],
+        info: vec![],
+        last_updated: None,
+        valid_from: None,
+        valid_until: None,
+        warnings: vec![],
+        superseded: vec![],
+    }
+}
+
+fn recurring(
+    start: &str,
+    finsh: &str,
+    stage: u8,
+    recurrence: Recurrence,
+    day1: u8,
+) -> RecurringOutage {
+    RecurringOutage {
+        start_time: NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+        finsh_time: NaiveTime::parse_from_str(finsh, "%H:%M").unwrap(),
+        stage,
+        recurrence,
+        day1_of_recurrence: day1,
+    }
+}
+
+/// The (start, finsh) of each outage, for easier comparisons.
+fn spans(outages: &[PowerOutage]) -> Vec<(String, String)> {
+    outages
+        .iter()
+        .map(|outage| (outage.start.to_rfc3339(), outage.finsh.to_rfc3339()))
+        .collect()
+}
+
+#[test]
+fn expand_weekly_schedule() {
+    let schedule = recurring_schedule(vec![
+        recurring("22:00", "00:30", 1, Recurrence::Weekly, 1),
+        recurring("08:00", "10:30", 2, Recurrence::Weekly, 3),
+    ]);
+    // 2023-06-05 is a Monday
+    let from = datetime("2023-06-05T00:00:00+02:00");
+    let to = datetime("2023-06-12T00:00:00+02:00");
+
+    let outages = schedule.expand("western-cape-stellenbosch", "src", 2, from, to);
+    assert_eq!(
+        spans(&outages),
+        [
+            ("2023-06-05T22:00:00+02:00", "2023-06-06T00:30:00+02:00"),
+            ("2023-06-07T08:00:00+02:00", "2023-06-07T10:30:00+02:00"),
+        ]
+        .map(|(start, finsh)| (start.to_string(), finsh.to_string()))
+    );
+    assert!(outages.iter().all(|outage| outage.stage == 2
+        && outage.area_name == "western-cape-stellenbosch"
+        && outage.source == "src"));
+
+    // Stage 2's outages don't apply at stage 1
+    let outages = schedule.expand("western-cape-stellenbosch", "src", 1, from, to);
+    assert_eq!(spans(&outages).len(), 1);
+    assert!(schedule
+        .expand("western-cape-stellenbosch", "src", 0, from, to)
+        .is_empty());
+}
+
+#[test]
+fn expand_monthly_schedule_over_month_boundary() {
+    let schedule = recurring_schedule(vec![recurring(
+        "22:00",
+        "00:30",
+        3,
+        Recurrence::Monthly,
+        31,
+    )]);
+
+    let outages = schedule.expand(
+        "western-cape-stellenbosch",
+        "src",
+        4,
+        datetime("2023-05-30T00:00:00+02:00"),
+        datetime("2023-06-02T00:00:00+02:00"),
+    );
+    assert_eq!(
+        spans(&outages),
+        [(
+            "2023-05-31T22:00:00+02:00".to_string(),
+            "2023-06-01T00:30:00+02:00".to_string()
+        )]
+    );
+
+    // An outage that started the day before the window is included if it's still going
+    let outages = schedule.expand(
+        "western-cape-stellenbosch",
+        "src",
+        4,
+        datetime("2023-06-01T00:00:00+02:00"),
+        datetime("2023-06-01T12:00:00+02:00"),
+    );
+    assert_eq!(spans(&outages).len(), 1);
+
+    // June doesn't have a 31st
+    let outages = schedule.expand(
+        "western-cape-stellenbosch",
+        "src",
+        4,
+        datetime("2023-06-01T01:00:00+02:00"),
+        datetime("2023-07-01T00:00:00+02:00"),
+    );
+    assert!(outages.is_empty());
+}
+
+#[test]
+fn expand_periodic_schedule() {
+    let recurrence = Recurrence::Periodic {
+        offset: NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+        period_days: 4,
+    };
+    let schedule = recurring_schedule(vec![recurring("12:00", "14:00", 1, recurrence, 2)]);
+    let outages = schedule.expand(
+        "western-cape-stellenbosch",
+        "src",
+        1,
+        datetime("2023-05-28T00:00:00+02:00"),
+        datetime("2023-06-11T00:00:00+02:00"),
+    );
+    let starts: Vec<_> = outages
+        .iter()
+        .map(|outage| outage.start.to_rfc3339())
+        .collect();
+    assert_eq!(
+        starts,
+        [
+            "2023-05-29T12:00:00+02:00",
+            "2023-06-02T12:00:00+02:00",
+            "2023-06-06T12:00:00+02:00",
+            "2023-06-10T12:00:00+02:00",
+        ]
+    );
+}
+
+#[test]
+fn schedules_expand_endpoint() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get(
+            "/schedules/western-cape-stellenbosch/expand?stage=2\
+            &from=2023-06-05T00:00:00%2B02:00&to=2023-06-12T00:00:00%2B02:00",
+        )
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outages = response.into_json::<Vec<PowerOutage>>().unwrap();
+    assert_eq!(
+        stages(&outages),
+        [2, 2],
+        "both of the fixture's outages apply at stage 2"
+    );
+    assert_eq!(outages[0].start, datetime("2023-06-05T22:00:00+02:00"));
+    assert_eq!(outages[0].finsh, datetime("2023-06-06T00:30:00+02:00"));
+
+    // Without a window, the next week is used
+    let response = client
+        .get("/schedules/western-cape-stellenbosch/expand?stage=1")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outages = response.into_json::<Vec<PowerOutage>>().unwrap();
+    assert!(!outages.is_empty());
+    assert!(outages
+        .iter()
+        .all(|outage| outage.finsh > sast_now()
+            && outage.start < sast_now() + ChronoDuration::days(7)));
+
+    for query in [
+        "stage=9",
+        "stage=four",
+        "stage=4&from=2023-06-12T00:00:00&to=2023-06-05T00:00:00",
+        "stage=4&from=2023-06-01T00:00:00&to=2023-08-01T00:00:00",
+    ] {
+        let response = client
+            .get(format!(
+                "/schedules/western-cape-stellenbosch/expand?{query}"
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{query}");
+    }
+
+    let response = client
+        .get("/schedules/western-cape-atlantis/expand?stage=4")
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+/// Outages in areas spread over a few provinces and municipalities.
+fn provinces_fixture() -> String {
+    machine_friendly(&[
+        row_from_now("western-cape-stellenbosch", 60, 120, 4),
+        row_from_now("western-cape-worcester", 60, 120, 4),
+        row_from_now("city-of-cape-town-area-15", 60, 120, 4),
+        row_from_now("gauteng-tshwane-group-9", 60, 120, 4),
+        row_from_now("gauteng-ekurhuleni-block-3", 60, 120, 4),
+        row_from_now("eskom-direct-test-area", 60, 120, 4),
+    ])
+}
+
+#[test]
+fn areas_in_province() {
+    let client = client_with(&provinces_fixture());
+    let response = client
+        .get(uri!(crate::latest::areas_in_province("western-cape")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let areas = response.into_json::<Vec<String>>().unwrap();
+    assert_eq!(
+        areas,
+        ["western-cape-stellenbosch", "western-cape-worcester"]
+    );
+    assert!(!areas.iter().any(|area| area.starts_with("gauteng")));
+
+    let areas = client
+        .get(uri!(crate::latest::areas_in_province("gauteng")))
+        .dispatch()
+        .into_json::<Vec<String>>()
+        .unwrap();
+    assert_eq!(
+        areas,
+        ["gauteng-ekurhuleni-block-3", "gauteng-tshwane-group-9"]
+    );
+
+    let response = client
+        .get(uri!(crate::latest::areas_in_province("atlantis")))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let body = response.into_json::<ApiErrorBody>().unwrap();
+    assert!(body.message.contains("western-cape"));
+}
+
+#[test]
+fn areas_in_municipality() {
+    let client = client_with(&provinces_fixture());
+    let response = client
+        .get(uri!(crate::latest::areas_in_municipality("stellenbosch")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let areas = response.into_json::<Vec<String>>().unwrap();
+    assert_eq!(areas, ["western-cape-stellenbosch"]);
+
+    let areas = client
+        .get(uri!(crate::latest::areas_in_municipality(
+            "city-of-cape-town"
+        )))
+        .dispatch()
+        .into_json::<Vec<String>>()
+        .unwrap();
+    assert_eq!(areas, ["city-of-cape-town-area-15"]);
+
+    let response = client
+        .get(uri!(crate::latest::areas_in_municipality("gotham")))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn municipality_from_slug() {
+    assert_eq!(
+        Municipality::from_slug("city-of-cape-town"),
+        Some(Municipality::Metro(MetroMunic::CityOfCapeTown))
+    );
+    assert_eq!(
+        Municipality::from_slug("CityOfCapeTown"),
+        Some(Municipality::Metro(MetroMunic::CityOfCapeTown))
+    );
+    assert_eq!(
+        Municipality::from_slug("stellenbosch"),
+        Some(Municipality::District {
+            district: DistrictMunic::CapeWinelands,
+            local: LocalMunic::Stellenbosch,
+        })
+    );
+    assert_eq!(Municipality::from_slug("gotham"), None);
+}
+
+#[test]
+fn areas_grouped_into_tree() {
+    let client = client_with(&provinces_fixture());
+    let response = client.get(uri!(crate::latest::areas_tree)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let tree = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(
+        tree,
+        serde_json::json!({
+            "gauteng": {
+                "unknown": ["gauteng-ekurhuleni-block-3", "gauteng-tshwane-group-9"]
+            },
+            "unknown": {
+                "city-of-cape-town": ["city-of-cape-town-area-15"],
+                "unknown": ["eskom-direct-test-area"]
+            },
+            "western-cape": {
+                "stellenbosch": ["western-cape-stellenbosch"],
+                // Worcester is a town in the Breede Valley municipality
+                "unknown": ["western-cape-worcester"]
+            }
+        })
+    );
+}
+
+#[test]
+fn municipality_slugs() {
+    assert_eq!(
+        Municipality::Metro(MetroMunic::CityOfCapeTown).slug(),
+        "city-of-cape-town"
+    );
+    assert_eq!(
+        Municipality::Metro(MetroMunic::eThekwini).slug(),
+        "ethekwini"
+    );
+    for municipality in MetroMunic::ALL.map(Municipality::Metro) {
+        assert_eq!(
+            Municipality::from_slug(&municipality.slug()),
+            Some(municipality)
+        );
+    }
+    for local in LocalMunic::ALL {
+        let municipality = Municipality::District {
+            district: local.district(),
+            local,
+        };
+        assert_eq!(
+            Municipality::from_slug(&municipality.slug()),
+            Some(municipality)
+        );
+    }
+}
+
+#[test]
+fn current_for_many_areas() {
+    let client = client_with(&machine_friendly(&[
+        row_from_now("western-cape-stellenbosch", -30, 90, 4),
+        row_from_now("city-of-cape-town-area-15", 60, 120, 2),
+    ]));
+    let response = client
+        .post(uri!(crate::latest::current_for_areas))
+        .header(ContentType::JSON)
+        .body(
+            serde_json::json!({"areas": [
+                "western-cape-stellenbosch",
+                "city-of-cape-town-area-15",
+                QUIET_AREAS[0],
+                "western-cape-atlantis",
+            ]})
+            .to_string(),
+        )
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<AreasCurrentStatus>().unwrap();
+    assert_eq!(body.statuses.len(), 3);
+    assert_eq!(body.not_found, ["western-cape-atlantis"]);
+
+    let stellenbosch = &body.statuses["western-cape-stellenbosch"];
+    assert_eq!(stellenbosch.area_name, "western-cape-stellenbosch");
+    assert!(stellenbosch.is_shedding);
+    assert_eq!(stellenbosch.current_outage.as_ref().unwrap().stage, 4);
+    assert!(stellenbosch.next_outage.is_none());
+
+    let cape_town = &body.statuses["city-of-cape-town-area-15"];
+    assert!(!cape_town.is_shedding);
+    assert!(cape_town.current_outage.is_none());
+    assert_eq!(cape_town.next_outage.as_ref().unwrap().stage, 2);
+
+    let quiet = &body.statuses[QUIET_AREAS[0]];
+    assert!(!quiet.is_shedding);
+    assert!(quiet.current_outage.is_none() && quiet.next_outage.is_none());
+}
+
+#[test]
+fn current_for_too_many_areas() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let areas: Vec<String> = (0..101).map(|i| format!("western-cape-area-{i}")).collect();
+    let response = client
+        .post(uri!(crate::latest::current_for_areas))
+        .header(ContentType::JSON)
+        .body(serde_json::json!({ "areas": areas }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let body = response.into_json::<ApiErrorBody>().unwrap();
+    assert!(body.message.contains("at most 100 areas"));
+}
+
+/// Fails the first `failures` fetches, and then serves `MACHINE_FRIENDLY`.
+struct FlakyFetcher {
+    failures: usize,
+    fetches: Arc<AtomicUsize>,
+}
+
+#[rocket::async_trait]
+impl Fetcher for FlakyFetcher {
+    async fn fetch(&self, url: &str, _etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        if self.fetches.fetch_add(1, Ordering::SeqCst) < self.failures {
+            return Err(format!("Failed to get {url}").into());
+        }
+        Ok(Fetched::Modified {
+            text: MACHINE_FRIENDLY.to_string(),
+            etag: None,
+        })
+    }
+
+    async fn exists(&self, _url: &str) -> Result<bool, UpstreamError> {
+        Ok(true)
+    }
+}
+
+#[tokio::test]
+async fn fetch_retried_after_failures() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetcher = FlakyFetcher {
+        failures: 2,
+        fetches: Arc::clone(&fetches),
+    };
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL).with_retry(RetryPolicy {
+        attempts: 3,
+        delay: Duration::from_millis(1),
+    });
+
+    let outages = cache.outages().await.unwrap();
+    assert_eq!(outages.len(), 3);
+    assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    assert_eq!(cache.counters().upstream_fetches, 3);
+}
+
+#[tokio::test]
+async fn fetch_fails_once_out_of_attempts() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetcher = FlakyFetcher {
+        failures: 2,
+        fetches: Arc::clone(&fetches),
+    };
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL).with_retry(RetryPolicy {
+        attempts: 2,
+        delay: Duration::from_millis(1),
+    });
+
+    assert!(cache.outages().await.is_err());
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+}
+
+/// Serves `MACHINE_FRIENDLY` once, and then fails every fetch after that as if GitHub went down.
+struct FailsAfterFirstFetch {
+    fetches: AtomicUsize,
+}
+
+#[rocket::async_trait]
+impl Fetcher for FailsAfterFirstFetch {
+    async fn fetch(&self, url: &str, _etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        if self.fetches.fetch_add(1, Ordering::SeqCst) > 0 {
+            return Err(format!("Failed to get {url}").into());
+        }
+        Ok(Fetched::Modified {
+            text: MACHINE_FRIENDLY.to_string(),
+            etag: None,
+        })
+    }
+
+    async fn exists(&self, url: &str) -> Result<bool, UpstreamError> {
+        Err(format!("Failed to check {url}").into())
+    }
+}
+
+#[test]
+fn stale_data_served_when_upstream_is_down() {
+    let fetcher = FailsAfterFirstFetch {
+        fetches: AtomicUsize::new(0),
+    };
+    // A TTL of zero means every request tries to refresh the cache
+    let cache = OutageCache::new(fetcher, Duration::ZERO).with_retry(RetryPolicy {
+        attempts: 1,
+        delay: Duration::ZERO,
+    });
+    let client = Client::tracked(build_rocket_with(cache.clone())).expect("valid rocket instance");
+
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("X-Data-Stale"), None);
+    let fresh = response.into_json::<Vec<PowerOutage>>().unwrap();
+
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("X-Data-Stale"), Some("true"));
+    let age = response.headers().get_one("X-Cache-Age-Seconds").unwrap();
+    assert!(age.parse::<u64>().is_ok());
+    assert_eq!(response.into_json::<Vec<PowerOutage>>().unwrap(), fresh);
+
+    // Having just failed, the cache waits before asking GitHub again instead of making every
+    // request wait for another failure
+    let fetches = cache.counters().upstream_fetches;
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("X-Data-Stale"), Some("true"));
+    assert_eq!(response.into_json::<Vec<PowerOutage>>().unwrap(), fresh);
+    assert_eq!(cache.counters().upstream_fetches, fetches);
+
+    let status = client
+        .get(uri!(crate::latest::cache_status))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(status["stale"], true);
+    assert!(status["cache_age_seconds"].is_u64());
+}
+
+#[test]
+fn stats_for_area() {
+    let client = client_with(
+        "\
+area_name,start,finsh,stage,source
+western-cape-stellenbosch,2023-06-01T18:00:00+02:00,2023-06-01T20:30:00+02:00,4,src
+western-cape-stellenbosch,2023-06-01T22:00:00+02:00,2023-06-02T02:00:00+02:00,6,src
+western-cape-stellenbosch,2023-06-02T10:00:00+02:00,2023-06-02T12:00:00+02:00,4,src
+city-of-cape-town-area-15,2023-06-01T20:00:00+02:00,2023-06-01T22:00:00+02:00,4,src
+",
+    );
+    let response = client
+        .get(uri!(crate::latest::stats("western-cape-stellenbosch")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let stats = response.into_json::<AreaStats>().unwrap();
+    assert_eq!(stats.area_name, "western-cape-stellenbosch");
+    assert_eq!(stats.outage_count, 3);
+    assert_eq!(stats.total_hours, 8.5);
+    assert!((stats.average_hours - 8.5 / 3.0).abs() < 1e-9);
+    assert_eq!(
+        stats.hours_by_stage.into_iter().collect::<Vec<_>>(),
+        [(4, 4.5), (6, 4.0)]
+    );
+    // 2.5 hours + 2 hours before midnight on the 1st, 2 hours after midnight + 2 hours on the 2nd
+    assert_eq!(
+        stats.busiest_day,
+        Some(DayHours {
+            date: NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+            hours: 4.5,
+        })
+    );
+
+    let response = client
+        .get(uri!(crate::latest::stats(QUIET_AREAS[0])))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let stats = response.into_json::<AreaStats>().unwrap();
+    assert_eq!(stats.outage_count, 0);
+    assert_eq!(stats.total_hours, 0.0);
+    assert_eq!(stats.average_hours, 0.0);
+    assert_eq!(stats.busiest_day, None);
+
+    let response = client
+        .get(uri!(crate::latest::stats("western-cape-atlantis")))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn national_stats_endpoint() {
+    let client = client_with(&machine_friendly(&[
+        row_from_now("western-cape-stellenbosch", -30, 90, 4),
+        row_from_now("city-of-cape-town-area-15", 60, 120, 2),
+    ]));
+    let response = client.get(uri!(crate::latest::national_stats)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let stats = response.into_json::<NationalStats>().unwrap();
+    assert_eq!(stats.areas_shedding, 1);
+    assert_eq!(
+        stats.areas_by_stage.into_iter().collect::<Vec<_>>(),
+        [(0, 1), (4, 1)]
+    );
+}
+
+#[test]
+fn national_stats_only_count_today() {
+    let outages = [
+        // Started yesterday, so only the 1.5 hours after midnight count
+        outage("2023-05-31T22:30:00+02:00", "2023-06-01T01:30:00+02:00", 6),
+        outage("2023-06-01T10:00:00+02:00", "2023-06-01T12:00:00+02:00", 4),
+        // Goes into tomorrow, so only the hour before midnight counts
+        outage("2023-06-01T23:00:00+02:00", "2023-06-02T02:00:00+02:00", 4),
+        outage("2023-06-02T10:00:00+02:00", "2023-06-02T12:00:00+02:00", 4),
+    ];
+    let stats = NationalStats::at(&outages, datetime("2023-06-01T11:00:00+02:00"));
+    assert_eq!(stats.area_hours_today, 4.5);
+    assert_eq!(stats.areas_shedding, 1);
+    assert_eq!(
+        stats.areas_by_stage.into_iter().collect::<Vec<_>>(),
+        [(4, 1)]
+    );
+}
+
+#[test]
+fn outages_filtered_by_min_minutes() {
+    let rows = [
+        row_from_now("western-cape-stellenbosch", 60, 90, 4),
+        row_from_now("western-cape-stellenbosch", 90, 120, 4),
+        row_from_now("western-cape-stellenbosch", 300, 540, 2),
+        row_from_now("western-cape-stellenbosch", 600, 630, 6),
+    ];
+    let client = client_with(&machine_friendly(&rows));
+    let (status, outages) = filtered_outages(&client, "min_minutes=60");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), [2]);
+    // The two half-hour outages only count as long enough once they're merged
+    let (_, outages) = filtered_outages(&client, "min_minutes=60&merge=true");
+    assert_eq!(stages(&outages), [4, 2]);
+    let (_, outages) = filtered_outages(&client, "min_minutes=30&min_stage=4");
+    assert_eq!(stages(&outages), [4, 4, 6]);
+    let (status, _) = filtered_outages(&client, "min_minutes=-5");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn schedules_only_fetched_for_known_areas() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let cache = client.rocket().state::<OutageCache>().unwrap();
+    let response = client
+        .get("/schedules/western-cape-stellenbosch")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let fetches = cache.counters().upstream_fetches;
+
+    let response = client.get("/schedules/western-cape-stellenbosh").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let body = response.into_json::<ApiErrorBody>().unwrap();
+    assert!(
+        body.message.contains("`western-cape-stellenbosch`"),
+        "{}",
+        body.message
+    );
+    assert_eq!(
+        cache.counters().upstream_fetches,
+        fetches,
+        "GitHub shouldn't be asked about unknown areas"
+    );
+
+    let response = client.get("/schedules/..%2F..%2Fsecrets").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(cache.counters().upstream_fetches, fetches);
+}
+
+#[test]
+fn misspelled_areas_get_suggestions() {
+    let client = client_with(MACHINE_FRIENDLY);
+    for uri in [
+        "/outages/western-cape-stellenbosh",
+        "/schedules/western-cape-stellenbosh",
+    ] {
+        let response = client.get(uri).dispatch();
+        assert_eq!(response.status(), Status::NotFound, "{uri}");
+        let body = response.into_json::<ApiErrorBody>().unwrap();
+        assert_eq!(body.error, "not_found");
+        assert_eq!(body.suggestions, ["western-cape-stellenbosch"], "{uri}");
+    }
+
+    // Nothing is suggested when nothing is similar
+    let response = client.get("/outages/zzzzzz").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let body = response.into_json::<ApiErrorBody>().unwrap();
+    assert!(body.suggestions.is_empty());
+
+    // Suggestions are only for areas that can't be found
+    let response = client
+        .get("/outages/western-cape-stellenbosch?min_stage=9")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let body = response.into_json::<ApiErrorBody>().unwrap();
+    assert!(body.suggestions.is_empty());
+}
+
+#[test]
+fn outages_include_past_on_request() {
+    let rows = [
+        row_from_now("western-cape-stellenbosch", -180, -60, 4),
+        row_from_now("western-cape-stellenbosch", -30, 30, 6),
+        row_from_now("western-cape-stellenbosch", 60, 120, 2),
+    ];
+    let client = client_with(&machine_friendly(&rows));
+    let outages = |query: &str| {
+        let response = client
+            .get(format!("/outages/western-cape-stellenbosch{query}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok, "{query}");
+        stages(&response.into_json::<Vec<PowerOutage>>().unwrap())
+    };
+    // Outages which are still happening aren't in the past
+    assert_eq!(outages(""), [6, 2]);
+    assert_eq!(outages("?include_past=false"), [6, 2]);
+    assert_eq!(outages("?include_past=true"), [4, 6, 2]);
+
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=maybe")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn area_lists_streamed_like_buffered_json() {
+    // Enough areas that the list is streamed in several chunks
+    let mut area_names = (0..600)
+        .map(|i| format!("western-cape-town-{i:03}"))
+        .collect::<Vec<_>>();
+    let rows = area_names
+        .iter()
+        .map(|area_name| row_from_now(area_name, 60, 120, 2))
+        .collect::<Vec<_>>();
+    let client = client_with(&machine_friendly(&rows));
+    area_names.sort();
+
+    for (query, offset, limit) in [
+        ("", 0, None),
+        ("?limit=300&offset=100", 100, Some(300)),
+        ("?offset=1000", 1000, None),
+    ] {
+        let response = client.get(format!("/list_areas{query}")).dispatch();
+        assert_eq!(response.status(), Status::Ok, "{query}");
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        let streamed = response.into_string().unwrap();
+        let buffered =
+            serde_json::to_string(&AreasPage::paginate(area_names.clone(), offset, limit)).unwrap();
+        assert_eq!(streamed, buffered, "{query}");
+        serde_json::from_str::<AreasPage>(&streamed).unwrap();
+    }
+}
+
+#[test]
+fn fuzzy_search_matches_aliases() {
+    let client = client_with(&provinces_fixture());
+    let response = client.get("/fuzzy_search/stellies").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let results = response.into_json::<Vec<SearchResult<Area>>>().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].result.name, "western-cape-stellenbosch");
+    assert!(results[0].result.aliases.contains(&"Stellies".to_string()));
+}
+
+#[test]
+fn aliases_normalised_and_deduplicated() {
+    let aliases = AreaAliases::from_json(
+        r#"{"western-cape-stellenbosch": [
+            "  Stellies ", "stellies", "Western-Cape-Stellenbosch", "Stellen   Bosch", ""
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        aliases.of("western-cape-stellenbosch"),
+        ["Stellies", "Stellen Bosch"]
+    );
+    assert!(aliases.of("western-cape-worcester").is_empty());
+    assert!(AreaAliases::from_json(r#"{"western-cape-stellenbosch": "Stellies"}"#).is_err());
+    AreaAliases::bundled();
+}
+
+#[test]
+fn area_details() {
+    let client = client_with(&provinces_fixture());
+    let response = client
+        .get(uri!(crate::latest::area("western-cape-stellenbosch")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let area = response.into_json::<Area>().unwrap();
+    assert_eq!(area.name, "western-cape-stellenbosch");
+    assert_eq!(area.id, AreaId::from_name("western-cape-stellenbosch"));
+    assert_eq!(
+        area.schedule,
+        ScheduleId::from_name("western-cape-stellenbosch")
+    );
+    assert!(area.aliases.contains(&"Stellies".to_string()));
+    assert_eq!(area.province, Some(Province::WesternCape));
+    assert!(area.municipality.is_some());
+
+    let response = client
+        .get(uri!(crate::latest::area("western-cape-stellenbosh")))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let body = response.into_json::<ApiErrorBody>().unwrap();
+    assert_eq!(body.suggestions, ["western-cape-stellenbosch"]);
+}
+
+#[test]
+fn area_names_tolerate_case_and_separators() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let expected = filtered_outages(&client, "").1;
+    assert_eq!(expected.len(), 2);
+    for area_name in [
+        "Western-Cape-Stellenbosch",
+        "western_cape_stellenbosch",
+        "WESTERN%20CAPE%20STELLENBOSCH",
+        "western--cape__stellenbosch",
+    ] {
+        let response = client
+            .get(format!("/outages/{area_name}?include_past=true"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok, "{area_name}");
+        let outages = response.into_json::<Vec<PowerOutage>>().unwrap();
+        assert_eq!(outages, expected, "{area_name}");
+        assert!(outages
+            .iter()
+            .all(|outage| outage.area_name == "western-cape-stellenbosch"));
+
+        for path in [
+            format!("/schedules/{area_name}"),
+            format!("/outages/{area_name}/calendar.ics"),
+            format!("/current/{area_name}"),
+            format!("/next/{area_name}"),
+            format!("/outlook/{area_name}"),
+            format!("/stats/{area_name}"),
+        ] {
+            let response = client.get(&path).dispatch();
+            assert!(response.status().class().is_success(), "{path}");
+        }
+    }
+}
+
+#[test]
+fn openapi_documents_error_responses() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/api-docs/openapi.json").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let openapi = response.into_json::<serde_json::Value>().unwrap();
+    let responses = &openapi["paths"]["/outages/{area_name}"]["get"]["responses"];
+    for status in ["400", "404", "502"] {
+        assert_eq!(
+            responses[status]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ApiErrorBody",
+            "{status}"
+        );
+    }
+    let error_body = &openapi["components"]["schemas"]["ApiErrorBody"];
+    assert!(error_body["properties"]["suggestions"].is_object());
+}
+
+#[test]
+fn typescript_types_served() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get(uri!(crate::latest::typescript_client))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.content_type(),
+        Some(ContentType::new("application", "typescript"))
+    );
+    let types = response.into_string().unwrap();
+    let power_outage = types
+        .split("export interface PowerOutage {")
+        .nth(1)
+        .and_then(|rest| rest.split("\n}").next())
+        .expect("PowerOutage should be declared as an interface");
+    for field in [
+        "area_name: string;",
+        "stage: number;",
+        "start: string;",
+        "finsh: string;",
+        "source: string;",
+    ] {
+        assert!(power_outage.contains(field), "{field} in {power_outage}");
+    }
+    assert!(types.contains("export interface Area {"));
+    assert!(types.contains("export interface RecurringSchedule {"));
+}
+
+/// Listen for one request on a random local port, like a webhook would. Returns the URL to send the
+/// request to, and the raw text of the request once it arrives.
+async fn mock_webhook() -> (String, tokio::task::JoinHandle<String>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = vec![];
+        let mut buffer = [0; 1024];
+        // Read until the headers and the whole body have arrived
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                let content_length = headers
+                    .lines()
+                    .find_map(|line| {
+                        line.to_lowercase()
+                            .strip_prefix("content-length: ")?
+                            .parse()
+                            .ok()
+                    })
+                    .unwrap_or(0);
+                if body.len() >= content_length {
+                    break;
+                }
+            }
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+    (url, handle)
+}
+
+#[rocket::async_test]
+async fn stage_changes_sent_to_webhooks() {
+    let webhooks = Webhooks::allowing_private_addresses();
+    let (url, request) = mock_webhook().await;
+    let subscription = webhooks
+        .subscribe(
+            "western-cape-stellenbosch".to_string(),
+            url,
+            "you@example.com",
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+    let outages = [outage(
+        "2023-06-01T18:00:00+02:00",
+        "2023-06-01T20:30:00+02:00",
+        4,
+    )];
+
+    // Nothing has changed before the outage starts
+    let before = datetime("2023-06-01T17:00:00+02:00");
+    assert!(webhooks.check(&outages, before).await.is_empty());
+
+    let during = datetime("2023-06-01T19:00:00+02:00");
+    let sent = webhooks.check(&outages, during).await;
+    let expected = StageChangeNotification {
+        subscription_id: subscription.id.clone(),
+        area_name: "western-cape-stellenbosch".to_string(),
+        previous_stage: 0,
+        stage: 4,
+        outage: Some(outages[0].clone()),
+    };
+    assert_eq!(sent, [expected]);
+
+    let request = request.await.unwrap();
+    assert!(request.starts_with("POST /webhook "));
+    let (headers, body) = request.split_once("\r\n\r\n").unwrap();
+    assert_eq!(
+        serde_json::from_str::<StageChangeNotification>(body).unwrap(),
+        sent[0]
+    );
+    let signature = format!(
+        "{}: sha256={}",
+        SIGNATURE_HEADER.to_lowercase(),
+        sign(&subscription.secret, body)
+    );
+    assert!(headers.lines().any(|line| line.to_lowercase() == signature));
+
+    // The stage is only sent when it changes
+    assert!(webhooks.check(&outages, during).await.is_empty());
+}
+
+#[test]
+fn subscribe_to_stage_changes() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let subscribe = |area: &str, callback_url: &str, email: &str| {
+        client
+            .post(uri!(crate::latest::subscribe))
+            .json(&serde_json::json!({
+                "area": area,
+                "callback_url": callback_url,
+                "email": email,
+            }))
+            .dispatch()
+    };
+
+    // The sandbox the tests run in might not be able to look up hostnames, so this uses a public
+    // IP address instead
+    let response = subscribe(
+        "western-cape-stellenbosch",
+        "https://93.184.216.34/webhook",
+        "you@example.com",
+    );
+    assert_eq!(response.status(), Status::Created);
+    let subscription = response.into_json::<Subscription>().unwrap();
+    assert_eq!(subscription.area_name, "western-cape-stellenbosch");
+    assert_eq!(subscription.secret.len(), 64);
+
+    for (callback_url, email) in [
+        ("ftp://93.184.216.34/webhook", "you@example.com"),
+        ("not a url", "you@example.com"),
+        ("https://93.184.216.34/webhook", "not-an-email"),
+        // Webhooks can't be used to reach anything inside the API's own network
+        ("http://localhost:8000/webhook", "you@example.com"),
+        ("http://127.0.0.1/webhook", "you@example.com"),
+        ("http://10.0.0.1/webhook", "you@example.com"),
+        ("http://192.168.1.1/webhook", "you@example.com"),
+        ("http://169.254.169.254/latest/meta-data", "you@example.com"),
+        ("http://0.0.0.0/webhook", "you@example.com"),
+        ("http://[::1]/webhook", "you@example.com"),
+        ("http://[::ffff:127.0.0.1]/webhook", "you@example.com"),
+        ("http://[fd00::1]/webhook", "you@example.com"),
+    ] {
+        let response = subscribe("western-cape-stellenbosch", callback_url, email);
+        assert_eq!(
+            response.status(),
+            Status::BadRequest,
+            "{callback_url} {email}"
+        );
+    }
+    let response = subscribe(
+        "western-cape-narnia",
+        "https://93.184.216.34/webhook",
+        "you@example.com",
+    );
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn subscriptions_are_limited_and_can_be_removed() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let subscribe = |email: &str, ip: &str| {
+        client
+            .post(uri!(crate::latest::subscribe))
+            .remote(format!("{ip}:1234").parse::<SocketAddr>().unwrap())
+            .json(&serde_json::json!({
+                "area": "western-cape-stellenbosch",
+                "callback_url": "https://93.184.216.34/webhook",
+                "email": email,
+            }))
+            .dispatch()
+    };
+
+    let mut subscriptions = (0..MAX_SUBSCRIPTIONS_PER_SUBSCRIBER)
+        .map(|i| {
+            let response = subscribe("you@example.com", &format!("1.2.3.{i}"));
+            assert_eq!(response.status(), Status::Created);
+            response.into_json::<Subscription>().unwrap()
+        })
+        .collect::<Vec<_>>();
+    // The same email is counted together however it's written
+    let response = subscribe(" You@Example.com", "5.6.7.8");
+    assert_eq!(response.status(), Status::TooManyRequests);
+    assert_eq!(
+        response.into_json::<ApiErrorBody>().unwrap().error,
+        "too_many"
+    );
+    for i in 0..MAX_SUBSCRIPTIONS_PER_SUBSCRIBER {
+        let response = subscribe(&format!("them{i}@example.com"), "5.6.7.8");
+        assert_eq!(response.status(), Status::Created);
+    }
+    let response = subscribe("someone-else@example.com", "5.6.7.8");
+    assert_eq!(response.status(), Status::TooManyRequests);
+
+    // Only the subscription's secret can unsubscribe it
+    let subscription = subscriptions.pop().unwrap();
+    let unsubscribe = |secret: &str| {
+        client
+            .delete(uri!(crate::latest::unsubscribe(&subscription.id)))
+            .header(Header::new("Authorization", format!("Bearer {secret}")))
+            .dispatch()
+    };
+    let response = unsubscribe(&subscriptions[0].secret);
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client
+        .delete(uri!(crate::latest::unsubscribe(&subscription.id)))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = unsubscribe(&subscription.secret);
+    assert_eq!(response.status(), Status::NoContent);
+    let response = unsubscribe(&subscription.secret);
+    assert_eq!(response.status(), Status::NotFound);
+
+    // Which makes room for another one
+    let response = subscribe("you@example.com", "1.2.3.200");
+    assert_eq!(response.status(), Status::Created);
+}
+
+/// Serves whatever machine_friendly.csv the test has set most recently.
+struct SwitchableFetcher {
+    body: Arc<Mutex<String>>,
+}
+
+#[rocket::async_trait]
+impl Fetcher for SwitchableFetcher {
+    async fn fetch(&self, _url: &str, _etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        Ok(Fetched::Modified {
+            text: self.body.lock().unwrap().clone(),
+            etag: None,
+        })
+    }
+
+    async fn exists(&self, _url: &str) -> Result<bool, UpstreamError> {
+        Ok(false)
+    }
+}
+
+#[rocket::async_test]
+async fn longpoll_woken_by_refresh() {
+    use rocket::local::asynchronous::Client;
+
+    let stellenbosch = row_from_now("western-cape-stellenbosch", 60, 120, 4);
+    let cape_town = row_from_now("city-of-cape-town-area-15", 60, 120, 2);
+    let body = Arc::new(Mutex::new(machine_friendly(&[
+        stellenbosch.clone(),
+        cape_town.clone(),
+    ])));
+    let fetcher = SwitchableFetcher {
+        body: Arc::clone(&body),
+    };
+    // The outages are refreshed for the long-poll long before the TTL is up
+    let cache =
+        OutageCache::new(fetcher, DEFAULT_TTL).with_longpoll_refresh(Duration::from_millis(100));
+    let rocket = build_rocket_with(cache).attach(LongPollRefresher);
+    let client = Client::tracked(rocket).await.unwrap();
+
+    // Without `since`, the outages are sent straight away
+    let response = client
+        .get("/outages/western-cape-stellenbosch/longpoll")
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let etag = response.headers().get_one("ETag").unwrap().to_string();
+    // The quotes around the ETag can be left off, which saves escaping them
+    let since = etag.trim_matches('"');
+
+    // Another area's outages changing doesn't wake up the long-poll
+    let cape_town = cape_town.replace(",2,", ",6,");
+    *body.lock().unwrap() = machine_friendly(&[stellenbosch.clone(), cape_town.clone()]);
+    let response = client
+        .get(format!(
+            "/outages/western-cape-stellenbosch/longpoll?since={since}&timeout=2"
+        ))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::NotModified);
+
+    // Nothing else asks for the outages, so the long-poll only finds out about the change because
+    // they're refreshed in the background
+    let longpoll = client
+        .get(format!(
+            "/outages/western-cape-stellenbosch/longpoll?since={since}&timeout=10"
+        ))
+        .dispatch();
+    let change = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let stellenbosch = stellenbosch.replace(",4,", ",6,");
+        *body.lock().unwrap() = machine_friendly(&[stellenbosch, cape_town]);
+    };
+    let (response, ()) = tokio::join!(longpoll, change);
+    assert_eq!(response.status(), Status::Ok);
+    assert_ne!(response.headers().get_one("ETag"), Some(etag.as_str()));
+    let outages = response.into_json::<Vec<PowerOutage>>().await.unwrap();
+    assert_eq!(stages(&outages), [6]);
+}
+
+#[test]
+fn outages_with_only_some_fields() {
+    let client = client_with(FILTER_FIXTURE);
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true&fields=start,finsh")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outages = response.into_json::<Vec<serde_json::Value>>().unwrap();
+    assert!(!outages.is_empty());
+    for outage in &outages {
+        let fields = outage.as_object().unwrap();
+        assert!(fields.contains_key("start"));
+        assert!(fields.contains_key("finsh"));
+        assert!(!fields.contains_key("area_name"));
+        assert!(!fields.contains_key("source"));
+        assert!(!fields.contains_key("stage"));
+    }
+
+    // CSV only has the columns asked for, in the usual order
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true&fields=finsh,stage")
+        .header(Accept::CSV)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().unwrap();
+    assert!(body.starts_with("stage,finsh\n"));
+}
+
+#[test]
+fn outages_with_unknown_fields() {
+    let client = client_with(FILTER_FIXTURE);
+    let response = client
+        .get("/outages/western-cape-stellenbosch?fields=stage,finish")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let error = response.into_json::<ApiErrorBody>().unwrap();
+    assert!(error.message.contains("`finish`"));
+    assert!(error
+        .message
+        .contains("area_name, stage, start, finsh, source"));
+}
+
+/// The outages from [`FILTER_FIXTURE`], but not in any particular order.
+const UNSORTED_FIXTURE: &str = "\
+area_name,start,finsh,stage,source
+western-cape-stellenbosch,2023-06-02T02:00:00+02:00,2023-06-02T04:30:00+02:00,6,https://twitter.com/Eskom_SA/status/2
+western-cape-stellenbosch,2023-06-01T18:00:00+02:00,2023-06-01T20:30:00+02:00,4,https://twitter.com/Eskom_SA/status/1
+western-cape-stellenbosch,2023-06-02T16:00:00+02:00,2023-06-02T18:30:00+02:00,8,https://twitter.com/CityofCT/status/3
+western-cape-stellenbosch,2023-06-01T08:00:00+02:00,2023-06-01T10:30:00+02:00,2,https://twitter.com/Eskom_SA/status/1
+western-cape-stellenbosch,2023-06-03T08:00:00+02:00,2023-06-03T10:30:00+02:00,8,https://twitter.com/CityofCT/status/4
+";
+
+#[test]
+fn outages_sorted_by_start_by_default() {
+    let client = client_with(UNSORTED_FIXTURE);
+    for query in ["", "sort=start"] {
+        let (status, outages) = filtered_outages(&client, query);
+        assert_eq!(status, Status::Ok);
+        assert_eq!(stages(&outages), [2, 4, 6, 8, 8]);
+        assert!(outages
+            .windows(2)
+            .all(|pair| pair[0].start <= pair[1].start));
+    }
+
+    let (status, outages) = filtered_outages(&client, "sort=-start");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), [8, 8, 6, 4, 2]);
+    assert_eq!(outages[0].start, datetime("2023-06-03T08:00:00+02:00"));
+}
+
+#[test]
+fn outages_sorted_by_stage() {
+    let client = client_with(UNSORTED_FIXTURE);
+    let (status, outages) = filtered_outages(&client, "sort=-stage");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), [8, 8, 6, 4, 2]);
+    // Outages at the same stage are still earliest first
+    assert_eq!(outages[0].start, datetime("2023-06-02T16:00:00+02:00"));
+    assert_eq!(outages[1].start, datetime("2023-06-03T08:00:00+02:00"));
+
+    let (status, outages) = filtered_outages(&client, "sort=stage");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), [2, 4, 6, 8, 8]);
+
+    let (status, _outages) = filtered_outages(&client, "sort=finish");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn stages_reference() {
+    let client = client_with(FILTER_FIXTURE);
+    let response = client.get("/stages").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let stages = response.into_json::<Vec<serde_json::Value>>().unwrap();
+    let numbers = stages
+        .iter()
+        .map(|stage| stage["stage"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(numbers, (0..=8).collect::<Vec<_>>());
+    for stage in &stages {
+        assert!(!stage["description"].as_str().unwrap().is_empty());
+        assert!(stage["daily_outage_slots"].is_number());
+        assert!(stage["hours_off_per_day"].is_number());
+    }
+    // Higher stages never mean less time without power
+    assert!(stages.windows(2).all(|pair| {
+        pair[0]["hours_off_per_day"].as_f64() <= pair[1]["hours_off_per_day"].as_f64()
+    }));
+}
+
+#[test]
+fn province_outages_as_ics() {
+    let client = client_with(&machine_friendly(&[
+        row_from_now("western-cape-stellenbosch", 60, 120, 4),
+        row_from_now("western-cape-worcester", 180, 300, 6),
+        row_from_now("western-cape-worcester", -300, -180, 2),
+        row_from_now("gauteng-ekurhuleni-block-1", 60, 120, 4),
+    ]));
+    let response = client
+        .get(uri!(crate::latest::province_outages_ics("western-cape")))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::Calendar));
+    let ics = response.into_string().unwrap();
+
+    // Both areas in the province are there, but not the finished outage or the other province
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+    assert!(ics.contains("Stage 4 loadshedding in western-cape-stellenbosch"));
+    assert!(ics.contains("Stage 6 loadshedding in western-cape-worcester"));
+    assert!(!ics.contains("gauteng"));
+    assert!(ics.lines().all(|line| line.len() <= 75));
+
+    let response = client
+        .get(uri!(crate::latest::province_outages_ics("narnia")))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn machine_friendly_csv_passed_through() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetcher = FakeFetcher {
+        body: FILTER_FIXTURE.to_string(),
+        etag: Some("\"v1\"".to_string()),
+        fetches: Arc::clone(&fetches),
+        delay: Duration::ZERO,
+    };
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    for _ in 0..2 {
+        let response = client
+            .get(uri!(crate::latest::machine_friendly_csv))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::CSV));
+        assert_eq!(response.headers().get_one("ETag"), Some("\"v1\""));
+        let body = response.into_string().unwrap();
+        assert!(body.starts_with("area_name,start,finsh,stage,source\n"));
+        assert_eq!(body, FILTER_FIXTURE);
+    }
+    // The second request was answered from the cache
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn request_ids() {
+    let client = client_with(FILTER_FIXTURE);
+
+    // Without an ID, the API makes one up
+    let response = client.get(uri!(crate::latest::stage_reference)).dispatch();
+    let id = response.headers().get_one(REQUEST_ID_HEADER).unwrap();
+    assert!(uuid::Uuid::parse_str(id).is_ok());
+    let other = client.get(uri!(crate::latest::stage_reference)).dispatch();
+    assert_ne!(other.headers().get_one(REQUEST_ID_HEADER), Some(id));
+
+    // The client's ID is used if there is one, even when the request fails
+    let response = client
+        .get("/outages/narnia")
+        .header(Header::new(REQUEST_ID_HEADER, "my-request-42"))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(
+        response.headers().get_one(REQUEST_ID_HEADER),
+        Some("my-request-42")
+    );
+
+    // IDs which can't be written to the logs safely are replaced
+    let response = client
+        .get(uri!(crate::latest::stage_reference))
+        .header(Header::new(REQUEST_ID_HEADER, "x".repeat(1000)))
+        .dispatch();
+    let id = response.headers().get_one(REQUEST_ID_HEADER).unwrap();
+    assert!(uuid::Uuid::parse_str(id).is_ok());
+}
+
+/// Serves the fixtures from a fork of eskom-calendar, and remembers every URL it was asked for.
+#[derive(Default)]
+struct ForkFetcher {
+    urls: Arc<Mutex<Vec<String>>>,
+}
+
+/// Where [`ForkFetcher`] keeps its data.
+fn fork_sources() -> DataSources {
+    DataSources {
+        machine_friendly_url: "http://localhost:9000/machine_friendly.csv".to_string(),
+        schedules_url: "http://localhost:9000/generated/".to_string(),
+    }
+}
+
+#[rocket::async_trait]
+impl Fetcher for ForkFetcher {
+    async fn fetch(&self, url: &str, _etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        self.urls.lock().unwrap().push(url.to_string());
+        let sources = fork_sources();
+        let text = if url == sources.machine_friendly_url {
+            MACHINE_FRIENDLY
+        } else if url == sources.schedule_url("western-cape-stellenbosch") {
+            SCHEDULE_FIXTURE
+        } else {
+            return Ok(Fetched::NotFound);
+        };
+        Ok(Fetched::Modified {
+            text: text.to_string(),
+            etag: None,
+        })
+    }
+
+    async fn exists(&self, url: &str) -> Result<bool, UpstreamError> {
+        self.urls.lock().unwrap().push(url.to_string());
+        Ok(url == fork_sources().machine_friendly_url)
+    }
+}
+
+#[test]
+fn data_fetched_from_configured_sources() {
+    let fetcher = ForkFetcher::default();
+    let urls = Arc::clone(&fetcher.urls);
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL).with_sources(fork_sources());
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let (status, outages) = filtered_outages(&client, "");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), [6, 4]);
+    let response = client
+        .get("/schedules/western-cape-stellenbosch")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let urls = urls.lock().unwrap().clone();
+    assert!(urls.contains(&"http://localhost:9000/machine_friendly.csv".to_string()));
+    // The trailing slash on the schedules URL doesn't give a double slash
+    assert!(
+        urls.contains(&"http://localhost:9000/generated/western-cape-stellenbosch.csv".to_string())
+    );
+    assert!(urls
+        .iter()
+        .all(|url| url.starts_with("http://localhost:9000/")));
+}
+
+/// Serves a fixed set of files, keyed by URL. Any other URL is not found.
+struct FilesFetcher(HashMap<String, String>);
+
+#[rocket::async_trait]
+impl Fetcher for FilesFetcher {
+    async fn fetch(&self, url: &str, _etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        Ok(self
+            .0
+            .get(url)
+            .map_or(Fetched::NotFound, |text| Fetched::Modified {
+                text: text.clone(),
+                etag: None,
+            }))
+    }
+
+    async fn exists(&self, url: &str) -> Result<bool, UpstreamError> {
+        Ok(self.0.contains_key(url))
+    }
+}
+
+/// A schedule like Nelson Mandela Bay's, which repeats every 20 days.
+const PERIODIC_SCHEDULE_FIXTURE: &str = "\
+start_time,finsh_time,stage,day_of_cycle,period_of_cycle,start_of_cycle
+00:00,02:30,1,1,20,2023-02-18
+08:00,10:30,2,1,20,2023-02-18
+16:00,18:30,1,20,20,2023-02-18
+";
+
+#[test]
+fn periodic_schedules_parsed() {
+    let area_name = "eastern-cape-nelson-mandela-bay-block-1";
+    let sources = DataSources::default();
+    let mut files = HashMap::from([(
+        sources.machine_friendly_url.clone(),
+        machine_friendly(&[row_from_now(area_name, 60, 120, 2)]),
+    )]);
+    files.insert(
+        sources.schedule_url(area_name),
+        PERIODIC_SCHEDULE_FIXTURE.to_string(),
+    );
+    let cache = OutageCache::new(FilesFetcher(files.clone()), DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let response = client.get(format!("/schedules/{area_name}")).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let schedule = response.into_json::<RecurringSchedule>().unwrap();
+    assert_eq!(schedule.outages.len(), 3);
+    let periodic = Recurrence::Periodic {
+        offset: NaiveDate::from_ymd_opt(2023, 2, 18).unwrap(),
+        period_days: 20,
+    };
+    assert!(schedule
+        .outages
+        .iter()
+        .all(|outage| outage.recurrence == periodic));
+    assert_eq!(schedule.outages[2].day1_of_recurrence, 20);
+    assert_eq!(
+        schedule.outages[2].start_time,
+        NaiveTime::from_hms_opt(16, 0, 0).unwrap()
+    );
+
+    // Older schedules used `day_of_20_day_cycle` for the day of the cycle
+    files.insert(
+        sources.schedule_url(area_name),
+        PERIODIC_SCHEDULE_FIXTURE.replace("day_of_cycle", "day_of_20_day_cycle"),
+    );
+    let cache = OutageCache::new(FilesFetcher(files), DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let response = client.get(format!("/schedules/{area_name}")).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let schedule = response.into_json::<RecurringSchedule>().unwrap();
+    assert_eq!(schedule.outages.len(), 3);
+}
+
+#[test]
+fn malformed_machine_friendly_rows_skipped() {
+    let body = format!(
+        "{}{}{}",
+        machine_friendly(&[row_from_now("western-cape-stellenbosch", 60, 120, 4)]),
+        "western-cape-stellenbosch,tomorrow,the day after,four,https://twitter.com/Eskom_SA\n",
+        row_from_now("western-cape-stellenbosch", 180, 240, 6),
+    );
+    let client = client_with(&body);
+
+    // The good rows are still served
+    let (status, outages) = filtered_outages(&client, "");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), [4, 6]);
+
+    // The bad row is described in the cache's status
+    let response = client.get(uri!(crate::latest::cache_status)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let status = response.into_json::<CacheStatus>().unwrap();
+    assert_eq!(status.warnings.len(), 1);
+    assert!(status.warnings[0].starts_with("Skipped line 3:"));
+}
+
+#[test]
+fn malformed_schedule_rows_skipped() {
+    let area_name = "eastern-cape-nelson-mandela-bay-block-1";
+    let sources = DataSources::default();
+    let files = HashMap::from([
+        (
+            sources.machine_friendly_url.clone(),
+            machine_friendly(&[row_from_now(area_name, 60, 120, 2)]),
+        ),
+        (
+            sources.schedule_url(area_name),
+            format!("{PERIODIC_SCHEDULE_FIXTURE}25:00,02:30,1,1,20,2023-02-18\n08:00,10:30,2,21,20,2023-02-18\n"),
+        ),
+    ]);
+    let cache = OutageCache::new(FilesFetcher(files), DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let response = client.get(format!("/schedules/{area_name}")).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let schedule = response.into_json::<RecurringSchedule>().unwrap();
+    assert_eq!(schedule.outages.len(), 3);
+    assert_eq!(schedule.warnings.len(), 2);
+    assert!(schedule.warnings[0].starts_with("Skipped line 5:"));
+    assert!(schedule.warnings[0].contains("`25:00`"));
+    assert!(schedule.warnings[1].starts_with("Skipped line 6:"));
+}
+
+#[test]
+fn invalid_schedule_rows_are_errors() {
+    let monthly = RawMonthlyShedding {
+        start_time: "08:00".to_string(),
+        finsh_time: "10:30".to_string(),
+        stage: 2,
+        date_of_month: 32,
+    };
+    let err = RecurringOutage::try_from(monthly).unwrap_err();
+    assert!(err.contains("32"), "{err}");
+
+    let weekly = RawWeeklyShedding {
+        start_time: "8am".to_string(),
+        finsh_time: "10:30".to_string(),
+        stage: 2,
+        day_of_week: 3,
+    };
+    let err = RecurringOutage::try_from(weekly).unwrap_err();
+    assert!(err.contains("`8am`"), "{err}");
+
+    let periodic = RawPeriodicShedding {
+        start_time: "08:00".to_string(),
+        finsh_time: "10:30".to_string(),
+        stage: 2,
+        day_of_cycle: 1,
+        period_of_cycle: 20,
+        start_of_cycle: "18 Feb 2023".to_string(),
+    };
+    let err = RecurringOutage::try_from(periodic).unwrap_err();
+    assert!(err.contains("`18 Feb 2023`"), "{err}");
+}
+
+#[test]
+fn invalid_monthly_schedule_rows_skipped() {
+    let area_name = "western-cape-monthly-town";
+    let sources = DataSources::default();
+    let files = HashMap::from([
+        (
+            sources.machine_friendly_url.clone(),
+            machine_friendly(&[row_from_now(area_name, 60, 120, 2)]),
+        ),
+        (
+            sources.schedule_url(area_name),
+            "\
+start_time,finsh_time,stage,date_of_month
+08:00,10:30,1,1
+08:00,10:30,1,32
+half past eight,10:30,1,2
+"
+            .to_string(),
+        ),
+    ]);
+    let cache = OutageCache::new(FilesFetcher(files), DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let response = client.get(format!("/schedules/{area_name}")).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let schedule = response.into_json::<RecurringSchedule>().unwrap();
+    assert_eq!(schedule.outages.len(), 1);
+    assert_eq!(schedule.outages[0].recurrence, Recurrence::Monthly);
+    assert_eq!(schedule.warnings.len(), 2);
+    assert!(schedule.warnings[0].contains("32"));
+    assert!(schedule.warnings[1].contains("`half past eight`"));
+}
+
+#[test]
+fn search_finds_provinces_municipalities_and_areas() {
+    let client = client_with(&machine_friendly(&[
+        row_from_now("gauteng-ekurhuleni-block-1", 60, 120, 4),
+        row_from_now("gauteng-tshwane-group-1", 60, 120, 4),
+        row_from_now("western-cape-stellenbosch", 60, 120, 4),
+    ]));
+
+    let response = client.get("/search/gauteng").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let matches = response.into_json::<Vec<SearchMatch>>().unwrap();
+    assert_eq!(matches[0].kind, SearchKind::Province);
+    assert_eq!(matches[0].name, "gauteng");
+    assert!(matches!(
+        matches[0].result,
+        SearchMatchResult::Province(Province::Gauteng)
+    ));
+    // The areas in the province are there too, after the province itself
+    assert!(matches
+        .iter()
+        .any(|found| found.kind == SearchKind::Area && found.name == "gauteng-tshwane-group-1"));
+    assert!(matches
+        .windows(2)
+        .all(|pair| pair[0].score >= pair[1].score));
+
+    let response = client.get("/search/stellenbosch?limit=5").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let matches = response.into_json::<Vec<SearchMatch>>().unwrap();
+    assert!(matches.len() <= 5);
+    let kinds = matches
+        .iter()
+        .filter(|found| found.name.ends_with("stellenbosch"))
+        .map(|found| found.kind)
+        .collect::<Vec<_>>();
+    assert!(kinds.contains(&SearchKind::Area));
+    assert!(kinds.contains(&SearchKind::Municipality));
+    let area = matches
+        .iter()
+        .find(|found| found.kind == SearchKind::Area)
+        .unwrap();
+    assert!(
+        matches!(&area.result, SearchMatchResult::Area(area) if area.name == "western-cape-stellenbosch")
+    );
+
+    let response = client.get("/search/stellenbosch?limit=lots").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn schedules_cached() {
+    let schedule_fetches = |ttl: Duration| {
+        let fetcher = ForkFetcher::default();
+        let urls = Arc::clone(&fetcher.urls);
+        let cache = OutageCache::new(fetcher, ttl).with_sources(fork_sources());
+        let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+        for uri in [
+            "/schedules/western-cape-stellenbosch",
+            "/schedules/western-cape-stellenbosch",
+            "/schedules/western-cape-stellenbosch/expand?stage=4",
+        ] {
+            assert_eq!(client.get(uri).dispatch().status(), Status::Ok);
+        }
+        let schedule_url = fork_sources().schedule_url("western-cape-stellenbosch");
+        let urls = urls.lock().unwrap();
+        urls.iter().filter(|url| **url == schedule_url).count()
+    };
+
+    // Every request after the first uses the cached schedule
+    assert_eq!(schedule_fetches(DEFAULT_TTL), 1);
+    // Once the TTL has passed, the schedule is fetched again
+    assert_eq!(schedule_fetches(Duration::ZERO), 3);
+}
+
+#[test]
+fn schedule_validation_reports_overlaps_and_gaps() {
+    let area_name = "western-cape-overlap-town";
+    // Stage 1 is missing on Friday, and Wednesday's stage 2 outages overlap
+    let mut schedule = "start_time,finsh_time,stage,day_of_week\n".to_string();
+    for day in [1, 2, 3, 4, 6, 7] {
+        schedule.push_str(&format!("22:00,00:30,1,{day}\n"));
+    }
+    for day in 1..=7 {
+        schedule.push_str(&format!("08:00,10:30,2,{day}\n"));
+    }
+    schedule.push_str("10:00,12:30,2,3\n");
+    // Back-to-back outages don't overlap
+    schedule.push_str("10:30,12:30,2,4\n");
+
+    let sources = DataSources::default();
+    let files = HashMap::from([
+        (
+            sources.machine_friendly_url.clone(),
+            machine_friendly(&[row_from_now(area_name, 60, 120, 2)]),
+        ),
+        (sources.schedule_url(area_name), schedule),
+    ]);
+    let cache = OutageCache::new(FilesFetcher(files), DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let response = client
+        .get(uri!(crate::latest::schedules_validate(area_name)))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let report = response.into_json::<ScheduleReport>().unwrap();
+    assert!(!report.is_valid);
+
+    assert_eq!(report.overlaps.len(), 1);
+    let overlap = &report.overlaps[0];
+    assert_eq!((overlap.stage, overlap.day), (2, 3));
+    assert_eq!(
+        overlap.first.start_time,
+        NaiveTime::from_hms_opt(8, 0, 0).unwrap()
+    );
+    assert_eq!(
+        overlap.second.start_time,
+        NaiveTime::from_hms_opt(10, 0, 0).unwrap()
+    );
+
+    assert_eq!(
+        report.gaps,
+        [ScheduleGap {
+            stage: 1,
+            day: 5,
+            recurrence: Recurrence::Weekly,
+        }]
+    );
+}
+
+#[test]
+fn schedule_validation_of_a_good_schedule() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get(uri!(crate::latest::schedules_validate(
+            "western-cape-stellenbosch"
+        )))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let report = response.into_json::<ScheduleReport>().unwrap();
+    assert!(report.overlaps.is_empty());
+    assert_eq!(report.area_name, "western-cape-stellenbosch");
+}
+
+#[test]
+fn cached_responses_have_cache_headers() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let uris = [
+        "/outages/western-cape-stellenbosch".to_string(),
+        uri!(crate::latest::list_all_areas(_, _)).to_string(),
+        uri!(crate::latest::list_areas("stellenbosch", _, _, _)).to_string(),
+        uri!(crate::latest::fuzzy_search("stellenbosch", _, _, _, _)).to_string(),
+    ];
+    for uri in uris {
+        let response = client.get(uri.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok, "{uri}");
+        let cache_control = response.headers().get_one("Cache-Control").unwrap();
+        let max_age = cache_control
+            .strip_prefix("public, max-age=")
+            .and_then(|max_age| max_age.parse::<u64>().ok())
+            .unwrap();
+        assert!(max_age > 0, "{uri} had {cache_control}");
+        let last_modified = response.headers().get_one("Last-Modified").unwrap();
+        assert!(
+            chrono::DateTime::parse_from_rfc2822(last_modified).is_ok(),
+            "{uri} had {last_modified}"
+        );
+    }
+}
+
+#[test]
+fn head_outages_has_the_same_headers_as_get() {
+    let fetcher = FakeFetcher {
+        body: MACHINE_FRIENDLY.to_string(),
+        etag: Some("\"v1\"".to_string()),
+        fetches: Arc::new(AtomicUsize::new(0)),
+        delay: Duration::ZERO,
+    };
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    let get = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(get.status(), Status::Ok);
+    let head = client.head("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(head.status(), Status::Ok);
+    for header in ["ETag", "Last-Modified", "Content-Type"] {
+        assert!(
+            head.headers().get_one(header).is_some(),
+            "{header} is missing"
+        );
+        assert_eq!(
+            head.headers().get_one(header),
+            get.headers().get_one(header),
+            "{header} differs"
+        );
+    }
+    assert_eq!(head.headers().get_one("ETag"), Some("W/\"v1\""));
+    assert!(head.headers().get_one("Cache-Control").is_some());
+    assert_eq!(head.into_string(), None);
+
+    // `format=` changes the content type of both
+    for format in ["json", "csv", "ics"] {
+        let uri = format!("/outages/western-cape-stellenbosch?include_past=true&format={format}");
+        let get = client.get(&uri).dispatch();
+        let head = client.head(&uri).dispatch();
+        assert_eq!(head.status(), Status::Ok, "{format}");
+        assert_eq!(head.content_type(), get.content_type(), "{format}");
+    }
+    let head = client
+        .head("/outages/western-cape-stellenbosch?format=csv")
+        .dispatch();
+    assert_eq!(head.content_type(), Some(ContentType::CSV));
+    let head = client
+        .head("/outages/western-cape-stellenbosch?format=xml")
+        .dispatch();
+    assert_eq!(head.status(), Status::BadRequest);
+
+    let missing = client.head("/outages/not-an-area").dispatch();
+    assert_eq!(missing.status(), Status::NotFound);
+}
+
+/// The areas returned by `/areas/search/<query>`, in order.
+fn autocomplete(client: &Client, uri: &str) -> Vec<String> {
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    response
+        .into_json::<Vec<Area>>()
+        .unwrap()
+        .into_iter()
+        .map(|area| area.name)
+        .collect()
+}
+
+#[test]
+fn areas_search_puts_prefix_matches_first() {
+    let rows = [
+        "limpopo-stanley",
+        "gauteng-soweto",
+        "gauteng-stanza-bopape",
+        "stanford",
+        "western-cape-stellenbosch",
+    ]
+    .map(|area_name| row_from_now(area_name, 60, 120, 2));
+    let client = client_with(&machine_friendly(&rows));
+
+    assert_eq!(
+        autocomplete(&client, "/areas/search/stan"),
+        ["stanford", "gauteng-stanza-bopape", "limpopo-stanley"]
+    );
+    assert_eq!(
+        autocomplete(&client, "/areas/search/stan?limit=2"),
+        ["stanford", "gauteng-stanza-bopape"]
+    );
+    assert_eq!(
+        autocomplete(&client, "/areas/search/Gauteng%20Stan"),
+        ["gauteng-stanza-bopape"]
+    );
+    // Aliases are matched too
+    assert_eq!(
+        autocomplete(&client, "/areas/search/stellies"),
+        ["western-cape-stellenbosch"]
+    );
+    assert!(autocomplete(&client, "/areas/search/durban").is_empty());
+
+    let response = client.get("/areas/search/%20-%20").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn public_holidays_take_precedence_over_weekends() {
+    let holidays = PublicHolidays::bundled();
+    let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    assert_eq!(holidays.day_type(date("2024-04-26")), DayType::Weekday);
+    assert_eq!(holidays.day_type(date("2024-04-28")), DayType::Weekend);
+    // Freedom Day fell on a Saturday in 2024
+    assert_eq!(
+        holidays.day_type(date("2024-04-27")),
+        DayType::PublicHoliday
+    );
+    assert_eq!(holidays.on(date("2024-04-27")), Some("Freedom Day"));
+    // Youth Day fell on a Sunday in 2024, so the Monday was a holiday too
+    assert_eq!(
+        holidays.day_type(date("2024-06-17")),
+        DayType::PublicHoliday
+    );
+    assert_eq!(holidays.on(date("2024-06-18")), None);
+
+    assert!(PublicHolidays::from_json(r#"{"27 April": "Freedom Day"}"#).is_err());
+    assert!(PublicHolidays::from_json(r#"{"2024-04-27": 1}"#).is_err());
+}
+
+#[test]
+fn schedules_expand_tags_public_holidays() {
+    let client = client_with(MACHINE_FRIENDLY);
+    // Workers' Day was on the Wednesday of this week
+    let uri = "/schedules/western-cape-stellenbosch/expand?stage=2\
+        &from=2024-04-29T00:00:00%2B02:00&to=2024-05-06T00:00:00%2B02:00";
+
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outages = response.into_json::<Vec<ExpandedOutage>>().unwrap();
+    let tags = outages
+        .iter()
+        .map(|expanded| {
+            (
+                expanded.outage.start,
+                expanded.day_type,
+                expanded.holiday.as_deref(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tags,
+        [
+            (
+                datetime("2024-04-29T22:00:00+02:00"),
+                DayType::Weekday,
+                None
+            ),
+            (
+                datetime("2024-05-01T08:00:00+02:00"),
+                DayType::PublicHoliday,
+                Some("Workers' Day")
+            ),
+        ]
+    );
+
+    let response = client.get(format!("{uri}&calendar=none")).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outages = response.into_json::<Vec<ExpandedOutage>>().unwrap();
+    assert!(outages
+        .iter()
+        .all(|expanded| expanded.day_type == DayType::Weekday && expanded.holiday.is_none()));
+
+    let response = client.get(format!("{uri}&calendar=uk")).dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+/// Serves each of `bodies` in turn as machine_friendly.csv, and then says that the last one is
+/// unchanged.
+struct SequenceFetcher {
+    bodies: Vec<String>,
+    fetches: AtomicUsize,
+}
+
+#[rocket::async_trait]
+impl Fetcher for SequenceFetcher {
+    async fn fetch(&self, _url: &str, etag: Option<&str>) -> Result<Fetched, UpstreamError> {
+        let version = self
+            .fetches
+            .fetch_add(1, Ordering::SeqCst)
+            .min(self.bodies.len() - 1);
+        let version_etag = format!("\"v{version}\"");
+        if etag == Some(version_etag.as_str()) {
+            return Ok(Fetched::NotModified);
+        }
+        Ok(Fetched::Modified {
+            text: self.bodies[version].clone(),
+            etag: Some(version_etag),
+        })
+    }
+
+    async fn exists(&self, _url: &str) -> Result<bool, UpstreamError> {
+        Ok(false)
+    }
+}
+
+#[test]
+fn outages_diff_reports_changes_since_a_snapshot() {
+    let area_name = "western-cape-stellenbosch";
+    // The rows are all relative to the same time, so that an outage in both bodies starts at
+    // exactly the same time in each
+    let now = sast_now();
+    let row = |area_name: &str, start_mins: i64, finsh_mins: i64, stage: u8| {
+        format!(
+            "{area_name},{},{},{stage},https://twitter.com/Eskom_SA\n",
+            (now + ChronoDuration::minutes(start_mins)).to_rfc3339(),
+            (now + ChronoDuration::minutes(finsh_mins)).to_rfc3339(),
+        )
+    };
+    let fetcher = SequenceFetcher {
+        bodies: vec![
+            machine_friendly(&[
+                row(area_name, 60, 120, 2),
+                row(area_name, 600, 660, 2),
+                row(area_name, 1200, 1260, 2),
+                row("city-of-cape-town-area-15", 60, 120, 2),
+            ]),
+            machine_friendly(&[
+                row(area_name, 60, 120, 2),
+                row(area_name, 600, 720, 4),
+                row(area_name, 1800, 1860, 4),
+            ]),
+        ],
+        fetches: AtomicUsize::new(0),
+    };
+    // A TTL of zero means every request refreshes the cache, so each request takes a snapshot
+    // until the fetcher runs out of new bodies
+    let cache = OutageCache::new(fetcher, Duration::ZERO);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let since = |time: DateTime<FixedOffset>| time.to_rfc3339().replace('+', "%2B");
+
+    let before_first = sast_now();
+    let response = client.get(format!("/outages/{area_name}")).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get(format!("/outages/{area_name}")).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .get(format!(
+            "/outages/{area_name}/diff?since={}",
+            since(before_first)
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let diff = response.into_json::<OutageDiff>().unwrap();
+    assert!(diff.compared_with >= before_first);
+    assert_eq!(stages(&diff.added), [4]);
+    assert_eq!(diff.added[0].start, now + ChronoDuration::minutes(1800));
+    assert_eq!(diff.removed[0].start, now + ChronoDuration::minutes(1200));
+    assert_eq!(stages(&diff.removed), [2]);
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(
+        (diff.changed[0].before.stage, diff.changed[0].after.stage),
+        (2, 4)
+    );
+
+    // Nothing has changed since the latest snapshot
+    let response = client
+        .get(format!(
+            "/outages/{area_name}/diff?since={}",
+            since(sast_now())
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let diff = response.into_json::<OutageDiff>().unwrap();
+    assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty());
+
+    let response = client
+        .get(format!("/outages/{area_name}/diff?since=yesterday"))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[rocket::async_test]
+async fn snapshots_only_taken_when_outages_change() {
+    let body = Arc::new(Mutex::new(MACHINE_FRIENDLY.to_string()));
+    let fetcher = SwitchableFetcher {
+        body: Arc::clone(&body),
+    };
+    // A TTL of zero means every call refetches machine_friendly.csv
+    let cache = OutageCache::new(fetcher, Duration::ZERO);
+    cache.outages().await.unwrap();
+    let after_first = sast_now();
+    let first = cache.snapshot_since(after_first).await.unwrap();
+    assert!(first.taken_at < after_first);
+
+    // GitHub sends the same outages again, which isn't worth another snapshot
+    cache.outages().await.unwrap();
+    let latest = cache.snapshot_since(after_first).await.unwrap();
+    assert_eq!(latest.taken_at, first.taken_at);
+
+    *body.lock().unwrap() = FILTER_FIXTURE.to_string();
+    cache.outages().await.unwrap();
+    let latest = cache.snapshot_since(after_first).await.unwrap();
+    assert!(latest.taken_at >= after_first);
+    assert_ne!(latest.outages, first.outages);
+}
+
+#[test]
+fn list_areas_rejects_oversized_regexes() {
+    let client = client_with(MACHINE_FRIENDLY);
+
+    // Short, but compiles to millions of states
+    let started = std::time::Instant::now();
+    let response = client
+        .get(uri!(crate::latest::list_areas("a{1000}{1000}", _, _, _)))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let error = response.into_json::<ApiErrorBody>().unwrap();
+    assert_eq!(error.error, "bad_request");
+    assert!(error.message.contains("too complex"), "{}", error.message);
+
+    let response = client
+        .get(uri!(crate::latest::list_areas("a".repeat(1001), _, _, _)))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    // Invalid regexes are still reported as such, and sensible ones still work
+    let response = client
+        .get(uri!(crate::latest::list_areas("stellenbosch(", _, _, _)))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client
+        .get(uri!(crate::latest::list_areas(
+            "a{10}|stellenbosch",
+            _,
+            _,
+            _
+        )))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<AreasPage>().unwrap().areas,
+        ["western-cape-stellenbosch"]
+    );
+}
+
+#[test]
+fn list_areas_can_be_anchored() {
+    let rows = [
+        "western-cape-stellenbosch",
+        "stellenbosch",
+        "stellenbosch-central",
+    ]
+    .map(|area_name| row_from_now(area_name, 60, 120, 2));
+    let client = client_with(&machine_friendly(&rows));
+    let areas = |anchored: Option<&str>| {
+        let response = client
+            .get(uri!(crate::latest::list_areas(
+                "stellenbosch",
+                _,
+                _,
+                anchored
+            )))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response.into_json::<AreasPage>().unwrap().areas
+    };
+
+    // Substrings match by default
+    let substring = [
+        "stellenbosch",
+        "stellenbosch-central",
+        "western-cape-stellenbosch",
+    ];
+    assert_eq!(areas(None), substring);
+    assert_eq!(areas(Some("false")), substring);
+    assert_eq!(areas(Some("true")), ["stellenbosch"]);
+
+    // Anchoring applies to every alternative, not just the first and last
+    let response = client
+        .get(uri!(crate::latest::list_areas(
+            "stellenbosch|western-cape-.*",
+            _,
+            _,
+            Some("true")
+        )))
+        .dispatch();
+    assert_eq!(
+        response.into_json::<AreasPage>().unwrap().areas,
+        ["stellenbosch", "western-cape-stellenbosch"]
+    );
+
+    let response = client
+        .get(uri!(crate::latest::list_areas(
+            "stellenbosch",
+            _,
+            _,
+            Some("yes please")
+        )))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn outages_today_and_tomorrow_split_on_start_day() {
+    let area_name = "western-cape-stellenbosch";
+    let today = sast_now().date_naive();
+    let at = |days: i64, time: &str| {
+        let time = NaiveTime::parse_from_str(time, "%H:%M").unwrap();
+        (today + ChronoDuration::days(days))
+            .and_time(time)
+            .and_local_timezone(sast())
+            .unwrap()
+    };
+    let row = |area_name: &str, start: DateTime<FixedOffset>, finsh: DateTime<FixedOffset>| {
+        format!(
+            "{area_name},{},{},2,https://twitter.com/Eskom_SA\n",
+            start.to_rfc3339(),
+            finsh.to_rfc3339()
+        )
+    };
+    let client = client_with(&machine_friendly(&[
+        row(area_name, at(-1, "23:30"), at(0, "01:00")),
+        row(area_name, at(0, "23:30"), at(1, "01:00")),
+        row(area_name, at(0, "08:00"), at(0, "10:00")),
+        row(area_name, at(1, "10:00"), at(1, "12:00")),
+        row("city-of-cape-town-area-15", at(0, "12:00"), at(0, "14:00")),
+    ]));
+    let starts = |day: &str| {
+        let response = client.get(format!("/outages/{area_name}/{day}")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response
+            .into_json::<Vec<PowerOutage>>()
+            .unwrap()
+            .into_iter()
+            .map(|outage| outage.start)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(starts("today"), [at(0, "08:00"), at(0, "23:30")]);
+    assert_eq!(starts("tomorrow"), [at(1, "10:00")]);
+
+    let response = client.get("/outages/western-cape-narnia/today").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn rate_limit_uses_forwarded_ip_behind_trusted_proxy() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let rocket = build_rocket_with(cache)
+        .attach(RateLimiter::per_minute(1))
+        .manage(ProxyPolicy { trusted_proxies: 1 });
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+    // Every request comes from the proxy, which says who it's forwarding for
+    let proxy: SocketAddr = "10.0.0.1:1234".parse().unwrap();
+    let get = |forwarded_for: &str| {
+        client
+            .get(uri!(crate::latest::list_all_areas(_, _)))
+            .remote(proxy)
+            .header(Header::new("X-Forwarded-For", forwarded_for.to_string()))
+            .dispatch()
+            .status()
+    };
+
+    assert_eq!(get("1.2.3.4"), Status::Ok);
+    assert_eq!(get("5.6.7.8"), Status::Ok);
+    assert_eq!(get("1.2.3.4"), Status::TooManyRequests);
+    // Clients can add their own entries, but only the proxy's entry is trusted
+    assert_eq!(get("9.9.9.9, 1.2.3.4"), Status::TooManyRequests);
+    assert_eq!(get("1.2.3.4, 9.9.9.9"), Status::Ok);
+}
+
+#[test]
+fn forwarded_ip_is_ignored_without_trusted_proxies() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let rocket = build_rocket_with(cache).attach(RateLimiter::per_minute(1));
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+    let user: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+    for (forwarded_for, status) in [
+        ("5.6.7.8", Status::Ok),
+        ("9.9.9.9", Status::TooManyRequests),
+    ] {
+        let response = client
+            .get(uri!(crate::latest::list_all_areas(_, _)))
+            .remote(user)
+            .header(Header::new("X-Forwarded-For", forwarded_for))
+            .dispatch();
+        assert_eq!(response.status(), status);
+    }
+}
+
+#[test]
+fn outages_all_filters_every_area() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/outages/all").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let all = response.into_json::<AllOutages>().unwrap();
+    assert_eq!(all.total, 2);
+    assert_eq!(stages(&all.outages["western-cape-stellenbosch"]), [6, 4]);
+    assert_eq!(stages(&all.outages["city-of-cape-town-area-15"]), [4]);
+
+    // Filters apply to every area, and areas left without outages are dropped
+    let response = client.get("/outages/all?min_stage=5").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let all = response.into_json::<AllOutages>().unwrap();
+    assert_eq!(all.total, 1);
+    assert_eq!(stages(&all.outages["western-cape-stellenbosch"]), [6]);
+    let response = client
+        .get("/outages/all?finsh=2023-06-01T21:00:00%2B02:00")
+        .dispatch();
+    let all = response.into_json::<AllOutages>().unwrap();
+    assert_eq!(stages(&all.outages["western-cape-stellenbosch"]), [6]);
+    assert_eq!(stages(&all.outages["city-of-cape-town-area-15"]), [4]);
+
+    // Areas are paged alphabetically, and pages can't be bigger than the maximum
+    let response = client.get("/outages/all?limit=1&offset=1").dispatch();
+    let all = response.into_json::<AllOutages>().unwrap();
+    assert_eq!((all.total, all.offset, all.limit), (2, 1, 1));
+    assert_eq!(
+        all.outages.keys().collect::<Vec<_>>(),
+        ["western-cape-stellenbosch"]
+    );
+    let response = client.get("/outages/all?limit=1000000").dispatch();
+    assert_eq!(response.into_json::<AllOutages>().unwrap().limit, 500);
+
+    let response = client.get("/outages/all?min_stage=9").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn fuzzy_search_scores_are_out_of_100() {
+    let rows = [
+        row_from_now("western-cape-stellenbosch", 60, 120, 2),
+        row_from_now("western-cape-stellen-village-bosch", 60, 120, 2),
+        row_from_now("western-cape-stellenbosch-farms", 60, 120, 2),
+    ];
+    let client = client_with(&machine_friendly(&rows));
+    let search = |query: &str| {
+        client
+            .get(format!("/fuzzy_search/{query}"))
+            .dispatch()
+            .into_json::<Vec<SearchResult<Area>>>()
+            .unwrap()
+    };
+
+    let results = search("stellenbosch");
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].score, 100);
+    let best_raw_score = results[0].raw_score;
+    for found in &results {
+        assert_eq!(found.score, found.raw_score * 100 / best_raw_score);
+    }
+    let weakest = results.last().unwrap();
+    assert_eq!(weakest.result.name, "western-cape-stellen-village-bosch");
+    assert!(weakest.score < 100);
+
+    // `min_score` is out of 100 too, so it works the same for a longer query
+    let strong = search(&format!("stellenbosch?min_score={}", weakest.score + 1));
+    assert_eq!(strong.len(), 2);
+    assert_eq!(search("cape%20stellenbosch")[0].score, 100);
+}
+
+#[test]
+fn fuzzy_search_rejects_short_queries() {
+    let client = client_with(&machine_friendly(&[row_from_now(
+        "western-cape-stellenbosch",
+        60,
+        120,
+        2,
+    )]));
+
+    for query in ["%20%20%20", "s", "-%20-", "%20s%20"] {
+        let response = client.get(format!("/fuzzy_search/{query}")).dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "query: {query}");
+        let body = response.into_json::<ApiErrorBody>().unwrap();
+        assert!(body.message.contains("at least 2"), "{}", body.message);
+    }
+
+    // Surrounding whitespace is trimmed rather than counted
+    let response = client.get("/fuzzy_search/%20st%20").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let results = response.into_json::<Vec<SearchResult<Area>>>().unwrap();
+    assert_eq!(results[0].result.name, "western-cape-stellenbosch");
+}
+
+#[rocket::async_test]
+async fn areas_with_the_same_schedule_are_siblings() {
+    let rows = [
+        row_from_now("gauteng-tshwane-group-1", 60, 120, 2),
+        row_from_now("gauteng-tshwane-group-1-centurion", 60, 120, 2),
+        row_from_now("gauteng-tshwane-group-2", 60, 120, 2),
+    ];
+    let (cache, _fetches) = fake_cache(&machine_friendly(&rows), Duration::ZERO);
+    let aliases = AreaAliases::bundled();
+    // eskom-calendar doesn't know about `gauteng-tshwane-group-1-gone`, so it's never listed
+    let schedules = AreaSchedules::from_json(
+        r#"{"gauteng-tshwane-group-1": [
+            "gauteng-tshwane-group-1",
+            "gauteng-tshwane-group-1-centurion",
+            "gauteng-tshwane-group-1-gone"
+        ]}"#,
+    )
+    .unwrap();
+    let siblings = |name: &str| {
+        crate::v0_0_1::area_siblings(
+            name.to_string(),
+            AcceptLanguage::default(),
+            State::from(&cache),
+            State::from(&aliases),
+            State::from(&schedules),
+        )
+    };
+
+    let of_group_1 = siblings("gauteng-tshwane-group-1").await.unwrap().0;
+    let of_centurion = siblings("gauteng-tshwane-group-1-centurion")
+        .await
+        .unwrap()
+        .0;
+    assert_eq!(of_group_1.len(), 1);
+    assert_eq!(of_group_1[0].name, "gauteng-tshwane-group-1-centurion");
+    assert_eq!(of_centurion.len(), 1);
+    assert_eq!(of_centurion[0].name, "gauteng-tshwane-group-1");
+    assert_eq!(of_group_1[0].schedule, of_centurion[0].schedule);
+    assert!(siblings("gauteng-tshwane-group-2")
+        .await
+        .unwrap()
+        .0
+        .is_empty());
+
+    // `/area/<name>` agrees about which schedule the area follows
+    let area = crate::v0_0_1::area(
+        "gauteng-tshwane-group-1".to_string(),
+        AcceptLanguage::default(),
+        State::from(&cache),
+        State::from(&aliases),
+        State::from(&schedules),
+    )
+    .await
+    .unwrap()
+    .0;
+    assert_eq!(area.schedule, of_group_1[0].schedule);
+
+    let error = siblings("gauteng-tshwane-group-9").await.unwrap_err();
+    assert_eq!(error.status(), Status::NotFound);
+}
+
+#[test]
+fn bundled_area_schedules_are_real_areas() {
+    let bundled: HashMap<String, Vec<String>> =
+        serde_json::from_str(include_str!("../data/area_schedules.json")).unwrap();
+    if bundled.is_empty() {
+        return;
+    }
+    // Like `non_empty_all_areas`, this asks GitHub for eskom-calendar's actual list of areas, so
+    // that the bundled schedules can't name areas which don't exist
+    let client = Client::tracked(build_rocket()).expect("valid rocket instance");
+    let response = client
+        .get(uri!(crate::latest::list_all_areas(Some("100000"), _)))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let areas = response.into_json::<AreasPage>().unwrap().areas;
+    for area_name in bundled.values().flatten() {
+        assert!(
+            areas.contains(area_name),
+            "{area_name} is in data/area_schedules.json but not in /list_areas"
+        );
+    }
+}
+
+#[test]
+fn area_schedules_are_parsed() {
+    let schedules =
+        AreaSchedules::from_json(r#"{"area-a": ["area-a", "area-b", "area-c"]}"#).unwrap();
+    assert_eq!(schedules.schedule_of("area-b"), "area-a");
+    assert_eq!(schedules.schedule_of("area-d"), "area-d");
+    assert_eq!(schedules.siblings_of("area-b"), ["area-a", "area-c"]);
+    assert!(schedules.siblings_of("area-d").is_empty());
+    // An area can't follow two schedules
+    assert!(AreaSchedules::from_json(r#"{"area-a": ["area-a"], "area-b": ["area-a"]}"#).is_err());
+    assert!(AreaSchedules::from_json(r#"{"area-a": "area-b"}"#).is_err());
+    AreaSchedules::bundled();
+}
+
+/// Somewhere for logs to be written in tests, so they can be checked afterwards.
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'w> tracing_subscriber::fmt::MakeWriter<'w> for CapturedLogs {
+    type Writer = CapturedLogs;
+
+    fn make_writer(&'w self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[test]
+fn json_logs_have_request_fields() {
+    assert_eq!(LogFormat::from_name("JSON"), LogFormat::Json);
+    assert_eq!(LogFormat::from_name("text"), LogFormat::Text);
+    assert_eq!(LogFormat::from_name("yaml"), LogFormat::Text);
+
+    let logs = CapturedLogs::default();
+    let _guard =
+        tracing::subscriber::set_default(logging::subscriber(LogFormat::Json, logs.clone()));
+    let client = client_with(&machine_friendly(&[row_from_now(
+        "western-cape-stellenbosch",
+        60,
+        120,
+        2,
+    )]));
+    let response = client
+        .get("/outages/western-cape-stellenbosch")
+        .header(Header::new(REQUEST_ID_HEADER, "log-me"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let lines = logs
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let responded = lines
+        .iter()
+        .find(|line| line["request_id"] == "log-me" && line.get("status").is_some())
+        .unwrap();
+    assert_eq!(responded["status"], 200);
+    assert_eq!(responded["endpoint"], "/outages/<area_name>");
+    assert_eq!(responded["area"], "western-cape-stellenbosch");
+    assert!(responded["duration_ms"].as_f64().unwrap() >= 0.0);
+}
+
+#[test]
+fn duplicate_outages_are_collapsed() {
+    let start = (sast_now() + ChronoDuration::hours(1)).to_rfc3339();
+    let finsh = (sast_now() + ChronoDuration::hours(3)).to_rfc3339();
+    let rows = [
+        format!("western-cape-stellenbosch,{start},{finsh},4,https://twitter.com/first\n"),
+        format!("western-cape-stellenbosch,{start},{finsh},4,https://twitter.com/second\n"),
+        // A different stage is a different outage
+        format!("western-cape-stellenbosch,{start},{finsh},5,https://twitter.com/third\n"),
+    ];
+    let client = client_with(&machine_friendly(&rows));
+
+    let outages = client
+        .get("/outages/western-cape-stellenbosch")
+        .dispatch()
+        .into_json::<Vec<PowerOutage>>()
+        .unwrap();
+    assert_eq!(outages.len(), 2);
+    assert_eq!(outages[0].stage, 4);
+    assert_eq!(outages[0].source, "https://twitter.com/first");
+    assert_eq!(outages[1].stage, 5);
+}
+
+#[test]
+fn schedules_picks_the_version_valid_at_a_date() {
+    let area_name = "western-cape-stellenbosch";
+    let sources = DataSources::default();
+    let files = HashMap::from([
+        (
+            sources.machine_friendly_url.clone(),
+            machine_friendly(&[row_from_now(area_name, 60, 120, 2)]),
+        ),
+        (
+            sources.schedule_url(area_name),
+            SCHEDULE_FIXTURE.to_string(),
+        ),
+        (
+            sources.schedule_metadata_url(area_name),
+            r#"{
+                "valid_from": "2024-01-01T00:00:00",
+                "superseded": [{
+                    "file": "western-cape-stellenbosch.2023.csv",
+                    "source": ["https://example.com/2023.xlsx"],
+                    "valid_from": "2023-01-01T00:00:00",
+                    "valid_until": "2023-12-31T23:59:59"
+                }]
+            }"#
+            .to_string(),
+        ),
+        (
+            sources.schedule_file_url("western-cape-stellenbosch.2023.csv"),
+            "start_time,finsh_time,stage,day_of_week\n06:00,08:30,1,2\n".to_string(),
+        ),
+    ]);
+    let cache = OutageCache::new(FilesFetcher(files), DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let schedule_at = |at: &str| {
+        client
+            .get(format!("/schedules/{area_name}{at}"))
+            .dispatch()
+            .into_json::<RecurringSchedule>()
+            .unwrap()
+    };
+
+    let old = schedule_at("?at=2023-06-01");
+    assert_eq!(old.outages.len(), 1);
+    assert_eq!(old.source, ["https://example.com/2023.xlsx"]);
+    assert_eq!(
+        old.valid_until,
+        Some(datetime("2023-12-31T23:59:59+02:00").naive_local())
+    );
+
+    let new = schedule_at("?at=2024-06-01");
+    assert_eq!(new.outages.len(), 2);
+    assert_eq!(new.valid_until, None);
+    // Today is after the new schedule started
+    assert_eq!(schedule_at("").outages.len(), 2);
+    // No version covers 2020, so the latest is the best there is
+    assert_eq!(schedule_at("?at=2020-01-01").outages.len(), 2);
+
+    let response = client
+        .get(format!("/schedules/{area_name}?at=yesterday"))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn endpoints_unavailable_until_cache_warmed_up() {
+    let body = machine_friendly(&[row_from_now("western-cape-stellenbosch", 60, 120, 2)]);
+    let (cache, fetches) = fake_cache(&body, Duration::from_millis(300));
+    let client = Client::tracked(build_rocket_with(cache).attach(CacheWarmer))
+        .expect("valid rocket instance");
+
+    // The warm-up is still fetching, so requests don't wait for it
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    assert_eq!(
+        response.headers().get_one("Retry-After"),
+        Some(RETRY_AFTER_SECONDS.to_string().as_str())
+    );
+    assert_eq!(
+        response.into_json::<ApiErrorBody>().unwrap().error,
+        "unavailable"
+    );
+
+    std::thread::sleep(Duration::from_millis(600));
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn only_missing_outages_are_unavailable_while_warming_up() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::from_millis(300));
+    let client = Client::tracked(build_rocket_with(cache.clone()).attach(CacheWarmer))
+        .expect("valid rocket instance");
+    assert!(cache.is_warming_up());
+    let request = client.get("/schedules/western-cape-stellenbosch");
+    let respond = |err: UpstreamError| ApiError::from(err).respond_to(request.inner()).unwrap();
+
+    let response = respond(UpstreamError::WarmingUp("Still warming up".to_string()));
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    assert!(response.headers().get_one("Retry-After").is_some());
+
+    // Anything else GitHub fails to give us has nothing to do with the warm-up
+    let response = respond(UpstreamError::Failed(
+        "Failed to get the schedule".to_string(),
+    ));
+    assert_eq!(response.status(), Status::BadGateway);
+    assert_eq!(response.headers().get_one("Retry-After"), None);
+}
+
+#[test]
+fn daily_summary_splits_outages_at_midnight() {
+    let body = machine_friendly(&[
+        "western-cape-stellenbosch,2023-06-01T23:00:00+02:00,2023-06-02T01:30:00+02:00,4,https://twitter.com/Eskom_SA\n".to_string(),
+        "western-cape-stellenbosch,2023-06-02T10:00:00+02:00,2023-06-02T12:00:00+02:00,2,https://twitter.com/Eskom_SA\n".to_string(),
+    ]);
+    let client = client_with(&body);
+
+    let response = client
+        .get("/outages/western-cape-stellenbosch/daily?from=2023-06-01&to=2023-06-03")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let days = response.into_json::<Vec<DailySummary>>().unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(2023, 6, day).unwrap();
+    assert_eq!(
+        days,
+        [
+            DailySummary {
+                date: date(1),
+                total_minutes_off: 60,
+                max_stage: 4,
+                num_outages: 1,
+            },
+            DailySummary {
+                date: date(2),
+                total_minutes_off: 90 + 120,
+                max_stage: 4,
+                num_outages: 2,
+            },
+            DailySummary {
+                date: date(3),
+                total_minutes_off: 0,
+                max_stage: 0,
+                num_outages: 0,
+            },
+        ]
+    );
+
+    // A week is summarised by default
+    let response = client
+        .get("/outages/western-cape-stellenbosch/daily")
+        .dispatch();
+    assert_eq!(response.into_json::<Vec<DailySummary>>().unwrap().len(), 7);
+
+    let response = client
+        .get("/outages/western-cape-stellenbosch/daily?from=2023-06-03&to=2023-06-01")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client
+        .get("/outages/western-cape-stellenbosch/daily?from=2023-06-01&to=2023-08-01")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn cors_allows_any_origin_without_credentials_by_default() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get("/list_areas")
+        .header(Header::new("Origin", "https://example.com"))
+        .dispatch();
+    assert_eq!(
+        response.headers().get_one("Access-Control-Allow-Origin"),
+        Some("*")
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get_one("Access-Control-Allow-Credentials"),
+        None
+    );
+}
+
+#[test]
+fn cors_allowlist_echoes_allowed_origins() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let rocket = build_rocket_with(cache).manage(CorsPolicy::from_list(
+        " https://eskomcalendar.co.za, ,https://example.com",
+    ));
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+    let from = |origin: &str| {
+        client
+            .get("/list_areas")
+            .header(Header::new("Origin", origin.to_string()))
+            .dispatch()
+    };
+
+    let response = from("https://example.com");
+    assert_eq!(
+        response.headers().get_one("Access-Control-Allow-Origin"),
+        Some("https://example.com")
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get_one("Access-Control-Allow-Credentials"),
+        Some("true")
+    );
+    assert_eq!(response.headers().get_one("Vary"), Some("Origin"));
+
+    let response = from("https://evil.example.net");
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.headers().get_one("Access-Control-Allow-Origin"),
+        None
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get_one("Access-Control-Allow-Credentials"),
+        None
+    );
+    assert_eq!(response.headers().get_one("Vary"), Some("Origin"));
+}
+
+#[test]
+fn outages_can_be_paged_with_cursors() {
+    let client = client_with(UNSORTED_FIXTURE);
+    for sort in ["start", "-stage"] {
+        let (status, all) = filtered_outages(&client, &format!("sort={sort}"));
+        assert_eq!(status, Status::Ok);
+
+        let mut paged = vec![];
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let query = match &cursor {
+                Some(cursor) => format!("sort={sort}&limit=2&cursor={cursor}"),
+                None => format!("sort={sort}&limit=2"),
+            };
+            let response = client
+                .get(format!(
+                    "/outages/western-cape-stellenbosch?include_past=true&{query}"
+                ))
+                .dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let page = response.into_json::<OutagesPage>().unwrap();
+            assert!(page.outages.len() <= 2);
+            paged.extend(page.outages);
+            pages += 1;
+            match page.next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => break,
+            }
+        }
+        // Every outage turns up exactly once, in the same order as without paging
+        assert_eq!(pages, 3);
+        assert_eq!(paged, all);
+    }
+
+    // A cursor only works with the order it was made in
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true&limit=2")
+        .dispatch();
+    let cursor = response
+        .into_json::<OutagesPage>()
+        .unwrap()
+        .next_cursor
+        .unwrap();
+    let response = client
+        .get(format!(
+            "/outages/western-cape-stellenbosch?include_past=true&sort=-stage&cursor={cursor}"
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(
+        response.into_json::<ApiErrorBody>().unwrap().message,
+        format!(
+            "`cursor={cursor}` is for outages sorted by `start`, so `sort` must be `start` too"
+        )
+    );
+    let (status, _outages) = filtered_outages(&client, "cursor=not-a-cursor");
+    assert_eq!(status, Status::BadRequest);
+    let (status, _outages) = filtered_outages(&client, "limit=0");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn ratelimit_reports_remaining_requests() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let rocket = build_rocket_with(cache).attach(RateLimiter::per_minute(5));
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+    let user: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+    let ratelimit = || {
+        let response = client.get("/ratelimit").remote(user).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().contains("X-RateLimit-Reset"));
+        response.into_json::<RateLimitStatus>().unwrap()
+    };
+
+    // Asking about the rate limit doesn't use up a request
+    let status = ratelimit();
+    assert_eq!(status.limit, 5);
+    assert_eq!(status.remaining, 5);
+    assert_eq!(ratelimit().remaining, 5);
+
+    let response = client
+        .get(uri!(crate::latest::list_all_areas(_, _)))
+        .remote(user)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("X-RateLimit-Limit"), Some("5"));
+    assert_eq!(
+        response.headers().get_one("X-RateLimit-Remaining"),
+        Some("4")
+    );
+    let status = ratelimit();
+    assert_eq!(status.remaining, 4);
+    assert!(status.reset_at > sast_now());
+
+    // Without a rate limiter, there's no rate limit to report
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/ratelimit").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn schedules_ics_repeats_with_rrules() {
+    let sources = DataSources::default();
+    let schedules = [
+        ("western-cape-stellenbosch", SCHEDULE_FIXTURE, "FREQ=WEEKLY"),
+        (
+            "gauteng-tshwane-group-2",
+            "start_time,finsh_time,stage,date_of_month\n08:00,10:30,1,4\n",
+            "FREQ=MONTHLY",
+        ),
+        (
+            "eastern-cape-nelson-mandela-bay-block-1",
+            PERIODIC_SCHEDULE_FIXTURE,
+            "FREQ=DAILY",
+        ),
+    ];
+    let mut files = HashMap::from([(
+        sources.machine_friendly_url.clone(),
+        machine_friendly(
+            &schedules.map(|(area_name, _schedule, _freq)| row_from_now(area_name, 60, 120, 2)),
+        ),
+    )]);
+    for (area_name, schedule, _freq) in schedules {
+        files.insert(sources.schedule_url(area_name), schedule.to_string());
+    }
+    files.insert(
+        sources.schedule_metadata_url("western-cape-stellenbosch"),
+        SCHEDULE_METADATA_FIXTURE.to_string(),
+    );
+    let cache = OutageCache::new(FilesFetcher(files), DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let calendar = |uri: String| {
+        let response = client.get(uri).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::Calendar));
+        response.into_string().unwrap()
+    };
+    let lines_starting = |ics: &str, prefix: &str| {
+        ics.split("\r\n")
+            .filter(|line| line.starts_with(prefix))
+            .map(|line| line[prefix.len()..].to_string())
+            .collect::<Vec<_>>()
+    };
+
+    for (area_name, schedule, freq) in schedules {
+        let ics = calendar(format!("/schedules/{area_name}/calendar.ics"));
+        assert!(ics.contains("BEGIN:VTIMEZONE"));
+        let rrules = lines_starting(&ics, "RRULE:");
+        assert_eq!(rrules.len(), schedule.lines().count() - 1);
+        assert!(rrules.iter().all(|rrule| rrule.starts_with(freq)));
+    }
+
+    // Weekly outages repeat on their day of the week, and ones over midnight end the next day
+    let ics = calendar("/schedules/western-cape-stellenbosch/calendar.ics".to_string());
+    let rrules = lines_starting(&ics, "RRULE:");
+    assert_eq!(rrules[0], "FREQ=WEEKLY;BYDAY=MO;UNTIL=20240514T220000Z");
+    assert_eq!(
+        lines_starting(&ics, "DTSTART;TZID=Africa/Johannesburg:")[0],
+        "20230515T220000"
+    );
+    assert_eq!(
+        lines_starting(&ics, "DTEND;TZID=Africa/Johannesburg:")[0],
+        "20230516T003000"
+    );
+
+    // Periodic outages repeat every period, and only the outages at a stage are given for it
+    let ics = calendar(
+        "/schedules/eastern-cape-nelson-mandela-bay-block-1/calendar.ics?stage=1".to_string(),
+    );
+    let rrules = lines_starting(&ics, "RRULE:");
+    assert_eq!(rrules, ["FREQ=DAILY;INTERVAL=20", "FREQ=DAILY;INTERVAL=20"]);
+    assert!(ics.contains("SUMMARY:Stage 1 loadshedding"));
+}
+
+#[test]
+fn outages_format_picks_the_content_type() {
+    let client = client_with(FILTER_FIXTURE);
+    for (format, content_type) in [
+        ("json", ContentType::JSON),
+        ("csv", ContentType::CSV),
+        ("ics", ContentType::Calendar),
+        ("CSV", ContentType::CSV),
+    ] {
+        let response = client
+            .get(format!(
+                "/outages/western-cape-stellenbosch?include_past=true&format={format}"
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(content_type));
+    }
+
+    // The format wins over the `Accept` header, which is still used without a format
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true&format=json")
+        .header(Accept::CSV)
+        .dispatch();
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true")
+        .header(Accept::CSV)
+        .dispatch();
+    assert_eq!(response.content_type(), Some(ContentType::CSV));
+
+    let response = client
+        .get("/outages/western-cape-stellenbosch?include_past=true&format=ics")
+        .dispatch();
+    let body = response.into_string().unwrap();
+    assert!(body.starts_with("BEGIN:VCALENDAR"));
+    assert!(body.contains("X-WR-CALNAME:Loadshedding for western-cape-stellenbosch"));
+
+    let (status, _outages) = filtered_outages(&client, "format=xml");
+    assert_eq!(status, Status::BadRequest);
+}
+
+#[test]
+fn provinces_and_municipalities_are_listed() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client.get("/provinces").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let provinces = response.into_json::<Vec<ProvinceInfo>>().unwrap();
+    assert_eq!(provinces.len(), 9);
+    assert_eq!(
+        provinces
+            .iter()
+            .map(|province| province.province)
+            .collect::<Vec<_>>(),
+        Province::ALL
+    );
+    let kzn = &provinces[3];
+    assert_eq!(kzn.slug, "kwazulu-natal");
+    assert_eq!(kzn.name, "KwaZulu-Natal");
+
+    let response = client.get("/municipalities").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let municipalities = response.into_json::<Vec<MunicipalityInfo>>().unwrap();
+    let named = |name: &str| {
+        municipalities
+            .iter()
+            .find(|municipality| municipality.name == name)
+            .unwrap()
+    };
+    let stellenbosch = named("Stellenbosch");
+    assert_eq!(stellenbosch.slug, "stellenbosch");
+    assert_eq!(stellenbosch.kind, MunicipalityKind::Local);
+    assert_eq!(stellenbosch.district, Some(DistrictMunic::CapeWinelands));
+    let cape_town = named("CityOfCapeTown");
+    assert_eq!(cape_town.slug, "city-of-cape-town");
+    assert_eq!(cape_town.kind, MunicipalityKind::Metro);
+    assert_eq!(named("CapeWinelands").kind, MunicipalityKind::District);
+    assert_eq!(
+        municipalities.len(),
+        MetroMunic::ALL.len() + DistrictMunic::ALL.len() + LocalMunic::ALL.len()
+    );
+}
+
+#[test]
+fn fuzzy_search_highlights_matched_characters() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let search = |query: &str| {
+        let response = client.get(format!("/fuzzy_search/{query}")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response.into_json::<Vec<SearchResult<Area>>>().unwrap()
+    };
+
+    let results = search("cape%20stell?highlight=true");
+    assert!(!results.is_empty());
+    for result in &results {
+        let highlight = result.highlight.as_ref().unwrap();
+        assert_eq!(highlight.name, result.result.name);
+        // The highlighted characters spell out the query, with the space matching a dash
+        let name = highlight.name.chars().collect::<Vec<_>>();
+        let matched = highlight
+            .ranges
+            .iter()
+            .flat_map(|[start, end]| &name[*start..*end])
+            .collect::<String>();
+        assert_eq!(matched.replace('-', " "), "cape stell");
+    }
+    let stellenbosch = &results[0];
+    assert_eq!(stellenbosch.result.name, "western-cape-stellenbosch");
+    assert_eq!(stellenbosch.highlight.as_ref().unwrap().ranges, [[8, 18]]);
+
+    // Highlights are only sent if asked for
+    assert!(search("cape%20stell")
+        .iter()
+        .all(|result| result.highlight.is_none()));
+}
+
+#[test]
+fn area_names_localised_with_accept_language() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let area = |languages: Option<&str>| {
+        let mut request = client.get("/area/city-of-cape-town-area-15");
+        if let Some(languages) = languages {
+            request = request.header(Header::new("Accept-Language", languages.to_string()));
+        }
+        let response = request.dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response.into_json::<Area>().unwrap()
+    };
+
+    let afrikaans = area(Some("af-ZA, en;q=0.5"));
+    assert_eq!(afrikaans.name, "Kaapstad area 15");
+    assert_eq!(
+        afrikaans.canonical_name.as_deref(),
+        Some("city-of-cape-town-area-15")
+    );
+    assert_eq!(area(Some("xh")).name, "iKapa area 15");
+    // English first, no header, or no name in the language all give the area's own name
+    for languages in [Some("en, af"), None, Some("zu")] {
+        let area = area(languages);
+        assert_eq!(area.name, "city-of-cape-town-area-15");
+        assert_eq!(area.canonical_name, None);
+    }
+
+    let response = client
+        .get("/outages/city-of-cape-town-area-15?include_past=true")
+        .header(Header::new("Accept-Language", "af"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outages = response.into_json::<serde_json::Value>().unwrap();
+    let outage = &outages.as_array().unwrap()[0];
+    assert_eq!(outage["area_name"], "Kaapstad area 15");
+    assert_eq!(outage["canonical_area_name"], "city-of-cape-town-area-15");
+
+    // Long-polls send the same outages as `/outages/<area_name>`, so they're localised too
+    let upcoming = client_with(&machine_friendly(&[row_from_now(
+        "city-of-cape-town-area-15",
+        60,
+        120,
+        2,
+    )]));
+    let response = upcoming
+        .get("/outages/city-of-cape-town-area-15/longpoll")
+        .header(Header::new("Accept-Language", "af"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let outages = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(outages[0]["area_name"], "Kaapstad area 15");
+
+    // Lists of areas keep the actual names, and give the localised ones alongside them
+    for path in ["/list_areas", "/list_areas/cape-town"] {
+        let response = client
+            .get(path)
+            .header(Header::new("Accept-Language", "af"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok, "{path}");
+        let page = response.into_json::<AreasPage>().unwrap();
+        assert!(page
+            .areas
+            .contains(&"city-of-cape-town-area-15".to_string()));
+        assert_eq!(
+            page.localised_names,
+            BTreeMap::from([(
+                "city-of-cape-town-area-15".to_string(),
+                "Kaapstad area 15".to_string()
+            )]),
+            "{path}"
+        );
+    }
+    let response = client.get("/list_areas").dispatch();
+    let page = response.into_json::<serde_json::Value>().unwrap();
+    assert!(page.get("localised_names").is_none());
+
+    assert_eq!(
+        AcceptLanguage::parse("en-GB;q=0.8, af-ZA, xh;q=0, AF;q=0.5, *;q=0.1").0,
+        ["af", "en"]
+    );
+}
+
+/// Accept connections on a random local port, but never answer them, like a stalled connection to
+/// GitHub. Returns the server's URL.
+async fn stalled_server() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut connections = vec![];
+        while let Ok((stream, _)) = listener.accept().await {
+            // Hold onto the connection so that it stays open
+            connections.push(stream);
+        }
+    });
+    url
+}
+
+#[rocket::async_test]
+async fn stalled_upstream_times_out_with_504() {
+    use rocket::local::asynchronous::Client;
+
+    let url = stalled_server().await;
+    let timeout = Duration::from_millis(300);
+    let fetcher = GitHubFetcher::new(HttpClients::new().github, timeout);
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL)
+        .with_retry(RetryPolicy {
+            attempts: 1,
+            delay: Duration::ZERO,
+        })
+        .with_sources(DataSources {
+            machine_friendly_url: format!("{url}/machine_friendly.csv"),
+            schedules_url: format!("{url}/generated"),
+        });
+    let client = Client::tracked(build_rocket_with(cache)).await.unwrap();
+
+    let started = std::time::Instant::now();
+    let response = client
+        .get("/outages/western-cape-stellenbosch")
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::GatewayTimeout);
+    assert!(started.elapsed() < timeout * 5);
+    let body = response.into_json::<ApiErrorBody>().await.unwrap();
+    assert_eq!(body.error, "upstream_timeout");
+}
+
+#[test]
+fn http_clients_built_once_per_rocket() {
+    // The cache's fetcher and the webhooks use the clients kept in the rocket's state, rather than
+    // building clients of their own
+    let before = http_client::clients_built();
+    let rocket = build_rocket();
+    assert_eq!(http_client::clients_built() - before, 1);
+    assert!(rocket.state::<HttpClients>().is_some());
+    let _another = build_rocket();
+    assert_eq!(http_client::clients_built() - before, 2);
+}
+
+#[test]
+fn raw_schedules_round_trip() {
+    let monthly = "start_time,finsh_time,stage,date_of_month\n08:00,10:30,1,4\n22:00,00:30,3,31\n";
+    let schedules = [
+        ("western-cape-stellenbosch", SCHEDULE_FIXTURE),
+        ("gauteng-tshwane-group-2", monthly),
+        (
+            "eastern-cape-nelson-mandela-bay-block-1",
+            PERIODIC_SCHEDULE_FIXTURE,
+        ),
+    ];
+    let sources = DataSources::default();
+    let client_serving = |schedules: &[(&str, String)]| {
+        // Schedules are only fetched for areas with outages
+        let rows = schedules
+            .iter()
+            .map(|(area_name, _csv)| row_from_now(area_name, 60, 120, 2))
+            .collect::<Vec<_>>();
+        let mut files = HashMap::from([(
+            sources.machine_friendly_url.clone(),
+            machine_friendly(&rows),
+        )]);
+        for (area_name, csv) in schedules {
+            files.insert(sources.schedule_url(area_name), csv.clone());
+        }
+        let cache = OutageCache::new(FilesFetcher(files), DEFAULT_TTL);
+        Client::tracked(build_rocket_with(cache)).expect("valid rocket instance")
+    };
+    let fixtures = schedules.map(|(area_name, csv)| (area_name, csv.to_string()));
+    let client = client_serving(&fixtures);
+
+    let mut raw = vec![];
+    for (area_name, fixture) in &fixtures {
+        let response = client
+            .get(format!("/schedules/{area_name}/raw.csv"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::CSV));
+        let csv = response.into_string().unwrap();
+        assert_eq!(&csv, fixture);
+        raw.push((*area_name, csv));
+    }
+
+    // Parsing the raw CSV again gives the same schedule as the first time
+    let reparsed = client_serving(&raw);
+    for (area_name, _csv) in &raw {
+        let schedule = |client: &Client| {
+            let response = client.get(format!("/schedules/{area_name}")).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            response.into_json::<RecurringSchedule>().unwrap().outages
+        };
+        assert_eq!(schedule(&client), schedule(&reparsed));
+    }
+
+    let response = client
+        .get("/schedules/western-cape-nowhere/raw.csv")
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn schedules_compare_stages() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let response = client
+        .get(
+            "/schedules/western-cape-stellenbosch/compare?stages=2,0,1,2\
+            &from=2023-06-05T00:00:00%2B02:00&to=2023-06-12T00:00:00%2B02:00",
+        )
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let comparison = response.into_json::<ScheduleComparison>().unwrap();
+    assert_eq!(comparison.area_name, "western-cape-stellenbosch");
+    let by_stage = comparison
+        .stages
+        .iter()
+        .map(|scenario| (scenario.stage, scenario.total_hours, scenario.outages.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(by_stage, [(0, 0.0, 0), (1, 2.5, 1), (2, 5.0, 2)]);
+    // Higher stages never mean less loadshedding
+    assert!(comparison
+        .stages
+        .windows(2)
+        .all(|pair| pair[0].total_hours <= pair[1].total_hours));
+
+    // Outages that stick out of the window only count for the part inside it
+    let response = client
+        .get(
+            "/schedules/western-cape-stellenbosch/compare?stages=1\
+            &from=2023-06-05T23:00:00%2B02:00&to=2023-06-06T23:00:00%2B02:00",
+        )
+        .dispatch();
+    let comparison = response.into_json::<ScheduleComparison>().unwrap();
+    assert_eq!(comparison.stages[0].total_hours, 1.5);
+
+    for query in [
+        "stages=",
+        "stages=2,9",
+        "stages=two",
+        "stages=4&from=2023-06-12T00:00:00&to=2023-06-05T00:00:00",
+    ] {
+        let response = client
+            .get(format!(
+                "/schedules/western-cape-stellenbosch/compare?{query}"
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{query}");
+    }
+}
+
+#[test]
+fn long_or_strange_paths_rejected() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let too_long = "a".repeat(MAX_SEGMENT_LEN + 1);
+    for path in [
+        format!("/area/{too_long}"),
+        format!("/fuzzy_search/{too_long}"),
+        format!("/v0.0.1/list_areas/{too_long}"),
+        "/outages/western-cape-stellenbosch%00".to_string(),
+        "/search/stellen%0Abosch".to_string(),
+        "/schedules/western-cape-%1Bstellenbosch/raw.csv".to_string(),
+    ] {
+        let response = client.get(&path).dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{path}");
+        let body = response.into_json::<ApiErrorBody>().unwrap();
+        assert_eq!(body.error, "bad_request");
+        let response = client.head(&path).dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{path}");
+    }
+
+    // Paths right at the limit, and everything else, are left alone
+    let at_limit = "a".repeat(MAX_SEGMENT_LEN);
+    let response = client.get(format!("/area/{at_limit}")).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/area/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/fuzzy_search/stellen%20bosch").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn outage_gaps_fill_the_spaces_between_outages() {
+    let client = client_with(
+        "\
+area_name,start,finsh,stage,source
+western-cape-stellenbosch,2023-06-01T10:00:00+02:00,2023-06-01T12:00:00+02:00,2,https://twitter.com/Eskom_SA
+western-cape-stellenbosch,2023-06-01T12:00:00+02:00,2023-06-01T14:00:00+02:00,4,https://twitter.com/Eskom_SA
+western-cape-stellenbosch,2023-06-01T18:00:00+02:00,2023-06-01T20:30:00+02:00,6,https://twitter.com/Eskom_SA
+western-cape-stellenbosch,2023-06-01T20:00:00+02:00,2023-06-01T22:00:00+02:00,4,https://twitter.com/Eskom_SA
+",
+    );
+    let gaps = |from: &str, to: &str| {
+        let response = client
+            .get(format!(
+                "/outages/western-cape-stellenbosch/gaps?from={}&to={}",
+                from.replace('+', "%2B"),
+                to.replace('+', "%2B")
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response
+            .into_json::<Vec<PowerGap>>()
+            .unwrap()
+            .into_iter()
+            .map(|gap| (gap.start.to_rfc3339(), gap.finsh.to_rfc3339(), gap.minutes))
+            .collect::<Vec<_>>()
+    };
+
+    // Back-to-back and overlapping outages don't have gaps between them
+    assert_eq!(
+        gaps("2023-06-01T08:00:00+02:00", "2023-06-02T00:00:00+02:00"),
+        [
+            (
+                "2023-06-01T08:00:00+02:00".to_string(),
+                "2023-06-01T10:00:00+02:00".to_string(),
+                120
+            ),
+            (
+                "2023-06-01T14:00:00+02:00".to_string(),
+                "2023-06-01T18:00:00+02:00".to_string(),
+                240
+            ),
+            (
+                "2023-06-01T22:00:00+02:00".to_string(),
+                "2023-06-02T00:00:00+02:00".to_string(),
+                120
+            ),
+        ]
+    );
+    // Gaps are cut off at the edges of the window
+    assert_eq!(
+        gaps("2023-06-01T13:00:00+02:00", "2023-06-01T19:00:00+02:00"),
+        [(
+            "2023-06-01T14:00:00+02:00".to_string(),
+            "2023-06-01T18:00:00+02:00".to_string(),
+            240
+        )]
+    );
+    // There's no gap at all while the power is off
+    assert!(gaps("2023-06-01T10:30:00+02:00", "2023-06-01T13:30:00+02:00").is_empty());
+
+    let response = client
+        .get("/outages/western-cape-stellenbosch/gaps?from=2023-06-02T00:00:00&to=2023-06-01T00:00:00")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client.get("/outages/western-cape-atlantis/gaps").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn if_modified_since_gives_not_modified() {
+    let client = client_with(MACHINE_FRIENDLY);
+    let get = |if_modified_since: Option<&str>| {
+        let mut request = client.get("/outages/western-cape-stellenbosch?include_past=true");
+        if let Some(since) = if_modified_since {
+            request = request.header(Header::new("If-Modified-Since", since.to_string()));
+        }
+        request.dispatch()
+    };
+
+    let first = get(None);
+    assert_eq!(first.status(), Status::Ok);
+    let last_modified = first
+        .headers()
+        .get_one("Last-Modified")
+        .unwrap()
+        .to_string();
+
+    let unchanged = get(Some(&last_modified));
+    assert_eq!(unchanged.status(), Status::NotModified);
+    assert_eq!(
+        unchanged.headers().get_one("Last-Modified"),
+        Some(last_modified.as_str())
+    );
+    assert!(unchanged.headers().get_one("Cache-Control").is_some());
+    assert_eq!(unchanged.into_string(), None);
+
+    // Anything older than the data, or that can't be understood, gets the data again
+    let earlier = DateTime::parse_from_rfc2822(&last_modified).unwrap() - ChronoDuration::hours(1);
+    let earlier = earlier.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    for since in [earlier.as_str(), "yesterday"] {
+        let response = get(Some(since));
+        assert_eq!(response.status(), Status::Ok, "{since}");
+        assert!(!stages(&response.into_json::<Vec<PowerOutage>>().unwrap()).is_empty());
+    }
+}
+
+#[test]
+fn last_modified_only_moves_when_the_data_changes() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let fetcher = FakeFetcher {
+        body: MACHINE_FRIENDLY.to_string(),
+        etag: Some("\"v1\"".to_string()),
+        fetches: Arc::clone(&fetches),
+        delay: Duration::ZERO,
+    };
+    // With a TTL of zero, every request asks GitHub whether machine_friendly.csv has changed
+    let cache = OutageCache::new(fetcher, Duration::ZERO);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let uri = "/outages/western-cape-stellenbosch?include_past=true";
+
+    let first = client.get(uri).dispatch();
+    assert_eq!(first.status(), Status::Ok);
+    let last_modified = first
+        .headers()
+        .get_one("Last-Modified")
+        .unwrap()
+        .to_string();
+
+    // HTTP dates only go down to the second, so wait long enough that a new one would differ
+    std::thread::sleep(Duration::from_millis(1100));
+    let response = client
+        .get(uri)
+        .header(Header::new("If-Modified-Since", last_modified.clone()))
+        .dispatch();
+    assert!(fetches.load(Ordering::SeqCst) >= 2);
+    assert_eq!(response.status(), Status::NotModified);
+    assert_eq!(
+        response.headers().get_one("Last-Modified"),
+        Some(last_modified.as_str())
+    );
+}
+
+#[test]
+fn if_none_match_is_checked_before_if_modified_since() {
+    let fetcher = FakeFetcher {
+        body: MACHINE_FRIENDLY.to_string(),
+        etag: Some("\"v1\"".to_string()),
+        fetches: Arc::new(AtomicUsize::new(0)),
+        delay: Duration::ZERO,
+    };
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+    let get = |if_none_match: &str, if_modified_since: &str| {
+        client
+            .get("/outages/western-cape-stellenbosch?include_past=true")
+            .header(Header::new("If-None-Match", if_none_match.to_string()))
+            .header(Header::new(
+                "If-Modified-Since",
+                if_modified_since.to_string(),
+            ))
+            .dispatch()
+    };
+
+    let first = client
+        .get("/outages/western-cape-stellenbosch?include_past=true")
+        .dispatch();
+    let etag = first.headers().get_one("ETag").unwrap().to_string();
+    let last_modified = first
+        .headers()
+        .get_one("Last-Modified")
+        .unwrap()
+        .to_string();
+    let long_ago = "Mon, 01 Jan 2001 00:00:00 GMT";
+
+    // A matching ETag is enough, however old the client thinks its copy is
+    for if_none_match in [etag.as_str(), "\"v1\"", "\"v0\", W/\"v1\"", "*"] {
+        let response = get(if_none_match, long_ago);
+        assert_eq!(response.status(), Status::NotModified, "{if_none_match}");
+        assert_eq!(response.headers().get_one("ETag"), Some(etag.as_str()));
+    }
+
+    // And a different ETag gets the data again, even though `If-Modified-Since` alone would say
+    // the client's copy is up to date
+    let response = get("\"v0\"", &last_modified);
+    assert_eq!(response.status(), Status::Ok);
+    assert!(!stages(&response.into_json::<Vec<PowerOutage>>().unwrap()).is_empty());
+}
+
+#[test]
+fn responses_capped_at_max_outages() {
+    let mut rows = (0..5)
+        .map(|hour| {
+            row_from_now(
+                "western-cape-stellenbosch",
+                hour * 60 + 10,
+                hour * 60 + 40,
+                2,
+            )
+        })
+        .collect::<Vec<_>>();
+    rows.push(row_from_now("city-of-cape-town-area-15", 10, 40, 2));
+    rows.push(row_from_now("city-of-cape-town-area-15", 70, 100, 2));
+    let (cache, _fetches) = fake_cache(&machine_friendly(&rows), Duration::ZERO);
+    let rocket = build_rocket_with(cache).manage(OutageCap { max_outages: 3 });
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+
+    // Too many outages for a list, so the first few are sent as a page which says where the rest are
+    let response = client.get("/outages/western-cape-stellenbosch").dispatch();
+    assert_eq!(response.headers().get_one("X-Truncated"), Some("true"));
+    let page = response.into_json::<OutagesPage>().unwrap();
+    assert!(page.truncated);
+    assert!(page.message.is_some());
+    assert_eq!(page.outages.len(), 3);
+    let rest = client
+        .get(format!(
+            "/outages/western-cape-stellenbosch?cursor={}",
+            page.next_cursor.unwrap()
+        ))
+        .dispatch()
+        .into_json::<OutagesPage>()
+        .unwrap();
+    assert_eq!(rest.outages.len(), 2);
+    assert!(!rest.truncated);
+
+    let response = client.get("/outages/city-of-cape-town-area-15").dispatch();
+    assert_eq!(response.headers().get_one("X-Truncated"), None);
+    assert_eq!(response.into_json::<Vec<PowerOutage>>().unwrap().len(), 2);
+
+    // The page stops before the area that would go over the cap
+    let response = client.get("/outages/all").dispatch();
+    assert_eq!(response.headers().get_one("X-Truncated"), Some("true"));
+    let all = response.into_json::<AllOutages>().unwrap();
+    assert!(all.truncated);
+    assert!(all.message.is_some());
+    assert_eq!(all.total, 2);
+    assert_eq!(
+        all.outages.keys().collect::<Vec<_>>(),
+        vec!["city-of-cape-town-area-15"]
+    );
+    let next = client
+        .get("/outages/all?offset=1")
+        .dispatch()
+        .into_json::<AllOutages>()
+        .unwrap();
+    assert!(next.truncated);
+    assert_eq!(next.outages["western-cape-stellenbosch"].len(), 3);
+
+    let areas = client
+        .get("/outages?areas=western-cape-stellenbosch,city-of-cape-town-area-15")
+        .dispatch()
+        .into_json::<AreasOutages>()
+        .unwrap();
+    assert!(areas.truncated);
+    assert_eq!(areas.outages["city-of-cape-town-area-15"].len(), 2);
+    assert_eq!(areas.outages["western-cape-stellenbosch"].len(), 1);
+
+    for url in [
+        "/outages/western-cape-stellenbosch/calendar.ics",
+        "/outages/western-cape-stellenbosch?format=ics",
+        "/province/western-cape/outages.ics",
+    ] {
+        let response = client.get(url).dispatch();
+        assert_eq!(
+            response.headers().get_one("X-Truncated"),
+            Some("true"),
+            "{url}"
+        );
+        let calendar = response.into_string().unwrap();
+        assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 3, "{url}");
+    }
+}
+
+#[test]
+fn unknown_paths_get_json_not_docs() {
+    let client = client_with(MACHINE_FRIENDLY);
+
+    for url in [
+        "/outages/western-cape-stellenbosch/nonsense",
+        "/outages/western-cape-stellenbosch/calendar.ics/extra",
+        "/v0.0.1/no-such-endpoint",
+    ] {
+        let response = client.get(url).dispatch();
+        assert_eq!(response.status(), Status::NotFound, "{url}");
+        assert_eq!(response.content_type(), Some(ContentType::JSON), "{url}");
+        let body = response.into_json::<ApiErrorBody>().unwrap();
+        assert_eq!(body.error, "not_found");
+        assert!(body.message.contains(url), "{}", body.message);
+    }
+
+    // The docs have moved out of the way of the API, and `/` sends people to them
+    for url in ["/", "/docs"] {
+        let response = client.get(url).dispatch();
+        assert_eq!(response.status(), Status::SeeOther, "{url}");
+        assert_eq!(response.headers().get_one("Location"), Some("/docs/"));
+    }
+    let docs = client.get("/docs/").dispatch();
+    assert_eq!(docs.status(), Status::Ok);
+    assert_eq!(docs.content_type(), Some(ContentType::HTML));
+    let openapi = client.get("/api-docs/openapi.json").dispatch();
+    assert_eq!(openapi.status(), Status::Ok);
+}
+
+#[test]
+fn unknown_routes_get_json_404() {
+    let client = client_with(MACHINE_FRIENDLY);
+
+    let responses = [
+        client.get("/no-such-endpoint").dispatch(),
+        client.post("/outages/western-cape-stellenbosch").dispatch(),
+        client.delete("/list_areas").dispatch(),
+    ];
+    for response in responses {
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        let body = response.into_json::<ApiErrorBody>().unwrap();
+        assert_eq!(body.error, "not_found");
+        assert!(body.message.contains("/docs/"), "{}", body.message);
+    }
+}
+
+#[test]
+fn outages_filtered_by_source() {
+    let client = client_with(FILTER_FIXTURE);
+
+    let (status, outages) = filtered_outages(&client, "source_contains=eskom_sa");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stages(&outages), [2, 4, 6]);
+    let (_, outages) = filtered_outages(&client, "source_contains=CityofCT");
+    assert_eq!(stages(&outages), [8]);
+    assert!(outages[0].source.contains("CityofCT"));
+    let (_, outages) = filtered_outages(&client, "source_contains=status/1&min_stage=4");
+    assert_eq!(stages(&outages), [4]);
+    let (_, outages) = filtered_outages(&client, "source_contains=nobody");
+    assert!(outages.is_empty());
+
+    // The other endpoints with filters can use it too
+    let all = client
+        .get("/outages/all?source_contains=CityofCT")
+        .dispatch()
+        .into_json::<AllOutages>()
+        .unwrap();
+    assert_eq!(stages(&all.outages["western-cape-stellenbosch"]), [8]);
+}
+
+#[test]
+fn outages_for_areas_checked_before_asking_github() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let client = Client::tracked(build_rocket_with(cache.clone())).expect("valid rocket instance");
+
+    let areas = (0..101)
+        .map(|i| format!("western-cape-made-up-{i}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    let response = client.get(format!("/outages?areas={areas}")).dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client
+        .get("/outages?areas=western-cape-made-up-1,western-cape-made-up-2&min_stage=10")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(cache.counters().upstream_fetches, 0);
+}
+
+#[test]
+fn outages_for_areas_survives_failed_checks() {
+    let fetcher = FailsAfterFirstFetch {
+        fetches: AtomicUsize::new(0),
+    };
+    let cache = OutageCache::new(fetcher, DEFAULT_TTL);
+    let client = Client::tracked(build_rocket_with(cache)).expect("valid rocket instance");
+
+    // GitHub can't say whether `narnia` exists, which shouldn't fail the other area
+    let response = client
+        .get("/outages?areas=western-cape-stellenbosch,narnia")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let areas = response.into_json::<AreasOutages>().unwrap();
+    assert_eq!(areas.not_found, vec!["narnia"]);
+    assert!(!areas.outages["western-cape-stellenbosch"].is_empty());
+
+    // Nor should it fail checking whether the other area is being loadshed
+    let response = client
+        .post(uri!(crate::latest::current_for_areas))
+        .json(&serde_json::json!({"areas": ["western-cape-stellenbosch", "narnia"]}))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let current = response.into_json::<AreasCurrentStatus>().unwrap();
+    assert_eq!(current.not_found, vec!["narnia"]);
+    assert!(current.statuses.contains_key("western-cape-stellenbosch"));
+}
+
+#[test]
+fn rate_limited_requests_do_no_work() {
+    let (cache, _fetches) = fake_cache(MACHINE_FRIENDLY, Duration::ZERO);
+    let rocket = build_rocket_with(cache.clone()).attach(RateLimiter::per_minute(1));
+    let client = Client::tracked(rocket).expect("valid rocket instance");
+    let user: SocketAddr = "1.2.3.4:1234".parse().unwrap();
+    let get_outages = || {
+        client
+            .get("/outages/western-cape-stellenbosch?include_past=true")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .remote(user)
+            .dispatch()
+    };
+
+    let response = get_outages();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.headers().get_one("Cache-Control").is_some());
+    let counters = cache.counters();
+
+    // Over the limit, the endpoint doesn't run, and nothing about its response is left over
+    let response = get_outages();
+    assert_eq!(response.status(), Status::TooManyRequests);
+    assert_eq!(response.headers().get_one("Content-Encoding"), None);
+    assert_eq!(
+        response.headers().get_one("Cache-Control"),
+        Some("no-store")
+    );
+    assert_eq!(response.headers().get_one("ETag"), None);
+    assert_eq!(response.headers().get_one("Last-Modified"), None);
+    assert!(response.headers().get_one("Retry-After").is_some());
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+    let error = response.into_json::<ApiErrorBody>().unwrap();
+    assert_eq!(error.error, "too_many");
+    assert!(error.message.starts_with("Too many requests"));
+    assert_eq!(cache.counters(), counters);
+
+    let response = client
+        .post(uri!(crate::latest::subscribe))
+        .remote(user)
+        .json(&serde_json::json!({
+            "area": "western-cape-stellenbosch",
+            "callback_url": "https://example.com/webhook",
+            "email": "you@example.com",
+        }))
+        .dispatch();
+    assert_eq!(response.status(), Status::TooManyRequests);
+
+    // The internal route for rate limited requests can't be asked for directly
+    let response = client
+        .get("/ratelimit/exceeded")
+        .remote("5.6.7.8:1234".parse::<SocketAddr>().unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
@@ -0,0 +1,161 @@
+use rocket::serde::json::Value;
+use utoipa::openapi::OpenApi;
+
+/// TypeScript declarations, served as a `.d.ts` file.
+#[derive(Responder)]
+#[response(content_type = "application/typescript")]
+pub struct TypeScriptFile(pub String);
+
+/// TypeScript declarations for every schema in the API's OpenAPI spec, so that web developers can
+/// use the API's types without needing a code generator. These are generated once at startup.
+#[derive(Debug)]
+pub struct TypeScriptTypes(pub String);
+
+impl TypeScriptTypes {
+    /// Generate one declaration for each of the schemas in `openapi`'s components, in
+    /// alphabetical order.
+    pub fn from_openapi(openapi: &OpenApi) -> Self {
+        let openapi = rocket::serde::json::to_value(openapi).unwrap_or_default();
+        let mut declarations = vec![format!(
+            "// TypeScript types for the eskom-calendar API (version {}), generated from its \
+            OpenAPI spec.\n",
+            env!("CARGO_PKG_VERSION")
+        )];
+        if let Some(schemas) = openapi["components"]["schemas"].as_object() {
+            let mut schemas = schemas.iter().collect::<Vec<_>>();
+            schemas.sort_by_key(|(name, _schema)| name.as_str());
+            declarations.extend(
+                schemas
+                    .into_iter()
+                    .map(|(name, schema)| declaration(name, schema)),
+            );
+        }
+        TypeScriptTypes(declarations.join("\n"))
+    }
+}
+
+/// A TypeScript declaration for the schema called `name`. Objects become interfaces and
+/// everything else becomes a type alias.
+fn declaration(name: &str, schema: &Value) -> String {
+    let name = identifier(name);
+    let mut declaration = doc_comment(schema, "");
+    if schema["properties"].is_object() && !is_nullable(schema) {
+        declaration.push_str(&format!(
+            "export interface {name} {}\n",
+            object_type(schema, "")
+        ));
+    } else {
+        declaration.push_str(&format!("export type {name} = {};\n", ts_type(schema, "")));
+    }
+    declaration
+}
+
+/// The TypeScript type for a JSON schema. `indent` is how far nested object types are indented.
+fn ts_type(schema: &Value, indent: &str) -> String {
+    let ty = if let Some(reference) = schema["$ref"].as_str() {
+        identifier(reference.rsplit('/').next().unwrap_or(reference))
+    } else if let Some(variants) = schema["oneOf"].as_array().or(schema["anyOf"].as_array()) {
+        union(variants.iter().map(|variant| ts_type(variant, indent)))
+    } else if let Some(parts) = schema["allOf"].as_array() {
+        parts
+            .iter()
+            .map(|part| ts_type(part, indent))
+            .collect::<Vec<_>>()
+            .join(" & ")
+    } else if let Some(values) = schema["enum"].as_array() {
+        union(values.iter().map(Value::to_string))
+    } else {
+        match schema["type"].as_str() {
+            Some("string") => "string".to_string(),
+            Some("integer") | Some("number") => "number".to_string(),
+            Some("boolean") => "boolean".to_string(),
+            Some("array") => {
+                let items = ts_type(&schema["items"], indent);
+                if items.contains(' ') {
+                    format!("({items})[]")
+                } else {
+                    format!("{items}[]")
+                }
+            }
+            Some("object") if schema["properties"].is_object() => object_type(schema, indent),
+            Some("object") if schema["additionalProperties"].is_object() => format!(
+                "Record<string, {}>",
+                ts_type(&schema["additionalProperties"], indent)
+            ),
+            Some("object") => "Record<string, unknown>".to_string(),
+            _ => "unknown".to_string(),
+        }
+    };
+    if is_nullable(schema) {
+        format!("{ty} | null")
+    } else {
+        ty
+    }
+}
+
+/// An object type like `{ name: string; aliases: string[]; }`, with one property per line.
+/// Properties which aren't required are optional.
+fn object_type(schema: &Value, indent: &str) -> String {
+    let required = schema["required"]
+        .as_array()
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let inner_indent = format!("{indent}  ");
+    let properties = schema["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(property, property_schema)| {
+            let optional = if required.contains(&property.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            format!(
+                "{}{inner_indent}{property}{optional}: {};\n",
+                doc_comment(property_schema, &inner_indent),
+                ts_type(property_schema, &inner_indent)
+            )
+        })
+        .collect::<String>();
+    format!("{{\n{properties}{indent}}}")
+}
+
+/// A union of TypeScript types, like `"a" | "b"`.
+fn union(types: impl Iterator<Item = String>) -> String {
+    types.collect::<Vec<_>>().join(" | ")
+}
+
+/// A `/** ... */` comment with the schema's description, or nothing if it doesn't have one.
+fn doc_comment(schema: &Value, indent: &str) -> String {
+    match schema["description"].as_str() {
+        Some(description) if !description.trim().is_empty() => {
+            let lines = description
+                .trim()
+                .lines()
+                .map(|line| format!("{indent} * {line}").trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("{indent}/**\n{lines}\n{indent} */\n")
+        }
+        _ => String::new(),
+    }
+}
+
+/// Whether the schema can also be `null`.
+fn is_nullable(schema: &Value) -> bool {
+    schema["nullable"].as_bool().unwrap_or(false)
+}
+
+/// A schema name as a valid TypeScript identifier, since generic schemas can have names like
+/// `SearchResult<Area>`.
+fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
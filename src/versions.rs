@@ -1,39 +1,1156 @@
+use crate::aliases::AreaAliases;
+use crate::area_schedules::AreaSchedules;
+use crate::cache::{parse_csv_rows, OutageCache, UpstreamError};
+use crate::client_ip::ClientIp;
+use crate::email::{is_valid_email, ContactEmail};
+use crate::error::ApiError;
+use crate::geo::{self, AreaBoundaries};
+use crate::holidays::PublicHolidays;
+use crate::ics::{self, Ics};
+use crate::language::AcceptLanguage;
+use crate::metrics::{Metrics, PrometheusText};
+use crate::response_cap::{mark_truncated, Capped, OutageCap};
+use crate::stages::{StageInfo, STAGES};
 use crate::structs::{
-    Area, AreaId, HealthCheck, HealthStatus, PowerOutage, RawMonthlyShedding, RawPeriodicShedding,
-    RawWeeklyShedding, RecurringOutage, RecurringSchedule, ScheduleId, SearchResult,
+    municipality_from_area_name, parse_date, parse_datetime, parse_stage, province_from_area_name,
+    AllOutages, Area, AreaId, AreaStats, AreasCurrentStatus, AreasOutages, AreasPage, AreasRequest,
+    CacheStatus, CurrentStatus, DailySummary, Errors, ExpandedOutage, Health, HealthCheck,
+    HealthStatus, Municipality, MunicipalityInfo, NationalStage, NationalStats, NextOutage,
+    OutageCursor, OutageDiff, OutageFilter, OutageOrder, OutlookSegment, PowerGap, PowerOutage,
+    Province, ProvinceInfo, RateLimitStatus, RawMonthlyShedding, RawPeriodicShedding,
+    RawWeeklyShedding, RecurringOutage, RecurringSchedule, ScheduleComparison, ScheduleId,
+    ScheduleMetadata, ScheduleReport, SearchHighlight, SearchKind, SearchMatch, SearchMatchResult,
+    SearchResult, ServiceStatus, StageScenario, SubscribeRequest, Subscription, Version,
 };
+use crate::typescript::{TypeScriptFile, TypeScriptTypes};
+use crate::webhooks::{SubscriptionSecret, Webhooks};
 
-use chrono::FixedOffset;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Offset, TimeZone};
+use chrono_tz::Tz;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use rocket::http::{ContentType, Header, MediaType, Status};
+use rocket::response::{self, stream::ByteStream, Responder};
 use rocket::serde::json::Json;
-use std::collections::HashSet;
-use tracing::{self, Instrument};
-
-async fn get_machine_friendly() -> Result<Vec<PowerOutage>, String> {
-    let machine_friendly_span = tracing::info_span!("Getting machine friendly");
-    let _ = machine_friendly_span.enter();
-
-    let fetch_span = tracing::info_span!("Making GET request to GitHub");
-    let convert_span = tracing::info_span!("Converting GitHub reponse to text");
-    let url =
-        "https://github.com/beyarkay/eskom-calendar/releases/download/latest/machine_friendly.csv";
-    let text_data = reqwest::get(url)
-        .instrument(fetch_span)
+use rocket::{Request, Response, State};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// South African Standard Time, which is what all of eskom-calendar's times are in. It's always
+/// two hours ahead of UTC, since South Africa doesn't have daylight saving.
+pub fn sast() -> FixedOffset {
+    FixedOffset::east_opt(2 * 60 * 60).unwrap()
+}
+
+/// The current time in South African Standard Time.
+pub fn sast_now() -> DateTime<FixedOffset> {
+    chrono::offset::Local::now().with_timezone(&sast())
+}
+
+/// When the API was built, as recorded by build.rs.
+fn built_at() -> DateTime<FixedOffset> {
+    let secs = env!("BUILT_AT_UNIX").parse().unwrap_or_default();
+    sast().timestamp_opt(secs, 0).unwrap()
+}
+
+/// How many results `/fuzzy_search/<query>` returns, unless asked for a different number.
+const FUZZY_SEARCH_LIMIT: usize = 10;
+
+/// The fewest letters and numbers a `/fuzzy_search/<query>` query can have. Shorter queries match
+/// nearly every area, which isn't a useful search.
+const MIN_FUZZY_QUERY_LEN: usize = 2;
+
+/// How many areas `/areas/search/<query>` returns, unless asked for a different number.
+const AUTOCOMPLETE_LIMIT: usize = 10;
+
+/// How many hours ahead `/outlook/<area_name>` looks.
+const OUTLOOK_HOURS: i64 = 48;
+
+/// The most areas that can be asked about in one request to `POST /current` or `/outages?areas=`.
+const MAX_AREAS_PER_REQUEST: usize = 100;
+
+/// How many days `/schedules/<area_name>/expand` covers, unless asked for a different window.
+const EXPAND_DAYS: i64 = 7;
+
+/// The longest window that `/schedules/<area_name>/expand` will cover, so that one request can't
+/// ask for years of outages.
+const MAX_EXPAND_DAYS: i64 = 31;
+
+/// How many days `/outages/<area_name>/daily` covers, unless asked for a different window.
+const DAILY_DAYS: i64 = 7;
+
+/// The most days `/outages/<area_name>/daily` can cover.
+const MAX_DAILY_DAYS: i64 = 31;
+
+/// How long `/outages/<area_name>/longpoll` waits for the data to change, unless asked to wait
+/// for less time.
+const LONGPOLL_TIMEOUT_SECONDS: usize = 30;
+
+/// How many areas `/outages/all` returns on each page, unless asked for fewer. It's also the most
+/// that can be asked for, since every area's outages at once would be a very big response.
+const ALL_OUTAGES_PAGE_SIZE: usize = 500;
+
+/// How many outages `/outages/<area_name>` returns on each page when it's paged, unless asked for
+/// fewer. It's also the most that can be asked for.
+const OUTAGES_PAGE_SIZE: usize = 100;
+
+/// The most events in `/province/<province>/outages.ics`, so that calendar apps aren't sent a
+/// calendar too big for them to handle.
+const MAX_PROVINCE_EVENTS: usize = 1000;
+
+/// The longest regex that `/list_areas/<regex>` will compile.
+const MAX_REGEX_LEN: usize = 1000;
+
+/// The most memory (in bytes) that a compiled `/list_areas/<regex>` regex, and the lazy DFA used
+/// when matching it, can use. Anything bigger is too complex.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// How long compiling a `/list_areas/<regex>` regex can take. The size limits should stop any
+/// regex from getting near this, so it's only a safety net.
+const REGEX_COMPILE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How many area names are written at a time when streaming a list of areas.
+const AREAS_PER_CHUNK: usize = 256;
+
+/// How many similarly named areas are suggested when an area can't be found.
+const SUGGESTION_LIMIT: usize = 3;
+
+/// Get all the outages for one area. This is empty if the area exists but isn't getting any
+/// loadshedding, and an error if eskom-calendar doesn't know about the area at all.
+async fn area_outages(area_name: &str, cache: &OutageCache) -> Result<Vec<PowerOutage>, ApiError> {
+    tracing::info!("Getting outages for {area_name}");
+    let outages: Vec<PowerOutage> = cache
+        .outages()
+        .await
+        .map_err(ApiError::from)?
+        .iter()
+        .filter(|outage| outage.area_name == area_name)
+        .cloned()
+        .collect();
+
+    if outages.is_empty() {
+        tracing::info!("No outages found for {area_name}");
+        check_area_exists(area_name, cache).await?;
+    }
+    Ok(outages)
+}
+
+/// The outages for `area_name` which start `days_from_today` days after today, in SAST and in the
+/// order they start. Outages which go over midnight only count for the day they start on.
+async fn outages_on_day(
+    area_name: &str,
+    days_from_today: i64,
+    cache: &OutageCache,
+) -> Result<Outages, ApiError> {
+    let area_name = canonical_area_name(area_name, cache).await?;
+    let now = sast_now();
+    let day = now.date_naive() + Duration::days(days_from_today);
+    tracing::info!("Getting the outages for {area_name} on {day}");
+    let mut outages = area_outages(&area_name, cache).await?;
+    outages.retain(|outage| outage.start.with_timezone(now.offset()).date_naive() == day);
+    OutageOrder::default().sort(&mut outages);
+    Ok(Outages {
+        area_name,
+        localised_name: None,
+        outages,
+        fields: None,
+        paging: Paging::All,
+        truncated: None,
+        format: None,
+    })
+}
+
+/// An iCalendar file for `area_name` with one event per outage.
+fn area_calendar(area_name: &str, outages: &[PowerOutage]) -> String {
+    let now = sast_now();
+    let events = outages
+        .iter()
+        .map(|outage| ics::vevent(outage, &format!("Stage {} loadshedding", outage.stage), now))
+        .collect::<Vec<_>>();
+    ics::vcalendar(&format!("Loadshedding for {area_name}"), &events)
+}
+
+/// Check that eskom-calendar knows about `area_name`, without copying any of its outages.
+async fn check_area_exists(area_name: &str, cache: &OutageCache) -> Result<(), ApiError> {
+    let has_outages = cache
+        .outages()
+        .await
+        .map_err(ApiError::from)?
+        .iter()
+        .any(|outage| outage.area_name == area_name);
+    if has_outages
+        || cache
+            .is_known_area(area_name)
+            .await
+            .map_err(ApiError::from)?
+    {
+        return Ok(());
+    }
+    let message = format!("No areas found that match `{area_name}`");
+    Err(unknown_area(area_name, message, cache).await)
+}
+
+/// Which of `area_names` eskom-calendar doesn't know about, in alphabetical order. This can mean
+/// asking GitHub about each of them, so they're all asked about at once, and if GitHub can't say
+/// then the area is counted as unknown rather than failing the whole request.
+async fn unknown_areas<'a>(
+    area_names: impl Iterator<Item = &'a String>,
+    cache: &OutageCache,
+) -> Vec<String> {
+    let checks = area_names.map(|area_name| async move {
+        match cache.is_known_area(area_name).await {
+            Ok(is_known) => (area_name, is_known),
+            Err(err) => {
+                tracing::warn!("Couldn't check whether {area_name} exists: {err}");
+                (area_name, false)
+            }
+        }
+    });
+    let mut unknown = rocket::futures::future::join_all(checks)
+        .await
+        .into_iter()
+        .filter(|(_, is_known)| !is_known)
+        .map(|(area_name, _)| area_name.clone())
+        .collect::<Vec<_>>();
+    unknown.sort();
+    unknown
+}
+
+/// Get the names of every area with outages, in alphabetical order.
+async fn area_names(cache: &OutageCache) -> Result<Vec<String>, ApiError> {
+    let mut area_names = cache
+        .outages()
+        .await
+        .map_err(ApiError::from)?
+        .iter()
+        .map(|outage| outage.area_name.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    area_names.sort();
+    Ok(area_names)
+}
+
+/// Normalise an area name or a search query so they can be fuzzy matched against each other.
+fn normalise_for_search(query: &str) -> String {
+    // Replace all non a-z0-9_ chars with a space to aid in fuzzy matching. This is done for every
+    // area on every search, so it's done by hand rather than with a regex
+    query
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                ' '
+            }
+        })
+        .collect()
+}
+
+/// Scale a raw fuzzy matching score to be out of 100, where the best match of a search scores
+/// 100. Raw scores grow with the length of the query, so this keeps `min_score` meaning the same
+/// thing no matter what was searched for.
+fn normalised_score(raw_score: i64, best_raw_score: i64) -> i64 {
+    if best_raw_score <= 0 {
+        return 0;
+    }
+    raw_score * 100 / best_raw_score
+}
+
+/// The (at most) `SUGGESTION_LIMIT` area names which are the closest fuzzy matches for
+/// `area_name`, best match first.
+fn closest_area_names(area_name: &str, area_names: &[String]) -> Vec<String> {
+    let matcher = SkimMatcherV2::default();
+    let query = normalise_for_search(area_name);
+    let mut scored = area_names
+        .iter()
+        .filter_map(|name| {
+            matcher
+                .fuzzy_match(&normalise_for_search(name), &query)
+                .map(|score| (score, name))
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    scored
+        .into_iter()
+        .take(SUGGESTION_LIMIT)
+        .map(|(_score, name)| name.clone())
+        .collect()
+}
+
+/// Normalise an area name so that differences in case and separators don't matter, so
+/// `Western_Cape Stellenbosch` becomes `western-cape-stellenbosch`.
+fn normalise_area_name(area_name: &str) -> String {
+    area_name
+        .split(|c: char| c == '-' || c == '_' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+/// The actual name of the area the user meant by `area_name`, ignoring differences in case and
+/// separators. If no area matches, the normalised `area_name` is returned so that the caller can
+/// decide whether the area exists.
+async fn canonical_area_name(area_name: &str, cache: &OutageCache) -> Result<String, ApiError> {
+    let normalised = normalise_area_name(area_name);
+    if normalised == area_name {
+        return Ok(normalised);
+    }
+    Ok(area_names(cache)
+        .await?
+        .into_iter()
+        .find(|name| normalise_area_name(name) == normalised)
+        .unwrap_or(normalised))
+}
+
+/// The area called `area_name`, including its aliases.
+fn area_with_aliases(area_name: String, aliases: &AreaAliases) -> Area {
+    Area {
+        aliases: aliases.of(&area_name),
+        ..Area::from_name(area_name)
+    }
+}
+
+/// The area called `area_name`, including its aliases and the schedule it shares with other areas.
+fn area_with_schedule(area_name: String, aliases: &AreaAliases, schedules: &AreaSchedules) -> Area {
+    let schedule = ScheduleId::from_name(schedules.schedule_of(&area_name));
+    Area {
+        schedule,
+        ..area_with_aliases(area_name, aliases)
+    }
+}
+
+/// `area` with its name in the first of `languages` that it has a name in, keeping its actual name
+/// in `canonical_name`. Areas without a name in any of the languages are left as they are.
+fn localise_area(mut area: Area, aliases: &AreaAliases, languages: &AcceptLanguage) -> Area {
+    if let Some(localised) = aliases.localised(&area.name, &languages.0) {
+        area.canonical_name = Some(std::mem::replace(&mut area.name, localised.to_string()));
+    }
+    area
+}
+
+/// The names in `languages` of the areas on `page`, for the areas which have one.
+fn localise_page(
+    mut page: AreasPage,
+    aliases: &AreaAliases,
+    languages: &AcceptLanguage,
+) -> AreasPage {
+    page.localised_names = page
+        .areas
+        .iter()
+        .filter_map(|area_name| {
+            let localised = aliases.localised(area_name, &languages.0)?;
+            Some((area_name.clone(), localised.to_string()))
+        })
+        .collect();
+    page
+}
+
+/// The error for when `area_name` doesn't exist, suggesting some similarly named areas that do.
+async fn unknown_area(area_name: &str, message: String, cache: &OutageCache) -> ApiError {
+    // The suggestions are only a nicety, so don't let them turn a 404 into a 502
+    let suggestions = match area_names(cache).await {
+        Ok(area_names) => closest_area_names(area_name, &area_names),
+        Err(_err) => vec![],
+    };
+    if suggestions.is_empty() {
+        return ApiError::NotFound(message);
+    }
+    ApiError::UnknownArea {
+        message: format!("{message}. Did you mean `{}`?", suggestions.join("`, `")),
+        suggestions,
+    }
+}
+
+/// Get the loadshedding schedule for one area, along with its metadata (if it has any).
+async fn area_schedule(
+    area_name: &str,
+    cache: &OutageCache,
+) -> Result<RecurringSchedule, ApiError> {
+    tracing::info!("Getting schedules for {area_name}");
+    // Only ask GitHub about areas we know exist, rather than building a URL out of whatever the
+    // user sent and finding out from a failed request
+    let is_listed = cache
+        .is_listed_area(area_name)
         .await
-        .map_err(|_err| "Failed to get machine_friendly.csv that defines the outages")?
-        .text()
-        .instrument(convert_span)
+        .map_err(ApiError::from)?;
+    if !is_listed {
+        let message = format!("No schedule found for `{area_name}`");
+        return Err(unknown_area(area_name, message, cache).await);
+    }
+    if let Some(schedule) = cache.cached_schedule(area_name).await {
+        tracing::info!("Using the cached schedule for {area_name}");
+        return Ok(schedule);
+    }
+
+    let sources = cache.sources();
+    let (schedule_url, metadata_url) = (
+        sources.schedule_url(area_name),
+        sources.schedule_metadata_url(area_name),
+    );
+    let (text_data, metadata) = tokio::join!(
+        cache.fetch_file(&schedule_url),
+        cache.fetch_file(&metadata_url),
+    );
+    let text_data = text_data
+        .map_err(|err| {
+            ApiError::from(err.with_message(format!(
+                "Failed to get CSV file defining schedules for {area_name}"
+            )))
+        })?
+        .ok_or_else(|| ApiError::NotFound(format!("No schedule found for `{area_name}`")))?;
+    let metadata = parse_schedule_metadata(area_name, metadata);
+    let schedule = parse_schedule(area_name, &text_data, metadata)?;
+    cache.cache_schedule(area_name, schedule.clone()).await;
+    Ok(schedule)
+}
+
+/// The version of `area_name`'s schedule which is in use on `date`. This is the current version,
+/// unless it isn't valid on `date` and one of the older versions listed in its metadata is.
+async fn area_schedule_on(
+    area_name: &str,
+    date: NaiveDate,
+    cache: &OutageCache,
+) -> Result<RecurringSchedule, ApiError> {
+    let schedule = area_schedule(area_name, cache).await?;
+    if schedule.is_valid_on(date) {
+        return Ok(schedule);
+    }
+    let Some(older) = schedule
+        .superseded
+        .iter()
+        .find(|older| older.is_valid_on(date))
+    else {
+        // No version covers `date`, so the current version is the best there is
+        return Ok(schedule);
+    };
+
+    // Older versions are cached alongside the current one, under the name of their file
+    let key = format!("{area_name}/{}", older.file);
+    if let Some(schedule) = cache.cached_schedule(&key).await {
+        tracing::info!("Using the cached schedule {} for {area_name}", older.file);
+        return Ok(schedule);
+    }
+    tracing::info!("Getting the schedule {} for {area_name}", older.file);
+    let text_data = cache
+        .fetch_file(&cache.sources().schedule_file_url(&older.file))
         .await
-        .map_err(|_err| "Failed to get text of machine_friendly.csv")?;
+        .map_err(|err| {
+            ApiError::from(err.with_message(format!(
+                "Failed to get the CSV file defining {area_name}'s schedule on {date}"
+            )))
+        })?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("No schedule found for `{area_name}` on {date}"))
+        })?;
+    let metadata = ScheduleMetadata {
+        source: older.source.clone(),
+        info: older.info.clone(),
+        last_updated: older.last_updated,
+        valid_from: older.valid_from,
+        valid_until: older.valid_until,
+        superseded: vec![],
+    };
+    let schedule = parse_schedule(area_name, &text_data, metadata)?;
+    cache.cache_schedule(&key, schedule.clone()).await;
+    Ok(schedule)
+}
 
-    tracing::info!("Parsing machine_friendly.csv");
+/// Parse the text of one of `area_name`'s schedule CSV files, whose format depends on its headers.
+fn parse_schedule(
+    area_name: &str,
+    text_data: &str,
+    metadata: ScheduleMetadata,
+) -> Result<RecurringSchedule, ApiError> {
+    tracing::info!("Parsing schedule CSV as text");
     let mut reader = csv::Reader::from_reader(text_data.as_bytes());
-    Ok(reader
-        .deserialize::<PowerOutage>()
-        .map(|result| result.unwrap())
-        .collect())
+    let headers = reader
+        .headers()
+        .map_err(|_err| ApiError::Upstream("Couldn't read headers for CSV file".to_string()))?
+        .clone();
+
+    // Parse the CSV file in a manner that depends on the headers
+    let (outages, warnings) = if headers.iter().any(|h| h == "date_of_month") {
+        parse_csv_rows::<RawMonthlyShedding, RecurringOutage>(reader)
+    } else if headers.iter().any(|h| h == "day_of_week") {
+        parse_csv_rows::<RawWeeklyShedding, RecurringOutage>(reader)
+    } else if headers
+        .iter()
+        .any(|h| h == "day_of_cycle" || h == "day_of_20_day_cycle")
+    {
+        parse_csv_rows::<RawPeriodicShedding, RecurringOutage>(reader)
+    } else {
+        return Err(ApiError::Other(Errors::Unspecified(format!(
+            "Couldn't parse headers {:?}",
+            headers
+        ))));
+    };
+    for warning in &warnings {
+        tracing::warn!("In the schedule for {area_name}: {warning}");
+    }
+
+    tracing::info!("Returning parsed CSV as a RecurringSchedule");
+    Ok(RecurringSchedule {
+        id: ScheduleId::from_name(area_name),
+        outages,
+        source: metadata.source,
+        info: metadata.info,
+        last_updated: metadata.last_updated,
+        valid_from: metadata.valid_from,
+        valid_until: metadata.valid_until,
+        warnings,
+        superseded: metadata.superseded,
+    })
+}
+
+/// A list of power outages. This is sent in the `format` asked for, or otherwise as CSV if the
+/// request's `Accept` header prefers `text/csv`, and as JSON otherwise.
+pub struct Outages {
+    /// The area the outages are for, which names the calendar when they're sent as iCalendar.
+    pub area_name: String,
+    /// The area's name in the language the client prefers, if it has one. JSON outages then have
+    /// this as their `area_name`, and the area's actual name as their `canonical_area_name`.
+    pub localised_name: Option<String>,
+    pub outages: Vec<PowerOutage>,
+    /// Which of the outages' fields to send, or `None` to send all of them. iCalendar events
+    /// always have every field.
+    pub fields: Option<Vec<&'static str>>,
+    /// Whether the outages are one page of a longer list.
+    pub paging: Paging,
+    /// If the `OutageCap` left some of the outages out, a message saying how to get the rest.
+    pub truncated: Option<String>,
+    /// The format asked for with `format=`, or `None` to go by the `Accept` header.
+    pub format: Option<OutagesFormat>,
+}
+
+/// The formats that a list of outages can be sent in, as asked for with `format=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutagesFormat {
+    Json,
+    Csv,
+    Ics,
+}
+
+impl OutagesFormat {
+    /// Every format, in the same order as they're documented.
+    const ALL: [OutagesFormat; 3] = [OutagesFormat::Json, OutagesFormat::Csv, OutagesFormat::Ics];
+
+    /// How the format is written in the `format` query parameter, like `csv`.
+    fn param(&self) -> &'static str {
+        match self {
+            OutagesFormat::Json => "json",
+            OutagesFormat::Csv => "csv",
+            OutagesFormat::Ics => "ics",
+        }
+    }
+
+    /// The format asked for with `format=`, or whichever of CSV and JSON `req` would rather have
+    /// if none was.
+    fn for_request(format: Option<OutagesFormat>, req: &Request<'_>) -> Self {
+        format.unwrap_or(if wants_csv(req) {
+            OutagesFormat::Csv
+        } else {
+            OutagesFormat::Json
+        })
+    }
+
+    /// The content type that outages are sent with in this format.
+    fn content_type(&self) -> ContentType {
+        match self {
+            OutagesFormat::Json => ContentType::JSON,
+            OutagesFormat::Csv => ContentType::CSV,
+            OutagesFormat::Ics => ContentType::Calendar,
+        }
+    }
+}
+
+/// Whether a list of outages is all of them, or one page of them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Paging {
+    /// Every outage is in the list, which is sent as a JSON array.
+    #[default]
+    All,
+    /// The list is one page, which is sent as an `OutagesPage` so that users know where the next
+    /// page starts. CSV and iCalendar pages have the cursor in the `X-Next-Cursor` header
+    /// instead.
+    Page { next_cursor: Option<String> },
+}
+
+/// Whether `req` would rather have CSV than JSON.
+fn wants_csv(req: &Request<'_>) -> bool {
+    req.accept()
+        .is_some_and(|accept| accept.preferred().media_type() == &MediaType::CSV)
+}
+
+impl<'r> Responder<'r, 'static> for Outages {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let fields = self.fields.as_deref().unwrap_or(&PowerOutage::FIELDS);
+        let format = OutagesFormat::for_request(self.format, req);
+        if format != OutagesFormat::Json {
+            let mut response = if format == OutagesFormat::Csv {
+                let csv = outages_to_csv(&self.outages, fields).map_err(|err| {
+                    tracing::error!("Failed to convert outages to CSV: {err:?}");
+                    Status::InternalServerError
+                })?;
+                (ContentType::CSV, csv).respond_to(req)?
+            } else {
+                Ics(area_calendar(&self.area_name, &self.outages)).respond_to(req)?
+            };
+            mark_truncated(&mut response, self.truncated.is_some());
+            if let Paging::Page {
+                next_cursor: Some(next_cursor),
+            } = self.paging
+            {
+                response.set_header(Header::new("X-Next-Cursor", next_cursor));
+            }
+            Ok(response)
+        } else {
+            let mut outages = if self.fields.is_some() {
+                self.outages
+                    .iter()
+                    .map(|outage| only_fields(outage, fields))
+                    .collect()
+            } else {
+                serde_json::to_value(&self.outages).unwrap_or_default()
+            };
+            if let Some(localised_name) = &self.localised_name {
+                let objects = outages.as_array_mut().into_iter().flatten();
+                for outage in objects.filter_map(serde_json::Value::as_object_mut) {
+                    if let Some(area_name) = outage.get_mut("area_name") {
+                        let canonical = std::mem::replace(area_name, localised_name.clone().into());
+                        outage.insert("canonical_area_name".to_string(), canonical);
+                    }
+                }
+            }
+            let truncated = self.truncated.is_some();
+            let mut response = match self.paging {
+                Paging::All => Json(outages).respond_to(req)?,
+                Paging::Page { next_cursor } => {
+                    let mut page = serde_json::json!({
+                        "outages": outages,
+                        "next_cursor": next_cursor,
+                        "truncated": truncated,
+                    });
+                    if let Some(message) = self.truncated {
+                        page["message"] = message.into();
+                    }
+                    Json(page).respond_to(req)?
+                }
+            };
+            mark_truncated(&mut response, truncated);
+            Ok(response)
+        }
+    }
+}
+
+/// A response built from the cached outages, which HTTP caches are told they can keep until the
+/// outages are next refreshed, with the headers `Cache-Control: public, max-age=<seconds>` and
+/// `Last-Modified`. If GitHub sent an ETag with the outages, a weak version of it is sent as the
+/// `ETag` header, so that clients can tell when the data has changed.
+///
+/// If the request has an `If-None-Match` header with the same ETag, or (without `If-None-Match`)
+/// an `If-Modified-Since` header which isn't before `Last-Modified`, the response is `304 Not
+/// Modified` without a body, so that the client can keep using its copy.
+pub struct Cacheable<R> {
+    pub inner: R,
+    /// How many more seconds the cached outages will be used for.
+    pub max_age: u64,
+    /// When machine_friendly.csv last changed, if it's been fetched.
+    pub last_modified: Option<DateTime<FixedOffset>>,
+    /// The ETag GitHub sent with the cached outages, if any.
+    pub etag: Option<String>,
+}
+
+impl<R> Cacheable<R> {
+    /// Wrap `inner` with the freshness of the outages in `cache`.
+    async fn new(inner: R, cache: &OutageCache) -> Self {
+        let freshness = cache.freshness().await;
+        Cacheable {
+            inner,
+            max_age: freshness
+                .as_ref()
+                .map_or(0, |freshness| freshness.remaining.as_secs()),
+            last_modified: freshness.as_ref().map(|freshness| freshness.last_changed),
+            etag: freshness.and_then(|freshness| freshness.etag),
+        }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Cacheable<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.inner.respond_to(req)?;
+        let not_modified = response.status() == Status::Ok
+            && is_not_modified(req, self.last_modified, self.etag.as_deref());
+        if not_modified {
+            // Rocket won't respond with a bare redirection status, so build the response by hand
+            response = Response::build().status(Status::NotModified).finalize();
+        }
+        response.set_header(Header::new(
+            "Cache-Control",
+            format!("public, max-age={}", self.max_age),
+        ));
+        if let Some(last_modified) = self.last_modified {
+            response.set_header(Header::new(
+                "Last-Modified",
+                last_modified
+                    .with_timezone(&chrono::Utc)
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string(),
+            ));
+        }
+        // The response depends on more than just machine_friendly.csv (like the query
+        // parameters), so it's only weakly equivalent to other responses with this ETag
+        if let Some(etag) = self.etag {
+            response.set_header(Header::new("ETag", format!("W/\"{}\"", bare_etag(&etag))));
+        }
+        Ok(response)
+    }
+}
+
+/// Whether the client already has the data last modified at `last_modified` with the ETag `etag`,
+/// going by the request's `If-None-Match` header, or its `If-Modified-Since` header if it doesn't
+/// have one. `If-Modified-Since` is ignored when there's an `If-None-Match` header, as HTTP says
+/// it should be.
+fn is_not_modified(
+    req: &Request<'_>,
+    last_modified: Option<DateTime<FixedOffset>>,
+    etag: Option<&str>,
+) -> bool {
+    if let Some(if_none_match) = req.headers().get_one("If-None-Match") {
+        return etag.is_some_and(|etag| {
+            if_none_match
+                .split(',')
+                .any(|tag| tag.trim() == "*" || bare_etag(tag) == bare_etag(etag))
+        });
+    }
+    let since = req
+        .headers()
+        .get_one("If-Modified-Since")
+        .and_then(|since| DateTime::parse_from_rfc2822(since).ok());
+    // HTTP dates only go down to the second, so the last modified time has to be truncated before
+    // comparing it
+    last_modified
+        .zip(since)
+        .is_some_and(|(last_modified, since)| last_modified.timestamp() <= since.timestamp())
+}
+
+/// The response to a `HEAD` request for a list of power outages: the same content type as
+/// `Outages` would have, but without working out what the outages are.
+pub struct OutagesHead {
+    /// The format asked for with `format=`, or `None` to go by the `Accept` header.
+    pub format: Option<OutagesFormat>,
+}
+
+impl<'r> Responder<'r, 'static> for OutagesHead {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let content_type = OutagesFormat::for_request(self.format, req).content_type();
+        Response::build().header(content_type).ok()
+    }
+}
+
+/// machine_friendly.csv exactly as GitHub sent it, with GitHub's ETag.
+pub struct MachineFriendlyCsv {
+    pub csv: std::sync::Arc<str>,
+    pub etag: Option<String>,
+}
+
+impl<'r> Responder<'r, 'static> for MachineFriendlyCsv {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = (ContentType::CSV, self.csv.to_string()).respond_to(req)?;
+        if let Some(etag) = self.etag {
+            response.set_header(Header::new("ETag", etag));
+        }
+        Ok(response)
+    }
+}
+
+/// The result of long-polling for outages: either the outages (because the data has changed),
+/// or a `304 Not Modified` because nothing changed before the long-poll timed out.
+pub enum LongPoll {
+    Changed {
+        outages: Outages,
+        etag: Option<String>,
+    },
+    Unchanged,
+}
+
+impl<'r> Responder<'r, 'static> for LongPoll {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        match self {
+            LongPoll::Changed { outages, etag } => {
+                let mut response = outages.respond_to(req)?;
+                if let Some(etag) = etag {
+                    response.set_header(Header::new("ETag", etag));
+                }
+                Ok(response)
+            }
+            // Rocket won't respond with a bare redirection status, so build the response by hand
+            LongPoll::Unchanged => Response::build().status(Status::NotModified).ok(),
+        }
+    }
+}
+
+/// An ETag for one area's outages, which only changes when they do. The ETag of
+/// machine_friendly.csv changes whenever any area's outages change, which would wake up long-polls
+/// for areas that haven't changed at all.
+fn area_etag(outages: &[PowerOutage]) -> String {
+    let json = serde_json::to_string(outages).unwrap_or_default();
+    let digest = Sha256::digest(json.as_bytes());
+    format!("\"{}\"", hex::encode(&digest[..8]))
+}
+
+/// An ETag without the quotes or the weak marker, so that ETags can be compared however the user
+/// wrote them.
+fn bare_etag(etag: &str) -> &str {
+    etag.trim().trim_start_matches("W/").trim_matches('"')
+}
+
+/// A page of area names, sent as JSON which is written a few areas at a time rather than all at
+/// once. The full list of areas is big, and lots of clients ask for it, so this saves having a
+/// copy of the whole JSON document in memory for each of them.
+pub struct StreamedAreasPage(pub AreasPage);
+
+impl<'r> Responder<'r, 'r> for StreamedAreasPage {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'r> {
+        let AreasPage {
+            total,
+            offset,
+            limit,
+            areas,
+            localised_names,
+        } = self.0;
+        // The fields are written in the same order as `AreasPage` is serialised, so this is
+        // exactly what `Json<AreasPage>` would have sent
+        let head = format!(
+            r#"{{"total":{total},"offset":{offset},"limit":{},"areas":["#,
+            serde_json::to_string(&limit).map_err(|_err| Status::InternalServerError)?
+        );
+        let mut areas = areas.into_iter().peekable();
+        let mut is_first = true;
+        let body = std::iter::from_fn(move || {
+            areas.peek()?;
+            let mut chunk = String::new();
+            for area in areas.by_ref().take(AREAS_PER_CHUNK) {
+                if !is_first {
+                    chunk.push(',');
+                }
+                is_first = false;
+                // Serialising a string can't fail
+                chunk.push_str(&serde_json::to_string(&area).unwrap_or_default());
+            }
+            Some(chunk)
+        });
+        let tail = if localised_names.is_empty() {
+            "]}".to_string()
+        } else {
+            format!(
+                r#"],"localised_names":{}}}"#,
+                serde_json::to_string(&localised_names)
+                    .map_err(|_err| Status::InternalServerError)?
+            )
+        };
+        let chunks = std::iter::once(head)
+            .chain(body)
+            .chain(std::iter::once(tail));
+        (
+            ContentType::JSON,
+            ByteStream(rocket::futures::stream::iter(chunks)),
+        )
+            .respond_to(req)
+    }
+}
+
+/// Convert power outages into CSV with the same columns as machine_friendly.csv.
+fn outages_to_csv(outages: &[PowerOutage], fields: &[&str]) -> Result<String, csv::Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(vec![]);
+    // The header is written separately so that it's there even if there aren't any outages
+    writer.write_record(fields)?;
+    for outage in outages {
+        let outage = only_fields(outage, fields);
+        writer.write_record(fields.iter().map(|field| match &outage[field] {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        }))?;
+    }
+    let bytes = writer.into_inner().map_err(|err| err.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `outage` as a JSON object with only the given fields.
+fn only_fields(outage: &PowerOutage, fields: &[&str]) -> serde_json::Value {
+    let mut outage = serde_json::to_value(outage).unwrap_or_default();
+    if let Some(object) = outage.as_object_mut() {
+        object.retain(|field, _value| fields.contains(&field.as_str()));
+    }
+    outage
+}
+
+/// Parse a comma separated list of `PowerOutage` fields, like `stage,start,finsh`. The fields
+/// are given back in the same order as `PowerOutage::FIELDS`, without duplicates.
+fn parse_fields(fields: Option<String>) -> Result<Option<Vec<&'static str>>, String> {
+    let Some(fields) = fields else {
+        return Ok(None);
+    };
+    let asked_for = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect::<Vec<_>>();
+    let valid = PowerOutage::FIELDS.join(", ");
+    if asked_for.is_empty() {
+        return Err(format!(
+            "`fields` must list at least one field. Valid fields are {valid}"
+        ));
+    }
+    if let Some(unknown) = asked_for
+        .iter()
+        .find(|field| !PowerOutage::FIELDS.contains(field))
+    {
+        return Err(format!(
+            "`{unknown}` isn't a field of an outage. Valid fields are {valid}"
+        ));
+    }
+    Ok(Some(
+        PowerOutage::FIELDS
+            .into_iter()
+            .filter(|field| asked_for.contains(field))
+            .collect(),
+    ))
+}
+
+/// Parse a query parameter which should be a non-negative whole number, like `limit` or `offset`.
+fn parse_count(name: &str, value: Option<String>) -> Result<Option<usize>, String> {
+    value
+        .map(|value| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_err| format!("`{name}={value}` must be a non-negative whole number"))
+        })
+        .transpose()
+}
+
+/// Parse the format to send outages in, like `csv`. None if no format was asked for.
+fn parse_format(format: Option<String>) -> Result<Option<OutagesFormat>, String> {
+    let Some(format) = format else {
+        return Ok(None);
+    };
+    OutagesFormat::ALL
+        .into_iter()
+        .find(|known| known.param().eq_ignore_ascii_case(format.trim()))
+        .map(Some)
+        .ok_or_else(|| {
+            let valid = OutagesFormat::ALL.map(|known| known.param()).join(", ");
+            format!("`format={format}` must be one of {valid}")
+        })
+}
+
+/// Parse the order to sort outages in, like `-stage`. Defaults to `start`.
+fn parse_sort(sort: Option<String>) -> Result<OutageOrder, String> {
+    let Some(sort) = sort else {
+        return Ok(OutageOrder::default());
+    };
+    OutageOrder::from_param(sort.trim()).ok_or_else(|| {
+        let valid = OutageOrder::ALL.map(|order| order.param()).join(", ");
+        format!("`sort={sort}` must be one of {valid}")
+    })
+}
+
+/// Parse a province's slug given by a user, like `western-cape`.
+fn parse_province(slug: String) -> Result<Province, ApiError> {
+    Province::from_slug(slug.trim()).ok_or_else(|| {
+        let valid = Province::ALL.map(|province| province.slug()).join(", ");
+        ApiError::BadRequest(format!(
+            "`{slug}` isn't a province. It must be one of {valid}"
+        ))
+    })
+}
+
+/// Compile a regex from a user, rejecting any which are too long or too complex so that they can't
+/// use up lots of memory or CPU. If `anchored`, the regex has to match the whole of a string
+/// rather than just part of it.
+async fn compile_user_regex(regex: &str, anchored: bool) -> Result<Regex, ApiError> {
+    if regex.len() > MAX_REGEX_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "The regex is {} characters long, but can be at most {MAX_REGEX_LEN}",
+            regex.len()
+        )));
+    }
+    let too_complex = || {
+        ApiError::BadRequest(format!(
+            "The regex '{regex}' is too complex. Try a simpler one"
+        ))
+    };
+    let pattern = if anchored {
+        format!("^(?:{regex})$")
+    } else {
+        regex.to_string()
+    };
+    let compile = tokio::task::spawn_blocking(move || {
+        RegexBuilder::new(&pattern)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .dfa_size_limit(REGEX_SIZE_LIMIT)
+            .build()
+    });
+    match tokio::time::timeout(REGEX_COMPILE_TIMEOUT, compile).await {
+        Ok(Ok(Ok(re))) => Ok(re),
+        Ok(Ok(Err(regex::Error::CompiledTooBig(_)))) | Err(_) => Err(too_complex()),
+        Ok(Ok(Err(e))) => Err(ApiError::BadRequest(format!(
+            "Error parsing '{regex}' as regex: {e:?}"
+        ))),
+        Ok(Err(e)) => Err(ApiError::Other(Errors::Unspecified(format!(
+            "Failed to compile the regex '{regex}': {e}"
+        )))),
+    }
+}
+
+/// Parse the `from` and `to` of a window of time, like the one to expand a schedule over. `from`
+/// defaults to now and `to` defaults to `EXPAND_DAYS` after `from`, and the window can't be longer
+/// than `MAX_EXPAND_DAYS`.
+fn parse_window(
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>), String> {
+    let from = from
+        .map(|from| parse_datetime("from", &from))
+        .transpose()?
+        .unwrap_or_else(sast_now);
+    let to = to
+        .map(|to| parse_datetime("to", &to))
+        .transpose()?
+        .unwrap_or(from + Duration::days(EXPAND_DAYS));
+    if to <= from {
+        return Err(format!("`to` ({to}) must be after `from` ({from})"));
+    } else if to - from > Duration::days(MAX_EXPAND_DAYS) {
+        return Err(format!(
+            "The window from `from` to `to` can be at most {MAX_EXPAND_DAYS} days long"
+        ));
+    }
+    Ok((from, to))
+}
+
+/// Parse a comma separated list of stages, like `2,4,6`. The stages are given back lowest first,
+/// without duplicates.
+fn parse_stages(name: &str, value: &str) -> Result<Vec<u8>, String> {
+    let stages = value
+        .split(',')
+        .map(str::trim)
+        .filter(|stage| !stage.is_empty())
+        .map(|stage| parse_stage(name, stage))
+        .collect::<Result<BTreeSet<_>, _>>()?;
+    if stages.is_empty() {
+        return Err(format!(
+            "`{name}` must list at least one stage, like `2,4,6`"
+        ));
+    }
+    Ok(stages.into_iter().collect())
+}
+
+/// Parse the `calendar` query parameter, which is `za` (the default) to take South Africa's public
+/// holidays into account, or `none` to ignore them. Returns whether public holidays are used.
+fn parse_calendar(calendar: Option<String>) -> Result<bool, String> {
+    match calendar.as_deref().map(str::trim) {
+        None | Some("za") => Ok(true),
+        Some("none") => Ok(false),
+        Some(other) => Err(format!("`calendar={other}` must be `za` or `none`")),
+    }
+}
+
+/// Parse a query parameter which should be `true` or `false`, defaulting to false.
+fn parse_flag(name: &str, value: Option<String>) -> Result<bool, String> {
+    match value.as_deref().map(str::trim) {
+        None | Some("false") => Ok(false),
+        Some("true") => Ok(true),
+        Some(value) => Err(format!("`{name}={value}` must be either `true` or `false`")),
+    }
+}
+
+/// Parse the fetched metadata for `area_name`'s schedule. The schedule is still useful without its
+/// metadata, so if the metadata is missing or broken this just logs a warning and leaves
+/// everything empty.
+fn parse_schedule_metadata(
+    area_name: &str,
+    fetched: Result<Option<String>, UpstreamError>,
+) -> ScheduleMetadata {
+    match fetched {
+        Ok(Some(text)) => serde_json::from_str(&text).unwrap_or_else(|err| {
+            tracing::warn!("Couldn't parse the schedule metadata for {area_name}: {err}");
+            ScheduleMetadata::default()
+        }),
+        Ok(None) => {
+            tracing::warn!("There's no schedule metadata for {area_name}");
+            ScheduleMetadata::default()
+        }
+        Err(err) => {
+            tracing::warn!("Failed to get the schedule metadata for {area_name}: {err}");
+            ScheduleMetadata::default()
+        }
+    }
+}
+
+/// The timezone that a user wants times in, given with `?tz=`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Timezone {
+    /// A fixed offset from UTC, like `+00:00`.
+    Fixed(FixedOffset),
+    /// An IANA timezone like `Europe/London`, whose offset depends on daylight saving.
+    Named(Tz),
+}
+
+impl Timezone {
+    /// The same moment as `datetime`, but in this timezone.
+    fn convert(self, datetime: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self {
+            Timezone::Fixed(offset) => datetime.with_timezone(&offset),
+            Timezone::Named(tz) => {
+                let offset = datetime.with_timezone(&tz).offset().fix();
+                datetime.with_timezone(&offset)
+            }
+        }
+    }
+}
+
+/// Parse a timezone given by a user, which can be `utc`, a fixed offset like `+05:30`, or an IANA
+/// name like `Africa/Johannesburg`.
+fn parse_timezone(value: &str) -> Result<Timezone, String> {
+    // An unencoded `+` in a query string gets decoded as a space, so `?tz=+01:00` arrives as
+    // ` 01:00`
+    let value = match value.strip_prefix(' ') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => format!("+{rest}"),
+        _ => value.trim().to_string(),
+    };
+    if value.eq_ignore_ascii_case("utc") || value == "Z" {
+        return Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap()));
+    }
+    let offset_re = Regex::new(r"^([+-])(\d{2}):?(\d{2})$").unwrap();
+    if let Some(captures) = offset_re.captures(&value) {
+        let sign = if &captures[1] == "-" { -1 } else { 1 };
+        let hours: i32 = captures[2].parse().unwrap();
+        let minutes: i32 = captures[3].parse().unwrap();
+        return (minutes < 60)
+            .then(|| FixedOffset::east_opt(sign * (hours * 60 * 60 + minutes * 60)))
+            .flatten()
+            .map(Timezone::Fixed)
+            .ok_or_else(|| format!("`tz={value}` isn't a valid offset from UTC"));
+    }
+    value.parse::<Tz>().map(Timezone::Named).map_err(|_err| {
+        format!(
+            "`tz={value}` isn't a timezone. It must be `utc`, an offset like `+05:30`, or an IANA \
+            name like `Africa/Johannesburg`"
+        )
+    })
+}
+
+/// Either the next outage, or `204 No Content` if there are no upcoming outages.
+#[derive(Responder)]
+pub enum MaybeNextOutage {
+    Next(Json<NextOutage>),
+    #[response(status = 204)]
+    NoneScheduled(()),
 }
 
 pub mod latest {
@@ -41,101 +1158,1450 @@ pub mod latest {
 
     pub fn routes() -> Vec<rocket::Route> {
         routes![
+            area,
+            area_at,
+            area_by_id,
+            area_siblings,
+            areas_in_municipality,
+            areas_in_province,
+            areas_search,
+            areas_tree,
+            cache_status,
+            current,
+            current_for_areas,
             fuzzy_search,
+            health,
             health_check,
             list_all_areas,
             list_areas,
+            machine_friendly_csv,
+            metrics,
+            municipalities,
+            national_stage,
+            national_stats,
+            next,
             outages,
+            outages_all,
+            outages_daily,
+            outages_diff,
+            outages_for_areas,
+            outages_gaps,
+            outages_head,
+            outages_ics,
+            outages_longpoll,
+            outages_today,
+            outages_tomorrow,
+            outlook,
+            province_outages_ics,
+            provinces,
+            ratelimit,
             schedules,
+            schedules_compare,
+            schedules_expand,
+            schedules_ics,
+            schedules_raw_csv,
+            schedules_validate,
+            search,
+            stage_reference,
+            stats,
+            subscribe,
+            typescript_client,
+            unsubscribe,
+            version,
         ]
     }
 
+    /// Find the areas which contain a point on the map.
+    ///
+    /// Give the latitude and longitude of a point (for example, from your phone's GPS) and you'll
+    /// get the areas whose boundaries contain that point. Points exactly on the border between two
+    /// areas are in both of them. If the point isn't inside any area with a known boundary, you'll
+    /// get an empty list. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("lat" = f64, Query, example = -33.9321, description = "Latitude of the point, between -90 and 90"),
+            ("lng" = f64, Query, example = 18.8602, description = "Longitude of the point, between -180 and 180"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get a list of areas containing the point", body = [Area]),
+            (status = 400, description = "The latitude or longitude was invalid", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/area_at?<lat>&<lng>")]
+    pub async fn area_at(
+        lat: String,
+        lng: String,
+        boundaries: &State<AreaBoundaries>,
+    ) -> Result<Json<Vec<Area>>, ApiError> {
+        super::v0_0_1::area_at(lat, lng, boundaries).await
+    }
+
+    /// Get everything that's known about one area.
+    ///
+    /// The `name` must be exactly one of the ones listed in the endpoint `list_areas`. You'll get
+    /// the area's IDs, aliases, province, and municipality (when they're known). If the area can't
+    /// be found, you'll get a `404 Not Found` with some similarly named areas in `suggestions`.
+    ///
+    /// If your `Accept-Language` header prefers a language that the area has a name in (like
+    /// `af` for Afrikaans), `name` is that name and the area's usual name is in `canonical_name`.
+    /// Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("name" = String, example = "western-cape-stellenbosch", description = "The name of the area")),
+        responses(
+            (status = 200, description = "Success. You'll get the area", body = Area),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/area/<name>")]
+    pub async fn area(
+        name: String,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+        schedules: &State<AreaSchedules>,
+    ) -> Result<Json<Area>, ApiError> {
+        super::v0_0_1::area(name, languages, cache, aliases, schedules).await
+    }
+
+    /// Get the other areas which follow the same schedule as one area.
+    ///
+    /// Areas which follow the same schedule get loadshed at the same times, so this tells you
+    /// which neighbourhoods are shed together. Most areas have a schedule of their own, in which
+    /// case you'll get an empty list. The `name` must be exactly one of the ones listed in the
+    /// endpoint `list_areas`. Like `/area/{name}`, areas' names are localised with the
+    /// `Accept-Language` header. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("name" = String, example = "western-cape-stellenbosch", description = "The name of the area")),
+        responses(
+            (status = 200, description = "Success. You'll get a list of areas with the same schedule", body = [Area]),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/area/<name>/siblings")]
+    pub async fn area_siblings(
+        name: String,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+        schedules: &State<AreaSchedules>,
+    ) -> Result<Json<Vec<Area>>, ApiError> {
+        super::v0_0_1::area_siblings(name, languages, cache, aliases, schedules).await
+    }
+
+    /// Look up an area by its ID.
+    ///
+    /// Every area's `id` (as returned by `fuzzy_search`) is always the same for the same area, so
+    /// you can store the ID and use this endpoint to get the area back later. Click 'Try it out'
+    /// on the right to have a go!
+    #[utoipa::path(
+        params(("id" = i64, example = 1, description = "The ID of the area")),
+        responses(
+            (status = 200, description = "Success. You'll get the area with that ID", body = Area),
+            (status = 404, description = "There's no area with that ID", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/area_by_id/<id>")]
+    pub async fn area_by_id(id: i64, cache: &State<OutageCache>) -> Result<Json<Area>, ApiError> {
+        super::v0_0_1::area_by_id(id, cache).await
+    }
+
     /// Search for an area using approximate (or "fuzzy") matching.
     ///
     /// For example, `west dorp` will match all areas that have `west` and `dorp` in their names in
     /// that order. This is useful if you don't know what eskom-calendar calls the area you are in.
+    /// Areas' aliases (like common misspellings or their names in other languages) are searched
+    /// too, but you'll get back the area's actual name. The exception is when your
+    /// `Accept-Language` header prefers a language that the area has a name in, in which case
+    /// you'll get that name and the actual name is in `canonical_name`.
     ///
-    /// The returned `score` describes how good a match each item is. The higher the score, the
-    /// better the match. Click 'Try it out' on the right to have a go!
+    /// The returned `score` describes how good a match each item is, out of 100. The best match
+    /// always scores 100, and the others are scored relative to it, so `min_score=50` drops
+    /// anything less than half as good as the best match whatever you searched for. The
+    /// matcher's own score is in `raw_score`, but it grows with the length of the query so it's
+    /// only useful for comparing results of the same search. Only the best 10 matches are
+    /// returned unless you ask for more with `limit`, and you can drop weak matches with
+    /// `min_score`. Matches with the same score are sorted alphabetically.
+    ///
+    /// Some towns have the same name in different provinces, so you can only search in one
+    /// province with `province`. The query needs at least 2 letters or numbers, otherwise you'll
+    /// get a `400 Bad Request`.
+    ///
+    /// To show which parts of each result matched (for example, in bold while autocompleting),
+    /// use `highlight=true`. Each result then has a `highlight` with the name that matched best
+    /// (which might be an alias) and the ranges of its characters that matched the query. Click
+    /// 'Try it out' on the right to have a go!
     #[utoipa::path(
-        params(("query" = String, example="west dorp", description = "Space separated search queryies (order matters)")),
+        params(
+            ("query" = String, example="west dorp", description = "Space separated search queryies (order matters)"),
+            ("limit" = Option<usize>, Query, example = 10, description = "The most results to return. Defaults to 10"),
+            ("min_score" = Option<i64>, Query, example = 50, description = "Only return results with at least this score, out of 100"),
+            ("province" = Option<String>, Query, example = "western-cape", description = "Only return areas in this province, written like `western-cape` or `kwazulu-natal`"),
+            ("highlight" = Option<bool>, Query, example = true, description = "Say which characters of each result matched the query. Defaults to false"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
         responses(
-            (status = 200, description = "Success. You'll get a list of search results", body = [SearchResult])
+            (status = 200, description = "Success. You'll get a list of search results", body = [SearchResult]),
+            (status = 400, description = "The query was too short, or the limit, min_score, province, or highlight was invalid", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
         ),
     )]
-    #[get("/fuzzy_search/<query>")]
-    pub async fn fuzzy_search(query: String) -> Result<Json<Vec<SearchResult<Area>>>, String> {
-        super::v0_0_1::fuzzy_search(query).await
+    #[get("/fuzzy_search/<query>?<limit>&<min_score>&<province>&<highlight>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn fuzzy_search(
+        query: String,
+        limit: Option<String>,
+        min_score: Option<String>,
+        province: Option<String>,
+        highlight: Option<String>,
+        email: ContactEmail,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<Json<Vec<SearchResult<Area>>>>, ApiError> {
+        super::v0_0_1::fuzzy_search(
+            query, limit, min_score, province, highlight, email, languages, cache, aliases,
+        )
+        .await
     }
 
-    /// Get all the known times when power will be off for a certain area.
+    /// Search for areas, provinces, and municipalities all at once.
     ///
-    /// The `area_name` must be one of the ones listed in the endpoint `list_areas`. Click 'Try it
-    /// out' on the right to have a go!
+    /// This is handy for a single search box: `gauteng` will find the province of Gauteng, and
+    /// `stellenbosch` will find both the area and the municipality called Stellenbosch. Each
+    /// result has a `kind` (`area`, `province`, or `municipality`), a `score` like
+    /// `/fuzzy_search/{query}`, a `name` to use with the other endpoints, and the `result` itself.
+    ///
+    /// Results are sorted best match first. Provinces come before municipalities, which come
+    /// before areas, when they match equally well. Only the best 10 matches are returned unless
+    /// you ask for more with `limit`. Click 'Try it out' on the right to have a go!
     #[utoipa::path(
-        params(("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the outages for")),
+        params(
+            ("query" = String, example="gauteng", description = "What to search for"),
+            ("limit" = Option<usize>, Query, example = 10, description = "The most results to return. Defaults to 10"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
         responses(
-            (status = 200, description = "200 will return a list of PowerOutage objects.", body = [PowerOutage])
+            (status = 200, description = "Success. You'll get a list of search results", body = [SearchMatch]),
+            (status = 400, description = "The limit was invalid", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
         ),
     )]
-    #[get("/outages/<area_name>")]
-    pub async fn outages(area_name: String) -> Result<Json<Vec<PowerOutage>>, String> {
-        super::v0_0_1::outages(area_name).await
+    #[get("/search/<query>?<limit>")]
+    pub async fn search(
+        query: String,
+        limit: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Json<Vec<SearchMatch>>, ApiError> {
+        super::v0_0_1::search(query, limit, email, cache, aliases).await
+    }
+
+    /// Get all the known times when power will be off for a certain area.
+    ///
+    /// The `area_name` must be one of the ones listed in the endpoint `list_areas`, although case
+    /// and separators don't matter (so `Western_Cape_Stellenbosch` works too). You can optionally
+    /// only get the outages which overlap a window of time with the `start` and `finsh`
+    /// parameters, or only the outages within a range of stages with the `min_stage` and
+    /// `max_stage` parameters. Use `source_contains` to only get the outages announced by a
+    /// certain account or document, like `source_contains=CityofCT`.
+    ///
+    /// Sometimes one continuous blackout is listed as several back-to-back outages at the same
+    /// stage. Use `merge=true` to combine them into one outage. Use `min_minutes` to drop any
+    /// outages too short to matter to you, which is checked after merging.
+    ///
+    /// Outages which have already finished are left out, unless you ask for them with
+    /// `include_past=true`.
+    ///
+    /// Times are given in South African time (+02:00), unless you ask for a different timezone
+    /// with `tz`. This can be `utc`, an offset like `+05:30` (remember to URL encode the `+` as
+    /// `%2B`), or an IANA name like `Europe/London`.
+    ///
+    /// If the area exists but isn't getting any loadshedding, you'll get an empty list. You'll only
+    /// get a `404 Not Found` if eskom-calendar doesn't know about the area at all.
+    ///
+    /// The outages are returned as JSON, unless you send the header `Accept: text/csv` in which
+    /// case they're returned as CSV with the same columns as eskom-calendar's
+    /// `machine_friendly.csv`. You can also pick the format with `format`, which is easier from a
+    /// browser: `json`, `csv`, or `ics` for an iCalendar file with one event per outage. To save
+    /// bandwidth, use `fields` to only get some of each outage's
+    /// fields, like `fields=stage,start,finsh`.
+    ///
+    /// If your `Accept-Language` header prefers a language that the area has a name in (like
+    /// `af` for Afrikaans), each JSON outage's `area_name` is that name and the area's usual name
+    /// is in `canonical_area_name`.
+    ///
+    /// The outages are sorted by when they start, earliest first. Use `sort` to get them in a
+    /// different order: `-start` for latest first, or `stage`/`-stage` for lowest/highest stage
+    /// first (with outages at the same stage sorted earliest first).
+    ///
+    /// Use `limit` to get the outages a page at a time (up to 100 per page). You'll get an
+    /// `OutagesPage`, and can give its `next_cursor` as `cursor` to get the next page, keeping the
+    /// other parameters the same. CSV pages have the cursor in the `X-Next-Cursor` header instead.
+    /// The last page has no `next_cursor`.
+    ///
+    /// No response has more than 5000 outages. If there are more than that, you'll get the first
+    /// 5000 as an `OutagesPage` with `truncated: true` and a `next_cursor` for the rest, and the
+    /// `X-Truncated: true` header.
+    ///
+    /// The `Cache-Control` header says how many more seconds the response can be cached for
+    /// before the API next checks eskom-calendar for new data. Click 'Try it out' on the right to
+    /// have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the outages for"),
+            ("merge" = Option<bool>, Query, example = true, description = "Combine back-to-back outages at the same stage into one outage. Defaults to false"),
+            ("include_past" = Option<bool>, Query, example = true, description = "Also include outages which have already finished. Defaults to false"),
+            ("tz" = Option<String>, Query, example = "utc", description = "The timezone to give times in: `utc`, an offset like `+05:30`, or an IANA name like `Europe/London`. Defaults to +02:00"),
+            ("fields" = Option<String>, Query, example = "stage,start,finsh", description = "Comma separated list of the fields to send for each outage, out of `area_name`, `stage`, `start`, `finsh`, and `source`. Defaults to all of them"),
+            ("sort" = Option<String>, Query, example = "-stage", description = "The order to give the outages in: `start`, `-start`, `stage`, or `-stage`. Defaults to `start`, which is earliest first"),
+            ("cursor" = Option<String>, Query, description = "The `next_cursor` of the previous page, to get the page after it. Defaults to the first page"),
+            ("limit" = Option<usize>, Query, example = 20, description = "The most outages to send on a page, up to 100. Defaults to sending every outage, or 100 if `cursor` is given"),
+            ("format" = Option<String>, Query, example = "csv", description = "The format to send the outages in: `json`, `csv`, or `ics`. Defaults to CSV if the `Accept` header prefers `text/csv`, and JSON otherwise"),
+            OutageFilter,
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "200 will return a list of PowerOutage objects, or an OutagesPage if `limit` or `cursor` was given.", content(
+                ("application/json" = [PowerOutage]),
+                ("text/csv" = String),
+                ("text/calendar" = String),
+            )),
+            (status = 400, description = "One of the query parameters was invalid, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>?<merge>&<include_past>&<tz>&<fields>&<sort>&<cursor>&<limit>&<format>&<filter..>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn outages(
+        area_name: String,
+        merge: Option<String>,
+        include_past: Option<String>,
+        tz: Option<String>,
+        fields: Option<String>,
+        sort: Option<String>,
+        cursor: Option<String>,
+        limit: Option<String>,
+        format: Option<String>,
+        filter: OutageFilter,
+        email: ContactEmail,
+        languages: AcceptLanguage,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<Outages>, ApiError> {
+        super::v0_0_1::outages(
+            area_name,
+            merge,
+            include_past,
+            tz,
+            fields,
+            sort,
+            cursor,
+            limit,
+            format,
+            filter,
+            email,
+            languages,
+            outage_cap,
+            cache,
+            aliases,
+        )
+        .await
+    }
+
+    /// Check whether the outages for a certain area have changed, without downloading them.
+    ///
+    /// This sends the same headers as `GET /outages/{area_name}`, but without a body. The `ETag`
+    /// and `Last-Modified` headers change whenever eskom-calendar's data changes, so scripts can
+    /// poll this cheaply and only get the outages when they need to. `format` picks the
+    /// `Content-Type` just like it does for `GET`, and the other query parameters are ignored,
+    /// since they don't change the headers.
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to check the outages of"),
+            ("format" = Option<String>, Query, example = "csv", description = "The format the outages would be sent in: `json`, `csv`, or `ics`. Defaults to CSV if the `Accept` header prefers `text/csv`, and JSON otherwise"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "The area exists. The headers describe its outages"),
+            (status = 400, description = "The format was invalid, or the email was missing when it's required"),
+            (status = 404, description = "The area couldn't be found"),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub")
+        ),
+    )]
+    #[head("/outages/<area_name>?<format>")]
+    pub async fn outages_head(
+        area_name: String,
+        format: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Cacheable<OutagesHead>, ApiError> {
+        super::v0_0_1::outages_head(area_name, format, email, cache).await
+    }
+
+    /// Wait until the outages for a certain area change, instead of having to keep asking.
+    ///
+    /// Every response from this endpoint has an `ETag` header, which changes whenever the area's
+    /// outages change. Send that back as `since`, and the API will hold the connection open until
+    /// the area's outages change and then send them (just like `/outages/{area_name}`). Changes to
+    /// other areas don't count. If nothing changes within 30 seconds (or `timeout` seconds, if
+    /// that's shorter), you'll get a `304 Not Modified` and can just ask again.
+    ///
+    /// Without `since`, or if the outages have already changed since then, you'll get the outages
+    /// straight away. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the outages for"),
+            ("since" = Option<String>, Query, example = "\"0x8DB62A3D6B6C2A1\"", description = "The `ETag` of the last response you got. Defaults to returning the outages straight away"),
+            ("timeout" = Option<usize>, Query, example = 30, description = "The most seconds to wait for the data to change. Defaults to, and can be at most, 30"),
+        ),
+        responses(
+            (status = 200, description = "The data has changed. You'll get the area's outages", body = [PowerOutage]),
+            (status = 304, description = "The data didn't change before the timeout"),
+            (status = 400, description = "The timeout was invalid", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>/longpoll?<since>&<timeout>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn outages_longpoll(
+        area_name: String,
+        since: Option<String>,
+        timeout: Option<String>,
+        email: ContactEmail,
+        languages: AcceptLanguage,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<LongPoll, ApiError> {
+        super::v0_0_1::outages_longpoll(
+            area_name, since, timeout, email, languages, outage_cap, cache, aliases,
+        )
+        .await
+    }
+
+    /// Get the outages for a certain area which start today.
+    ///
+    /// "Today" is the current day in South Africa (SAST). Outages which go over midnight are
+    /// included on the day they start, so an outage from 23:30 to 01:00 is in today's outages and
+    /// not tomorrow's. Outages from earlier today are included even if they've finished. Like
+    /// `/outages/{area_name}`, send `Accept: text/csv` to get CSV instead of JSON. Click 'Try it
+    /// out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to get today's outages for"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get today's outages, earliest first", content(
+                ("application/json" = [PowerOutage]),
+                ("text/csv" = String),
+            )),
+            (status = 400, description = "The email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>/today")]
+    pub async fn outages_today(
+        area_name: String,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Outages, ApiError> {
+        super::v0_0_1::outages_today(area_name, email, cache).await
+    }
+
+    /// Get the outages for a certain area which start tomorrow.
+    ///
+    /// This works just like `/outages/{area_name}/today`, but for tomorrow in South Africa
+    /// (SAST). An outage which starts late tonight and finishes tomorrow morning is in today's
+    /// outages, not tomorrow's. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to get tomorrow's outages for"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get tomorrow's outages, earliest first", content(
+                ("application/json" = [PowerOutage]),
+                ("text/csv" = String),
+            )),
+            (status = 400, description = "The email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>/tomorrow")]
+    pub async fn outages_tomorrow(
+        area_name: String,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Outages, ApiError> {
+        super::v0_0_1::outages_tomorrow(area_name, email, cache).await
+    }
+
+    /// Get a summary of each day of loadshedding in a certain area.
+    ///
+    /// There's one row per day from `from` to `to` (including both), saying how many minutes the
+    /// power is off, the highest stage, and how many outages there are. This is the shape most
+    /// spreadsheets want. Days are in South African time (SAST), and outages which go over
+    /// midnight are split between the days they cover, so an outage from 23:00 to 01:00 adds 60
+    /// minutes to each day. Days without any loadshedding are included with everything set to 0.
+    ///
+    /// `from` defaults to today and `to` defaults to 6 days after `from`, so you get a week. The
+    /// window can be at most 31 days long. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to summarise the outages of"),
+            ("from" = Option<String>, Query, example = "2023-06-01", description = "The first day to summarise, like `2023-06-01`. Defaults to today"),
+            ("to" = Option<String>, Query, example = "2023-06-07", description = "The last day to summarise, like `2023-06-07`. Defaults to 6 days after `from`"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get one summary per day, earliest first", body = [DailySummary]),
+            (status = 400, description = "One of the dates was invalid, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>/daily?<from>&<to>")]
+    pub async fn outages_daily(
+        area_name: String,
+        from: Option<String>,
+        to: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<DailySummary>>, ApiError> {
+        super::v0_0_1::outages_daily(area_name, from, to, email, cache).await
+    }
+
+    /// See what's changed in a certain area's outages since some time.
+    ///
+    /// The API keeps the last few versions of eskom-calendar's data, and this compares the area's
+    /// current outages with the first version fetched at or after `since`. You'll get the
+    /// outages which were `added`, the ones which were `removed`, and the ones which still start
+    /// at the same time but whose stage or finish time `changed`. `compared_with` says exactly
+    /// which version was used: if `since` is older than every version the API still has, it'll
+    /// be later than `since`. Past outages are compared too. Click 'Try it out' on the right to
+    /// have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to compare the outages of"),
+            ("since" = String, Query, example = "2023-06-01T12:00:00+02:00", description = "The ISO-8601 datetime to compare the current outages with"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get the added, removed, and changed outages", body = OutageDiff),
+            (status = 400, description = "`since` wasn't a valid datetime, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>/diff?<since>")]
+    pub async fn outages_diff(
+        area_name: String,
+        since: String,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<OutageDiff>, ApiError> {
+        super::v0_0_1::outages_diff(area_name, since, email, cache).await
+    }
+
+    /// Find out when the power will be on in a certain area.
+    ///
+    /// This is the opposite of `/outages/{area_name}`: you'll get the stretches of time between
+    /// `from` and `to` when there isn't any loadshedding, which is often what you actually want to
+    /// plan around. Outages which overlap or are back-to-back don't have a gap between them, and
+    /// the first and last gaps are cut off at `from` and `to`.
+    ///
+    /// `from` defaults to now, and `to` defaults to a week after `from`. The window can be at most
+    /// 31 days long. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to find the power-on times of"),
+            ("from" = Option<String>, Query, example = "2023-06-01T00:00:00+02:00", description = "The ISO-8601 datetime to start from. Defaults to now"),
+            ("to" = Option<String>, Query, example = "2023-06-08T00:00:00+02:00", description = "The ISO-8601 datetime to finish at. Defaults to a week after `from`"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get the times when the power is on, earliest first", body = [PowerGap]),
+            (status = 400, description = "One of the datetimes was invalid, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>/gaps?<from>&<to>")]
+    pub async fn outages_gaps(
+        area_name: String,
+        from: Option<String>,
+        to: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<PowerGap>>, ApiError> {
+        super::v0_0_1::outages_gaps(area_name, from, to, email, cache).await
+    }
+
+    /// Get the outages for every area at once, for keeping your own copy of the whole dataset.
+    ///
+    /// This is one big response, so it's split into pages of at most 500 areas (in alphabetical
+    /// order). Use `offset` to get the next page, until `offset` reaches `total`. Send
+    /// `Accept-Encoding: gzip` to have each page compressed. The same filters as
+    /// `/outages/{area_name}` are applied to every area, and areas without any outages matching
+    /// them are left out. Unlike `/outages/{area_name}`, outages which have already finished are
+    /// included unless you filter them out with `start`. A page never has more than 5000 outages,
+    /// so a page with lots of outages can have fewer areas than `limit`. It then has
+    /// `truncated: true`, and the next page starts at `offset` plus the number of areas on it.
+    /// Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("limit" = Option<usize>, Query, example = 500, description = "The most areas to return. Defaults to, and can be at most, 500"),
+            ("offset" = Option<usize>, Query, example = 0, description = "How many areas to skip. Defaults to 0"),
+            OutageFilter,
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get a page of areas and their outages", body = AllOutages),
+            (status = 400, description = "One of the query parameters was invalid, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/all?<limit>&<offset>&<filter..>")]
+    pub async fn outages_all(
+        limit: Option<String>,
+        offset: Option<String>,
+        filter: OutageFilter,
+        email: ContactEmail,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Json<AllOutages>>, ApiError> {
+        super::v0_0_1::outages_all(limit, offset, filter, email, outage_cap, cache).await
+    }
+
+    /// Get all the known times when power will be off for several areas at once.
+    ///
+    /// `areas` is a comma separated list of up to 100 area names, each of which should be one of
+    /// the ones listed in the endpoint `list_areas`. Any areas which can't be found will have an
+    /// empty list of outages and will be listed in `not_found`, instead of failing the whole
+    /// request. The same filters as `/outages/{area_name}` can be applied. At most 5000 outages
+    /// are sent between all the areas, and if some are left out the response has
+    /// `truncated: true`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("areas" = String, Query, example="western-cape-stellenbosch,city-of-cape-town-area-15", description = "Comma separated list of up to 100 areas to get the outages for"),
+            OutageFilter,
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get the outages for each area", body = AreasOutages),
+            (status = 400, description = "One of the query parameters was invalid, more than 100 areas were asked for, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages?<areas>&<filter..>")]
+    pub async fn outages_for_areas(
+        areas: String,
+        filter: OutageFilter,
+        email: ContactEmail,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Json<AreasOutages>>, ApiError> {
+        super::v0_0_1::outages_for_areas(areas, filter, email, outage_cap, cache).await
+    }
+
+    /// Get all the known times when power will be off for a certain area, as a calendar.
+    ///
+    /// This returns an iCalendar (.ics) file, which you can subscribe to from Google Calendar,
+    /// Outlook, Apple Calendar, etc. Each outage is one event, and events keep the same UID across
+    /// updates so your calendar app won't show duplicates. The calendar has at most 5000 events
+    /// (the earliest ones), and has the `X-Truncated: true` header if any were left out. The
+    /// `area_name` must be one of the ones listed in the endpoint `list_areas`. Click 'Try it
+    /// out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the outages for"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get an iCalendar file with one event per outage", body = String, content_type = "text/calendar"),
+            (status = 400, description = "The email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outages/<area_name>/calendar.ics")]
+    pub async fn outages_ics(
+        area_name: String,
+        email: ContactEmail,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Ics>, ApiError> {
+        super::v0_0_1::outages_ics(area_name, email, outage_cap, cache).await
+    }
+
+    /// Get eskom-calendar's `machine_friendly.csv`, exactly as it is on GitHub.
+    ///
+    /// This is the file that all of the API's outages come from, with one row per outage and the
+    /// columns `area_name,start,finsh,stage,source`. If you'd rather parse it yourself, getting it
+    /// from here instead of GitHub means you'll get the API's cached copy. The `ETag` header
+    /// changes whenever the file does. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. You'll get machine_friendly.csv", body = String, content_type = "text/csv"),
+        (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+    ))]
+    #[get("/machine_friendly.csv")]
+    pub async fn machine_friendly_csv(
+        cache: &State<OutageCache>,
+    ) -> Result<MachineFriendlyCsv, ApiError> {
+        super::v0_0_1::machine_friendly_csv(cache).await
+    }
+
+    /// Get all the known times when power will be off anywhere in a certain province, as one
+    /// calendar.
+    ///
+    /// This is like `/outages/{area_name}/calendar.ics`, but with the outages of every area in the
+    /// province, which is handy if you move between towns. Each event's title says which area it's
+    /// for. Outages which have already finished are left out, and the calendar has at most 1000
+    /// events (the earliest ones) so that calendar apps can cope with it, with the `X-Truncated:
+    /// true` header if any were left out. The province is written like `western-cape` or
+    /// `kwazulu-natal`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("province" = String, example = "western-cape", description = "The province, written like `western-cape` or `kwazulu-natal`")),
+        responses(
+            (status = 200, description = "Success. You'll get an iCalendar file with one event per outage", body = String, content_type = "text/calendar"),
+            (status = 400, description = "The province isn't one of South Africa's nine provinces", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/province/<province>/outages.ics")]
+    pub async fn province_outages_ics(
+        province: String,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Ics>, ApiError> {
+        super::v0_0_1::province_outages_ics(province, outage_cap, cache).await
+    }
+
+    /// Check if a certain area is being loadshed right now.
+    ///
+    /// Along with a simple `is_shedding` true/false, this gives the outage that's happening right
+    /// now (if any) and the next outage after that. The `area_name` must be one of the ones listed
+    /// in the endpoint `list_areas`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the current status of")),
+        responses(
+            (status = 200, description = "Success. You'll get the area's current status", body = CurrentStatus),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/current/<area_name>")]
+    pub async fn current(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<CurrentStatus>, ApiError> {
+        super::v0_0_1::current(area_name, cache).await
+    }
+
+    /// Check if several areas are being loadshed right now.
+    ///
+    /// This is the same as `/current/{area_name}`, but for up to 100 areas at once, which is much
+    /// faster than asking about each area separately. Send the area names as JSON like `{"areas":
+    /// ["western-cape-stellenbosch", "city-of-cape-town-area-15"]}`. Areas which eskom-calendar
+    /// doesn't know about are listed in `not_found`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        request_body = AreasRequest,
+        responses(
+            (status = 200, description = "Success. You'll get the current status of each area", body = AreasCurrentStatus),
+            (status = 400, description = "Too many areas were asked about", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[post("/current", format = "json", data = "<request>")]
+    pub async fn current_for_areas(
+        request: Json<AreasRequest>,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<AreasCurrentStatus>, ApiError> {
+        super::v0_0_1::current_for_areas(request, cache).await
+    }
+
+    /// Be told whenever an area's loadshedding stage changes, instead of having to keep asking.
+    ///
+    /// Send the area, the URL of your webhook, and a contact email as JSON like `{"area":
+    /// "western-cape-stellenbosch", "callback_url": "https://example.com/webhook", "email":
+    /// "you@example.com"}`. Whenever the area's stage changes (including when loadshedding starts
+    /// or stops, which is stage 0), a `StageChangeNotification` will be `POST`ed to your webhook.
+    ///
+    /// You'll get back a `secret` which is only sent once. Every notification has an
+    /// `X-Signature-256` header of `sha256=` followed by the hex HMAC-SHA256 of the body, keyed
+    /// with that secret, so you can check that the notification really came from this API. Click
+    /// 'Try it out' on the right to have a go!
+    ///
+    /// The webhook has to be on the public internet, and it has to answer directly since
+    /// redirects aren't followed. Each email and IP address can have up to 20 webhooks at once.
+    #[utoipa::path(
+        request_body = SubscribeRequest,
+        responses(
+            (status = 201, description = "Success. The webhook is subscribed", body = Subscription),
+            (status = 400, description = "The callback URL or email was invalid", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 429, description = "Too many webhooks are already subscribed", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[post("/subscribe", format = "json", data = "<request>")]
+    pub async fn subscribe(
+        request: Json<SubscribeRequest>,
+        cache: &State<OutageCache>,
+        webhooks: &State<Webhooks>,
+        ip: ClientIp,
+    ) -> Result<(Status, Json<Subscription>), ApiError> {
+        super::v0_0_1::subscribe(request, cache, webhooks, ip).await
+    }
+
+    /// Stop being told about an area's stage changes.
+    ///
+    /// Send the `secret` you got when subscribing as an `Authorization: Bearer <secret>` header,
+    /// so that nobody else can unsubscribe your webhook.
+    #[utoipa::path(
+        params(("id" = String, example="7c4b4d3e9a0f4f0c8b0a6f0e5d2c1b3a", description = "The ID of the subscription")),
+        responses(
+            (status = 204, description = "Success. The webhook won't be sent any more notifications"),
+            (status = 404, description = "There's no subscription with that ID and secret", body = ApiErrorBody)
+        ),
+    )]
+    #[delete("/subscribe/<id>")]
+    pub async fn unsubscribe(
+        id: String,
+        secret: SubscriptionSecret,
+        webhooks: &State<Webhooks>,
+    ) -> Result<Status, ApiError> {
+        super::v0_0_1::unsubscribe(id, secret, webhooks).await
+    }
+
+    /// Get the next power outage for a certain area.
+    ///
+    /// This skips any outage that's already in progress (use `/current/{area_name}` for that), and
+    /// tells you how many minutes there are until the next outage starts. If there are no upcoming
+    /// outages, you'll get a `204 No Content` with an empty body. The `area_name` must be one of
+    /// the ones listed in the endpoint `list_areas`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the next outage for")),
+        responses(
+            (status = 200, description = "Success. You'll get the next outage and the minutes until it starts", body = NextOutage),
+            (status = 204, description = "There are no upcoming outages for this area"),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/next/<area_name>")]
+    pub async fn next(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<MaybeNextOutage, ApiError> {
+        super::v0_0_1::next(area_name, cache).await
+    }
+
+    /// Get the loadshedding stage of the whole country right now.
+    ///
+    /// Different areas can technically be at different stages, so this is the highest stage that
+    /// any area is at right now. You'll also get when the country went to that stage, and when
+    /// the stage will next change (if that's known). Click 'Try it out' on the right to have a
+    /// go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. You'll get the current national stage", body = NationalStage),
+        (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+    ))]
+    #[get("/national_stage")]
+    pub async fn national_stage(
+        cache: &State<OutageCache>,
+    ) -> Result<Json<NationalStage>, ApiError> {
+        super::v0_0_1::national_stage(cache).await
+    }
+
+    /// Get an outlook of the stages of loadshedding for a certain area over the next 48 hours.
+    ///
+    /// This collapses the area's outages into a timeline of segments, each with one stage. Times
+    /// without loadshedding are included as stage 0, so the segments cover the whole 48 hours
+    /// without any gaps. If outages overlap, the highest stage is used. The `area_name` must be
+    /// one of the ones listed in the endpoint `list_areas`. Click 'Try it out' on the right to
+    /// have a go!
+    #[utoipa::path(
+        params(("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the outlook for")),
+        responses(
+            (status = 200, description = "Success. You'll get the stage timeline for the next 48 hours", body = [OutlookSegment]),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/outlook/<area_name>")]
+    pub async fn outlook(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<OutlookSegment>>, ApiError> {
+        super::v0_0_1::outlook(area_name, cache).await
+    }
+
+    /// Get the loadshedding schedule for a certain area.
+    ///
+    /// Note that this does *not* describe when the power will be off (use `/outages/{area_name}`
+    /// instead). The `area_name` must be one of the ones listed in the endpoint `list_areas`,
+    /// although case and separators don't matter.
+    ///
+    /// Where eskom-calendar knows it, the schedule says where it came from and when it's valid.
+    /// Otherwise `source` and `info` are empty and the dates are null. Any rows of the schedule
+    /// which couldn't be understood are left out and described in `warnings`.
+    ///
+    /// Schedules sometimes get replaced by new ones. You'll get the version of the schedule that's
+    /// in use today, or on the date `at`. If eskom-calendar doesn't have a version of the schedule
+    /// for that date, you'll get the latest version, so check `valid_from` and `valid_until`.
+    /// Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="north-west-zeerust", description = "The name of the area you want the schedule for"),
+            ("at" = Option<String>, Query, example = "2023-06-01", description = "The date to get the schedule for, like `2023-06-01`. Defaults to today"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get a Recurring Schedule", body = RecurringSchedule),
+            (status = 400, description = "The date was invalid", body = ApiErrorBody),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/schedules/<area_name>?<at>")]
+    pub async fn schedules(
+        area_name: String,
+        at: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<RecurringSchedule>, ApiError> {
+        super::v0_0_1::schedules(area_name, at, email, cache).await
+    }
+
+    /// Compare how much loadshedding a certain area would get at several different stages.
+    ///
+    /// This expands the area's schedule at each of `stages` (like `2,4,6`) between `from` and
+    /// `to`, in the same way as `/schedules/{area_name}/expand`. For each stage you get the
+    /// outages, along with how many hours the power would be off in total, so you can see how
+    /// much worse stage 6 is than stage 4 for you.
+    ///
+    /// `from` defaults to now, and `to` defaults to a week after `from`. The window can be at most
+    /// 31 days long. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "The name of the area you want to compare stages for"),
+            ("stages" = String, Query, example = "2,4,6", description = "A comma separated list of stages to compare, each between 0 and 8"),
+            ("from" = Option<String>, Query, example = "2023-06-05T00:00:00+02:00", description = "The ISO-8601 datetime to start from. Defaults to now"),
+            ("to" = Option<String>, Query, example = "2023-06-12T00:00:00+02:00", description = "The ISO-8601 datetime to finish at. Defaults to a week after `from`"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get the outages and total hours off at each stage", body = ScheduleComparison),
+            (status = 400, description = "One of the query parameters was invalid, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area doesn't have a schedule", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/schedules/<area_name>/compare?<stages>&<from>&<to>")]
+    pub async fn schedules_compare(
+        area_name: String,
+        stages: String,
+        from: Option<String>,
+        to: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<ScheduleComparison>, ApiError> {
+        super::v0_0_1::schedules_compare(area_name, stages, from, to, email, cache).await
+    }
+
+    /// Work out when the power will be off in a certain area if it's at a certain stage.
+    ///
+    /// This takes the area's schedule and works out the actual outages at `stage` between `from`
+    /// and `to`. Stages are cumulative, so at stage 4 the outages for stages 1 to 4 all apply.
+    /// This is useful for planning ahead ("if we're at stage 4 next week, when will my power be
+    /// off?"), but the outages from `/outages/{area_name}` are the ones to use for what's actually
+    /// going to happen.
+    ///
+    /// `from` defaults to now, and `to` defaults to a week after `from`. The window can be at most
+    /// 31 days long.
+    ///
+    /// Each outage says whether it starts on a `weekday`, a `weekend`, or a `public_holiday`
+    /// (along with the holiday's name), since some municipalities shed load differently on those
+    /// days. South Africa's public holidays are used unless you ask for `calendar=none`. Click
+    /// 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "The name of the area you want outages for"),
+            ("stage" = u8, Query, example = 4, description = "The stage of loadshedding, between 0 and 8"),
+            ("from" = Option<String>, Query, example = "2023-06-05T00:00:00+02:00", description = "The ISO-8601 datetime to start from. Defaults to now"),
+            ("to" = Option<String>, Query, example = "2023-06-12T00:00:00+02:00", description = "The ISO-8601 datetime to finish at. Defaults to a week after `from`"),
+            ("calendar" = Option<String>, Query, example = "za", description = "Which public holidays to use when working out each outage's `day_type`: `za` for South Africa's, or `none`. Defaults to `za`"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get a list of ExpandedOutage objects", body = [ExpandedOutage]),
+            (status = 400, description = "One of the query parameters was invalid, or the email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area doesn't have a schedule", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/schedules/<area_name>/expand?<stage>&<from>&<to>&<calendar>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn schedules_expand(
+        area_name: String,
+        stage: String,
+        from: Option<String>,
+        to: Option<String>,
+        calendar: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+        holidays: &State<PublicHolidays>,
+    ) -> Result<Json<Vec<ExpandedOutage>>, ApiError> {
+        super::v0_0_1::schedules_expand(
+            area_name, stage, from, to, calendar, email, cache, holidays,
+        )
+        .await
+    }
+
+    /// Check a certain area's schedule for likely mistakes.
+    ///
+    /// This is meant for people who help keep eskom-calendar's data correct. It reports any
+    /// outages which overlap another outage at the same stage on the same day, and any days
+    /// which don't have outages at a stage that the schedule's other days do (for example, a
+    /// weekly schedule with stage 2 outages on every day except Thursday). Neither is necessarily
+    /// wrong, but both are worth a second look. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("area_name" = String, example="western-cape-stellenbosch", description = "The name of the area whose schedule to check")),
+        responses(
+            (status = 200, description = "Success. You'll get a report of any problems found", body = ScheduleReport),
+            (status = 404, description = "The area doesn't have a schedule", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/schedules/<area_name>/validate")]
+    pub async fn schedules_validate(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<ScheduleReport>, ApiError> {
+        super::v0_0_1::schedules_validate(area_name, cache).await
+    }
+
+    /// Get the loadshedding schedule for a certain area, as a calendar of recurring events.
+    ///
+    /// Unlike `/outages/{area_name}/calendar.ics`, this doesn't say when the power will actually
+    /// be off. Each of the schedule's outages is one event which repeats with an `RRULE`, weekly,
+    /// monthly, or every so many days, so that your calendar app can show the whole pattern. The
+    /// events start from when the schedule is valid (or today, if that isn't known) and stop
+    /// repeating when it's no longer valid.
+    ///
+    /// Stages are cumulative, so use `stage` to only get the outages that happen at that stage.
+    /// Otherwise every outage is included, and each says the lowest stage it happens at. Click
+    /// 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "The name of the area you want the schedule for"),
+            ("stage" = Option<u8>, Query, example = 4, description = "Only include the outages at this stage of loadshedding, between 0 and 8. Defaults to including every outage"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get an iCalendar file with one recurring event per outage in the schedule", body = String, content_type = "text/calendar"),
+            (status = 400, description = "The stage was invalid", body = ApiErrorBody),
+            (status = 404, description = "The area doesn't have a schedule", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/schedules/<area_name>/calendar.ics?<stage>")]
+    pub async fn schedules_ics(
+        area_name: String,
+        stage: Option<String>,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Ics, ApiError> {
+        super::v0_0_1::schedules_ics(area_name, stage, email, cache).await
+    }
+
+    /// Get the loadshedding schedule for a certain area, as a CSV file like the ones
+    /// eskom-calendar keeps its schedules in.
+    ///
+    /// The columns depend on how the schedule repeats: monthly schedules have a `date_of_month`
+    /// column, weekly schedules have a `day_of_week` column, and schedules which repeat every so
+    /// many days have `day_of_cycle`, `period_of_cycle`, and `start_of_cycle` columns. This is
+    /// handy if you're contributing a schedule and want to start from an existing one. Click 'Try
+    /// it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("area_name" = String, example="western-cape-stellenbosch", description = "The name of the area you want the schedule for"),
+            ("email" = Option<String>, Query, example = "you@example.com", description = "A contact email, so that we can get in touch if your app is using the API a lot"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get the schedule as CSV", body = String, content_type = "text/csv"),
+            (status = 400, description = "The email was missing when it's required", body = ApiErrorBody),
+            (status = 404, description = "The area doesn't have a schedule", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/schedules/<area_name>/raw.csv")]
+    pub async fn schedules_raw_csv(
+        area_name: String,
+        email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<(ContentType, String), ApiError> {
+        super::v0_0_1::schedules_raw_csv(area_name, email, cache).await
+    }
+
+    /// Get figures describing loadshedding across the whole country.
+    ///
+    /// This gives how many areas are being loadshed right now, how many areas are at each stage,
+    /// and how many hours of loadshedding are scheduled today added up across every area. Click
+    /// 'Try it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. You'll get the national figures", body = NationalStats),
+        (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+    ))]
+    #[get("/stats")]
+    pub async fn national_stats(
+        cache: &State<OutageCache>,
+    ) -> Result<Json<NationalStats>, ApiError> {
+        super::v0_0_1::national_stats(cache).await
+    }
+
+    /// Find out what each stage of loadshedding means.
+    ///
+    /// This gives a description of every stage from 0 to 8, along with roughly how many times a
+    /// day an area's power is turned off at that stage and how many hours a day it's off in
+    /// total. These are rough guides, since every municipality has its own schedules. Click 'Try
+    /// it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. You'll get every stage, from 0 to 8", body = [StageInfo])
+    ))]
+    #[get("/stages")]
+    pub async fn stage_reference() -> Json<Vec<StageInfo>> {
+        super::v0_0_1::stage_reference().await
+    }
+
+    /// Get figures describing how badly a certain area is affected by loadshedding.
+    ///
+    /// These are worked out from all of the area's known outages: how many there are, how many
+    /// hours of loadshedding they add up to (in total and at each stage), how long the average
+    /// outage is, and which day has the most loadshedding. The `area_name` must be one of the
+    /// ones listed in the endpoint `list_areas`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("area_name" = String, example="western-cape-stellenbosch", description = "Area to get the figures for")),
+        responses(
+            (status = 200, description = "Success. You'll get the figures for the area", body = AreaStats),
+            (status = 404, description = "The area couldn't be found", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/stats/<area_name>")]
+    pub async fn stats(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<AreaStats>, ApiError> {
+        super::v0_0_1::stats(area_name, cache).await
+    }
+
+    /// Get a list of all areas known to eskom-calendar.
+    ///
+    /// Each area name is unique, and describes a different `Area` that can get loadshedding.
+    ///
+    /// There are a lot of areas, so you can get them one page at a time with `limit` and
+    /// `offset`. By default every area is returned on one page. Areas with a name in the language
+    /// asked for with the `Accept-Language` header have it in `localised_names`. Click 'Try it
+    /// out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("limit" = Option<usize>, Query, example = 100, description = "The most areas to return. Defaults to returning every area"),
+            ("offset" = Option<usize>, Query, example = 0, description = "How many areas to skip. Defaults to 0"),
+        ),
+        responses(
+            (status = 200, description = "Success. A page of the areas known to eskom-calendar.", body = AreasPage),
+            (status = 400, description = "The limit or offset wasn't a non-negative whole number", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/list_areas?<limit>&<offset>")]
+    pub async fn list_all_areas(
+        limit: Option<String>,
+        offset: Option<String>,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<StreamedAreasPage>, ApiError> {
+        super::v0_0_1::list_all_areas(limit, offset, languages, cache, aliases).await
+    }
+
+    /// Search for areas by a rust-regex.
+    ///
+    /// Have a look [here](https://regex101.com/r/XspP8R/1) to try out your query on a long list of
+    /// areas. Don't forget to [URI escape](https://en.wikipedia.org/wiki/URL_encoding) your query
+    /// before you try to send it.
+    ///
+    /// By default an area matches if any part of its name matches the regex, so `stellenbosch`
+    /// finds `western-cape-stellenbosch`. To only find areas whose whole name matches, use
+    /// `anchored=true`, which is the same as wrapping the regex in `^(?:` and `)$`.
+    ///
+    /// Like `list_areas`, you can page through the results with `limit` and `offset`, and get the
+    /// areas' names in your language with the `Accept-Language` header. Regexes
+    /// longer than 1000 characters, or which are too complex (like `a{1000}{1000}`), are
+    /// rejected with a `400 Bad Request`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("regex" = String, example="\\w+(ville|water)", description = "Valid Rust regex describing the place you're looking for."),
+            ("limit" = Option<usize>, Query, example = 100, description = "The most areas to return. Defaults to returning every area"),
+            ("offset" = Option<usize>, Query, example = 0, description = "How many areas to skip. Defaults to 0"),
+            ("anchored" = Option<bool>, Query, example = false, description = "Only find areas whose whole name matches the regex, instead of any part of it. Defaults to false"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get a page of areas matching your regex", body = AreasPage),
+            (status = 400, description = "The regex was invalid, too long, or too complex, or the limit, offset, or anchored was invalid", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/list_areas/<regex>?<limit>&<offset>&<anchored>")]
+    pub async fn list_areas(
+        regex: String,
+        limit: Option<String>,
+        offset: Option<String>,
+        anchored: Option<String>,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<StreamedAreasPage>, ApiError> {
+        super::v0_0_1::list_areas(regex, limit, offset, anchored, languages, cache, aliases).await
+    }
+
+    /// Check whether the API can serve data, for uptime monitoring.
+    ///
+    /// This checks whether GitHub (where eskom-calendar's data lives) can be reached without
+    /// downloading everything, and describes the cached data. If GitHub can't be reached but the
+    /// API has cached data, the status is `degraded` since the API still works (with slightly
+    /// older data). If there's no cached data either, the status is `down` and you'll get a `503
+    /// Service Unavailable`. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "The API is working, although it might be degraded", body = Health),
+        (status = 503, description = "The API can't serve any data", body = Health)
+    ))]
+    #[get("/health")]
+    pub async fn health(cache: &State<OutageCache>) -> (Status, Json<Health>) {
+        super::v0_0_1::health(cache).await
+    }
+
+    /// Perform a health check on the system and its dependencies. Right now this only checks if
+    /// the API is responsive. You can also see the system health
+    /// [here](https://stats.uptimerobot.com/l9gAnCO16z).
+    #[utoipa::path(responses(
+        (status = 200, description = "A HealthCheck struct containing details about the health of the system and the datetime at which the health was captured.", body = HealthCheck)
+    ))]
+    #[get("/health_check")]
+    pub async fn health_check() -> Result<Json<HealthCheck>, ApiError> {
+        super::v0_0_1::health_check().await
+    }
+
+    /// Get the names of all the areas in a certain municipality.
+    ///
+    /// The municipality can be a metro (like `city-of-cape-town`) or a local municipality (like
+    /// `stellenbosch`). The municipality of an area is worked out from its name, which is only a
+    /// best guess. So the results are approximate: some areas in the municipality might be
+    /// missing. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("municipality" = String, example = "stellenbosch", description = "The metro or local municipality, written like `city-of-cape-town` or `stellenbosch`")),
+        responses(
+            (status = 200, description = "Success. You'll get a list of area names in alphabetical order", body = [String]),
+            (status = 400, description = "The municipality isn't a known metro or local municipality", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/areas/municipality/<municipality>")]
+    pub async fn areas_in_municipality(
+        municipality: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<String>>, ApiError> {
+        super::v0_0_1::areas_in_municipality(municipality, cache).await
+    }
+
+    /// Get the names of all the areas in a certain province.
+    ///
+    /// The province is written like `western-cape` or `kwazulu-natal`. Areas whose province can't
+    /// be worked out from their name (like `city-of-cape-town-area-15`) aren't included. Click
+    /// 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(("province" = String, example = "western-cape", description = "The province, written like `western-cape` or `kwazulu-natal`")),
+        responses(
+            (status = 200, description = "Success. You'll get a list of area names in alphabetical order", body = [String]),
+            (status = 400, description = "The province isn't one of South Africa's nine provinces", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/areas/province/<province>")]
+    pub async fn areas_in_province(
+        province: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<String>>, ApiError> {
+        super::v0_0_1::areas_in_province(province, cache).await
+    }
+
+    /// List South Africa's nine provinces.
+    ///
+    /// Each province has the `slug` that eskom-calendar starts area names with (and that
+    /// `/areas/province/{province}` expects), along with its usual name for showing to people.
+    /// This never changes, so it's handy for building filters. Click 'Try it out' on the right to
+    /// have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. You'll get the provinces in alphabetical order", body = [ProvinceInfo])
+    ))]
+    #[get("/provinces")]
+    pub async fn provinces() -> Json<Vec<ProvinceInfo>> {
+        super::v0_0_1::provinces().await
+    }
+
+    /// List South Africa's municipalities.
+    ///
+    /// This has every metro, district, and local municipality, and says which kind each one is.
+    /// Local municipalities also say which district they're part of. The `slug` of a metro or
+    /// local municipality is what `/areas/municipality/{municipality}` expects. This never
+    /// changes, so it's handy for building filters. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. You'll get the metros, then the districts, then the local municipalities", body = [MunicipalityInfo])
+    ))]
+    #[get("/municipalities")]
+    pub async fn municipalities() -> Json<Vec<MunicipalityInfo>> {
+        super::v0_0_1::municipalities().await
+    }
+
+    /// Find areas whose name starts with (or contains) some text, for autocompleting as you type.
+    ///
+    /// Unlike `/fuzzy_search/{query}`, this doesn't forgive typos, but it's fast enough to call on
+    /// every keystroke. Areas whose name or one of whose aliases starts with `query` come first,
+    /// followed by areas which only contain `query` somewhere in their name or aliases. Each group
+    /// is in alphabetical order. Capitals and punctuation are ignored, so `western cape st`
+    /// matches `western-cape-stellenbosch`. Only the first 10 areas are returned unless you ask
+    /// for more with `limit`. Like `/area/{name}`, areas' names are localised with the
+    /// `Accept-Language` header. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(
+        params(
+            ("query" = String, example = "western-cape-st", description = "The start of (or some part of) the area's name"),
+            ("limit" = Option<usize>, Query, example = 10, description = "The most areas to return. Defaults to 10"),
+        ),
+        responses(
+            (status = 200, description = "Success. You'll get a list of matching areas", body = [Area]),
+            (status = 400, description = "The query had no letters or numbers, or the limit was invalid", body = ApiErrorBody),
+            (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+        ),
+    )]
+    #[get("/areas/search/<query>?<limit>")]
+    pub async fn areas_search(
+        query: String,
+        limit: Option<String>,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Json<Vec<Area>>, ApiError> {
+        super::v0_0_1::areas_search(query, limit, languages, cache, aliases).await
+    }
+
+    /// Get the names of all areas, grouped by province and then by municipality.
+    ///
+    /// This is handy for letting users pick their area by first choosing their province and then
+    /// their municipality. The response looks like `{"western-cape": {"stellenbosch":
+    /// ["western-cape-stellenbosch"]}}`. The province and municipality of an area are worked out
+    /// from its name, and areas where that isn't possible go under `unknown` instead. Click 'Try
+    /// it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. You'll get the area names grouped by province and municipality", body = Object, example = json!({
+            "western-cape": {
+                "stellenbosch": ["western-cape-stellenbosch"],
+                "unknown": ["western-cape-somewhere"]
+            },
+            "unknown": {
+                "city-of-cape-town": ["city-of-cape-town-area-15"]
+            }
+        })),
+        (status = 502, description = "eskom-calendar's data couldn't be fetched from GitHub", body = ApiErrorBody)
+    ))]
+    #[get("/areas/tree")]
+    pub async fn areas_tree(
+        cache: &State<OutageCache>,
+    ) -> Result<Json<BTreeMap<String, BTreeMap<String, Vec<String>>>>, ApiError> {
+        super::v0_0_1::areas_tree(cache).await
+    }
+
+    /// Check how fresh the API's copy of eskom-calendar's data is.
+    ///
+    /// The API keeps a copy of eskom-calendar's data for a few minutes before checking GitHub for
+    /// any changes. This tells you when that last happened, and the ETag of the data (which
+    /// changes whenever the data changes). If any outages had to be left out because they
+    /// couldn't be understood, they're described in `warnings`. Click 'Try it out' on the right
+    /// to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. Details about the cached data.", body = CacheStatus)
+    ))]
+    #[get("/cache_status")]
+    pub async fn cache_status(cache: &State<OutageCache>) -> Result<Json<CacheStatus>, ApiError> {
+        super::v0_0_1::cache_status(cache).await
     }
 
-    /// Get the loadshedding schedule for a certain area.
+    /// Get metrics about the API in the Prometheus text format, for monitoring.
     ///
-    /// Note that this does *not* describe when the power will be off (use `/outages/{area_name}`
-    /// instead). The `area_name` must be one of the ones listed in the endpoint `list_areas`.
-    /// Click 'Try it out' on the right to have a go!
-    #[utoipa::path(
-        params(("area_name" = String, example="north-west-zeerust", description = "The name of the area you want the schedule for")),
-        responses(
-            (status = 200, description = "Success. You'll get a Recurring Schedule", body = RecurringSchedule)
-        ),
-    )]
-    #[get("/schedules/<area_name>")]
-    pub async fn schedules(area_name: String) -> Result<Json<RecurringSchedule>, String> {
-        super::v0_0_1::schedules(area_name).await
+    /// This includes how many requests each endpoint has handled (by status code), how long they
+    /// took, how many requests have been made to GitHub, and how often the cached data could be
+    /// used. Requests to `/metrics` aren't counted. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. The metrics in the Prometheus text format", body = String, content_type = "text/plain")
+    ))]
+    #[get("/metrics")]
+    pub async fn metrics(metrics: &State<Metrics>, cache: &State<OutageCache>) -> PrometheusText {
+        super::v0_0_1::metrics(metrics, cache).await
     }
 
-    /// Get a list of all areas known to eskom-calendar.
+    /// Find out how many more requests you can make before being rate limited.
     ///
-    /// Each area name is unique, and describes a different `Area` that can get loadshedding. Click
-    /// 'Try it out' on the right to have a go!
+    /// Each IP address can make a limited number of requests per minute, which trickle back in
+    /// over time. This tells you the limit, how many requests you have left, and when you'll have
+    /// all of them again. Asking doesn't count as a request. Every other response has the same
+    /// information in its `X-RateLimit-Limit`, `X-RateLimit-Remaining`, and `X-RateLimit-Reset`
+    /// (a Unix timestamp) headers. Click 'Try it out' on the right to have a go!
     #[utoipa::path(responses(
-        (status = 200, description = "Success. A list of every area known to eskom-calendar.", body = [String])
+        (status = 200, description = "Success. Your rate limit", body = RateLimitStatus),
+        (status = 404, description = "Requests aren't being rate limited", body = ApiErrorBody)
     ))]
-    #[get("/list_areas")]
-    pub async fn list_all_areas() -> Result<Json<Vec<String>>, String> {
-        super::v0_0_1::list_all_areas().await
+    #[get("/ratelimit")]
+    pub async fn ratelimit(
+        status: Option<RateLimitStatus>,
+    ) -> Result<Json<RateLimitStatus>, ApiError> {
+        super::v0_0_1::ratelimit(status).await
     }
 
-    /// Search for areas by a rust-regex.
+    /// Get TypeScript types for everything the API sends and receives.
     ///
-    /// Have a look [here](https://regex101.com/r/XspP8R/1) to try out your query on a long list of
-    /// areas. Don't forget to [URI escape](https://en.wikipedia.org/wiki/URL_encoding) your query
-    /// before you try to send it. Click 'Try it out' on the right to have a go!
-    #[utoipa::path(
-        params(("regex" = String, example="\\w+(ville|water)", description = "Valid Rust regex describing the place you're looking for.")),
-        responses(
-            (status = 200, description = "Success. You'll get a list of areas matching your regex", body = [String])
-        ),
-    )]
-    #[get("/list_areas/<regex>")]
-    pub async fn list_areas(regex: String) -> Result<Json<Vec<String>>, String> {
-        super::v0_0_1::list_areas(regex).await
+    /// Save this as `eskom-calendar-api.d.ts` in your project to get types like `PowerOutage` and
+    /// `Area` without needing a code generator. The types are generated from this API's OpenAPI
+    /// spec, so they're always up to date. Click 'Try it out' on the right to have a go!
+    #[utoipa::path(responses(
+        (status = 200, description = "Success. TypeScript declarations for the API's types", body = String, content_type = "application/typescript")
+    ))]
+    #[get("/clients/typescript.d.ts")]
+    pub async fn typescript_client(types: &State<TypeScriptTypes>) -> TypeScriptFile {
+        super::v0_0_1::typescript_client(types).await
     }
 
-    /// Perform a health check on the system and its dependencies. Right now this only checks if
-    /// the API is responsive. You can also see the system health
-    /// [here](https://stats.uptimerobot.com/l9gAnCO16z).
+    /// Find out which deployment of the API you're talking to, and how fresh its data is.
+    ///
+    /// This gives the version of the API, the git commit it was built from and when it was built,
+    /// along with when the API last checked GitHub for new data and the ETag of that data. Click
+    /// 'Try it out' on the right to have a go!
     #[utoipa::path(responses(
-        (status = 200, description = "A HealthCheck struct containing details about the health of the system and the datetime at which the health was captured.", body = HealthCheck)
+        (status = 200, description = "Success. Details about the running API", body = Version)
     ))]
-    #[get("/health_check")]
-    pub async fn health_check() -> Result<Json<HealthCheck>, String> {
-        super::v0_0_1::health_check().await
+    #[get("/version")]
+    pub async fn version(cache: &State<OutageCache>) -> Json<Version> {
+        super::v0_0_1::version(cache).await
     }
 }
 
@@ -145,178 +2611,1423 @@ pub mod v0_0_1 {
 
     pub fn routes() -> Vec<rocket::Route> {
         routes![
+            area,
+            area_at,
+            area_by_id,
+            area_siblings,
+            areas_in_municipality,
+            areas_in_province,
+            areas_search,
+            areas_tree,
+            cache_status,
+            current,
+            current_for_areas,
             fuzzy_search,
+            health,
             health_check,
             list_all_areas,
             list_areas,
+            machine_friendly_csv,
+            metrics,
+            municipalities,
+            national_stage,
+            national_stats,
+            next,
             outages,
+            outages_all,
+            outages_daily,
+            outages_diff,
+            outages_for_areas,
+            outages_gaps,
+            outages_head,
+            outages_ics,
+            outages_longpoll,
+            outages_today,
+            outages_tomorrow,
+            outlook,
+            province_outages_ics,
+            provinces,
+            ratelimit,
             schedules,
+            schedules_compare,
+            schedules_expand,
+            schedules_ics,
+            schedules_raw_csv,
+            schedules_validate,
+            search,
+            stage_reference,
+            stats,
+            subscribe,
+            typescript_client,
+            unsubscribe,
+            version,
         ]
     }
 
     #[utoipa::path(context_path = "/v0.0.1")]
     #[get("/health_check")]
-    pub async fn health_check() -> Result<Json<HealthCheck>, String> {
-        let sast_timezone = FixedOffset::east_opt(2 * 60 * 60).unwrap();
+    pub async fn health_check() -> Result<Json<HealthCheck>, ApiError> {
         Ok(Json(HealthCheck {
-            date_time: chrono::offset::Local::now()
-                .with_timezone(&sast_timezone)
-                .naive_local(),
+            date_time: sast_now().naive_local(),
             overall: HealthStatus::Healthy,
             api_hosting_service: HealthStatus::Healthy,
         }))
     }
 
     #[utoipa::path(context_path = "/v0.0.1")]
-    #[get("/fuzzy_search/<query>")]
-    pub async fn fuzzy_search(query: String) -> Result<Json<Vec<SearchResult<Area>>>, String> {
-        tracing::info!("Fuzzy searching on {query}");
-        let matcher = SkimMatcherV2::default();
+    #[get("/health")]
+    pub async fn health(cache: &State<OutageCache>) -> (Status, Json<Health>) {
+        let health = cache.health().await;
+        tracing::info!("Health is {:?}", health.status);
+        let status = match health.status {
+            ServiceStatus::Down => Status::ServiceUnavailable,
+            ServiceStatus::Ok | ServiceStatus::Degraded => Status::Ok,
+        };
+        (status, Json(health))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/cache_status")]
+    pub async fn cache_status(cache: &State<OutageCache>) -> Result<Json<CacheStatus>, ApiError> {
+        Ok(Json(cache.status().await))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/metrics")]
+    pub async fn metrics(metrics: &State<Metrics>, cache: &State<OutageCache>) -> PrometheusText {
+        PrometheusText(metrics.render(cache.counters()))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/ratelimit")]
+    pub async fn ratelimit(
+        status: Option<RateLimitStatus>,
+    ) -> Result<Json<RateLimitStatus>, ApiError> {
+        status.map(Json).ok_or_else(|| {
+            ApiError::NotFound("Requests to this API aren't being rate limited".to_string())
+        })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[post("/subscribe", format = "json", data = "<request>")]
+    pub async fn subscribe(
+        request: Json<SubscribeRequest>,
+        cache: &State<OutageCache>,
+        webhooks: &State<Webhooks>,
+        ip: ClientIp,
+    ) -> Result<(Status, Json<Subscription>), ApiError> {
+        let SubscribeRequest {
+            area,
+            callback_url,
+            email,
+        } = request.into_inner();
+        if !is_valid_email(email.trim()) {
+            return Err(ApiError::BadRequest(format!(
+                "`{email}` isn't a valid email address. Please give an email like \
+                `you@example.com` so that we can get in touch if something goes wrong with your \
+                webhook"
+            )));
+        }
+        webhooks
+            .check_callback_url(callback_url.trim())
+            .await
+            .map_err(ApiError::BadRequest)?;
+
+        let area_name = canonical_area_name(&area, cache).await?;
+        let outages = area_outages(&area_name, cache).await?;
+        let stage = CurrentStatus::at(area_name.clone(), &outages, sast_now())
+            .current_outage
+            .map_or(0, |outage| outage.stage);
+        let subscription = webhooks
+            .subscribe(
+                area_name,
+                callback_url.trim().to_string(),
+                &email,
+                ip.0,
+                stage,
+            )
+            .await
+            .map_err(ApiError::TooMany)?;
+        Ok((Status::Created, Json(subscription)))
+    }
 
-        // Normalise a query
-        let preprocess = |q: &str| {
-            // Replace all non a-z0-9_ chars with a space to aid in fuzzy matching
-            let re = Regex::new(r"[^a-zA-Z0-9_]").unwrap();
-            re.replace_all(q, " ").to_ascii_lowercase()
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[delete("/subscribe/<id>")]
+    pub async fn unsubscribe(
+        id: String,
+        secret: SubscriptionSecret,
+        webhooks: &State<Webhooks>,
+    ) -> Result<Status, ApiError> {
+        let unsubscribed = match secret.0 {
+            Some(secret) => webhooks.unsubscribe(&id, &secret).await,
+            None => false,
         };
+        if !unsubscribed {
+            return Err(ApiError::NotFound(format!(
+                "There's no subscription `{id}` with that secret. Send the secret you got when \
+                subscribing as an `Authorization: Bearer <secret>` header"
+            )));
+        }
+        Ok(Status::NoContent)
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/clients/typescript.d.ts")]
+    pub async fn typescript_client(types: &State<TypeScriptTypes>) -> TypeScriptFile {
+        TypeScriptFile(types.0.clone())
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/version")]
+    pub async fn version(cache: &State<OutageCache>) -> Json<Version> {
+        let status = cache.status().await;
+        Json(Version {
+            api_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: env!("GIT_SHA").to_string(),
+            built_at: built_at(),
+            machine_friendly_last_fetched: status.last_fetched,
+            machine_friendly_etag: status.etag,
+        })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/area_at?<lat>&<lng>")]
+    pub async fn area_at(
+        lat: String,
+        lng: String,
+        boundaries: &State<AreaBoundaries>,
+    ) -> Result<Json<Vec<Area>>, ApiError> {
+        let point = geo::parse_coords(&lat, &lng).map_err(ApiError::BadRequest)?;
+        tracing::info!("Finding areas containing {point:?}");
+        Ok(Json(
+            boundaries
+                .areas_containing(&point)
+                .into_iter()
+                .map(Area::from_name)
+                .collect(),
+        ))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/area/<name>")]
+    pub async fn area(
+        name: String,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+        schedules: &State<AreaSchedules>,
+    ) -> Result<Json<Area>, ApiError> {
+        tracing::info!("Looking up the area {name}");
+        check_area_exists(&name, cache).await?;
+        let area = area_with_schedule(name, aliases, schedules);
+        Ok(Json(localise_area(area, aliases, &languages)))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/area/<name>/siblings")]
+    pub async fn area_siblings(
+        name: String,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+        schedules: &State<AreaSchedules>,
+    ) -> Result<Json<Vec<Area>>, ApiError> {
+        tracing::info!("Finding the areas with the same schedule as {name}");
+        check_area_exists(&name, cache).await?;
+        // The bundled schedules can fall behind eskom-calendar, so leave out any areas which it
+        // doesn't know about (any more)
+        let checks = schedules
+            .siblings_of(&name)
+            .into_iter()
+            .map(|sibling| async move {
+                match cache.is_known_area(&sibling).await {
+                    Ok(is_known) => (sibling, is_known),
+                    Err(err) => {
+                        tracing::warn!("Couldn't check whether {sibling} exists: {err}");
+                        (sibling, false)
+                    }
+                }
+            });
+        let siblings = rocket::futures::future::join_all(checks).await;
+        Ok(Json(
+            siblings
+                .into_iter()
+                .filter(|(_, is_known)| *is_known)
+                .map(|(sibling, _)| area_with_schedule(sibling, aliases, schedules))
+                .map(|sibling| localise_area(sibling, aliases, &languages))
+                .collect(),
+        ))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/area_by_id/<id>")]
+    pub async fn area_by_id(id: i64, cache: &State<OutageCache>) -> Result<Json<Area>, ApiError> {
+        tracing::info!("Looking up the area with ID {id}");
+        let machine_friendly = cache.outages().await.map_err(ApiError::from)?;
+
+        machine_friendly
+            .iter()
+            .find(|outage| AreaId::from_name(&outage.area_name) == AreaId(id))
+            .map(|outage| Json(Area::from_name(outage.area_name.clone())))
+            .ok_or_else(|| ApiError::NotFound(format!("No area has the ID {id}")))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/fuzzy_search/<query>?<limit>&<min_score>&<province>&<highlight>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn fuzzy_search(
+        query: String,
+        limit: Option<String>,
+        min_score: Option<String>,
+        province: Option<String>,
+        highlight: Option<String>,
+        _email: ContactEmail,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<Json<Vec<SearchResult<Area>>>>, ApiError> {
+        tracing::info!("Fuzzy searching on {query}");
+        let limit = parse_count("limit", limit)
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(FUZZY_SEARCH_LIMIT);
+        let min_score = min_score
+            .map(|min_score| {
+                min_score.trim().parse::<i64>().map_err(|_err| {
+                    ApiError::BadRequest(format!("`min_score={min_score}` must be a whole number"))
+                })
+            })
+            .transpose()?;
+        let province = province.map(parse_province).transpose()?;
+        let highlight = parse_flag("highlight", highlight).map_err(ApiError::BadRequest)?;
+        let matcher = SkimMatcherV2::default();
 
-        // Normalise the query
-        let query = preprocess(&query);
+        // Normalise the query, and make sure there's enough of it left to search for
+        let query = normalise_for_search(&query).trim().to_string();
+        if query.chars().filter(|c| *c != ' ').count() < MIN_FUZZY_QUERY_LEN {
+            return Err(ApiError::BadRequest(format!(
+                "The query must have at least {MIN_FUZZY_QUERY_LEN} letters or numbers in it"
+            )));
+        }
 
         // Get the machine friendly data
         tracing::info!("Fetching machine friendly");
-        let machine_friendly = get_machine_friendly().await?;
+        let machine_friendly = cache.outages().await.map_err(ApiError::from)?;
 
         tracing::info!("Fuzzy searching for matching areas");
         // Find all matching areas
         let mut matching_areas = machine_friendly
-            .into_iter()
-            .map(|outage| outage.area_name)
+            .iter()
+            .map(|outage| outage.area_name.clone())
             .collect::<HashSet<_>>()
             .into_iter()
+            .filter(|area_name| {
+                province.is_none_or(|province| province_from_area_name(area_name) == Some(province))
+            })
             .filter_map(|area_name| {
-                matcher
-                    .fuzzy_match(&preprocess(&area_name), &preprocess(&query))
-                    .map(|score| SearchResult {
-                        score,
-                        result: Area {
-                            name: area_name,
-                            id: AreaId(0),
-                            schedule: ScheduleId(0),
-                            aliases: vec![],
-                            province: None,
-                            municipality: None,
-                        },
+                // An area matches as well as its best matching name, whether that's its actual
+                // name or one of its aliases. Normalising keeps every character where it was, so
+                // the matched indices line up with the name
+                let area = area_with_aliases(area_name, aliases);
+                let (raw_score, name, indices) = std::iter::once(&area.name)
+                    .chain(&area.aliases)
+                    .filter_map(|name| {
+                        let normalised = normalise_for_search(name);
+                        if highlight {
+                            let (score, indices) = matcher.fuzzy_indices(&normalised, &query)?;
+                            Some((score, name, indices))
+                        } else {
+                            let score = matcher.fuzzy_match(&normalised, &query)?;
+                            Some((score, name, vec![]))
+                        }
                     })
+                    // Ties go to the area's actual name, since it comes first
+                    .reduce(|best, next| if next.0 > best.0 { next } else { best })?;
+                let highlight = highlight.then(|| SearchHighlight::new(name.clone(), &indices));
+                Some(SearchResult {
+                    score: raw_score,
+                    raw_score,
+                    result: area,
+                    highlight,
+                })
             })
             .collect::<Vec<_>>();
 
+        // Scores are only comparable within one search, so scale them relative to the best match
+        // before dropping the weak ones
+        let best_raw_score = matching_areas.iter().map(|found| found.raw_score).max();
+        for found in &mut matching_areas {
+            found.score = normalised_score(found.raw_score, best_raw_score.unwrap_or(0));
+        }
+        matching_areas.retain(|found| min_score.is_none_or(|min_score| found.score >= min_score));
+
         tracing::info!("Sorting matching areas");
-        matching_areas.sort();
-        matching_areas.reverse();
+        // Best matches first, with ties in alphabetical order so the results don't jump around
+        matching_areas.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.result.name.cmp(&b.result.name))
+        });
+        matching_areas.truncate(limit);
+        let matching_areas = matching_areas
+            .into_iter()
+            .map(|found| SearchResult {
+                result: localise_area(found.result, aliases, &languages),
+                ..found
+            })
+            .collect::<Vec<_>>();
 
         tracing::info!("Returning result");
-        Ok(Json(matching_areas))
+        Ok(Cacheable::new(Json(matching_areas), cache).await)
     }
 
     #[utoipa::path(context_path = "/v0.0.1")]
-    #[get("/outages/<area_name>")]
-    pub async fn outages(area_name: String) -> Result<Json<Vec<PowerOutage>>, String> {
-        tracing::info!("Getting outages for {area_name}");
-        let outages: Vec<PowerOutage> = get_machine_friendly()
-            .await?
-            .into_iter()
-            .filter(|outage| outage.area_name == area_name)
-            .collect();
+    #[get("/search/<query>?<limit>")]
+    pub async fn search(
+        query: String,
+        limit: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Json<Vec<SearchMatch>>, ApiError> {
+        tracing::info!("Searching areas, provinces, and municipalities for {query}");
+        let limit = parse_count("limit", limit)
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(FUZZY_SEARCH_LIMIT);
+        let matcher = SkimMatcherV2::default();
+        let query = normalise_for_search(&query);
+        let score = |name: &str| matcher.fuzzy_match(&normalise_for_search(name), &query);
+
+        let provinces = Province::ALL.into_iter().filter_map(|province| {
+            score(province.slug()).map(|raw_score| SearchMatch {
+                kind: SearchKind::Province,
+                score: raw_score,
+                raw_score,
+                name: province.slug().to_string(),
+                result: SearchMatchResult::Province(province),
+            })
+        });
+        let municipalities = Municipality::all().filter_map(|municipality| {
+            let slug = municipality.slug();
+            score(&slug).map(|raw_score| SearchMatch {
+                kind: SearchKind::Municipality,
+                score: raw_score,
+                raw_score,
+                name: slug,
+                result: SearchMatchResult::Municipality(municipality),
+            })
+        });
+        let area_names = area_names(cache).await?;
+        let areas = area_names.into_iter().filter_map(|area_name| {
+            // An area matches as well as its best matching name, like in `fuzzy_search`
+            let area = area_with_aliases(area_name, aliases);
+            std::iter::once(&area.name)
+                .chain(&area.aliases)
+                .filter_map(|name| score(name))
+                .max()
+                .map(|raw_score| SearchMatch {
+                    kind: SearchKind::Area,
+                    score: raw_score,
+                    raw_score,
+                    name: area.name.clone(),
+                    result: SearchMatchResult::Area(area),
+                })
+        });
+
+        let mut matches = provinces
+            .chain(municipalities)
+            .chain(areas)
+            .collect::<Vec<_>>();
+        let best_raw_score = matches.iter().map(|found| found.raw_score).max();
+        for found in &mut matches {
+            found.score = normalised_score(found.raw_score, best_raw_score.unwrap_or(0));
+        }
+        // Best matches first. Broader matches come first when the scores are tied, since
+        // someone searching for `gauteng` most likely means the province
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.kind.cmp(&b.kind))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        matches.truncate(limit);
+        Ok(Json(matches))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>?<merge>&<include_past>&<tz>&<fields>&<sort>&<cursor>&<limit>&<format>&<filter..>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn outages(
+        area_name: String,
+        merge: Option<String>,
+        include_past: Option<String>,
+        tz: Option<String>,
+        fields: Option<String>,
+        sort: Option<String>,
+        cursor: Option<String>,
+        limit: Option<String>,
+        format: Option<String>,
+        filter: OutageFilter,
+        _email: ContactEmail,
+        languages: AcceptLanguage,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<Outages>, ApiError> {
+        let merge = parse_flag("merge", merge).map_err(ApiError::BadRequest)?;
+        let include_past =
+            parse_flag("include_past", include_past).map_err(ApiError::BadRequest)?;
+        let tz = tz
+            .as_deref()
+            .map(parse_timezone)
+            .transpose()
+            .map_err(ApiError::BadRequest)?;
+        let fields = parse_fields(fields).map_err(ApiError::BadRequest)?;
+        let sort = parse_sort(sort).map_err(ApiError::BadRequest)?;
+        let format = parse_format(format).map_err(ApiError::BadRequest)?;
+        let cursor = cursor
+            .as_deref()
+            .map(|cursor| OutageCursor::decode(cursor, sort))
+            .transpose()
+            .map_err(ApiError::BadRequest)?;
+        let limit = parse_count("limit", limit).map_err(ApiError::BadRequest)?;
+        if limit == Some(0) {
+            return Err(ApiError::BadRequest(
+                "`limit=0` must be at least 1".to_string(),
+            ));
+        }
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let mut outages = area_outages(&area_name, cache).await?;
+        if merge {
+            tracing::info!("Merging back-to-back outages for {area_name}");
+            outages = PowerOutage::merge_contiguous(outages);
+        }
+        if !include_past {
+            let now = sast_now();
+            outages.retain(|outage| outage.finsh >= now);
+        }
 
-        if outages.is_empty() {
-            tracing::info!("No outages found for {area_name}");
-            return Err(format!("No areas found that match `{area_name}`"));
+        tracing::info!("Filtering outages for {area_name} with {filter:?}");
+        let mut outages = filter.apply(outages).map_err(ApiError::BadRequest)?;
+        sort.sort(&mut outages);
+
+        // Only page the outages if asked to, so that clients which expect a list still get one
+        let mut truncated = None;
+        let paging = if cursor.is_some() || limit.is_some() {
+            let limit = limit.unwrap_or(OUTAGES_PAGE_SIZE).min(OUTAGES_PAGE_SIZE);
+            if let Some(cursor) = cursor {
+                outages.retain(|outage| cursor.is_before(outage));
+            }
+            let next_cursor = (outages.len() > limit)
+                .then(|| OutageCursor::after(sort, &outages[limit - 1]).encode());
+            outages.truncate(limit);
+            tracing::info!("Returning a page of {limit} outages for {area_name}");
+            Paging::Page { next_cursor }
+        } else if outages.len() > outage_cap.max_outages {
+            // Too many to send at once, so send the first ones as a page which says where the
+            // rest start
+            let last_sent = &outages[outage_cap.max_outages - 1];
+            let next_cursor = OutageCursor::after(sort, last_sent).encode();
+            outage_cap.apply(&mut outages);
+            truncated = Some(outage_cap.message());
+            Paging::Page {
+                next_cursor: Some(next_cursor),
+            }
+        } else {
+            Paging::All
+        };
+
+        if let Some(tz) = tz {
+            tracing::info!("Converting outages for {area_name} to {tz:?}");
+            for outage in &mut outages {
+                outage.start = tz.convert(outage.start);
+                outage.finsh = tz.convert(outage.finsh);
+            }
         }
 
         tracing::info!("Returning outages for {area_name}");
-        Ok(Json(outages))
+        Ok(Cacheable::new(
+            Outages {
+                localised_name: aliases
+                    .localised(&area_name, &languages.0)
+                    .map(str::to_string),
+                area_name,
+                outages,
+                fields,
+                paging,
+                truncated,
+                format,
+            },
+            cache,
+        )
+        .await)
     }
 
     #[utoipa::path(context_path = "/v0.0.1")]
-    #[get("/schedules/<area_name>")]
-    pub async fn schedules(area_name: String) -> Result<Json<RecurringSchedule>, String> {
-        tracing::info!("Getting schedules for {area_name}");
-        let url = format!( "https://raw.githubusercontent.com/beyarkay/eskom-calendar/main/generated/{area_name}.csv");
-        let response = reqwest::get(url)
-            .await
-            .map_err(|_err| format!("Failed to get CSV file defining schedules for {area_name}"))?;
+    #[head("/outages/<area_name>?<format>")]
+    pub async fn outages_head(
+        area_name: String,
+        format: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Cacheable<OutagesHead>, ApiError> {
+        let format = parse_format(format).map_err(ApiError::BadRequest)?;
+        // Checking the area exists is enough for a 404 to match GET, and is much cheaper than
+        // filtering, sorting, and serialising the outages
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        check_area_exists(&area_name, cache).await?;
+        Ok(Cacheable::new(OutagesHead { format }, cache).await)
+    }
 
-        tracing::info!("Checking if GitHub request was successful");
-        if !response.status().is_success() {
-            return Err(format!(
-                "Failed to get CSV file from GitHub: {:?}",
-                response
-            ));
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>/longpoll?<since>&<timeout>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn outages_longpoll(
+        area_name: String,
+        since: Option<String>,
+        timeout: Option<String>,
+        _email: ContactEmail,
+        languages: AcceptLanguage,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<LongPoll, ApiError> {
+        let timeout = parse_count("timeout", timeout)
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(LONGPOLL_TIMEOUT_SECONDS)
+            .min(LONGPOLL_TIMEOUT_SECONDS);
+        let area_outages = || {
+            outages(
+                area_name.clone(),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                OutageFilter::default(),
+                ContactEmail,
+                languages.clone(),
+                outage_cap,
+                cache,
+                aliases,
+            )
+        };
+
+        // Start watching before looking at the outages, so that a change in between isn't missed
+        let mut changes = cache.changes();
+        let current = area_outages().await?.inner;
+        changes.borrow_and_update();
+        let etag = area_etag(&current.outages);
+        let is_unchanged = since
+            .as_deref()
+            .is_some_and(|since| bare_etag(&etag) == bare_etag(since));
+        if !is_unchanged {
+            return Ok(LongPoll::Changed {
+                outages: current,
+                etag: Some(etag),
+            });
+        }
+
+        tracing::info!("Waiting up to {timeout}s for the outages of {area_name} to change");
+        let _long_polling = cache.long_poll();
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(timeout as u64);
+        // machine_friendly.csv changing doesn't mean this area's outages did, so keep waiting
+        // until they do
+        while let Ok(Ok(())) = tokio::time::timeout_at(deadline, changes.changed()).await {
+            let latest = area_outages().await?.inner;
+            // Getting the outages might have refreshed the cache, which isn't a change to wait for
+            changes.borrow_and_update();
+            let latest_etag = area_etag(&latest.outages);
+            if latest_etag != etag {
+                return Ok(LongPoll::Changed {
+                    outages: latest,
+                    etag: Some(latest_etag),
+                });
+            }
+        }
+        Ok(LongPoll::Unchanged)
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/all?<limit>&<offset>&<filter..>")]
+    pub async fn outages_all(
+        limit: Option<String>,
+        offset: Option<String>,
+        filter: OutageFilter,
+        _email: ContactEmail,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Json<AllOutages>>, ApiError> {
+        let limit = parse_count("limit", limit)
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(ALL_OUTAGES_PAGE_SIZE)
+            .min(ALL_OUTAGES_PAGE_SIZE);
+        let offset = parse_count("offset", offset)
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(0);
+        let machine_friendly = cache.outages().await.map_err(ApiError::from)?;
+
+        tracing::info!("Filtering every area's outages with {filter:?}");
+        let outages = filter
+            .apply(machine_friendly.to_vec())
+            .map_err(ApiError::BadRequest)?;
+        let mut by_area: BTreeMap<String, Vec<PowerOutage>> = BTreeMap::new();
+        for outage in outages {
+            by_area
+                .entry(outage.area_name.clone())
+                .or_default()
+                .push(outage);
+        }
+
+        // Cut the page short at the last area whose outages fit under the cap, so that the next
+        // page can start at the first area that was left out
+        let total = by_area.len();
+        let mut page = BTreeMap::new();
+        let mut outage_count = 0;
+        let mut truncated = false;
+        for (area_name, mut area_outages) in by_area.into_iter().skip(offset).take(limit) {
+            if outage_count + area_outages.len() > outage_cap.max_outages {
+                truncated = true;
+                // An area with too many outages on its own still gets as many as can be sent
+                if page.is_empty() {
+                    outage_cap.apply(&mut area_outages);
+                    page.insert(area_name, area_outages);
+                }
+                break;
+            }
+            outage_count += area_outages.len();
+            page.insert(area_name, area_outages);
+        }
+
+        tracing::info!(
+            "Returning a page of {} areas' outages from {offset}",
+            page.len()
+        );
+        Ok(Capped {
+            inner: Json(AllOutages {
+                total,
+                offset,
+                limit,
+                outages: page,
+                truncated,
+                message: truncated.then(|| outage_cap.message()),
+            }),
+            truncated,
+        })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>/today")]
+    pub async fn outages_today(
+        area_name: String,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Outages, ApiError> {
+        outages_on_day(&area_name, 0, cache).await
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>/tomorrow")]
+    pub async fn outages_tomorrow(
+        area_name: String,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Outages, ApiError> {
+        outages_on_day(&area_name, 1, cache).await
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>/daily?<from>&<to>")]
+    pub async fn outages_daily(
+        area_name: String,
+        from: Option<String>,
+        to: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<DailySummary>>, ApiError> {
+        let from = from
+            .map(|from| parse_date("from", &from))
+            .transpose()
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or_else(|| sast_now().date_naive());
+        let to = to
+            .map(|to| parse_date("to", &to))
+            .transpose()
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(from + Duration::days(DAILY_DAYS - 1));
+        if to < from {
+            return Err(ApiError::BadRequest(format!(
+                "`to` ({to}) can't be before `from` ({from})"
+            )));
+        } else if (to - from).num_days() >= MAX_DAILY_DAYS {
+            return Err(ApiError::BadRequest(format!(
+                "The window from `from` to `to` can be at most {MAX_DAILY_DAYS} days long"
+            )));
         }
 
-        let text_data = response.text().await.map_err(|_err| {
-            format!("Failed to get text of the CSV file defining schedules for {area_name}")
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let outages = area_outages(&area_name, cache).await?;
+        tracing::info!("Summarising the outages for {area_name} from {from} to {to}");
+        Ok(Json(DailySummary::from_outages(&outages, from, to)))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>/diff?<since>")]
+    pub async fn outages_diff(
+        area_name: String,
+        since: String,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<OutageDiff>, ApiError> {
+        let since = parse_datetime("since", &since).map_err(ApiError::BadRequest)?;
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let current = area_outages(&area_name, cache).await?;
+        let snapshot = cache.snapshot_since(since).await.ok_or_else(|| {
+            ApiError::Upstream("eskom-calendar's data hasn't been fetched yet".to_string())
         })?;
 
-        tracing::info!("Parsing schedule CSV as text");
-        let mut reader = csv::Reader::from_reader(text_data.as_bytes());
-        let headers = reader
-            .headers()
-            .map_err(|_err| "Couldn't read headers for CSV file")?;
-        let outages: Vec<RecurringOutage>;
-
-        // Parse the CSV file in a manner that depends on the headers
-        if headers.iter().any(|h| h == "date_of_month") {
-            outages = reader
-                .deserialize::<RawMonthlyShedding>()
-                .map(|res| Into::<RecurringOutage>::into(res.unwrap()))
-                .collect::<Vec<_>>();
-        } else if headers.iter().any(|h| h == "day_of_week") {
-            outages = reader
-                .deserialize::<RawWeeklyShedding>()
-                .map(|res| Into::<RecurringOutage>::into(res.unwrap()))
-                .collect::<Vec<_>>();
-        } else if headers.iter().any(|h| h == "day_of_20_day_cycle") {
-            outages = reader
-                .deserialize::<RawPeriodicShedding>()
-                .map(|res| Into::<RecurringOutage>::into(res.unwrap()))
-                .collect::<Vec<_>>();
-        } else {
-            return Err(format!("Couldn't parse headers {:?}", headers));
+        tracing::info!(
+            "Comparing the outages for {area_name} with those from {}",
+            snapshot.taken_at
+        );
+        let before = snapshot
+            .outages
+            .iter()
+            .filter(|outage| outage.area_name == area_name)
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok(Json(OutageDiff::between(
+            area_name,
+            snapshot.taken_at,
+            &before,
+            &current,
+        )))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>/gaps?<from>&<to>")]
+    pub async fn outages_gaps(
+        area_name: String,
+        from: Option<String>,
+        to: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<PowerGap>>, ApiError> {
+        let (from, to) = parse_window(from, to).map_err(ApiError::BadRequest)?;
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let outages = area_outages(&area_name, cache).await?;
+        tracing::info!("Finding when the power is on in {area_name} from {from} to {to}");
+        Ok(Json(PowerGap::between(&outages, from, to)))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages?<areas>&<filter..>")]
+    pub async fn outages_for_areas(
+        areas: String,
+        filter: OutageFilter,
+        _email: ContactEmail,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Json<AreasOutages>>, ApiError> {
+        let area_names = areas
+            .split(',')
+            .map(|area_name| area_name.trim())
+            .filter(|area_name| !area_name.is_empty())
+            .collect::<HashSet<_>>();
+        if area_names.len() > MAX_AREAS_PER_REQUEST {
+            return Err(ApiError::BadRequest(format!(
+                "Asked about {} areas, but at most {MAX_AREAS_PER_REQUEST} areas can be asked \
+                about at once",
+                area_names.len()
+            )));
         }
+        // Check the filter before doing anything that might have to ask GitHub about the areas
+        filter.apply(vec![]).map_err(ApiError::BadRequest)?;
+        tracing::info!("Getting outages for {} areas", area_names.len());
 
-        tracing::info!("Returning parsed CSV as a RecurringSchedule");
-        // TODO actually assign values for id, source, info, last_updated, valid_from, valid_until
-        Ok(Json(RecurringSchedule {
-            id: ScheduleId(0),
-            outages,
-            source: vec![],
-            info: vec![],
-            last_updated: None,
-            valid_from: None,
-            valid_until: None,
+        let machine_friendly = cache.outages().await.map_err(ApiError::from)?;
+
+        let mut outages = area_names
+            .iter()
+            .map(|area_name| (area_name.to_string(), vec![]))
+            .collect::<HashMap<_, _>>();
+        for outage in machine_friendly.iter() {
+            if let Some(area_outages) = outages.get_mut(&outage.area_name) {
+                area_outages.push(outage.clone());
+            }
+        }
+
+        // Areas without outages might still exist
+        let without_outages = outages
+            .iter()
+            .filter(|(_, area_outages)| area_outages.is_empty())
+            .map(|(area_name, _)| area_name);
+        let not_found = unknown_areas(without_outages, cache).await;
+
+        tracing::info!("Filtering outages with {filter:?}");
+        let mut outages = outages
+            .into_iter()
+            .map(|(area_name, area_outages)| Ok((area_name, filter.apply(area_outages)?)))
+            .collect::<Result<HashMap<_, _>, String>>()
+            .map_err(ApiError::BadRequest)?;
+
+        let mut by_name = outages.iter_mut().collect::<Vec<_>>();
+        by_name.sort_by_key(|(area_name, _)| *area_name);
+        let truncated = outage_cap.apply_to_each(by_name.into_iter().map(|(_, list)| list));
+
+        Ok(Capped {
+            inner: Json(AreasOutages {
+                outages,
+                not_found,
+                truncated,
+                message: truncated.then(|| outage_cap.message()),
+            }),
+            truncated,
+        })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outages/<area_name>/calendar.ics")]
+    pub async fn outages_ics(
+        area_name: String,
+        _email: ContactEmail,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Ics>, ApiError> {
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let mut outages = area_outages(&area_name, cache).await?;
+        OutageOrder::default().sort(&mut outages);
+        let truncated = outage_cap.apply(&mut outages);
+        tracing::info!("Converting outages for {area_name} to iCalendar");
+        Ok(Capped {
+            inner: Ics(area_calendar(&area_name, &outages)),
+            truncated,
+        })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/machine_friendly.csv")]
+    pub async fn machine_friendly_csv(
+        cache: &State<OutageCache>,
+    ) -> Result<MachineFriendlyCsv, ApiError> {
+        let (csv, etag) = cache.machine_friendly().await.map_err(ApiError::from)?;
+        tracing::info!("Returning the cached machine_friendly.csv");
+        Ok(MachineFriendlyCsv { csv, etag })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/province/<province>/outages.ics")]
+    pub async fn province_outages_ics(
+        province: String,
+        outage_cap: OutageCap,
+        cache: &State<OutageCache>,
+    ) -> Result<Capped<Ics>, ApiError> {
+        let province = parse_province(province)?;
+        let machine_friendly = cache.outages().await.map_err(ApiError::from)?;
+        let now = sast_now();
+        let mut outages = machine_friendly
+            .iter()
+            .filter(|outage| province_from_area_name(&outage.area_name) == Some(province))
+            .filter(|outage| outage.finsh >= now)
+            .collect::<Vec<_>>();
+        outages.sort_by_key(|outage| (outage.start, &outage.area_name));
+        let mut truncated = outage_cap.apply(&mut outages);
+        if outages.len() > MAX_PROVINCE_EVENTS {
+            tracing::info!(
+                "Only including {MAX_PROVINCE_EVENTS} of the {} outages in {province:?}",
+                outages.len()
+            );
+            outages.truncate(MAX_PROVINCE_EVENTS);
+            truncated = true;
+        }
+
+        tracing::info!("Converting outages in {province:?} to iCalendar");
+        let events = outages
+            .iter()
+            .map(|outage| {
+                let summary = format!(
+                    "Stage {} loadshedding in {}",
+                    outage.stage, outage.area_name
+                );
+                ics::vevent(outage, &summary, now)
+            })
+            .collect::<Vec<_>>();
+        Ok(Capped {
+            inner: Ics(ics::vcalendar(
+                &format!("Loadshedding in {}", province.slug()),
+                &events,
+            )),
+            truncated,
+        })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/current/<area_name>")]
+    pub async fn current(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<CurrentStatus>, ApiError> {
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let outages = area_outages(&area_name, cache).await?;
+        tracing::info!("Checking if {area_name} is currently being loadshed");
+        Ok(Json(CurrentStatus::at(area_name, &outages, sast_now())))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[post("/current", format = "json", data = "<request>")]
+    pub async fn current_for_areas(
+        request: Json<AreasRequest>,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<AreasCurrentStatus>, ApiError> {
+        let area_names = request
+            .areas
+            .iter()
+            .map(|area_name| area_name.trim())
+            .filter(|area_name| !area_name.is_empty())
+            .collect::<HashSet<_>>();
+        if area_names.len() > MAX_AREAS_PER_REQUEST {
+            return Err(ApiError::BadRequest(format!(
+                "Asked about {} areas, but at most {MAX_AREAS_PER_REQUEST} areas can be asked \
+                about at once",
+                area_names.len()
+            )));
+        }
+        tracing::info!("Checking if {} areas are being loadshed", area_names.len());
+
+        let machine_friendly = cache.outages().await.map_err(ApiError::from)?;
+        let mut outages = area_names
+            .iter()
+            .map(|area_name| (area_name.to_string(), vec![]))
+            .collect::<HashMap<_, _>>();
+        for outage in machine_friendly.iter() {
+            if let Some(area_outages) = outages.get_mut(&outage.area_name) {
+                area_outages.push(outage.clone());
+            }
+        }
+
+        // Areas without outages might still exist
+        let without_outages = outages
+            .iter()
+            .filter(|(_, area_outages)| area_outages.is_empty())
+            .map(|(area_name, _)| area_name);
+        let not_found = unknown_areas(without_outages, cache).await;
+
+        let now = sast_now();
+        let statuses = outages
+            .into_iter()
+            .filter(|(area_name, _)| !not_found.contains(area_name))
+            .map(|(area_name, area_outages)| {
+                let status = CurrentStatus::at(area_name.clone(), &area_outages, now);
+                (area_name, status)
+            })
+            .collect();
+
+        Ok(Json(AreasCurrentStatus {
+            statuses,
+            not_found,
+        }))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/stats")]
+    pub async fn national_stats(
+        cache: &State<OutageCache>,
+    ) -> Result<Json<NationalStats>, ApiError> {
+        let outages = cache.outages().await.map_err(ApiError::from)?;
+        tracing::info!("Working out the national stats");
+        Ok(Json(NationalStats::at(&outages, sast_now())))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/stages")]
+    pub async fn stage_reference() -> Json<Vec<StageInfo>> {
+        Json(STAGES.to_vec())
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/stats/<area_name>")]
+    pub async fn stats(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<AreaStats>, ApiError> {
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let outages = area_outages(&area_name, cache).await?;
+        tracing::info!("Working out the stats for {area_name}");
+        Ok(Json(AreaStats::from_outages(area_name, &outages)))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/national_stage")]
+    pub async fn national_stage(
+        cache: &State<OutageCache>,
+    ) -> Result<Json<NationalStage>, ApiError> {
+        let outages = cache.outages().await.map_err(ApiError::from)?;
+        tracing::info!("Working out the national stage");
+        Ok(Json(NationalStage::at(&outages, sast_now())))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/next/<area_name>")]
+    pub async fn next(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<MaybeNextOutage, ApiError> {
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let outages = area_outages(&area_name, cache).await?;
+        tracing::info!("Finding the next outage for {area_name}");
+        Ok(match NextOutage::after(&outages, sast_now()) {
+            Some(next) => MaybeNextOutage::Next(Json(next)),
+            None => MaybeNextOutage::NoneScheduled(()),
+        })
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/outlook/<area_name>")]
+    pub async fn outlook(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<OutlookSegment>>, ApiError> {
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let outages = area_outages(&area_name, cache).await?;
+        tracing::info!("Building the outlook for {area_name}");
+        let now = sast_now();
+        Ok(Json(OutlookSegment::timeline(
+            &outages,
+            now,
+            now + Duration::hours(OUTLOOK_HOURS),
+        )))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/schedules/<area_name>?<at>")]
+    pub async fn schedules(
+        area_name: String,
+        at: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<RecurringSchedule>, ApiError> {
+        let at = at
+            .map(|at| parse_date("at", &at))
+            .transpose()
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or_else(|| sast_now().date_naive());
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        Ok(Json(area_schedule_on(&area_name, at, cache).await?))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/schedules/<area_name>/compare?<stages>&<from>&<to>")]
+    pub async fn schedules_compare(
+        area_name: String,
+        stages: String,
+        from: Option<String>,
+        to: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<ScheduleComparison>, ApiError> {
+        let stages = parse_stages("stages", &stages).map_err(ApiError::BadRequest)?;
+        let (from, to) = parse_window(from, to).map_err(ApiError::BadRequest)?;
+
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let schedule = area_schedule(&area_name, cache).await?;
+        tracing::info!("Comparing the schedule for {area_name} at stages {stages:?}");
+        let source = cache.sources().schedule_url(&area_name);
+        let stages = stages
+            .into_iter()
+            .map(|stage| {
+                let outages = schedule.expand(&area_name, &source, stage, from, to);
+                StageScenario::new(stage, outages, from, to)
+            })
+            .collect();
+        Ok(Json(ScheduleComparison {
+            area_name,
+            from,
+            to,
+            stages,
         }))
     }
 
     #[utoipa::path(context_path = "/v0.0.1")]
-    #[get("/list_areas")]
-    pub async fn list_all_areas() -> Result<Json<Vec<String>>, String> {
-        list_areas(".*".to_string()).await
+    #[get("/schedules/<area_name>/expand?<stage>&<from>&<to>&<calendar>")]
+    #[allow(clippy::too_many_arguments)] // Rocket needs one argument per query parameter
+    pub async fn schedules_expand(
+        area_name: String,
+        stage: String,
+        from: Option<String>,
+        to: Option<String>,
+        calendar: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+        holidays: &State<PublicHolidays>,
+    ) -> Result<Json<Vec<ExpandedOutage>>, ApiError> {
+        let stage = parse_stage("stage", &stage).map_err(ApiError::BadRequest)?;
+        let no_holidays = PublicHolidays::default();
+        let holidays = if parse_calendar(calendar).map_err(ApiError::BadRequest)? {
+            holidays.inner()
+        } else {
+            &no_holidays
+        };
+        let (from, to) = parse_window(from, to).map_err(ApiError::BadRequest)?;
+
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let schedule = area_schedule(&area_name, cache).await?;
+        tracing::info!("Expanding the schedule for {area_name} at stage {stage}");
+        let outages = schedule.expand(
+            &area_name,
+            &cache.sources().schedule_url(&area_name),
+            stage,
+            from,
+            to,
+        );
+        Ok(Json(
+            outages
+                .into_iter()
+                .map(|outage| {
+                    // Expanded outages are always in SAST, so this is the local date
+                    let date = outage.start.date_naive();
+                    ExpandedOutage {
+                        day_type: holidays.day_type(date),
+                        holiday: holidays.on(date).map(str::to_string),
+                        outage,
+                    }
+                })
+                .collect(),
+        ))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/schedules/<area_name>/calendar.ics?<stage>")]
+    pub async fn schedules_ics(
+        area_name: String,
+        stage: Option<String>,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<Ics, ApiError> {
+        let stage = stage
+            .map(|stage| parse_stage("stage", &stage))
+            .transpose()
+            .map_err(ApiError::BadRequest)?;
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let schedule = area_schedule(&area_name, cache).await?;
+        tracing::info!("Converting the schedule for {area_name} to iCalendar");
+        let now = sast_now();
+        let from = schedule
+            .valid_from
+            .map_or(now.date_naive(), |valid_from| valid_from.date());
+        let events = schedule
+            .outages
+            .iter()
+            .filter(|outage| outage.stage > 0 && stage.is_none_or(|stage| outage.stage <= stage))
+            .filter_map(|outage| {
+                let first = outage.first_on_or_after(from)?;
+                let summary = match stage {
+                    Some(stage) => format!("Stage {stage} loadshedding"),
+                    None => format!("Loadshedding from stage {}", outage.stage),
+                };
+                Some(ics::recurring_vevent(
+                    &area_name,
+                    outage,
+                    first,
+                    schedule.valid_until,
+                    &summary,
+                    now,
+                ))
+            });
+        let components = std::iter::once(ics::sast_vtimezone())
+            .chain(events)
+            .collect::<Vec<_>>();
+        Ok(Ics(ics::vcalendar(
+            &format!("Loadshedding schedule for {area_name}"),
+            &components,
+        )))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/schedules/<area_name>/raw.csv")]
+    pub async fn schedules_raw_csv(
+        area_name: String,
+        _email: ContactEmail,
+        cache: &State<OutageCache>,
+    ) -> Result<(ContentType, String), ApiError> {
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let schedule = area_schedule(&area_name, cache).await?;
+        tracing::info!("Converting the schedule for {area_name} back to CSV");
+        let csv = schedule
+            .to_raw_csv()
+            .map_err(|err| ApiError::Other(Errors::Unspecified(err)))?;
+        Ok((ContentType::CSV, csv))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/schedules/<area_name>/validate")]
+    pub async fn schedules_validate(
+        area_name: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<ScheduleReport>, ApiError> {
+        let area_name = canonical_area_name(&area_name, cache).await?;
+        let schedule = area_schedule(&area_name, cache).await?;
+        tracing::info!("Checking the schedule for {area_name}");
+        Ok(Json(ScheduleReport::check(area_name, &schedule)))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/list_areas?<limit>&<offset>")]
+    pub async fn list_all_areas(
+        limit: Option<String>,
+        offset: Option<String>,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<StreamedAreasPage>, ApiError> {
+        list_areas(
+            ".*".to_string(),
+            limit,
+            offset,
+            None,
+            languages,
+            cache,
+            aliases,
+        )
+        .await
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/areas/municipality/<municipality>")]
+    pub async fn areas_in_municipality(
+        municipality: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<String>>, ApiError> {
+        let municipality = Municipality::from_slug(municipality.trim()).ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "`{municipality}` isn't a metro or local municipality. Try something like \
+                `city-of-cape-town` or `stellenbosch`"
+            ))
+        })?;
+        tracing::info!("Listing all areas in {municipality:?}");
+        Ok(Json(
+            area_names(cache)
+                .await?
+                .into_iter()
+                .filter(|area_name| municipality_from_area_name(area_name) == Some(municipality))
+                .collect(),
+        ))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/areas/search/<query>?<limit>")]
+    pub async fn areas_search(
+        query: String,
+        limit: Option<String>,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Json<Vec<Area>>, ApiError> {
+        let limit = parse_count("limit", limit)
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(AUTOCOMPLETE_LIMIT);
+        let normalised = normalise_for_search(&query);
+        let query = normalised.trim();
+        if query.is_empty() {
+            return Err(ApiError::BadRequest(
+                "The query must have at least one letter or number in it".to_string(),
+            ));
+        }
+
+        tracing::info!("Finding areas starting with or containing `{query}`");
+        let mut matches = area_names(cache)
+            .await?
+            .into_iter()
+            .map(|area_name| area_with_aliases(area_name, aliases))
+            .filter_map(|area| {
+                let names = std::iter::once(&area.name)
+                    .chain(&area.aliases)
+                    .map(|name| normalise_for_search(name))
+                    .collect::<Vec<_>>();
+                if names.iter().any(|name| name.starts_with(query)) {
+                    Some((true, area))
+                } else if names.iter().any(|name| name.contains(query)) {
+                    Some((false, area))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        // The areas are already in alphabetical order, and sorting is stable, so this puts the
+        // prefix matches first without jumbling up either group
+        matches.sort_by_key(|(is_prefix, _area)| !is_prefix);
+        Ok(Json(
+            matches
+                .into_iter()
+                .take(limit)
+                .map(|(_is_prefix, area)| localise_area(area, aliases, &languages))
+                .collect(),
+        ))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/areas/tree")]
+    pub async fn areas_tree(
+        cache: &State<OutageCache>,
+    ) -> Result<Json<BTreeMap<String, BTreeMap<String, Vec<String>>>>, ApiError> {
+        tracing::info!("Grouping all areas by province and municipality");
+        Ok(Json(crate::structs::areas_tree(area_names(cache).await?)))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/areas/province/<province>")]
+    pub async fn areas_in_province(
+        province: String,
+        cache: &State<OutageCache>,
+    ) -> Result<Json<Vec<String>>, ApiError> {
+        let province = parse_province(province)?;
+        tracing::info!("Listing all areas in {province:?}");
+        Ok(Json(
+            area_names(cache)
+                .await?
+                .into_iter()
+                .filter(|area_name| province_from_area_name(area_name) == Some(province))
+                .collect(),
+        ))
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/provinces")]
+    pub async fn provinces() -> Json<Vec<ProvinceInfo>> {
+        Json(Province::ALL.into_iter().map(ProvinceInfo::new).collect())
     }
 
     #[utoipa::path(context_path = "/v0.0.1")]
-    #[get("/list_areas/<regex>")]
-    pub async fn list_areas(regex: String) -> Result<Json<Vec<String>>, String> {
+    #[get("/municipalities")]
+    pub async fn municipalities() -> Json<Vec<MunicipalityInfo>> {
+        Json(MunicipalityInfo::all())
+    }
+
+    #[utoipa::path(context_path = "/v0.0.1")]
+    #[get("/list_areas/<regex>?<limit>&<offset>&<anchored>")]
+    pub async fn list_areas(
+        regex: String,
+        limit: Option<String>,
+        offset: Option<String>,
+        anchored: Option<String>,
+        languages: AcceptLanguage,
+        cache: &State<OutageCache>,
+        aliases: &State<AreaAliases>,
+    ) -> Result<Cacheable<StreamedAreasPage>, ApiError> {
         tracing::info!("Listing all areas matching the regex `{regex}`");
-        let machine_friendly = get_machine_friendly().await?;
-        let re =
-            Regex::new(&regex).map_err(|e| format!("Error parsing '{regex}' as regex: {e:?}"))?;
+        let limit = parse_count("limit", limit).map_err(ApiError::BadRequest)?;
+        let offset = parse_count("offset", offset)
+            .map_err(ApiError::BadRequest)?
+            .unwrap_or(0);
+        let anchored = parse_flag("anchored", anchored).map_err(ApiError::BadRequest)?;
+        let re = compile_user_regex(&regex, anchored).await?;
+        let machine_friendly = cache.outages().await.map_err(ApiError::from)?;
 
         let mut uniq_areas = machine_friendly
-            .into_iter()
+            .iter()
             .filter(|outage| re.is_match(&outage.area_name))
-            .map(|outage| outage.area_name)
+            .map(|outage| outage.area_name.clone())
             .collect::<HashSet<_>>()
             .into_iter()
             .collect::<Vec<_>>();
@@ -324,7 +4035,9 @@ pub mod v0_0_1 {
         tracing::info!("Sorting the areas");
         uniq_areas.sort();
 
-        tracing::info!("Returning the sorted areas");
-        Ok(Json(uniq_areas))
+        tracing::info!("Returning a page of the sorted areas");
+        let page = AreasPage::paginate(uniq_areas, offset, limit);
+        let page = StreamedAreasPage(localise_page(page, aliases, &languages));
+        Ok(Cacheable::new(page, cache).await)
     }
 }
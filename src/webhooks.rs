@@ -0,0 +1,357 @@
+use crate::cache::OutageCache;
+use crate::http_client::{self, HttpClients};
+use crate::structs::{CurrentStatus, PowerOutage, StageChangeNotification, Subscription};
+use crate::versions::sast_now;
+
+use chrono::{DateTime, FixedOffset};
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    request::{FromRequest, Outcome},
+    Orbit, Request, Rocket,
+};
+use sha2::Sha256;
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use uuid::Uuid;
+
+/// How often the subscribed areas are checked for stage changes.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The header which holds a notification's signature.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// How long a webhook has to respond before we give up on it.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most webhooks that can be subscribed at once, across everyone.
+pub const MAX_SUBSCRIPTIONS: usize = 10_000;
+
+/// The most webhooks that can be subscribed at once with the same email, or from the same IP
+/// address.
+pub const MAX_SUBSCRIPTIONS_PER_SUBSCRIBER: usize = 20;
+
+/// A subscription, along with who subscribed it and the stage its area was at when it was last
+/// checked.
+#[derive(Debug)]
+struct Watched {
+    subscription: Subscription,
+    /// The contact email, lowercased so that the same email is always counted together.
+    email: String,
+    ip: Option<IpAddr>,
+    stage: u8,
+}
+
+/// Everything that's subscribed to stage changes. This is cheap to clone, and every clone shares
+/// the same subscriptions.
+#[derive(Clone)]
+pub struct Webhooks {
+    watched: Arc<Mutex<Vec<Watched>>>,
+    client: reqwest::Client,
+    /// Whether webhooks can be on this machine or a private network. This is only for tests,
+    /// which run their webhooks on `localhost`.
+    allow_private_addresses: bool,
+}
+
+impl Webhooks {
+    /// Webhooks which are sent with `clients`.
+    pub fn new(clients: &HttpClients) -> Self {
+        Webhooks {
+            watched: Arc::default(),
+            client: clients.webhooks.clone(),
+            allow_private_addresses: false,
+        }
+    }
+
+    /// Webhooks which can be sent to this machine, so that tests can receive them.
+    #[cfg(test)]
+    pub fn allowing_private_addresses() -> Self {
+        Webhooks {
+            allow_private_addresses: true,
+            ..Webhooks::new(&HttpClients::new())
+        }
+    }
+
+    /// Check that webhooks can be sent to `callback_url`, which has to be an `http` or `https`
+    /// URL whose host is on the public internet. Otherwise anyone could have the API send
+    /// requests to things which are only reachable from inside its own network.
+    pub async fn check_callback_url(&self, callback_url: &str) -> Result<Url, String> {
+        let url = Url::parse(callback_url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+            .ok_or_else(|| format!("`{callback_url}` isn't an http or https URL"))?;
+        if self.allow_private_addresses {
+            return Ok(url);
+        }
+        let addresses = match (ip_host(&url), url.host_str(), url.port_or_known_default()) {
+            (Some(ip), _, _) => vec![ip],
+            (None, Some(host), Some(port)) => tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|err| format!("Couldn't look up the host of `{callback_url}`: {err}"))?
+                .map(|addr| addr.ip())
+                .collect(),
+            _ => vec![],
+        };
+        if addresses.is_empty() {
+            return Err(format!("Couldn't look up the host of `{callback_url}`"));
+        }
+        if let Some(ip) = addresses.iter().find(|ip| !http_client::is_public(**ip)) {
+            return Err(format!(
+                "`{callback_url}` points at {ip}, which isn't on the public internet"
+            ));
+        }
+        Ok(url)
+    }
+
+    /// Start watching `area_name`, which is at `stage` right now. Fails with a description of
+    /// which limit was hit if there are already too many subscriptions, either for `email` and
+    /// `ip` or altogether.
+    pub async fn subscribe(
+        &self,
+        area_name: String,
+        callback_url: String,
+        email: &str,
+        ip: Option<IpAddr>,
+        stage: u8,
+    ) -> Result<Subscription, String> {
+        let email = email.trim().to_lowercase();
+        let mut watched = self.watched.lock().await;
+        if watched.len() >= MAX_SUBSCRIPTIONS {
+            return Err(format!(
+                "There are already {MAX_SUBSCRIPTIONS} webhooks subscribed, which is as many as \
+                the API can keep track of. Please try again later"
+            ));
+        }
+        let by_email = watched.iter().filter(|w| w.email == email).count();
+        let by_ip = watched
+            .iter()
+            .filter(|w| ip.is_some() && w.ip == ip)
+            .count();
+        if by_email.max(by_ip) >= MAX_SUBSCRIPTIONS_PER_SUBSCRIBER {
+            return Err(format!(
+                "You already have {MAX_SUBSCRIPTIONS_PER_SUBSCRIBER} webhooks subscribed, which \
+                is the most anyone can have. Unsubscribe some of them with `DELETE \
+                /subscribe/{{id}}` first"
+            ));
+        }
+
+        let subscription = Subscription {
+            id: Uuid::new_v4().simple().to_string(),
+            area_name,
+            callback_url,
+            secret: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+        };
+        tracing::info!(
+            "Subscribing {} to stage changes in {}",
+            subscription.callback_url,
+            subscription.area_name
+        );
+        watched.push(Watched {
+            subscription: subscription.clone(),
+            email,
+            ip,
+            stage,
+        });
+        Ok(subscription)
+    }
+
+    /// Stop sending notifications for the subscription `id`, but only if `secret` is the secret it
+    /// was given, so that nobody else can unsubscribe it. Returns whether it was unsubscribed.
+    pub async fn unsubscribe(&self, id: &str, secret: &str) -> bool {
+        let mut watched = self.watched.lock().await;
+        let before = watched.len();
+        watched.retain(|watched| {
+            watched.subscription.id != id || !constant_time_eq(&watched.subscription.secret, secret)
+        });
+        let unsubscribed = watched.len() < before;
+        if unsubscribed {
+            tracing::info!("Unsubscribed {id} from stage changes");
+        }
+        unsubscribed
+    }
+
+    /// Check every subscribed area's stage at the time `now`, and notify the webhooks of any
+    /// areas whose stage has changed since the last check. Returns the notifications which were
+    /// sent successfully.
+    pub async fn check(
+        &self,
+        outages: &[PowerOutage],
+        now: DateTime<FixedOffset>,
+    ) -> Vec<StageChangeNotification> {
+        // Work out what to send before sending anything, so that the lock isn't held while
+        // waiting for slow webhooks
+        let changes = {
+            let mut watched = self.watched.lock().await;
+            watched
+                .iter_mut()
+                .filter_map(|watched| {
+                    let area_name = &watched.subscription.area_name;
+                    let area_outages = outages
+                        .iter()
+                        .filter(|outage| &outage.area_name == area_name)
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    let outage =
+                        CurrentStatus::at(area_name.clone(), &area_outages, now).current_outage;
+                    let stage = outage.as_ref().map_or(0, |outage| outage.stage);
+                    if stage == watched.stage {
+                        return None;
+                    }
+                    let notification = StageChangeNotification {
+                        subscription_id: watched.subscription.id.clone(),
+                        area_name: area_name.clone(),
+                        previous_stage: watched.stage,
+                        stage,
+                        outage,
+                    };
+                    watched.stage = stage;
+                    Some((watched.subscription.clone(), notification))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut sent = vec![];
+        for (subscription, notification) in changes {
+            match self.notify(&subscription, &notification).await {
+                Ok(()) => sent.push(notification),
+                Err(err) => tracing::warn!(
+                    "Failed to notify {} of a stage change: {err}",
+                    subscription.callback_url
+                ),
+            }
+        }
+        sent
+    }
+
+    /// POST `notification` to the subscription's webhook, signed with the subscription's secret.
+    async fn notify(
+        &self,
+        subscription: &Subscription,
+        notification: &StageChangeNotification,
+    ) -> Result<(), String> {
+        // Hostnames are checked as they're resolved by the client, but IP addresses don't get
+        // resolved so they're checked here
+        let url = Url::parse(&subscription.callback_url).map_err(|err| err.to_string())?;
+        if let Some(ip) =
+            ip_host(&url).filter(|ip| !self.allow_private_addresses && !http_client::is_public(*ip))
+        {
+            return Err(format!("{ip} isn't on the public internet"));
+        }
+        let body = serde_json::to_string(notification).map_err(|err| err.to_string())?;
+        let response = self
+            .client
+            .post(&subscription.callback_url)
+            .timeout(CALLBACK_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", sign(&subscription.secret, &body)),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("The webhook responded with {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// The host of `url`, if it's an IP address rather than a hostname.
+fn ip_host(url: &Url) -> Option<IpAddr> {
+    url.host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// Whether `a` and `b` are the same, taking just as long to say so wherever they differ, so that
+/// secrets can't be guessed one character at a time by timing the answer.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The secret sent with a request to unsubscribe a webhook, as `Authorization: Bearer <secret>`.
+pub struct SubscriptionSecret(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SubscriptionSecret {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let secret = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|secret| secret.trim().to_string());
+        Outcome::Success(SubscriptionSecret(secret))
+    }
+}
+
+/// The hex HMAC-SHA256 of `body`, keyed with `secret`.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take keys of any size");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Checks the subscribed areas for stage changes every `CHECK_INTERVAL`, for as long as the API
+/// is running. It uses whatever outages the cache last fetched, so it doesn't make any requests
+/// to GitHub itself.
+pub struct StageChangeWatcher;
+
+#[rocket::async_trait]
+impl Fairing for StageChangeWatcher {
+    fn info(&self) -> Info {
+        Info {
+            name: "Webhook Stage Change Watcher",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (Some(webhooks), Some(cache)) =
+            (rocket.state::<Webhooks>(), rocket.state::<OutageCache>())
+        else {
+            tracing::error!("Can't watch for stage changes without the webhooks and the cache");
+            return;
+        };
+        let webhooks = webhooks.clone();
+        let changes = cache.changes();
+        tokio::spawn(watch_for_stage_changes(webhooks, changes));
+    }
+}
+
+/// Check for stage changes every `CHECK_INTERVAL`, and whenever the outages change.
+async fn watch_for_stage_changes(
+    webhooks: Webhooks,
+    mut changes: watch::Receiver<Option<Arc<Vec<PowerOutage>>>>,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            changed = changes.changed() => {
+                if changed.is_err() {
+                    // The cache has been dropped, so the API is shutting down
+                    return;
+                }
+            }
+        }
+        let outages = changes.borrow().clone();
+        if let Some(outages) = outages {
+            webhooks.check(&outages, sast_now()).await;
+        }
+    }
+}